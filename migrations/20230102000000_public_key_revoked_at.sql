@@ -0,0 +1,2 @@
+-- Revocation keeps the key row; record when it happened
+ALTER TABLE user_public_keys ADD COLUMN IF NOT EXISTS revoked_at TIMESTAMPTZ;
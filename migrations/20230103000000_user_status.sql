@@ -0,0 +1,2 @@
+-- Account lifecycle: active, suspended or pending_verification
+ALTER TABLE users ADD COLUMN IF NOT EXISTS status VARCHAR(32) NOT NULL DEFAULT 'active';
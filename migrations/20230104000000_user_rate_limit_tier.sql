@@ -0,0 +1,2 @@
+-- Per-user rate-limit tier: basic, standard or premium
+ALTER TABLE users ADD COLUMN IF NOT EXISTS rate_limit_tier VARCHAR(32) NOT NULL DEFAULT 'standard';
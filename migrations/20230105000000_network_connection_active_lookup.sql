@@ -0,0 +1,3 @@
+-- Create-or-refresh of a user's active connection on a network looks it up by (user_id, network_name)
+CREATE INDEX IF NOT EXISTS idx_network_connections_user_network_active
+    ON network_connections(user_id, network_name) WHERE connected;
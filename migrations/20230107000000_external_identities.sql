@@ -0,0 +1,11 @@
+-- Subjects at external identity providers, linked to the local user they sign in as
+CREATE TABLE IF NOT EXISTS external_identities (
+    issuer VARCHAR(255) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (issuer, subject)
+);
+
+CREATE INDEX IF NOT EXISTS idx_external_identities_user
+    ON external_identities (user_id);
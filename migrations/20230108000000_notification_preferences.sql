@@ -0,0 +1,8 @@
+-- Notification channels each user has muted, per delivery route; users without a row get
+-- every notification
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    muted_websocket_channels TEXT[] NOT NULL DEFAULT '{}',
+    muted_webhook_channels TEXT[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,14 @@
+-- Login sessions for the PostgreSQL user storage backend
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id VARCHAR(255) PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    ip_address VARCHAR(64) NOT NULL,
+    user_agent VARCHAR(512) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_sessions_user_id ON user_sessions (user_id);
+
+-- A public key identifies a single user, whoever registers it first
+CREATE UNIQUE INDEX IF NOT EXISTS idx_user_public_keys_public_key_unique ON user_public_keys (public_key);
@@ -0,0 +1,219 @@
+use actix_web::body::MessageBody;
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpResponse};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::metrics::Metrics;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Cookie carrying the signed affinity hint
+pub const AFFINITY_COOKIE: &str = "dashboard_affinity";
+
+/// Header carrying the same hint, for clients that don't keep cookies
+pub const AFFINITY_HEADER: &str = "X-Instance-Affinity";
+
+/// Response header reporting whether a presented hint named this instance: `hit` or `miss`
+pub const AFFINITY_STATUS_HEADER: &str = "X-Affinity-Status";
+
+/// Domain separator so affinity MACs can't be confused with other uses of the secret
+const AFFINITY_CONTEXT: &[u8] = b"instance-affinity:v1:";
+
+/// Issues and verifies signed hints naming the instance a client should stick to.
+///
+/// A hint is `{instance_id}.{expires_at}.{mac}`, so a load balancer can route on the text
+/// before the last two dots without knowing the secret. The server only trusts hints signed
+/// with the shared secret.
+#[derive(Clone)]
+pub struct AffinitySigner {
+    key: Vec<u8>,
+    instance_id: String,
+    ttl: Duration,
+}
+
+impl AffinitySigner {
+    /// Create a signer for `instance_id` whose hints stay valid for `ttl`
+    pub fn new(secret: &str, instance_id: &str, ttl: Duration) -> Self {
+        Self {
+            key: secret.as_bytes().to_vec(),
+            instance_id: instance_id.to_string(),
+            ttl,
+        }
+    }
+
+    /// The instance hints issued here point at
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    /// Sign a hint for this instance, expiring `ttl` after `now` (Unix seconds)
+    pub fn issue(&self, now: i64) -> String {
+        let expires_at = now + self.ttl.as_secs() as i64;
+        let payload = format!("{}.{}", self.instance_id, expires_at);
+        format!("{}.{}", payload, hex::encode(self.sign(&payload)))
+    }
+
+    /// Verify a hint and return the instance it names, or `None` if forged, malformed or expired
+    pub fn verify(&self, hint: &str, now: i64) -> Option<String> {
+        let (payload, mac_hex) = hint.rsplit_once('.')?;
+        let (instance_id, expires_at) = payload.rsplit_once('.')?;
+        let mac = hex::decode(mac_hex).ok()?;
+
+        let mut verifier = self.mac();
+        verifier.update(payload.as_bytes());
+        verifier.verify_slice(&mac).ok()?;
+
+        let expires_at: i64 = expires_at.parse().ok()?;
+        if instance_id.is_empty() || expires_at <= now {
+            return None;
+        }
+        Some(instance_id.to_string())
+    }
+
+    /// Set the affinity cookie and header on a login or refresh response
+    pub fn attach(&self, response: &mut HttpResponse, now: i64) {
+        let hint = self.issue(now);
+        let cookie = Cookie::build(AFFINITY_COOKIE, hint.clone())
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .max_age(time::Duration::seconds(self.ttl.as_secs() as i64))
+            .finish();
+        if let Err(e) = response.add_cookie(&cookie) {
+            debug!("Failed to set affinity cookie: {}", e);
+        }
+        if let Ok(value) = HeaderValue::from_str(&hint) {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static("x-instance-affinity"), value);
+        }
+    }
+
+    fn sign(&self, payload: &str) -> Vec<u8> {
+        let mut mac = self.mac();
+        mac.update(payload.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(AFFINITY_CONTEXT);
+        mac
+    }
+}
+
+/// Compare a request's affinity hint with this instance.
+///
+/// Requests are always served: a miss only means the load balancer didn't honour the hint
+/// (e.g. the preferred instance is gone), and user events still reach every instance through
+/// the Redis broadcast bridge. Misses are logged and counted so stickiness can be monitored.
+pub async fn check_affinity(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let preferred = req.app_data::<web::Data<AffinitySigner>>().and_then(|signer| {
+        let hint = req
+            .headers()
+            .get(AFFINITY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+            .or_else(|| req.cookie(AFFINITY_COOKIE).map(|cookie| cookie.value().to_owned()))?;
+        let preferred = signer.verify(&hint, Utc::now().timestamp())?;
+        Some(preferred == signer.instance_id())
+    });
+    if preferred == Some(false) {
+        debug!("{} {} arrived off its preferred instance", req.method(), req.path());
+    }
+    if let (Some(hit), Some(metrics)) = (preferred, req.app_data::<web::Data<Metrics>>()) {
+        metrics.record_affinity(hit);
+    }
+
+    let mut res = next.call(req).await?;
+    if let Some(hit) = preferred {
+        res.headers_mut().insert(
+            HeaderName::from_static("x-affinity-status"),
+            HeaderValue::from_static(if hit { "hit" } else { "miss" }),
+        );
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{middleware, App};
+
+    const TTL: Duration = Duration::from_secs(3600);
+
+    #[test]
+    fn test_hint_round_trips_and_expires() {
+        let signer = AffinitySigner::new("secret", "pod-1", TTL);
+        let hint = signer.issue(1_000);
+        assert!(hint.starts_with("pod-1."));
+        assert_eq!(signer.verify(&hint, 1_000).as_deref(), Some("pod-1"));
+        assert_eq!(signer.verify(&hint, 1_000 + 3600), None);
+
+        // Hints from other instances of the deployment verify too, since they share the secret
+        let other = AffinitySigner::new("secret", "pod.2", TTL).issue(1_000);
+        assert_eq!(signer.verify(&other, 1_000).as_deref(), Some("pod.2"));
+    }
+
+    #[test]
+    fn test_forged_hints_are_rejected() {
+        let signer = AffinitySigner::new("secret", "pod-1", TTL);
+        let hint = signer.issue(1_000);
+        let (_, mac) = hint.rsplit_once('.').unwrap();
+
+        let retargeted = format!("pod-2.{}.{}", 1_000 + 3600, mac);
+        assert_eq!(signer.verify(&retargeted, 1_000), None);
+        let foreign = AffinitySigner::new("other", "pod-1", TTL).issue(1_000);
+        assert_eq!(signer.verify(&foreign, 1_000), None);
+        for garbage in ["", "pod-1", "pod-1.9999999999", "..", ".1.zz"] {
+            assert_eq!(signer.verify(garbage, 1_000), None);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_middleware_reports_hit_and_miss() {
+        let here = AffinitySigner::new("secret", "pod-1", TTL);
+        let there = AffinitySigner::new("secret", "pod-2", TTL);
+        let now = Utc::now().timestamp();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(here.clone()))
+                .wrap(middleware::from_fn(check_affinity))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        fn status<B>(res: &ServiceResponse<B>) -> Option<String> {
+            res.headers()
+                .get(AFFINITY_STATUS_HEADER)
+                .map(|value| value.to_str().unwrap().to_owned())
+        }
+        let res = call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert_eq!(status(&res), None);
+
+        let req = TestRequest::get()
+            .uri("/")
+            .cookie(Cookie::new(AFFINITY_COOKIE, here.issue(now)))
+            .to_request();
+        assert_eq!(status(&call_service(&app, req).await).as_deref(), Some("hit"));
+
+        let req = TestRequest::get()
+            .uri("/")
+            .insert_header((AFFINITY_HEADER, there.issue(now)))
+            .to_request();
+        let res = call_service(&app, req).await;
+        assert!(res.status().is_success());
+        assert_eq!(status(&res).as_deref(), Some("miss"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Fixed-capacity map that evicts the least recently used entry when full
+#[derive(Debug)]
+pub struct BoundedCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, (V, u64)>,
+    /// Recency order: lowest tick is the least recently used key
+    order: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+impl<K: Eq + Hash + Clone, V> BoundedCache<K, V> {
+    /// Create a cache holding at most `capacity` entries (at least one)
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Maximum number of entries
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Current number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether `key` is present, without affecting recency
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Look up `key` and mark it as most recently used
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let tick = self.next_tick();
+        let (value, last_tick) = self.entries.get_mut(key)?;
+        self.order.remove(last_tick);
+        self.order.insert(tick, key.clone());
+        *last_tick = tick;
+        Some(value)
+    }
+
+    /// Insert or replace `key`, evicting the least recently used entry if full
+    pub fn insert(&mut self, key: K, value: V) {
+        let tick = self.next_tick();
+        if let Some((_, last_tick)) = self.entries.remove(&key) {
+            self.order.remove(&last_tick);
+        } else if self.entries.len() >= self.capacity {
+            self.evict_oldest();
+        }
+        self.order.insert(tick, key.clone());
+        self.entries.insert(key, (value, tick));
+    }
+
+    /// Remove `key`, returning its value
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (value, tick) = self.entries.remove(key)?;
+        self.order.remove(&tick);
+        Some(value)
+    }
+
+    /// Remove every entry for which `keep` returns false
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let order = &mut self.order;
+        self.entries.retain(|key, (value, tick)| {
+            let kept = keep(key, value);
+            if !kept {
+                order.remove(tick);
+            }
+            kept
+        });
+    }
+
+    fn evict_oldest(&mut self) {
+        if let Some((_, key)) = self.order.pop_first() {
+            self.entries.remove(&key);
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_beyond_capacity_evicts() {
+        let mut cache = BoundedCache::new(100);
+        for i in 0..10_000 {
+            cache.insert(i, i);
+        }
+
+        assert_eq!(cache.len(), 100);
+        assert!(!cache.contains(&0));
+        assert!(cache.contains(&9_999));
+    }
+
+    #[test]
+    fn test_recently_used_entries_survive_eviction() {
+        let mut cache = BoundedCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+
+        // Touch "a" so "b" becomes the least recently used
+        *cache.get_mut(&"a").unwrap() += 1;
+        cache.insert("c", 3);
+
+        assert!(cache.contains(&"a"));
+        assert!(!cache.contains(&"b"));
+        assert_eq!(cache.get_mut(&"a"), Some(&mut 2));
+    }
+
+    #[test]
+    fn test_retain_keeps_order_consistent() {
+        let mut cache = BoundedCache::new(3);
+        cache.insert(1, "old");
+        cache.insert(2, "new");
+        cache.retain(|_, v| *v != "old");
+        cache.insert(3, "new");
+        cache.insert(4, "new");
+
+        assert_eq!(cache.len(), 3);
+        assert!(cache.contains(&2));
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Source of monotonic time, abstracted so timeouts can be tested without sleeping
+pub trait Clock: Send + Sync {
+    /// Current instant
+    fn now(&self) -> Instant;
+}
+
+/// Clock backed by the system monotonic clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Manually advanced clock for tests
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    /// Create a clock starting at the current instant
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Whether more than `timeout` has elapsed since `since`; a zero timeout never expires
+pub fn has_elapsed(since: Instant, now: Instant, timeout: Duration) -> bool {
+    !timeout.is_zero() && now.saturating_duration_since(since) > timeout
+}
+
+/// Time left before `timeout` elapses if it is within `warn_before`; `None` otherwise.
+///
+/// Zero `timeout` or `warn_before` disables the warning, and an already expired timeout
+/// yields `None` since there is nothing left to warn about.
+pub fn warning_due(since: Instant, now: Instant, timeout: Duration, warn_before: Duration) -> Option<Duration> {
+    if timeout.is_zero() || warn_before.is_zero() {
+        return None;
+    }
+    let remaining = timeout.checked_sub(now.saturating_duration_since(since))?;
+    (remaining <= warn_before).then_some(remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_clock_timeouts() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let timeout = Duration::from_secs(60);
+
+        clock.advance(Duration::from_secs(60));
+        assert!(!has_elapsed(start, clock.now(), timeout));
+
+        clock.advance(Duration::from_secs(1));
+        assert!(has_elapsed(start, clock.now(), timeout));
+    }
+
+    #[test]
+    fn test_zero_timeout_never_expires() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+
+        clock.advance(Duration::from_secs(86_400));
+        assert!(!has_elapsed(start, clock.now(), Duration::ZERO));
+    }
+
+    #[test]
+    fn test_warning_due_before_timeout() {
+        let clock = ManualClock::new();
+        let start = clock.now();
+        let timeout = Duration::from_secs(120);
+        let warn_before = Duration::from_secs(30);
+
+        clock.advance(Duration::from_secs(89));
+        assert_eq!(warning_due(start, clock.now(), timeout, warn_before), None);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(warning_due(start, clock.now(), timeout, warn_before), Some(Duration::from_secs(30)));
+
+        clock.advance(Duration::from_secs(31));
+        assert_eq!(warning_due(start, clock.now(), timeout, warn_before), None);
+        assert_eq!(warning_due(start, start, timeout, Duration::ZERO), None);
+    }
+}
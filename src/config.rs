@@ -1,6 +1,33 @@
 use dotenv::dotenv;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use std::fs;
+
+use crate::errors::{DashboardError, DashboardResult};
+use crate::json_case::{FieldCase, UnknownFieldPolicy};
+use crate::services::connection_gate::ConnectionGateKind;
+use crate::services::reconnect::IpChangePolicy;
+use crate::storage::memory::SessionEviction;
+
+/// Known feature flags and their defaults.
+///
+/// Each flag can be overridden in the JSON file named by `FEATURES_FILE` and then by a
+/// `FEATURE_<NAME>=true|false` environment variable.
+pub const FEATURE_DEFAULTS: &[(&str, bool)] = &[
+    // Prometheus `/metrics` endpoint
+    ("metrics", false),
+    // Admin API under `/api/admin`
+    ("admin_api", true),
+    // Periodic maintenance banner frames on open sockets
+    ("maintenance_broadcast", true),
+    // `POST /api/users/{id}/keys/bulk`
+    ("bulk_key_registration", true),
+    // `GET /api/users/{id}/networks/export`
+    ("network_export", true),
+    // Passive points for connected time (rate set by `POINTS_PER_MINUTE`)
+    ("points_accrual", false),
+];
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
@@ -9,6 +36,11 @@ pub struct Config {
     pub redis: RedisConfig,
     pub websocket: WebSocketConfig,
     pub auth: AuthConfig,
+    pub earnings: EarningsConfig,
+    pub shutdown: ShutdownConfig,
+    pub rate_limits: RateLimitConfig,
+    pub memory_storage: MemoryStorageConfig,
+    pub webhooks: WebhookConfig,
     pub features: FeatureFlags,
 }
 
@@ -17,6 +49,32 @@ pub struct ServerConfig {
     pub port: u16,
     pub log_level: String,
     pub environment: String,
+    pub maintenance_message: Option<String>,
+    /// Start with writes refused; toggleable at runtime via the admin API
+    pub read_only: bool,
+    pub keep_alive: u64,
+    pub client_request_timeout: u64,
+    pub client_disconnect_timeout: u64,
+    pub shutdown_timeout: u64,
+    /// Origins allowed for CORS and WebSocket handshakes; `*` allows any
+    pub allowed_origins: Vec<String>,
+    /// Identifies this process among the instances of a deployment
+    pub instance_id: String,
+    /// Seconds a signed instance affinity hint stays valid; 0 stops issuing and checking them
+    pub affinity_ttl: u64,
+    /// Key naming of JSON responses and server-sent WebSocket frames
+    pub json_field_case: FieldCase,
+    /// Whether request bodies may carry fields their DTO doesn't declare
+    pub unknown_json_fields: UnknownFieldPolicy,
+    /// Include uptime, build, connection and dependency details in `/health`
+    pub health_details: bool,
+}
+
+impl ServerConfig {
+    /// Whether every origin is accepted: always in development, otherwise only with `*`
+    pub fn allows_any_origin(&self) -> bool {
+        self.environment == "development" || self.allowed_origins.iter().any(|origin| origin == "*")
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -25,28 +83,266 @@ pub struct DatabaseConfig {
     pub max_connections: u32,
     pub connection_timeout: u64,
     pub seed_on_start: bool,
+    /// Consecutive failed or timed-out storage calls that open the circuit breaker (0 disables)
+    pub breaker_failure_threshold: u32,
+    /// Seconds the open breaker refuses storage calls before letting a trial call through
+    pub breaker_cooldown: u64,
+    /// Milliseconds after which a storage call counts as failed (0 disables)
+    pub call_timeout_ms: u64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct RedisConfig {
     pub url: Option<String>,
+    /// Pub/sub channel relaying broadcasts between instances
+    pub broadcast_channel: String,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct WebSocketConfig {
     pub heartbeat_interval: u64,
+    /// Seconds from connect to the first ping; defaults to `heartbeat_interval`
+    pub initial_ping_delay: u64,
     pub client_timeout: u64,
+    pub maintenance_broadcast_interval: u64,
+    pub app_idle_timeout: u64,
+    /// Seconds without an application `Heartbeat` before the socket is closed; 0 disables
+    pub app_heartbeat_timeout: u64,
+    pub dedup_window: u64,
+    pub reconnect_grace: u64,
+    /// Whether a reconnect token presented from another IP is refused or accepted and audited
+    pub reconnect_ip_change: IpChangePolicy,
+    pub idle_warning: u64,
+    pub ticket_ttl: u64,
+    pub close_on_auth_hook_error: bool,
+    /// Seconds allowed for the WebSocket upgrade before it is aborted; 0 disables
+    pub handshake_timeout: u64,
+    /// Milliseconds `NetworkUpdate` writes for one connection are coalesced over; 0 writes each
+    pub network_update_coalesce_ms: u64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct AuthConfig {
     pub jwt_secret: String,
     pub jwt_expiration: u64,
+    pub admin_user_ids: Vec<i64>,
+    pub max_public_keys_per_user: usize,
+    pub jwt_issuer: String,
+    pub jwt_audience: Option<String>,
+    pub signature_verify_concurrency: usize,
+    pub nonce_cache_max_entries: usize,
+    pub public_key_cache_max_entries: usize,
+    pub public_key_cache_warmup: bool,
+    pub failed_login_cache_max_entries: usize,
+    pub max_failed_logins: u32,
+    pub password_history: usize,
+    /// Seconds after login beyond which a session can no longer be refreshed
+    pub session_max_lifetime: i64,
+    pub denied_public_keys: Vec<String>,
+    pub require_email_verification: bool,
+    /// Checks a user must pass before a signature-authenticated WebSocket session is established
+    pub connection_gates: Vec<ConnectionGateKind>,
+    pub email_verification_ttl: u64,
+    pub verification_resend_interval: u64,
+    pub login_session_retries: u32,
+    pub data_export_interval: i64,
+    /// Seconds a token holder's account status is trusted before storage is read again
+    pub account_status_cache_ttl: i64,
+    pub auth_message_max_age: i64,
+    pub auth_message_max_future: i64,
+    pub max_email_length: usize,
+    pub max_username_length: usize,
+    /// Seconds a revoked public key still authenticates its owner; 0 rejects it at once
+    pub revoked_key_grace_period: u64,
+    /// Accept only WebSocket auth nonces issued by `GET /api/auth/challenge`
+    pub require_server_challenge: bool,
+    /// Seconds an issued challenge stays valid
+    pub auth_challenge_ttl: u64,
+    pub auth_challenge_max_entries: usize,
+    /// JWKS of the external identity provider; enables token auth on WebSockets when set
+    pub external_auth_jwks_url: Option<String>,
+    /// `iss` required of identity provider tokens
+    pub external_auth_issuer: Option<String>,
+    /// `aud` required of identity provider tokens, if any
+    pub external_auth_audience: Option<String>,
+    /// Seconds between JWKS refreshes
+    pub external_auth_jwks_refresh: u64,
+    /// Create a user for identity provider subjects seen for the first time
+    pub external_auth_auto_provision: bool,
+    /// Seconds an admin impersonation token lasts
+    pub impersonation_ttl: i64,
+    /// Impersonation tokens one admin may mint per hour; 0 disables impersonation
+    pub max_impersonations_per_hour: u32,
+}
+
+impl AuthConfig {
+    /// Read the public key deny-list from `DENIED_PUBLIC_KEYS` (comma-separated) and the file
+    /// named by `DENIED_PUBLIC_KEYS_FILE` (one key per line, `#` comments allowed).
+    ///
+    /// Called at startup and again by the admin reload endpoint, so edits to the file take
+    /// effect without a restart.
+    pub fn load_denied_public_keys() -> Result<Vec<String>, config::ConfigError> {
+        let mut keys: Vec<String> = env::var("DENIED_PUBLIC_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect();
+
+        if let Ok(path) = env::var("DENIED_PUBLIC_KEYS_FILE") {
+            let contents = fs::read_to_string(&path).map_err(|e| {
+                config::ConfigError::Message(format!("Failed to read denied keys file {}: {}", path, e))
+            })?;
+            keys.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+
+        Ok(keys)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EarningsConfig {
+    pub points_per_minute: f64,
+    pub max_points_per_update: f64,
+}
+
+/// WebSocket limits for one rate-limit tier; zero disables a limit
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct TierLimits {
+    /// Messages per minute, per IP before authentication and per user after
+    pub messages_per_minute: u32,
+    /// Concurrent connections, per IP before authentication and per user after
+    pub max_connections: usize,
+}
+
+impl TierLimits {
+    /// Read `RATE_LIMIT_<TIER>_MESSAGES_PER_MINUTE` and `RATE_LIMIT_<TIER>_MAX_CONNECTIONS`
+    fn from_env(tier: &str, messages_per_minute: u32, max_connections: usize) -> Self {
+        Self {
+            messages_per_minute: env::var(format!("RATE_LIMIT_{}_MESSAGES_PER_MINUTE", tier))
+                .unwrap_or_else(|_| messages_per_minute.to_string())
+                .parse()
+                .unwrap_or(messages_per_minute),
+            max_connections: env::var(format!("RATE_LIMIT_{}_MAX_CONNECTIONS", tier))
+                .unwrap_or_else(|_| max_connections.to_string())
+                .parse()
+                .unwrap_or(max_connections),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    pub basic: TierLimits,
+    pub standard: TierLimits,
+    pub premium: TierLimits,
+}
+
+/// Capacity limits for the in-memory stores; 0 is unbounded
+#[derive(Debug, Deserialize, Clone)]
+pub struct MemoryStorageConfig {
+    /// New users are refused with `503` once this many exist
+    pub max_users: usize,
+    /// Sessions held before `session_eviction` applies
+    pub max_sessions: usize,
+    pub session_eviction: SessionEviction,
+    /// New network connections are refused with `503` once this many exist
+    pub max_connections: usize,
+}
+
+/// Outbound webhooks for network and earnings events
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhookConfig {
+    /// Every event is POSTed to each of these; empty disables webhooks
+    pub urls: Vec<String>,
+    /// Shared secret for the `X-Webhook-Signature` HMAC; required when `urls` is set
+    pub secret: Option<String>,
+    /// Attempts per event and URL before the delivery is marked failed
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled after each failed attempt
+    pub initial_backoff_ms: u64,
+    /// Seconds before an attempt is abandoned
+    pub timeout: u64,
+}
+
+/// Per-phase timeouts in seconds for the shutdown sequence; draining in-flight requests uses
+/// `ServerConfig::shutdown_timeout`
+#[derive(Debug, Deserialize, Clone)]
+pub struct ShutdownConfig {
+    pub stop_accepting_timeout: u64,
+    pub notify_timeout: u64,
+    /// Seconds allowed for writing coalesced network updates still pending at shutdown
+    pub flush_network_updates_timeout: u64,
+    pub close_pools_timeout: u64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct FeatureFlags {
     pub enable_metrics: bool,
+    flags: HashMap<String, bool>,
+    unknown: Vec<String>,
+}
+
+impl FeatureFlags {
+    /// Build flags from defaults, then the optional features file, then `FEATURE_*` env vars
+    fn load() -> Result<Self, config::ConfigError> {
+        let mut flags: HashMap<String, bool> = FEATURE_DEFAULTS
+            .iter()
+            .map(|(name, enabled)| (name.to_string(), *enabled))
+            .collect();
+        let mut unknown = Vec::new();
+
+        // Legacy toggle for the metrics endpoint
+        if let Ok(value) = env::var("ENABLE_METRICS") {
+            flags.insert("metrics".to_string(), value.parse().unwrap_or(false));
+        }
+
+        let mut overrides: Vec<(String, bool)> = Vec::new();
+        if let Ok(path) = env::var("FEATURES_FILE") {
+            let contents = fs::read_to_string(&path).map_err(|e| {
+                config::ConfigError::Message(format!("Failed to read features file {}: {}", path, e))
+            })?;
+            let file_flags: HashMap<String, bool> = serde_json::from_str(&contents).map_err(|e| {
+                config::ConfigError::Message(format!("Invalid features file {}: {}", path, e))
+            })?;
+            overrides.extend(file_flags);
+        }
+        for (key, value) in env::vars() {
+            if let Some(name) = key.strip_prefix("FEATURE_") {
+                overrides.push((name.to_lowercase(), value.parse().unwrap_or(false)));
+            }
+        }
+
+        for (name, enabled) in overrides {
+            if flags.contains_key(&name) {
+                flags.insert(name, enabled);
+            } else {
+                unknown.push(name);
+            }
+        }
+
+        Ok(Self {
+            enable_metrics: flags.get("metrics").copied().unwrap_or(false),
+            flags,
+            unknown,
+        })
+    }
+
+    /// Whether a feature is enabled; unknown names are treated as disabled
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.flags.get(name).copied().unwrap_or(false)
+    }
+
+    /// Flag names found in the features file or environment that are not known features
+    pub fn unknown_flags(&self) -> &[String] {
+        &self.unknown
+    }
 }
 
 impl Config {
@@ -61,6 +357,50 @@ impl Config {
                 .unwrap_or(8080),
             log_level: env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
             environment: env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string()),
+            maintenance_message: env::var("MAINTENANCE_MESSAGE").ok().filter(|m| !m.is_empty()),
+            read_only: env::var("READ_ONLY")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            keep_alive: env::var("SERVER_KEEP_ALIVE")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            client_request_timeout: env::var("SERVER_CLIENT_REQUEST_TIMEOUT")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            client_disconnect_timeout: env::var("SERVER_CLIENT_DISCONNECT_TIMEOUT")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            shutdown_timeout: env::var("SERVER_SHUTDOWN_TIMEOUT")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            allowed_origins: env::var("ALLOWED_ORIGINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+            instance_id: crate::instance::instance_id(env::var("SERVER_INSTANCE_ID").ok()),
+            affinity_ttl: env::var("AFFINITY_TTL")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .unwrap_or(86400),
+            json_field_case: match env::var("JSON_FIELD_CASE") {
+                Ok(case) => case.parse().map_err(config::ConfigError::Message)?,
+                Err(_) => FieldCase::default(),
+            },
+            unknown_json_fields: match env::var("JSON_UNKNOWN_FIELDS") {
+                Ok(policy) => policy.parse().map_err(config::ConfigError::Message)?,
+                Err(_) => UnknownFieldPolicy::default(),
+            },
+            health_details: env::var("HEALTH_DETAILS")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .unwrap_or(true),
         };
 
         let database = DatabaseConfig {
@@ -77,45 +417,326 @@ impl Config {
                 .unwrap_or_else(|_| "true".to_string())
                 .parse()
                 .unwrap_or(true),
+            breaker_failure_threshold: env::var("DB_BREAKER_FAILURE_THRESHOLD")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            breaker_cooldown: env::var("DB_BREAKER_COOLDOWN")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            call_timeout_ms: env::var("DB_CALL_TIMEOUT_MS")
+                .unwrap_or_else(|_| "5000".to_string())
+                .parse()
+                .unwrap_or(5000),
         };
 
         let redis = RedisConfig {
             url: env::var("REDIS_URL").ok(),
+            broadcast_channel: env::var("REDIS_BROADCAST_CHANNEL")
+                .unwrap_or_else(|_| "dashboard:broadcast".to_string()),
         };
 
+        let heartbeat_interval = env::var("WS_HEARTBEAT_INTERVAL")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .unwrap_or(30);
         let websocket = WebSocketConfig {
-            heartbeat_interval: env::var("WS_HEARTBEAT_INTERVAL")
-                .unwrap_or_else(|_| "30".to_string())
-                .parse()
-                .unwrap_or(30),
+            heartbeat_interval,
+            initial_ping_delay: env::var("WS_INITIAL_PING_DELAY")
+                .ok()
+                .and_then(|delay| delay.parse().ok())
+                .unwrap_or(heartbeat_interval),
             client_timeout: env::var("WS_CLIENT_TIMEOUT")
                 .unwrap_or_else(|_| "120".to_string())
                 .parse()
                 .unwrap_or(120),
+            maintenance_broadcast_interval: env::var("WS_MAINTENANCE_BROADCAST_INTERVAL")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            app_idle_timeout: env::var("WS_APP_IDLE_TIMEOUT")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            app_heartbeat_timeout: env::var("WS_APP_HEARTBEAT_TIMEOUT")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            dedup_window: env::var("WS_DEDUP_WINDOW")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            reconnect_grace: env::var("WS_RECONNECT_GRACE")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            reconnect_ip_change: env::var("WS_RECONNECT_IP_CHANGE")
+                .ok()
+                .and_then(|policy| policy.parse().ok())
+                .unwrap_or_default(),
+            idle_warning: env::var("WS_IDLE_WARNING")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            ticket_ttl: env::var("WS_TICKET_TTL")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            close_on_auth_hook_error: env::var("WS_CLOSE_ON_AUTH_HOOK_ERROR")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            handshake_timeout: env::var("WS_HANDSHAKE_TIMEOUT")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            network_update_coalesce_ms: env::var("WS_NETWORK_UPDATE_COALESCE_MS")
+                .unwrap_or_else(|_| "250".to_string())
+                .parse()
+                .unwrap_or(250),
         };
 
+        let require_email_verification = env::var("REQUIRE_EMAIL_VERIFICATION")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
         let auth = AuthConfig {
             jwt_secret: env::var("JWT_SECRET").unwrap_or_else(|_| "default_jwt_secret".to_string()),
             jwt_expiration: env::var("JWT_EXPIRATION")
                 .unwrap_or_else(|_| "3600".to_string())
                 .parse()
                 .unwrap_or(3600),
-        };
-
-        let features = FeatureFlags {
-            enable_metrics: env::var("ENABLE_METRICS")
+            admin_user_ids: env::var("ADMIN_USER_IDS")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|id| id.trim().parse().ok())
+                .collect(),
+            max_public_keys_per_user: env::var("MAX_PUBLIC_KEYS_PER_USER")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            jwt_issuer: env::var("JWT_ISSUER").unwrap_or_else(|_| "dashboard_system".to_string()),
+            jwt_audience: env::var("JWT_AUDIENCE").ok().filter(|a| !a.is_empty()),
+            signature_verify_concurrency: env::var("SIGNATURE_VERIFY_CONCURRENCY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_else(num_cpus::get),
+            nonce_cache_max_entries: env::var("NONCE_CACHE_MAX_ENTRIES")
+                .unwrap_or_else(|_| "100000".to_string())
+                .parse()
+                .unwrap_or(100_000),
+            public_key_cache_max_entries: env::var("PUBLIC_KEY_CACHE_MAX_ENTRIES")
+                .unwrap_or_else(|_| "10000".to_string())
+                .parse()
+                .unwrap_or(10_000),
+            public_key_cache_warmup: env::var("PUBLIC_KEY_CACHE_WARMUP")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            failed_login_cache_max_entries: env::var("FAILED_LOGIN_CACHE_MAX_ENTRIES")
+                .unwrap_or_else(|_| "10000".to_string())
+                .parse()
+                .unwrap_or(10_000),
+            max_failed_logins: env::var("LOGIN_MAX_FAILED_ATTEMPTS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            password_history: env::var("PASSWORD_HISTORY")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            session_max_lifetime: env::var("SESSION_MAX_LIFETIME")
+                .unwrap_or_else(|_| "2592000".to_string())
+                .parse()
+                .unwrap_or(2_592_000),
+            denied_public_keys: AuthConfig::load_denied_public_keys()?,
+            require_email_verification,
+            connection_gates: match env::var("WS_CONNECTION_GATES") {
+                Ok(gates) => ConnectionGateKind::with_mandatory(
+                    ConnectionGateKind::parse_list(&gates).map_err(config::ConfigError::Message)?,
+                    require_email_verification,
+                ),
+                Err(_) => ConnectionGateKind::defaults(require_email_verification),
+            },
+            email_verification_ttl: env::var("EMAIL_VERIFICATION_TTL")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .unwrap_or(86_400),
+            verification_resend_interval: env::var("VERIFICATION_RESEND_INTERVAL")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            login_session_retries: env::var("LOGIN_SESSION_RETRIES")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse()
+                .unwrap_or(2),
+            data_export_interval: env::var("DATA_EXPORT_INTERVAL")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            account_status_cache_ttl: env::var("ACCOUNT_STATUS_CACHE_TTL")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse()
+                .unwrap_or(30),
+            auth_message_max_age: env::var("AUTH_MESSAGE_MAX_AGE")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            auth_message_max_future: env::var("AUTH_MESSAGE_MAX_FUTURE")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            max_email_length: env::var("MAX_EMAIL_LENGTH")
+                .unwrap_or_else(|_| "254".to_string())
+                .parse()
+                .unwrap_or(254),
+            max_username_length: env::var("MAX_USERNAME_LENGTH")
+                .unwrap_or_else(|_| "64".to_string())
+                .parse()
+                .unwrap_or(64),
+            revoked_key_grace_period: env::var("REVOKED_KEY_GRACE_PERIOD")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            require_server_challenge: env::var("REQUIRE_SERVER_CHALLENGE")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            auth_challenge_ttl: env::var("AUTH_CHALLENGE_TTL")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            auth_challenge_max_entries: env::var("AUTH_CHALLENGE_MAX_ENTRIES")
+                .unwrap_or_else(|_| "100000".to_string())
+                .parse()
+                .unwrap_or(100_000),
+            external_auth_jwks_url: env::var("EXTERNAL_AUTH_JWKS_URL").ok().filter(|u| !u.is_empty()),
+            external_auth_issuer: env::var("EXTERNAL_AUTH_ISSUER").ok().filter(|i| !i.is_empty()),
+            external_auth_audience: env::var("EXTERNAL_AUTH_AUDIENCE").ok().filter(|a| !a.is_empty()),
+            external_auth_jwks_refresh: env::var("EXTERNAL_AUTH_JWKS_REFRESH")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse()
+                .unwrap_or(3600),
+            external_auth_auto_provision: env::var("EXTERNAL_AUTH_AUTO_PROVISION")
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
                 .unwrap_or(false),
+            impersonation_ttl: env::var("IMPERSONATION_TTL")
+                .unwrap_or_else(|_| "900".to_string())
+                .parse()
+                .unwrap_or(900),
+            max_impersonations_per_hour: env::var("IMPERSONATION_MAX_PER_HOUR")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+        };
+
+        let earnings = EarningsConfig {
+            points_per_minute: env::var("POINTS_PER_MINUTE")
+                .unwrap_or_else(|_| "1.0".to_string())
+                .parse()
+                .unwrap_or(1.0),
+            max_points_per_update: env::var("POINTS_MAX_PER_UPDATE")
+                .unwrap_or_else(|_| "60.0".to_string())
+                .parse()
+                .unwrap_or(60.0),
+        };
+
+        let shutdown = ShutdownConfig {
+            stop_accepting_timeout: env::var("SHUTDOWN_STOP_ACCEPTING_TIMEOUT")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            notify_timeout: env::var("SHUTDOWN_NOTIFY_TIMEOUT")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            flush_network_updates_timeout: env::var("SHUTDOWN_FLUSH_NETWORK_UPDATES_TIMEOUT")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            close_pools_timeout: env::var("SHUTDOWN_CLOSE_POOLS_TIMEOUT")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
         };
 
+        let rate_limits = RateLimitConfig {
+            basic: TierLimits::from_env("BASIC", 30, 5),
+            standard: TierLimits::from_env("STANDARD", 120, 10),
+            premium: TierLimits::from_env("PREMIUM", 600, 50),
+        };
+
+        let memory_storage = MemoryStorageConfig {
+            max_users: env::var("MEMORY_MAX_USERS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            max_sessions: env::var("MEMORY_MAX_SESSIONS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            session_eviction: env::var("MEMORY_SESSION_EVICTION")
+                .ok()
+                .and_then(|policy| policy.parse().ok())
+                .unwrap_or_default(),
+            max_connections: env::var("MEMORY_MAX_CONNECTIONS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+        };
+
+        let webhooks = WebhookConfig {
+            urls: env::var("WEBHOOK_URLS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty())
+                .collect(),
+            secret: env::var("WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty()),
+            max_attempts: env::var("WEBHOOK_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            initial_backoff_ms: env::var("WEBHOOK_INITIAL_BACKOFF_MS")
+                .unwrap_or_else(|_| "500".to_string())
+                .parse()
+                .unwrap_or(500),
+            timeout: env::var("WEBHOOK_TIMEOUT")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+        };
+
+        let features = FeatureFlags::load()?;
+
         Ok(Config {
             server,
             database,
             redis,
             websocket,
             auth,
+            earnings,
+            shutdown,
+            rate_limits,
+            memory_storage,
+            webhooks,
             features,
         })
     }
-} 
\ No newline at end of file
+
+    /// Whether the named feature flag is enabled
+    pub fn feature_enabled(&self, name: &str) -> bool {
+        self.features.is_enabled(name)
+    }
+
+    /// Fail with `NotFound` when a feature is disabled, so gated endpoints look absent
+    pub fn require_feature(&self, name: &str) -> DashboardResult<()> {
+        if self.feature_enabled(name) {
+            Ok(())
+        } else {
+            Err(DashboardError::not_found(format!("Feature '{}' is disabled", name)))
+        }
+    }
+}
@@ -4,4 +4,7 @@
 //! These are used for development and testing purposes.
 
 #[cfg(debug_assertions)]
-pub mod test_keys; 
\ No newline at end of file
+pub mod test_keys;
+
+#[cfg(debug_assertions)]
+pub mod ws_trace;
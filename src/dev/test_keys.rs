@@ -1,6 +1,6 @@
 use ed25519_dalek::{SigningKey, VerifyingKey};
 use serde::{Deserialize, Serialize};
-use std::sync::Once;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::sync::Mutex;
 use tracing::{info, warn};
@@ -8,9 +8,10 @@ use tracing::{info, warn};
 // Use a seed to generate deterministic keys for development
 const TEST_SEED: &[u8; 32] = b"dashboard_test_key_seed_123456\0\0";
 const NUM_TEST_KEYS: usize = 10;
+// Upper bound for `DEV_TEST_KEY_COUNT`; the index is encoded in the last two seed bytes
+const MAX_TEST_KEYS: usize = 65_536;
 
-// Singleton pattern to ensure we only generate keys once
-static INIT: Once = Once::new();
+// Generated on first use, and replaced by `regenerate_test_keys`
 static TEST_KEYS: Mutex<Option<Vec<TestKeyPair>>> = Mutex::new(None);
 
 /// Represents an ed25519 key pair for testing
@@ -28,13 +29,62 @@ pub struct TestKeyPair {
     pub index: usize,
 }
 
-/// Initialize the test keys
+/// A test key without its private half, for listing identities
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TestPublicKey {
+    /// User ID associated with this key pair
+    pub user_id: i64,
+    /// Username for this test key
+    pub username: String,
+    /// Public key in hex format
+    pub public_key: String,
+    /// Index number of this test key
+    pub index: usize,
+}
+
+impl From<TestKeyPair> for TestPublicKey {
+    fn from(key: TestKeyPair) -> Self {
+        Self {
+            user_id: key.user_id,
+            username: key.username,
+            public_key: key.public_key,
+            index: key.index,
+        }
+    }
+}
+
+/// Number of test keys to generate, from `DEV_TEST_KEY_COUNT` (default 10)
+fn test_key_count() -> usize {
+    std::env::var("DEV_TEST_KEY_COUNT")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(NUM_TEST_KEYS)
+        .min(MAX_TEST_KEYS)
+}
+
+/// Initialize the test keys from the default seed, unless they already exist
 pub fn initialize_test_keys() {
-    INIT.call_once(|| {
-        let test_keys = generate_test_keys();
-        *TEST_KEYS.lock().unwrap() = Some(test_keys);
+    let mut test_keys = TEST_KEYS.lock().unwrap();
+    if test_keys.is_none() {
+        *test_keys = Some(generate_test_keys(TEST_SEED));
         info!("Test keys initialized for development");
-    });
+    }
+}
+
+/// Replace the test keys with ones derived from `seed`, or with the defaults when `None`,
+/// returning the new set.
+///
+/// The same seed always yields the same keys, so developers can pick distinct seeds to avoid
+/// sharing identities.
+pub fn regenerate_test_keys(seed: Option<&str>) -> Vec<TestKeyPair> {
+    let base_seed = match seed {
+        Some(seed) => Sha256::digest(seed.as_bytes()).into(),
+        None => *TEST_SEED,
+    };
+    let keys = generate_test_keys(&base_seed);
+    *TEST_KEYS.lock().unwrap() = Some(keys.clone());
+    info!("Test keys regenerated with {} seed", if seed.is_some() { "a custom" } else { "the default" });
+    keys
 }
 
 /// Get all test keys
@@ -73,14 +123,15 @@ pub fn get_public_key_to_user_id_map() -> HashMap<String, i64> {
     map
 }
 
-/// Generate deterministic test keys
-fn generate_test_keys() -> Vec<TestKeyPair> {
-    let mut keys = Vec::with_capacity(NUM_TEST_KEYS);
+/// Generate deterministic test keys from a base seed
+fn generate_test_keys(base_seed: &[u8; 32]) -> Vec<TestKeyPair> {
+    let count = test_key_count();
+    let mut keys = Vec::with_capacity(count);
     
-    for i in 0..NUM_TEST_KEYS {
-        // Create a deterministic seed based on the index
-        let mut seed = *TEST_SEED;
-        seed[31] = i as u8;
+    for i in 0..count {
+        // Create a deterministic seed based on the index (unchanged for the first 256 keys)
+        let mut seed = *base_seed;
+        seed[30..32].copy_from_slice(&(i as u16).to_be_bytes());
         
         // Generate the key pair
         let signing_key = SigningKey::from_bytes(&seed);
@@ -194,4 +245,62 @@ pub fn generate_auth_message(key_index: usize) -> Result<serde_json::Value, Stri
     });
     
     Ok(auth_message)
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user::CreateUserDto;
+    use crate::storage::memory::InMemoryUserStorage;
+    use crate::storage::UserStorage;
+
+    fn key_pairs(keys: &[TestKeyPair]) -> Vec<(i64, usize, String, String)> {
+        keys.iter()
+            .map(|key| (key.user_id, key.index, key.private_key.clone(), key.public_key.clone()))
+            .collect()
+    }
+
+    // One test, since the keys are process-wide and parallel tests would see each other's seeds
+    #[tokio::test]
+    async fn test_regenerated_keys_follow_the_seed() {
+        let defaults = get_test_keys();
+        let alice = regenerate_test_keys(Some("alice"));
+        assert_eq!(alice.len(), defaults.len());
+        assert_ne!(alice[0].public_key, defaults[0].public_key);
+        assert_eq!(get_test_key(0).unwrap().public_key, alice[0].public_key);
+        assert_eq!(key_pairs(&get_test_keys()), key_pairs(&alice));
+
+        // Deterministic per seed, distinct across seeds, for every key in the set
+        assert_eq!(key_pairs(&regenerate_test_keys(Some("alice"))), key_pairs(&alice));
+        let bob = regenerate_test_keys(Some("bob"));
+        assert!(bob.iter().zip(&alice).all(|(bob, alice)| bob.public_key != alice.public_key));
+        // Users and indices stay put; only the keys change
+        assert!(bob
+            .iter()
+            .zip(&defaults)
+            .all(|(bob, default)| (bob.user_id, bob.index) == (default.user_id, default.index)));
+
+        // Regenerated keys can be registered with the test users straight away
+        let storage = InMemoryUserStorage::new();
+        for key in &bob {
+            storage
+                .create_user(CreateUserDto {
+                    email: format!("{}@example.com", key.username),
+                    username: key.username.clone(),
+                    password: "password123".to_string(),
+                    wallet_address: None,
+                })
+                .await
+                .unwrap();
+        }
+        register_test_keys_with_users(&storage).await.unwrap();
+        for key in &bob {
+            assert_eq!(storage.find_user_by_public_key(&key.public_key).await.unwrap().unwrap().id, key.user_id);
+        }
+
+        // Omitting the seed restores every default key
+        let restored = regenerate_test_keys(None);
+        assert_eq!(key_pairs(&restored), key_pairs(&defaults));
+        assert_eq!(key_pairs(&get_test_keys()), key_pairs(&defaults));
+    }
+}
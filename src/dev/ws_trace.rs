@@ -0,0 +1,129 @@
+//! Structured tracing of WebSocket frames for debugging client/server exchanges
+//!
+//! Enabled per process with `WS_TRACE=true`. Events go to the `ws_trace` target with the
+//! session id, direction, frame type and a redacted, truncated payload. The module only exists
+//! in debug builds, so release builds carry no tracing code or checks.
+
+use serde_json::Value;
+use std::sync::OnceLock;
+use tracing::info;
+
+/// Longest payload logged per frame, in characters
+pub const MAX_TRACE_PAYLOAD: usize = 512;
+
+/// JSON fields whose values are replaced before logging
+const REDACTED_FIELDS: &[&str] =
+    &["signature", "public_key", "verified_keys", "nonce", "token", "reconnect_token", "ticket"];
+
+/// Whether `WS_TRACE` is set, read once per process
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        std::env::var("WS_TRACE")
+            .map(|value| matches!(value.as_str(), "1" | "true"))
+            .unwrap_or(false)
+    })
+}
+
+/// Which way a frame travelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Direction::Inbound => "inbound",
+            Direction::Outbound => "outbound",
+        }
+    }
+}
+
+/// Trace a text frame; JSON frames are typed by their `type` field and have key material redacted
+pub fn trace_text(session_id: &str, direction: Direction, text: &str) {
+    if !enabled() {
+        return;
+    }
+    let (frame_type, payload) = redact(text);
+    info!(
+        target: "ws_trace",
+        session_id,
+        direction = direction.as_str(),
+        frame_type = frame_type.as_str(),
+        len = text.len(),
+        payload = payload.as_str(),
+        "WebSocket frame"
+    );
+}
+
+/// Trace a frame whose payload is not logged, e.g. `binary` or `ping`
+pub fn trace_frame(session_id: &str, direction: Direction, frame_type: &str, len: usize) {
+    if !enabled() {
+        return;
+    }
+    info!(
+        target: "ws_trace",
+        session_id,
+        direction = direction.as_str(),
+        frame_type,
+        len,
+        "WebSocket frame"
+    );
+}
+
+/// Frame type and loggable payload of a text frame
+fn redact(text: &str) -> (String, String) {
+    match serde_json::from_str::<Value>(text) {
+        Ok(mut value) => {
+            let frame_type =
+                value.get("type").and_then(Value::as_str).unwrap_or("json").to_string();
+            redact_value(&mut value);
+            (frame_type, truncate(&value.to_string()))
+        }
+        Err(_) => ("text".to_string(), truncate(text)),
+    }
+}
+
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) {
+                    *value = Value::String("[redacted]".to_string());
+                } else {
+                    redact_value(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+/// Cut `text` to `MAX_TRACE_PAYLOAD` characters, marking the cut
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_TRACE_PAYLOAD) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_material_is_redacted_and_payload_truncated() {
+        let auth = r#"{"type":"Auth","data":{"public_key":"ab12","signature":"cd34","nonce":"n","timestamp":1}}"#;
+        let (frame_type, payload) = redact(auth);
+        assert_eq!(frame_type, "Auth");
+        assert!(!payload.contains("ab12") && !payload.contains("cd34"));
+        assert!(payload.contains("\"timestamp\":1"));
+
+        let (frame_type, payload) = redact(&"é".repeat(MAX_TRACE_PAYLOAD + 10));
+        assert_eq!(frame_type, "text");
+        assert_eq!(payload.chars().count(), MAX_TRACE_PAYLOAD + 1);
+    }
+}
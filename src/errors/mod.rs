@@ -1,6 +1,9 @@
-use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::http::header;
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, ResponseError};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -29,8 +32,44 @@ pub enum DashboardError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    /// Carries how long the client should wait, sent back as `Retry-After`
     #[error("Rate limit exceeded: {0}")]
-    RateLimit(String),
+    RateLimit(String, Duration),
+
+    #[error("Challenge required: {0}")]
+    ChallengeRequired(String),
+
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+
+    #[error("Service is read-only: {0}")]
+    ReadOnly(String),
+
+    #[error("Request timeout: {0}")]
+    RequestTimeout(String),
+
+    #[error("Database unavailable: {0}")]
+    DatabaseUnavailable(String),
+}
+
+/// How a failed storage call should be handled by operations that write to more than one
+/// backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageError {
+    /// The backend could not be reached or timed out; retrying may succeed
+    Transient,
+    /// The backend refused the operation or is in a bad state; retrying will not help
+    Permanent,
+}
+
+impl StorageError {
+    /// Classify an error returned by a storage call
+    pub fn classify(err: &DashboardError) -> Self {
+        match err {
+            DashboardError::Database(_) => StorageError::Transient,
+            _ => StorageError::Permanent,
+        }
+    }
 }
 
 impl DashboardError {
@@ -66,16 +105,63 @@ impl DashboardError {
         DashboardError::BadRequest(msg.into())
     }
 
-    pub fn rate_limit(msg: impl Into<String>) -> Self {
-        DashboardError::RateLimit(msg.into())
+    pub fn rate_limit(msg: impl Into<String>, retry_after: Duration) -> Self {
+        DashboardError::RateLimit(msg.into(), retry_after)
+    }
+
+    pub fn challenge_required(msg: impl Into<String>) -> Self {
+        DashboardError::ChallengeRequired(msg.into())
+    }
+
+    pub fn service_unavailable(msg: impl Into<String>) -> Self {
+        DashboardError::ServiceUnavailable(msg.into())
+    }
+
+    pub fn read_only(msg: impl Into<String>) -> Self {
+        DashboardError::ReadOnly(msg.into())
+    }
+
+    pub fn request_timeout(msg: impl Into<String>) -> Self {
+        DashboardError::RequestTimeout(msg.into())
+    }
+
+    pub fn database_unavailable(msg: impl Into<String>) -> Self {
+        DashboardError::DatabaseUnavailable(msg.into())
+    }
+
+    /// Machine-readable code for errors clients are expected to handle specially
+    pub fn error_code(&self) -> Option<&'static str> {
+        match self {
+            DashboardError::ReadOnly(_) => Some("SERVICE_READ_ONLY"),
+            DashboardError::DatabaseUnavailable(_) => Some("DATABASE_UNAVAILABLE"),
+            _ => None,
+        }
+    }
+
+    /// Whole seconds a rate-limited client should wait, rounded up and at least one
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            DashboardError::RateLimit(_, retry_after) => {
+                let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                Some(secs.max(1))
+            }
+            _ => None,
+        }
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct ErrorResponse {
-    status: String,
-    message: String,
-    code: u16,
+/// Content type of every error response
+pub const ERROR_CONTENT_TYPE: &str = "application/json; charset=utf-8";
+
+/// JSON body of every error response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub status: String,
+    pub message: String,
+    pub code: u16,
+    /// Machine-readable error code, e.g. `SERVICE_READ_ONLY`; omitted for most errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
 }
 
 impl ResponseError for DashboardError {
@@ -86,21 +172,39 @@ impl ResponseError for DashboardError {
             DashboardError::Validation(_) => StatusCode::BAD_REQUEST,
             DashboardError::NotFound(_) => StatusCode::NOT_FOUND,
             DashboardError::BadRequest(_) => StatusCode::BAD_REQUEST,
-            DashboardError::RateLimit(_) => StatusCode::TOO_MANY_REQUESTS,
+            DashboardError::RateLimit(..) => StatusCode::TOO_MANY_REQUESTS,
+            DashboardError::ChallengeRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            DashboardError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            DashboardError::ReadOnly(_) => StatusCode::SERVICE_UNAVAILABLE,
+            DashboardError::RequestTimeout(_) => StatusCode::REQUEST_TIMEOUT,
+            DashboardError::DatabaseUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
         let status = self.status_code();
-        HttpResponse::build(status).json(ErrorResponse {
+        let mut response = HttpResponse::build(status);
+        response.insert_header((header::CONTENT_TYPE, ERROR_CONTENT_TYPE));
+        if let Some(secs) = self.retry_after_secs() {
+            response.insert_header((header::RETRY_AFTER, secs.to_string()));
+        }
+        response.json(ErrorResponse {
             status: status.to_string(),
             message: self.to_string(),
             code: status.as_u16(),
+            error_code: self.error_code().map(str::to_string),
         })
     }
 }
 
+/// `JsonConfig` error handler that reports body-parse failures as a `400` `ErrorResponse`,
+/// matching every other API error
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let response = DashboardError::bad_request(format!("JSON error: {}", err)).error_response();
+    InternalError::from_response(err, response).into()
+}
+
 impl From<sqlx::Error> for DashboardError {
     fn from(err: sqlx::Error) -> Self {
         match err {
@@ -116,4 +220,67 @@ impl From<redis::RedisError> for DashboardError {
     }
 }
 
-pub type DashboardResult<T> = Result<T, DashboardError>; 
\ No newline at end of file
+pub type DashboardResult<T> = Result<T, DashboardError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, web, App};
+
+    #[actix_rt::test]
+    async fn test_bad_json_returns_error_response_shape() {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::JsonConfig::default().error_handler(json_error_handler))
+                .route(
+                    "/echo",
+                    web::post().to(|body: web::Json<serde_json::Value>| async move {
+                        HttpResponse::Ok().json(body.into_inner())
+                    }),
+                ),
+        )
+        .await;
+
+        let req = test::TestRequest::post()
+            .uri("/echo")
+            .insert_header(("content-type", "application/json"))
+            .set_payload("{not json")
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            ERROR_CONTENT_TYPE
+        );
+        let body: ErrorResponse = test::read_body_json(resp).await;
+        assert_eq!(body.code, 400);
+        assert_eq!(body.status, StatusCode::BAD_REQUEST.to_string());
+        assert!(body.message.contains("JSON error"));
+    }
+
+    #[actix_rt::test]
+    async fn test_read_only_error_is_unavailable_with_error_code() {
+        let resp = DashboardError::read_only("Writes are paused").error_response();
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let body = actix_web::body::to_bytes(resp.into_body()).await.unwrap();
+        let body: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.code, 503);
+        assert_eq!(body.error_code.as_deref(), Some("SERVICE_READ_ONLY"));
+    }
+
+    #[actix_rt::test]
+    async fn test_rate_limit_error_sets_retry_after() {
+        let resp = DashboardError::rate_limit("Slow down", Duration::from_millis(2_500)).error_response();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "3");
+
+        // Never tell a client to retry immediately
+        let resp = DashboardError::rate_limit("Slow down", Duration::ZERO).error_response();
+        assert_eq!(resp.headers().get(header::RETRY_AFTER).unwrap(), "1");
+
+        let resp = DashboardError::bad_request("nope").error_response();
+        assert!(resp.headers().get(header::RETRY_AFTER).is_none());
+    }
+}
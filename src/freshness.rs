@@ -0,0 +1,79 @@
+use thiserror::Error;
+
+/// Signed timestamps further than this from the server clock (about five years) are
+/// rejected outright, before the age and skew bounds are checked
+pub const MAX_TIMESTAMP_OFFSET_SECS: i64 = 5 * 365 * 24 * 60 * 60;
+
+/// Why a signed timestamp was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum FreshnessError {
+    /// Signed longer ago than the accepted age
+    #[error("Authentication message has expired: signed {age}s ago (limit {max_age}s)")]
+    TooOld { age: i64, max_age: i64 },
+    /// Ahead of the server clock by more than the accepted skew
+    #[error("Client clock ahead: timestamp is {ahead}s in the future (tolerance {skew}s)")]
+    TooFuture { ahead: i64, skew: i64 },
+    /// So far from the server clock that it can't be a real signing time
+    #[error("Timestamp is out of range")]
+    Implausible,
+}
+
+/// Check a signed unix timestamp against the server clock reading `now`, accepting it if it
+/// is at most `skew` seconds ahead and `max_age` seconds behind.
+///
+/// Shared by every signature-based auth path so they agree on what counts as a replay.
+pub fn check(timestamp: i64, now: i64, skew: i64, max_age: i64) -> Result<(), FreshnessError> {
+    // Reject absurd timestamps before any relative math so extreme values can't wrap
+    let offset = timestamp
+        .checked_sub(now)
+        .filter(|offset| offset.abs() <= MAX_TIMESTAMP_OFFSET_SECS)
+        .ok_or(FreshnessError::Implausible)?;
+
+    if offset > skew {
+        return Err(FreshnessError::TooFuture { ahead: offset, skew });
+    }
+    if -offset > max_age {
+        return Err(FreshnessError::TooOld { age: -offset, max_age });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    #[test]
+    fn test_timestamps_inside_the_window_are_fresh() {
+        assert_eq!(check(NOW, NOW, 60, 300), Ok(()));
+        assert_eq!(check(NOW - 300, NOW, 60, 300), Ok(()));
+        assert_eq!(check(NOW + 60, NOW, 60, 300), Ok(()));
+    }
+
+    #[test]
+    fn test_too_old() {
+        assert_eq!(
+            check(NOW - 301, NOW, 60, 300),
+            Err(FreshnessError::TooOld { age: 301, max_age: 300 })
+        );
+    }
+
+    #[test]
+    fn test_too_future() {
+        assert_eq!(
+            check(NOW + 61, NOW, 60, 300),
+            Err(FreshnessError::TooFuture { ahead: 61, skew: 60 })
+        );
+    }
+
+    #[test]
+    fn test_implausible() {
+        assert_eq!(check(i64::MIN, NOW, 60, 300), Err(FreshnessError::Implausible));
+        assert_eq!(check(i64::MAX, NOW, 60, i64::MAX), Err(FreshnessError::Implausible));
+        assert_eq!(
+            check(NOW - MAX_TIMESTAMP_OFFSET_SECS - 1, NOW, 60, i64::MAX),
+            Err(FreshnessError::Implausible)
+        );
+    }
+}
@@ -6,8 +6,11 @@ use std::path::Path;
 use crate::models::network::{NetworkConnection, NetworkStatus};
 use crate::models::user::{User, UserCredentials};
 
+/// Canonical dataset compiled into the binary, so tests don't depend on `assets/` or the CWD
+const EMBEDDED_GENESIS_DATA: &str = include_str!("../tests/fixtures/genesis_data.json");
+
 /// Comprehensive struct containing all genesis data for testing
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GenesisData {
     pub users: Vec<User>,
     pub user_credentials: Vec<UserCredentials>,
@@ -35,6 +38,19 @@ impl GenesisData {
         
         Ok(genesis_data)
     }
+
+    /// The canonical test dataset embedded at compile time.
+    ///
+    /// Unlike `load`, this never reads the filesystem, so every test run sees the same data
+    /// whatever the working directory or the current contents of `assets/genesis_data.json`.
+    pub fn embedded() -> Self {
+        serde_json::from_str(EMBEDDED_GENESIS_DATA).expect("embedded genesis data is valid JSON")
+    }
+
+    /// Start building custom genesis data, e.g. for a test needing a specific dataset
+    pub fn builder() -> GenesisBuilder {
+        GenesisBuilder::default()
+    }
     
     /// Load genesis data only in development environment
     pub fn load_if_dev() -> Result<Option<Self>> {
@@ -47,6 +63,56 @@ impl GenesisData {
     }
 }
 
+/// Builds `GenesisData` programmatically; rows are seeded in the order they were added
+#[derive(Debug, Clone, Default)]
+pub struct GenesisBuilder {
+    data: GenesisData,
+}
+
+impl GenesisBuilder {
+    /// Add a user, keeping its `id` when seeded
+    pub fn with_user(mut self, user: User) -> Self {
+        self.data.users.push(user);
+        self
+    }
+
+    /// Add credentials for a user
+    pub fn with_credentials(mut self, credentials: UserCredentials) -> Self {
+        self.data.user_credentials.push(credentials);
+        self
+    }
+
+    /// Add a network connection, keeping its `id` when seeded
+    pub fn with_network_connection(mut self, connection: NetworkConnection) -> Self {
+        self.data.network_connections.push(connection);
+        self
+    }
+
+    /// Add the status of a network connection
+    pub fn with_network_status(mut self, status: NetworkStatus) -> Self {
+        self.data.network_statuses.push(status);
+        self
+    }
+
+    /// Add a public key registered to a user
+    pub fn with_public_key(mut self, key: UserPublicKey) -> Self {
+        self.data.user_public_keys.push(key);
+        self
+    }
+
+    /// Finish building
+    pub fn build(self) -> GenesisData {
+        self.data
+    }
+}
+
+/// Extend existing data, e.g. `GenesisBuilder::from(GenesisData::embedded())`
+impl From<GenesisData> for GenesisBuilder {
+    fn from(data: GenesisData) -> Self {
+        Self { data }
+    }
+}
+
 /// Functions to seed the database with genesis data
 pub mod seed {
     use super::*;
@@ -81,8 +147,8 @@ pub mod seed {
         for user in users {
             sqlx::query!(
                 r#"
-                INSERT INTO users (id, email, username, wallet_address, created_at, last_active)
-                VALUES ($1, $2, $3, $4, $5, $6)
+                INSERT INTO users (id, email, username, wallet_address, created_at, last_active, status, rate_limit_tier)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
                 ON CONFLICT (id) DO NOTHING
                 "#,
                 user.id,
@@ -90,7 +156,9 @@ pub mod seed {
                 user.username,
                 user.wallet_address,
                 user.created_at,
-                user.last_active
+                user.last_active,
+                user.status as _,
+                user.rate_limit_tier as _
             )
             .execute(pool)
             .await?;
@@ -178,14 +246,17 @@ pub mod seed {
 /// Functions to seed in-memory storage for development
 pub mod memory_seed {
     use super::*;
-    use crate::storage::memory::InMemoryUserStorage;
+    use crate::storage::memory::{InMemoryNetworkStorage, InMemoryUserStorage};
     use crate::storage::UserStorage;
     use tracing::info;
     
     /// Seed in-memory storage with all genesis data
     pub async fn seed_storage(user_storage: &InMemoryUserStorage) -> Result<()> {
-        let genesis_data = GenesisData::load()?;
-        
+        seed_storage_with(user_storage, &GenesisData::load()?).await
+    }
+
+    /// Seed in-memory storage with the given data, e.g. `GenesisData::embedded()` in tests
+    pub async fn seed_storage_with(user_storage: &InMemoryUserStorage, genesis_data: &GenesisData) -> Result<()> {
         info!("Seeding in-memory storage with genesis data...");
         
         // Seed users
@@ -202,6 +273,40 @@ pub mod memory_seed {
         Ok(())
     }
     
+    /// Seed in-memory network storage with genesis connections and statuses
+    pub async fn seed_network_storage(network_storage: &InMemoryNetworkStorage) -> Result<()> {
+        seed_network_storage_with(network_storage, &GenesisData::load()?).await
+    }
+
+    /// Seed in-memory network storage with the given connections and statuses
+    pub async fn seed_network_storage_with(
+        network_storage: &InMemoryNetworkStorage,
+        genesis_data: &GenesisData,
+    ) -> Result<()> {
+        let connections_lock = network_storage.get_connections_map();
+        let mut connections_map = connections_lock.lock().map_err(|e| anyhow::anyhow!("Failed to lock connections map: {}", e))?;
+        
+        let next_id_lock = network_storage.get_next_id();
+        let mut next_id = next_id_lock.lock().map_err(|e| anyhow::anyhow!("Failed to lock next_id: {}", e))?;
+        
+        for conn in &genesis_data.network_connections {
+            connections_map.insert(conn.id, conn.clone());
+            if *next_id <= conn.id {
+                *next_id = conn.id + 1;
+            }
+        }
+        
+        let statuses_lock = network_storage.get_statuses_map();
+        let mut statuses_map = statuses_lock.lock().map_err(|e| anyhow::anyhow!("Failed to lock statuses map: {}", e))?;
+        
+        for status in &genesis_data.network_statuses {
+            statuses_map.insert(status.connection_id, status.clone());
+        }
+        
+        info!("Seeded {} network connections in memory", genesis_data.network_connections.len());
+        Ok(())
+    }
+    
     /// Seed users in in-memory storage
     async fn seed_users(storage: &InMemoryUserStorage, users: &[User]) -> Result<()> {
         for user in users {
@@ -245,13 +350,21 @@ pub mod memory_seed {
     /// Seed user public keys in in-memory storage
     async fn seed_user_public_keys(storage: &InMemoryUserStorage, keys: &[UserPublicKey]) -> Result<()> {
         for key in keys {
-            if key.revoked {
-                continue; // Skip revoked keys
-            }
-            
             // Store the public key using the built-in method
             storage.store_public_key(key.user_id, &key.public_key).await
                 .map_err(|e| anyhow::anyhow!("Failed to store public key: {}", e))?;
+            if key.revoked {
+                storage.revoke_public_key(key.user_id, &key.public_key).await
+                    .map_err(|e| anyhow::anyhow!("Failed to revoke public key: {}", e))?;
+            }
+            
+            // Preserve the genesis timestamps in the key metadata
+            let info_lock = storage.get_public_key_info_map();
+            let mut info_map = info_lock.lock().map_err(|e| anyhow::anyhow!("Failed to lock public key info map: {}", e))?;
+            if let Some(info) = info_map.get_mut(&key.public_key) {
+                info.created_at = key.created_at;
+                info.last_used = key.last_used;
+            }
         }
         
         info!("Seeded user public keys in memory");
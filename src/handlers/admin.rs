@@ -0,0 +1,250 @@
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, Responder};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::AuthConfig;
+use crate::errors::{DashboardError, DashboardResult};
+use crate::handlers::cursor::{CursorQuery, CursorSigner};
+use crate::handlers::extractors::{AdminUser, JsonBody, Pagination};
+use crate::handlers::response::{ApiResponse, PagedResponse};
+use crate::handlers::websocket::SessionServices;
+use crate::models::user::UserStatus;
+use crate::services::{
+    AuditEvent, KeyDenyList, MaintenanceMode, ReadOnlyMode, SessionRegistry, UserService, Webhooks,
+};
+use crate::storage::UserStorage;
+
+/// Request for updating the maintenance banner
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MaintenanceRequest {
+    /// Banner shown to clients; `null` or empty disables maintenance mode
+    pub message: Option<String>,
+}
+
+/// Request for impersonating a user
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImpersonateRequest {
+    /// Why support needs to act as the user, e.g. a ticket reference; recorded in the audit log
+    pub reason: String,
+}
+
+/// Request for toggling read-only mode
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadOnlyRequest {
+    /// Refuse writes while `true`
+    pub enabled: bool,
+}
+
+/// Request for changing a user's account status
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserStatusRequest {
+    /// New status: `active`, `suspended` or `pending_verification`
+    pub status: UserStatus,
+}
+
+/// Filters for the connection listing
+#[derive(Debug, Default, Deserialize)]
+pub struct ConnectionFilter {
+    /// Only sessions authenticated as this user
+    pub user_id: Option<i64>,
+}
+
+/// Filters for the user listing
+#[derive(Debug, Default, Deserialize)]
+pub struct UserFilter {
+    /// Only users whose email contains this text (case-insensitive)
+    pub email: Option<String>,
+}
+
+/// List live WebSocket connections on this instance, ordered by session ID
+pub async fn list_connections(
+    admin: AdminUser,
+    page: web::Query<CursorQuery>,
+    filter: web::Query<ConnectionFilter>,
+    registry: web::Data<SessionRegistry>,
+    cursors: web::Data<CursorSigner>,
+) -> DashboardResult<impl Responder> {
+    info!("Admin {} listing WebSocket connections", admin.user_id);
+
+    let limit = page.limit()?;
+    let filter_key = format!("user_id={:?}", filter.user_id);
+    let after = page
+        .cursor
+        .as_deref()
+        .map(|cursor| cursors.decode(cursor, &filter_key))
+        .transpose()?;
+
+    let mut connections: Vec<_> = registry
+        .list()
+        .into_iter()
+        .filter(|info| filter.user_id.map_or(true, |user_id| info.user_id == Some(user_id)))
+        .filter(|info| after.as_ref().map_or(true, |after| info.session_id > *after))
+        .collect();
+    connections.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+
+    Ok(PagedResponse::cursor(connections, limit, |last| cursors.encode(&last.session_id, &filter_key)).ok())
+}
+
+/// List users, ordered by ID
+pub async fn list_users<T: UserStorage>(
+    admin: AdminUser,
+    page: web::Query<CursorQuery>,
+    filter: web::Query<UserFilter>,
+    user_service: web::Data<UserService<T>>,
+    cursors: web::Data<CursorSigner>,
+) -> DashboardResult<impl Responder> {
+    info!("Admin {} listing users", admin.user_id);
+
+    let limit = page.limit()?;
+    let filter_key = format!("email={:?}", filter.email);
+    let after_id = match page.cursor.as_deref() {
+        Some(cursor) => Some(
+            cursors
+                .decode(cursor, &filter_key)?
+                .parse::<i64>()
+                .map_err(|_| DashboardError::validation("Invalid cursor"))?,
+        ),
+        None => None,
+    };
+
+    // Fetch one extra to learn whether another page exists
+    let users = user_service
+        .list_users(after_id, limit + 1, filter.email.as_deref())
+        .await?;
+
+    Ok(PagedResponse::cursor(users, limit, |last| cursors.encode(&last.id.to_string(), &filter_key)).ok())
+}
+
+/// Change a user's account status; suspending also ends all of the user's sessions
+pub async fn set_user_status<T: UserStorage>(
+    admin: AdminUser,
+    path: web::Path<i64>,
+    body: JsonBody<UserStatusRequest>,
+    user_service: web::Data<UserService<T>>,
+    sessions: web::Data<SessionServices>,
+) -> DashboardResult<impl Responder> {
+    let user_id = path.into_inner();
+    let status = body.into_inner().status;
+    let user = user_service.set_user_status(user_id, status).await?;
+
+    let mut disconnected = 0;
+    if status.is_suspended() {
+        sessions.reconnect_tokens.revoke_user(user_id);
+        sessions.ws_tickets.revoke_user(user_id);
+        disconnected = sessions.broadcast.disconnect_user(user_id, "Account suspended");
+    }
+    info!(
+        "Admin {} set status of user {} to {:?}, closed {} WebSocket sessions",
+        admin.user_id, user_id, status, disconnected
+    );
+
+    Ok(ApiResponse::ok(serde_json::json!({
+        "user": user,
+        "disconnected_sessions": disconnected
+    })))
+}
+
+/// Mint a short-lived token for acting as a user while reproducing a support issue
+pub async fn impersonate_user<T: UserStorage>(
+    admin: AdminUser,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: JsonBody<ImpersonateRequest>,
+    user_service: web::Data<UserService<T>>,
+    sessions: web::Data<SessionServices>,
+) -> DashboardResult<impl Responder> {
+    let user_id = path.into_inner();
+    let reason = body.into_inner().reason.trim().to_string();
+    if reason.is_empty() {
+        return Err(DashboardError::validation("reason: must not be empty"));
+    }
+
+    let impersonation = user_service.impersonate(admin.user_id, user_id).await?;
+    sessions.audit.record(AuditEvent::ImpersonationStarted {
+        admin_id: admin.user_id,
+        user_id,
+        reason,
+        expires_at: impersonation.expires_at,
+        client_ip: req.connection_info().realip_remote_addr().unwrap_or("unknown").to_owned(),
+    });
+
+    Ok(ApiResponse::ok(impersonation))
+}
+
+/// Set or clear the maintenance banner without a restart
+pub async fn set_maintenance(
+    admin: AdminUser,
+    body: JsonBody<MaintenanceRequest>,
+    maintenance: web::Data<MaintenanceMode>,
+) -> DashboardResult<impl Responder> {
+    maintenance.set_message(body.into_inner().message);
+    info!(
+        "Admin {} set maintenance mode: {:?}",
+        admin.user_id,
+        maintenance.message()
+    );
+
+    Ok(ApiResponse::ok(serde_json::json!({
+        "maintenance": maintenance.is_active(),
+        "message": maintenance.message()
+    })))
+}
+
+/// Turn read-only mode on or off without a restart
+pub async fn set_read_only(
+    admin: AdminUser,
+    body: JsonBody<ReadOnlyRequest>,
+    read_only: web::Data<ReadOnlyMode>,
+) -> DashboardResult<impl Responder> {
+    read_only.set_enabled(body.enabled);
+    info!("Admin {} set read-only mode: {}", admin.user_id, body.enabled);
+
+    Ok(ApiResponse::ok(serde_json::json!({ "read_only": read_only.is_enabled() })))
+}
+
+/// Recent webhook deliveries on this instance, newest first
+pub async fn list_webhook_deliveries(
+    admin: AdminUser,
+    pagination: Pagination,
+    webhooks: web::Data<Webhooks>,
+) -> DashboardResult<impl Responder> {
+    info!("Admin {} listing webhook deliveries", admin.user_id);
+
+    let mut response = PagedResponse::paginate(webhooks.recent_deliveries(), &pagination).into_api_response();
+    response.meta["enabled"] = serde_json::json!(webhooks.is_enabled());
+    Ok(response.respond(StatusCode::OK))
+}
+
+/// Re-read the public key deny-list from `DENIED_PUBLIC_KEYS` and `DENIED_PUBLIC_KEYS_FILE`
+pub async fn reload_denied_keys(
+    admin: AdminUser,
+    deny_list: web::Data<KeyDenyList>,
+) -> DashboardResult<impl Responder> {
+    let keys = AuthConfig::load_denied_public_keys()
+        .map_err(|e| DashboardError::internal_server(e.to_string()))?;
+    let count = deny_list.replace(keys);
+    info!("Admin {} reloaded the key deny-list: {} keys", admin.user_id, count);
+
+    Ok(ApiResponse::ok(serde_json::json!({ "denied_keys": count })))
+}
+
+/// Permanently delete a user's public key, including its revocation history
+pub async fn delete_public_key<T: UserStorage>(
+    admin: AdminUser,
+    path: web::Path<(i64, String)>,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    let (user_id, public_key) = path.into_inner();
+
+    if !user_service.delete_public_key(user_id, &public_key).await? {
+        return Err(DashboardError::not_found("Public key not associated with this user"));
+    }
+    info!("Admin {} deleted a public key of user {}", admin.user_id, user_id);
+
+    Ok(ApiResponse::ok(serde_json::json!({
+        "user_id": user_id,
+        "public_key": public_key,
+        "deleted": true
+    })))
+}
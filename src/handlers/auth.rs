@@ -1,9 +1,15 @@
 use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::time::Instant;
 use tracing::{error, info};
 
-use crate::errors::DashboardResult;
-use crate::services::UserService;
+use crate::affinity::AffinitySigner;
+use crate::errors::{DashboardError, DashboardResult};
+use crate::handlers::extractors::{bearer_token, AuthenticatedUser, JsonBody};
+use crate::handlers::response::ApiResponse;
+use crate::services::login_risk::CHALLENGE_TOKEN_HEADER;
+use crate::services::{AuthChallenges, UserService, WsTickets};
 use crate::storage::UserStorage;
 
 /// Login request
@@ -18,7 +24,7 @@ pub struct LoginRequest {
 /// Login handler
 pub async fn login<T: UserStorage>(
     req: HttpRequest,
-    login_data: web::Json<LoginRequest>,
+    login_data: JsonBody<LoginRequest>,
     user_service: web::Data<UserService<T>>,
 ) -> DashboardResult<impl Responder> {
     let ip = req
@@ -34,17 +40,122 @@ pub async fn login<T: UserStorage>(
         .unwrap_or("unknown")
         .to_owned();
     
+    let challenge_token = req
+        .headers()
+        .get(CHALLENGE_TOKEN_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_owned);
+    
     info!("Login attempt for user: {}", login_data.email);
     
     let login_response = user_service
-        .login(
+        .login_with_challenge(
             &login_data.email,
             &login_data.password,
             &ip,
             &user_agent,
+            challenge_token.as_deref(),
         )
         .await?;
     
     info!("Login successful for user: {}", login_response.user.id);
-    Ok(HttpResponse::Ok().json(login_response))
-} 
\ No newline at end of file
+    Ok(with_affinity(&req, ApiResponse::ok(login_response)))
+}
+
+/// Exchange the `Bearer` token for a fresh one on the same login session
+pub async fn refresh_token<T: UserStorage>(
+    req: HttpRequest,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    let token = bearer_token(&req).ok_or_else(|| DashboardError::authentication("Missing bearer token"))?;
+    let refreshed = user_service.refresh_token(&token).await?;
+
+    info!("Token refreshed for user: {}", refreshed.user.id);
+    Ok(with_affinity(&req, ApiResponse::ok(refreshed)))
+}
+
+/// Point the client at this instance with a signed affinity hint, when hints are enabled
+fn with_affinity(req: &HttpRequest, mut response: HttpResponse) -> HttpResponse {
+    if let Some(signer) = req.app_data::<web::Data<AffinitySigner>>() {
+        signer.attach(&mut response, Utc::now().timestamp());
+    }
+    response
+}
+
+/// Email verification request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyEmailRequest {
+    /// Token delivered to the user's email address
+    pub token: String,
+}
+
+/// Verification email resend request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResendVerificationRequest {
+    /// Email address the account was registered with
+    pub email: String,
+}
+
+/// Confirm a registration email address and activate the account
+pub async fn verify_email<T: UserStorage>(
+    body: JsonBody<VerifyEmailRequest>,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    let user = user_service.verify_email(&body.token).await?;
+    Ok(ApiResponse::ok(user))
+}
+
+/// Send a new verification token to a pending account
+pub async fn resend_verification<T: UserStorage>(
+    body: JsonBody<ResendVerificationRequest>,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    user_service.resend_verification(&body.email).await?;
+    Ok(ApiResponse::ok(serde_json::json!({ "sent": true })))
+}
+
+/// Response to a challenge request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuthChallengeResponse {
+    /// Nonce to sign in the WebSocket `Auth` message
+    pub nonce: String,
+    /// Seconds until the challenge expires
+    pub expires_in: u64,
+}
+
+/// Issue a single-use nonce for WebSocket signature auth
+pub async fn issue_auth_challenge(challenges: web::Data<AuthChallenges>) -> DashboardResult<impl Responder> {
+    let nonce = challenges
+        .issue(Instant::now())
+        .ok_or_else(|| DashboardError::internal_server("Failed to issue challenge"))?;
+
+    Ok(ApiResponse::ok(AuthChallengeResponse {
+        nonce,
+        expires_in: challenges.ttl().as_secs(),
+    }))
+}
+
+/// Response to a WebSocket ticket request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WsTicketResponse {
+    /// Single-use ticket to pass as `?ticket=` on the WebSocket handshake
+    pub ticket: String,
+    /// Seconds until the ticket expires
+    pub expires_in: u64,
+}
+
+/// Mint a short-lived WebSocket ticket for the authenticated user
+pub async fn issue_ws_ticket(
+    user: AuthenticatedUser,
+    tickets: web::Data<WsTickets>,
+) -> DashboardResult<impl Responder> {
+    let ticket = tickets
+        .issue(user.user_id, Instant::now())
+        .ok_or_else(|| DashboardError::internal_server("Failed to issue WebSocket ticket"))?;
+
+    info!("Issued WebSocket ticket for user {}", user.user_id);
+    Ok(ApiResponse::created(WsTicketResponse {
+        ticket,
+        expires_in: tickets.ttl().as_secs(),
+    }))
+}
@@ -0,0 +1,157 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::errors::{DashboardError, DashboardResult};
+use crate::handlers::extractors::MAX_PAGE_LIMIT;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Page size for cursor listings when `limit` is omitted
+pub const DEFAULT_CURSOR_PAGE_LIMIT: usize = 50;
+
+/// Domain separator so cursor MACs can't be confused with other uses of the secret
+const CURSOR_CONTEXT: &[u8] = b"list-cursor:v1:";
+
+/// `?cursor=&limit=` query parameters for cursor-paginated listings
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CursorQuery {
+    /// Opaque cursor from the previous page's `meta.next_cursor`
+    pub cursor: Option<String>,
+    /// Maximum number of items to return (1..=`MAX_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+}
+
+impl CursorQuery {
+    /// Requested page size, validated and defaulted
+    pub fn limit(&self) -> DashboardResult<usize> {
+        match self.limit {
+            None => Ok(DEFAULT_CURSOR_PAGE_LIMIT),
+            Some(limit) if limit == 0 || limit > MAX_PAGE_LIMIT => Err(DashboardError::validation(
+                format!("limit must be between 1 and {}", MAX_PAGE_LIMIT),
+            )),
+            Some(limit) => Ok(limit),
+        }
+    }
+}
+
+/// Signed cursor contents: where the previous page ended and which filters produced it
+#[derive(Serialize, Deserialize)]
+struct CursorPayload {
+    last_id: String,
+    filter: String,
+}
+
+/// Issues and verifies opaque, HMAC-signed pagination cursors.
+///
+/// A cursor is bound to the filters of the listing that issued it, so clients can neither
+/// forge a starting id nor replay a cursor against a different filter.
+#[derive(Clone)]
+pub struct CursorSigner {
+    key: Vec<u8>,
+}
+
+impl CursorSigner {
+    /// Create a signer keyed with the server secret
+    pub fn new(secret: &str) -> Self {
+        Self {
+            key: secret.as_bytes().to_vec(),
+        }
+    }
+
+    /// Encode a cursor pointing after `last_id` for a listing with the given filter string
+    pub fn encode(&self, last_id: &str, filter: &str) -> String {
+        let payload = serde_json::to_vec(&CursorPayload {
+            last_id: last_id.to_string(),
+            filter: filter_hash(filter),
+        })
+        .expect("cursor payload serializes");
+        format!("{}.{}", hex::encode(&payload), hex::encode(self.sign(&payload)))
+    }
+
+    /// Verify a cursor and return the id it points after.
+    ///
+    /// Malformed, tampered or filter-mismatched cursors are `Err(Validation)`.
+    pub fn decode(&self, cursor: &str, filter: &str) -> DashboardResult<String> {
+        let invalid = || DashboardError::validation("Invalid cursor");
+
+        let (payload_hex, mac_hex) = cursor.split_once('.').ok_or_else(invalid)?;
+        let payload = hex::decode(payload_hex).map_err(|_| invalid())?;
+        let mac = hex::decode(mac_hex).map_err(|_| invalid())?;
+
+        let mut verifier = self.mac();
+        verifier.update(&payload);
+        verifier.verify_slice(&mac).map_err(|_| invalid())?;
+
+        let payload: CursorPayload = serde_json::from_slice(&payload).map_err(|_| invalid())?;
+        if payload.filter != filter_hash(filter) {
+            return Err(DashboardError::validation("Cursor does not match the current filters"));
+        }
+        Ok(payload.last_id)
+    }
+
+    fn sign(&self, payload: &[u8]) -> Vec<u8> {
+        let mut mac = self.mac();
+        mac.update(payload);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        let mut mac = HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(CURSOR_CONTEXT);
+        mac
+    }
+}
+
+/// Short digest of a listing's filter string
+fn filter_hash(filter: &str) -> String {
+    hex::encode(&Sha256::digest(filter.as_bytes())[..16])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let signer = CursorSigner::new("secret");
+        let cursor = signer.encode("42", "user_id=7");
+        assert_eq!(signer.decode(&cursor, "user_id=7").unwrap(), "42");
+    }
+
+    #[test]
+    fn test_tampered_cursor_is_rejected() {
+        let signer = CursorSigner::new("secret");
+        let cursor = signer.encode("42", "");
+
+        // Swap in a payload pointing at a different id but keep the original MAC
+        let (_, mac) = cursor.split_once('.').unwrap();
+        let forged_payload = hex::encode(br#"{"last_id":"1","filter":""}"#);
+        let forged = format!("{}.{}", forged_payload, mac);
+        assert!(matches!(signer.decode(&forged, ""), Err(DashboardError::Validation(_))));
+
+        // A cursor signed with another secret is rejected too
+        let foreign = CursorSigner::new("other").encode("42", "");
+        assert!(matches!(signer.decode(&foreign, ""), Err(DashboardError::Validation(_))));
+
+        for garbage in ["", "abc", "zz.zz", "."] {
+            assert!(matches!(signer.decode(garbage, ""), Err(DashboardError::Validation(_))));
+        }
+    }
+
+    #[test]
+    fn test_cursor_bound_to_filter() {
+        let signer = CursorSigner::new("secret");
+        let cursor = signer.encode("42", "user_id=7");
+        assert!(matches!(signer.decode(&cursor, "user_id=8"), Err(DashboardError::Validation(_))));
+    }
+
+    #[test]
+    fn test_limit_validation() {
+        assert_eq!(CursorQuery::default().limit().unwrap(), DEFAULT_CURSOR_PAGE_LIMIT);
+        let query = |limit| CursorQuery { cursor: None, limit: Some(limit) };
+        assert!(query(0).limit().is_err());
+        assert!(query(MAX_PAGE_LIMIT + 1).limit().is_err());
+        assert_eq!(query(10).limit().unwrap(), 10);
+    }
+}
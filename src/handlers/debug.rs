@@ -0,0 +1,171 @@
+use actix_web::{web, Responder};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::config::Config;
+use crate::errors::{DashboardError, DashboardResult};
+use crate::handlers::extractors::{AdminUser, JsonBody};
+use crate::handlers::response::ApiResponse;
+use crate::services::SignatureService;
+use crate::storage::UserStorage;
+
+/// Encoding of the public key and signature in a diagnostic request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureEncoding {
+    /// Lowercase or uppercase hex, as used by WebSocket auth
+    #[default]
+    Hex,
+    /// Standard base64 with padding
+    Base64,
+}
+
+/// Request for checking a signature without authenticating
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifySignatureRequest {
+    /// Public key in `encoding`
+    pub public_key: String,
+    /// Exact message that was signed
+    pub message: String,
+    /// Signature in `encoding`
+    pub signature: String,
+    /// Encoding of `public_key` and `signature`; defaults to hex
+    #[serde(default)]
+    pub encoding: SignatureEncoding,
+    /// Signature algorithm; only `ed25519` is supported
+    #[serde(default = "default_algorithm")]
+    pub algorithm: String,
+}
+
+fn default_algorithm() -> String {
+    "ed25519".to_string()
+}
+
+/// Outcome of a diagnostic signature check
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct VerifySignatureResponse {
+    /// Whether the signature verifies
+    pub valid: bool,
+    /// Why the signature was rejected; `null` when valid
+    pub reason: Option<String>,
+}
+
+impl VerifySignatureResponse {
+    fn invalid(reason: impl Into<String>) -> Self {
+        Self {
+            valid: false,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+/// Re-encode a base64 field as hex, the form `SignatureService` expects
+fn to_hex(value: &str, encoding: SignatureEncoding, field: &str) -> Result<String, String> {
+    match encoding {
+        SignatureEncoding::Hex => Ok(value.to_string()),
+        SignatureEncoding::Base64 => base64::engine::general_purpose::STANDARD
+            .decode(value.trim())
+            .map(hex::encode)
+            .map_err(|e| format!("Invalid {} base64: {}", field, e)),
+    }
+}
+
+/// Check a signature the way WebSocket auth would, reporting why it fails instead of erroring
+pub fn check_signature<T: UserStorage>(
+    signature_service: &SignatureService<T>,
+    request: &VerifySignatureRequest,
+) -> VerifySignatureResponse {
+    if !request.algorithm.eq_ignore_ascii_case("ed25519") {
+        return VerifySignatureResponse::invalid(format!(
+            "Unsupported algorithm '{}', only ed25519 is supported",
+            request.algorithm
+        ));
+    }
+    let decoded = to_hex(&request.public_key, request.encoding, "public key").and_then(|public_key| {
+        to_hex(&request.signature, request.encoding, "signature").map(|signature| (public_key, signature))
+    });
+    let (public_key, signature) = match decoded {
+        Ok(decoded) => decoded,
+        Err(reason) => return VerifySignatureResponse::invalid(reason),
+    };
+
+    match signature_service.verify_signature(&public_key, &request.message, &signature) {
+        Ok(true) => VerifySignatureResponse {
+            valid: true,
+            reason: None,
+        },
+        Ok(false) => VerifySignatureResponse::invalid("Signature does not match the message and public key"),
+        Err(e) => VerifySignatureResponse::invalid(e.to_string()),
+    }
+}
+
+/// Check whether a signature would verify, without a user lookup or counting as a login.
+///
+/// Available to anyone in development; elsewhere it requires an admin.
+pub async fn verify_signature<T: UserStorage>(
+    admin: Option<AdminUser>,
+    config: web::Data<Config>,
+    body: JsonBody<VerifySignatureRequest>,
+    signature_service: web::Data<SignatureService<T>>,
+) -> DashboardResult<impl Responder> {
+    if config.server.environment != "development" && admin.is_none() {
+        return Err(DashboardError::authorization(
+            "Debug endpoints require development mode or admin privileges",
+        ));
+    }
+    if let Some(admin) = &admin {
+        info!("Admin {} checking a signature via the debug endpoint", admin.user_id);
+    }
+
+    Ok(ApiResponse::ok(check_signature(&signature_service, &body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::InMemoryUserStorage;
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::sync::Arc;
+
+    fn request(public_key: String, signature: String, encoding: SignatureEncoding) -> VerifySignatureRequest {
+        VerifySignatureRequest {
+            public_key,
+            message: "1700000000:abcdefgh".to_string(),
+            signature,
+            encoding,
+            algorithm: default_algorithm(),
+        }
+    }
+
+    #[test]
+    fn test_check_signature_reports_validity_and_reason() {
+        let service = SignatureService::new(Arc::new(InMemoryUserStorage::new()));
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let public_key = key.verifying_key().to_bytes();
+        let signature = key.sign(b"1700000000:abcdefgh").to_bytes();
+        let base64 = base64::engine::general_purpose::STANDARD;
+
+        let hex_request = request(hex::encode(public_key), hex::encode(signature), SignatureEncoding::Hex);
+        assert_eq!(
+            check_signature(&service, &hex_request),
+            VerifySignatureResponse { valid: true, reason: None }
+        );
+
+        let base64_request = request(base64.encode(public_key), base64.encode(signature), SignatureEncoding::Base64);
+        assert!(check_signature(&service, &base64_request).valid);
+
+        let mut tampered = request(hex::encode(public_key), hex::encode(signature), SignatureEncoding::Hex);
+        tampered.message.push('x');
+        let response = check_signature(&service, &tampered);
+        assert!(!response.valid);
+        assert!(response.reason.unwrap().contains("does not match"));
+
+        let malformed = request("zz".to_string(), hex::encode(signature), SignatureEncoding::Hex);
+        assert!(check_signature(&service, &malformed).reason.unwrap().contains("public key"));
+
+        let mut unsupported = request(hex::encode(public_key), hex::encode(signature), SignatureEncoding::Hex);
+        unsupported.algorithm = "secp256k1".to_string();
+        assert!(check_signature(&service, &unsupported).reason.unwrap().contains("Unsupported algorithm"));
+    }
+}
@@ -0,0 +1,364 @@
+use actix_web::dev::Payload;
+use actix_web::http::Method;
+use actix_web::{web, FromRequest, HttpRequest};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::errors::DashboardError;
+use crate::handlers::websocket::SessionServices;
+use crate::services::{AuditEvent, AuditSink, LogAuditSink, UserService};
+use crate::storage::AppUserStorage;
+
+/// A user authenticated via a `Bearer` JWT in the `Authorization` header
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    /// ID of the authenticated user
+    pub user_id: i64,
+    /// Whether the user is listed in `ADMIN_USER_IDS`
+    pub is_admin: bool,
+    /// Roles carried by the token
+    pub roles: Vec<String>,
+    /// Scopes carried by the token
+    pub scopes: Vec<String>,
+    /// Admin acting as this user through an impersonation token
+    pub impersonated_by: Option<i64>,
+}
+
+impl AuthenticatedUser {
+    /// Whether the token grants `scope`; a coarse check that skips storage
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// Require `scope` in the token
+    pub fn require_scope(&self, scope: &str) -> Result<(), DashboardError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(DashboardError::authorization(format!("Missing required scope: {}", scope)))
+        }
+    }
+
+    /// Refuse `action` when an admin is acting as the user, e.g. for credential changes and
+    /// account deletion
+    pub fn ensure_not_impersonated(&self, action: &str) -> Result<(), DashboardError> {
+        match self.impersonated_by {
+            Some(_) => Err(DashboardError::authorization(format!(
+                "Not allowed while impersonating: {}",
+                action
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Ensure the user is acting on their own resources or is an admin
+    pub fn ensure_self_or_admin(&self, user_id: i64) -> Result<(), DashboardError> {
+        if self.user_id == user_id || self.is_admin {
+            Ok(())
+        } else {
+            Err(DashboardError::authorization("Not allowed to access another user's resources"))
+        }
+    }
+}
+
+/// Scope an admin token needs for a request: `admin:read` for safe methods, else `admin:write`
+fn admin_scope(method: &Method) -> &'static str {
+    if method.is_safe() {
+        "admin:read"
+    } else {
+        "admin:write"
+    }
+}
+
+/// An authenticated user whose ID is listed in `ADMIN_USER_IDS` and whose token carries the
+/// admin scope for the request
+#[derive(Debug, Clone, Copy)]
+pub struct AdminUser {
+    /// ID of the authenticated admin
+    pub user_id: i64,
+}
+
+/// Extract the bearer token from the `Authorization` header
+pub(crate) fn bearer_token(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_owned())
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = DashboardError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let user_service = req
+            .app_data::<web::Data<UserService<AppUserStorage>>>()
+            .cloned();
+        let config = req.app_data::<web::Data<Config>>().cloned();
+        let audit: Arc<dyn AuditSink> = match req.app_data::<web::Data<SessionServices>>() {
+            Some(services) => services.audit.clone(),
+            None => Arc::new(LogAuditSink),
+        };
+        let (method, path) = (req.method().to_string(), req.path().to_string());
+        let token = bearer_token(req);
+
+        Box::pin(async move {
+            let user_service = user_service
+                .ok_or_else(|| DashboardError::internal_server("User service not configured"))?;
+            let config =
+                config.ok_or_else(|| DashboardError::internal_server("Config not configured"))?;
+            let token =
+                token.ok_or_else(|| DashboardError::authentication("Missing bearer token"))?;
+
+            let claims = user_service.verify_token_claims(&token).await?;
+            // Every request made under impersonation is attributed to the admin
+            if let Some(admin_id) = claims.impersonated_by {
+                audit.record(AuditEvent::ImpersonatedRequest {
+                    admin_id,
+                    user_id: claims.user_id,
+                    method,
+                    path,
+                });
+            }
+            Ok(AuthenticatedUser {
+                user_id: claims.user_id,
+                // Admin status always comes from config, not the token, and is never lent out
+                is_admin: claims.impersonated_by.is_none()
+                    && config.auth.admin_user_ids.contains(&claims.user_id),
+                roles: claims.roles,
+                scopes: claims.scopes,
+                impersonated_by: claims.impersonated_by,
+            })
+        })
+    }
+}
+
+impl FromRequest for AdminUser {
+    type Error = DashboardError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let config = req.app_data::<web::Data<Config>>().cloned();
+        let scope = admin_scope(req.method());
+        let user = AuthenticatedUser::from_request(req, payload);
+
+        Box::pin(async move {
+            if let Some(config) = config {
+                config.require_feature("admin_api")?;
+            }
+            let user = user.await?;
+
+            if !user.is_admin {
+                return Err(DashboardError::authorization("Admin privileges required"));
+            }
+            // Tokens issued before the user became an admin lack the scope until login or refresh
+            user.require_scope(scope)?;
+            Ok(AdminUser { user_id: user.user_id })
+        })
+    }
+}
+
+/// A JSON request body, checked against the configured `UnknownFieldPolicy` before it is
+/// deserialized into `T`
+#[derive(Debug)]
+pub struct JsonBody<T>(pub T);
+
+impl<T> JsonBody<T> {
+    /// Unwrap the deserialized body
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for JsonBody<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned + 'static> FromRequest for JsonBody<T> {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let policy = req
+            .app_data::<web::Data<Config>>()
+            .map(|config| config.server.unknown_json_fields)
+            .unwrap_or_default();
+        // Size limits and malformed bodies are handled by the app's `JsonConfig`
+        let body = web::Json::<serde_json::Value>::from_request(req, payload);
+
+        Box::pin(async move {
+            let body = body.await?.into_inner();
+            policy
+                .check::<T>(&body)
+                .map_err(|e| DashboardError::bad_request(format!("JSON error: {}", e)))?;
+            serde_json::from_value(body)
+                .map(JsonBody)
+                .map_err(|e| DashboardError::bad_request(format!("JSON error: {}", e)).into())
+        })
+    }
+}
+
+/// Largest page size accepted by `Pagination`
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+/// `?limit=&offset=` query parameters; a missing `limit` means "everything from `offset`"
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Pagination {
+    /// Maximum number of items to return (1..=`MAX_PAGE_LIMIT`)
+    pub limit: Option<usize>,
+    /// Number of items to skip
+    pub offset: Option<usize>,
+}
+
+impl Pagination {
+    /// Check the bounds of the requested page
+    pub fn validate(&self) -> Result<(), DashboardError> {
+        match self.limit {
+            Some(limit) if limit == 0 || limit > MAX_PAGE_LIMIT => Err(DashboardError::validation(
+                format!("limit must be between 1 and {}", MAX_PAGE_LIMIT),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Select the requested page from an ordered sequence
+    pub fn apply<T>(&self, items: impl IntoIterator<Item = T>) -> Vec<T> {
+        items
+            .into_iter()
+            .skip(self.offset.unwrap_or(0))
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+impl FromRequest for Pagination {
+    type Error = DashboardError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let pagination = web::Query::<Pagination>::from_query(req.query_string())
+            .map(web::Query::into_inner)
+            .map_err(|e| DashboardError::validation(format!("Invalid pagination parameters: {}", e)))
+            .and_then(|pagination| pagination.validate().map(|_| pagination));
+        ready(pagination)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json_case::UnknownFieldPolicy;
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+    use actix_web::test::TestRequest;
+    use serde_json::json;
+
+    async fn extract(query: &str) -> Result<Pagination, DashboardError> {
+        let req = TestRequest::with_uri(&format!("/dev/test-keys?{}", query)).to_http_request();
+        Pagination::extract(&req).await
+    }
+
+    #[actix_rt::test]
+    async fn test_pagination_defaults_to_everything() {
+        let pagination = extract("").await.unwrap();
+        assert_eq!(pagination.apply(0..5), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[actix_rt::test]
+    async fn test_pagination_limit_and_offset() {
+        let pagination = extract("limit=2&offset=1&public_only=true").await.unwrap();
+        assert_eq!(pagination.apply(0..5), vec![1, 2]);
+
+        let pagination = extract("offset=10").await.unwrap();
+        assert!(pagination.apply(0..5).is_empty());
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ReadOnlyBody {
+        enabled: bool,
+    }
+
+    async fn extract_body(
+        policy: UnknownFieldPolicy,
+        body: serde_json::Value,
+    ) -> Result<JsonBody<ReadOnlyBody>, actix_web::Error> {
+        let mut config = Config::from_env().unwrap();
+        config.server.unknown_json_fields = policy;
+        let (req, mut payload) = TestRequest::post()
+            .app_data(web::Data::new(config))
+            .set_json(body)
+            .to_http_parts();
+        JsonBody::<ReadOnlyBody>::from_request(&req, &mut payload).await
+    }
+
+    #[actix_rt::test]
+    async fn test_json_body_rejects_unknown_fields_when_strict() {
+        let err = extract_body(UnknownFieldPolicy::Reject, json!({"enabled": true, "enabeld": false}))
+            .await
+            .unwrap_err();
+        let resp = err.error_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(err.to_string().contains("unknown field `enabeld`"), "{}", err);
+
+        let body = extract_body(UnknownFieldPolicy::Reject, json!({"enabled": true})).await.unwrap();
+        assert!(body.enabled);
+    }
+
+    #[actix_rt::test]
+    async fn test_json_body_ignores_unknown_fields_when_lenient() {
+        let body =
+            extract_body(UnknownFieldPolicy::Ignore, json!({"enabled": true, "client": "cli/1.2"}))
+                .await
+                .unwrap();
+        assert!(body.into_inner().enabled);
+    }
+
+    #[test]
+    fn test_only_the_user_themself_or_an_admin_may_act_on_their_resources() {
+        let user = |user_id: i64, is_admin: bool| AuthenticatedUser {
+            user_id,
+            is_admin,
+            roles: Vec::new(),
+            scopes: Vec::new(),
+            impersonated_by: None,
+        };
+
+        assert!(user(1, false).ensure_self_or_admin(1).is_ok());
+        let err = user(2, false).ensure_self_or_admin(1).unwrap_err();
+        assert_eq!(err.error_response().status(), StatusCode::FORBIDDEN);
+        assert!(user(2, true).ensure_self_or_admin(1).is_ok());
+    }
+
+    #[test]
+    fn test_admin_requests_need_the_scope_matching_their_method() {
+        let admin = AuthenticatedUser {
+            user_id: 1,
+            is_admin: true,
+            roles: vec!["user".to_string(), "admin".to_string()],
+            scopes: vec!["admin:read".to_string()],
+            impersonated_by: None,
+        };
+
+        assert!(admin.require_scope(admin_scope(&Method::GET)).is_ok());
+        for method in [Method::POST, Method::PUT, Method::DELETE] {
+            let err = admin.require_scope(admin_scope(&method)).unwrap_err();
+            assert_eq!(err.error_response().status(), StatusCode::FORBIDDEN);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_pagination_rejects_bad_limits() {
+        assert!(extract("limit=0").await.is_err());
+        assert!(extract(&format!("limit={}", MAX_PAGE_LIMIT + 1)).await.is_err());
+        assert!(extract("limit=abc").await.is_err());
+    }
+}
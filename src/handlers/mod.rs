@@ -1,6 +1,11 @@
 pub mod websocket;
 pub mod auth;
 pub mod user;
-// pub mod network;
+pub mod admin;
+pub mod extractors;
+pub mod network;
+pub mod response;
+pub mod cursor;
+pub mod debug;
 // pub mod earnings;
-// pub mod referral; 
\ No newline at end of file
+// pub mod referral; 
@@ -0,0 +1,356 @@
+use actix_web::web::Bytes;
+use actix_web::{web, HttpResponse, Responder};
+use futures::stream;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::config::Config;
+use crate::errors::{DashboardError, DashboardResult};
+use crate::handlers::extractors::{AuthenticatedUser, JsonBody, Pagination};
+use crate::handlers::response::{ApiResponse, PagedResponse};
+use crate::models::network::{
+    ConnectionScoreFilter, CreateNetworkConnectionDto, NetworkConnection, UpdateNetworkConnectionDto,
+};
+use crate::services::NetworkService;
+use crate::storage::NetworkStorage;
+
+/// Number of connections fetched from storage per export page
+const EXPORT_PAGE_SIZE: usize = 500;
+
+/// Serialize a page of connections as newline-delimited JSON
+fn to_ndjson(page: &[NetworkConnection]) -> Result<Bytes, serde_json::Error> {
+    let mut body = Vec::new();
+    for connection in page {
+        serde_json::to_writer(&mut body, connection)?;
+        body.push(b'\n');
+    }
+    Ok(Bytes::from(body))
+}
+
+/// Query for listing network connections
+#[derive(Debug, Deserialize)]
+pub struct ConnectionListQuery {
+    /// Whose connections to list; defaults to the authenticated user
+    pub user_id: Option<i64>,
+}
+
+/// Create a network connection, or refresh the user's active one on the same network
+pub async fn create_connection<T: NetworkStorage>(
+    auth: AuthenticatedUser,
+    connection_data: JsonBody<CreateNetworkConnectionDto>,
+    network_service: web::Data<NetworkService<T>>,
+) -> DashboardResult<impl Responder> {
+    let connection_data = connection_data.into_inner();
+    auth.ensure_self_or_admin(connection_data.user_id)?;
+    info!("Creating network connection on {} for user: {}", connection_data.network_name, connection_data.user_id);
+
+    let connection = network_service.create_connection(connection_data).await?;
+    Ok(ApiResponse::created(connection))
+}
+
+/// List a user's network connections, ordered by ID
+pub async fn list_network_connections<T: NetworkStorage>(
+    auth: AuthenticatedUser,
+    query: web::Query<ConnectionListQuery>,
+    pagination: Pagination,
+    network_service: web::Data<NetworkService<T>>,
+) -> DashboardResult<impl Responder> {
+    let user_id = query.user_id.unwrap_or(auth.user_id);
+    auth.ensure_self_or_admin(user_id)?;
+
+    let connections = network_service.get_user_connections(user_id).await?;
+    Ok(PagedResponse::paginate(connections, &pagination).ok())
+}
+
+/// Get a network connection by ID
+pub async fn get_connection<T: NetworkStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<i64>,
+    network_service: web::Data<NetworkService<T>>,
+) -> DashboardResult<impl Responder> {
+    let connection = network_service.get_connection(path.into_inner()).await?;
+    auth.ensure_self_or_admin(connection.user_id)?;
+
+    Ok(ApiResponse::ok(connection))
+}
+
+/// Update a network connection, broadcasting status changes to the owner's sessions
+pub async fn update_connection<T: NetworkStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<i64>,
+    update_data: JsonBody<UpdateNetworkConnectionDto>,
+    network_service: web::Data<NetworkService<T>>,
+) -> DashboardResult<impl Responder> {
+    let connection_id = path.into_inner();
+    let existing = network_service.get_connection(connection_id).await?;
+    auth.ensure_self_or_admin(existing.user_id)?;
+    // Points otherwise only accrue from connected time
+    if update_data.additional_points.is_some() && !auth.is_admin {
+        return Err(DashboardError::authorization("Only admins can award points directly"));
+    }
+    info!("Updating network connection: {}", connection_id);
+
+    let connection = network_service
+        .update_connection(connection_id, update_data.into_inner())
+        .await?;
+    Ok(ApiResponse::ok(connection))
+}
+
+/// Delete a network connection and its status
+pub async fn delete_connection<T: NetworkStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<i64>,
+    network_service: web::Data<NetworkService<T>>,
+) -> DashboardResult<impl Responder> {
+    let connection_id = path.into_inner();
+    let existing = network_service.get_connection(connection_id).await?;
+    auth.ensure_self_or_admin(existing.user_id)?;
+    info!("Deleting network connection: {}", connection_id);
+
+    if network_service.delete_connection(connection_id).await? {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(DashboardError::not_found(format!("Network connection with ID {} not found", connection_id)))
+    }
+}
+
+/// List a user's network connections, optionally within a score range, lowest score first
+pub async fn list_user_connections<T: NetworkStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<i64>,
+    filter: web::Query<ConnectionScoreFilter>,
+    pagination: Pagination,
+    network_service: web::Data<NetworkService<T>>,
+) -> DashboardResult<impl Responder> {
+    let user_id = path.into_inner();
+    auth.ensure_self_or_admin(user_id)?;
+
+    let connections = network_service
+        .get_user_connections_by_score(user_id, &filter)
+        .await?;
+    Ok(PagedResponse::paginate(connections, &pagination).ok())
+}
+
+/// List a network connection's status transitions, newest first
+pub async fn list_connection_events<T: NetworkStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<i64>,
+    pagination: Pagination,
+    network_service: web::Data<NetworkService<T>>,
+) -> DashboardResult<impl Responder> {
+    let connection_id = path.into_inner();
+    let connection = network_service.get_connection(connection_id).await?;
+    auth.ensure_self_or_admin(connection.user_id)?;
+
+    let events = network_service
+        .get_connection_events(
+            connection_id,
+            pagination.offset.unwrap_or(0),
+            pagination.limit.unwrap_or(usize::MAX),
+        )
+        .await?;
+    let total = network_service.count_connection_events(connection_id).await?;
+    Ok(PagedResponse::offset(events, total, &pagination).ok())
+}
+
+/// Stream all of a user's network connections as NDJSON, one connection per line
+pub async fn export_user_connections<T: NetworkStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<i64>,
+    network_service: web::Data<NetworkService<T>>,
+    config: web::Data<Config>,
+) -> DashboardResult<impl Responder> {
+    config.require_feature("network_export")?;
+    let user_id = path.into_inner();
+    auth.ensure_self_or_admin(user_id)?;
+    info!("Exporting network connections for user: {}", user_id);
+
+    // State: (service, last exported ID, finished)
+    let body = stream::unfold(
+        (network_service, None::<i64>, false),
+        move |(service, after_id, finished)| async move {
+            if finished {
+                return None;
+            }
+
+            let page = match service
+                .get_user_connections_page(user_id, after_id, EXPORT_PAGE_SIZE)
+                .await
+            {
+                Ok(page) if page.is_empty() => return None,
+                Ok(page) => page,
+                Err(e) => {
+                    error!("Network export failed for user {}: {}", user_id, e);
+                    return Some((Err(e), (service, after_id, true)));
+                }
+            };
+
+            let last_id = page.last().map(|c| c.id);
+            let finished = page.len() < EXPORT_PAGE_SIZE;
+            let chunk = to_ndjson(&page).map_err(|e| {
+                DashboardError::internal_server(format!("Failed to serialize connection: {}", e))
+            });
+            let finished = finished || chunk.is_err();
+
+            Some((chunk, (service, last_id, finished)))
+        },
+    );
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::App;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::models::user::CreateUserDto;
+    use crate::services::{CircuitBreaker, UserService};
+    use crate::storage::memory::{InMemoryNetworkStorage, InMemoryUserStorage};
+    use crate::storage::{AppUserStorage, CircuitBreakerStorage, TracedStorage, UserBackend};
+
+    const ADMIN_ID: i64 = 1;
+
+    /// Users `1` (admin), `2` and `3`, with a bearer token each
+    async fn setup() -> (UserService<AppUserStorage>, Vec<String>) {
+        let storage = CircuitBreakerStorage::new(
+            TracedStorage::new(UserBackend::Memory(InMemoryUserStorage::new())),
+            CircuitBreaker::new(5, Duration::from_secs(30)),
+        );
+        let users = UserService::new(Arc::new(storage), "test_secret".to_string(), 3600);
+        let mut tokens = Vec::new();
+        for name in ["admin", "alice", "bob"] {
+            users
+                .register_user(CreateUserDto {
+                    email: format!("{}@example.com", name),
+                    username: name.to_string(),
+                    password: "password123".to_string(),
+                    wallet_address: None,
+                })
+                .await
+                .unwrap();
+            let login = users
+                .login(&format!("{}@example.com", name), "password123", "127.0.0.1", "test")
+                .await
+                .unwrap();
+            tokens.push(format!("Bearer {}", login.token));
+        }
+        (users, tokens)
+    }
+
+    macro_rules! test_app {
+        ($users:expr) => {{
+            let mut config = Config::from_env().unwrap();
+            config.auth.admin_user_ids = vec![ADMIN_ID];
+            let network = NetworkService::new(Arc::new(InMemoryNetworkStorage::new()));
+            init_service(
+                App::new()
+                    .app_data(web::Data::new(config))
+                    .app_data(web::Data::new($users))
+                    .app_data(web::Data::new(network))
+                    .route("/networks", web::post().to(create_connection::<InMemoryNetworkStorage>))
+                    .route("/networks", web::get().to(list_network_connections::<InMemoryNetworkStorage>))
+                    .route("/networks/{id}", web::get().to(get_connection::<InMemoryNetworkStorage>))
+                    .route("/networks/{id}", web::put().to(update_connection::<InMemoryNetworkStorage>))
+                    .route("/networks/{id}", web::delete().to(delete_connection::<InMemoryNetworkStorage>)),
+            )
+            .await
+        }};
+    }
+
+    fn authed(req: TestRequest, token: &str) -> TestRequest {
+        req.insert_header(("Authorization", token))
+    }
+
+    fn new_connection(user_id: i64) -> Value {
+        json!({"user_id": user_id, "network_name": "wifi", "ip_address": "10.0.0.1"})
+    }
+
+    #[actix_rt::test]
+    async fn test_connections_are_scoped_to_their_owner_or_an_admin() {
+        let (users, tokens) = setup().await;
+        let (admin, alice, bob) = (&tokens[0], &tokens[1], &tokens[2]);
+        let app = test_app!(users);
+
+        // Creating a connection for someone else is refused, unless by an admin
+        let req = authed(TestRequest::post().uri("/networks").set_json(new_connection(2)), bob).to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+        let req = authed(TestRequest::post().uri("/networks").set_json(new_connection(2)), admin).to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::CREATED);
+        let req = authed(TestRequest::post().uri("/networks").set_json(new_connection(2)), alice).to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body: Value = read_body_json(res).await;
+        let id = body["data"]["id"].as_i64().unwrap();
+
+        for (token, expected) in [(alice, StatusCode::OK), (admin, StatusCode::OK), (bob, StatusCode::FORBIDDEN)] {
+            let req = authed(TestRequest::get().uri(&format!("/networks/{}", id)), token).to_request();
+            assert_eq!(call_service(&app, req).await.status(), expected);
+        }
+
+        // Listing defaults to the caller's own connections
+        let req = authed(TestRequest::get().uri("/networks"), alice).to_request();
+        let body: Value = read_body_json(call_service(&app, req).await).await;
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        let req = authed(TestRequest::get().uri("/networks?user_id=2"), bob).to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+        let req = authed(TestRequest::delete().uri(&format!("/networks/{}", id)), bob).to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+        let req = authed(TestRequest::delete().uri(&format!("/networks/{}", id)), alice).to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+        let req = TestRequest::get().uri("/networks").to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_only_admins_award_points_directly() {
+        let (users, tokens) = setup().await;
+        let (admin, alice) = (&tokens[0], &tokens[1]);
+        let app = test_app!(users);
+
+        let req = authed(TestRequest::post().uri("/networks").set_json(new_connection(2)), alice).to_request();
+        let body: Value = read_body_json(call_service(&app, req).await).await;
+        let uri = format!("/networks/{}", body["data"]["id"].as_i64().unwrap());
+
+        let award = json!({"additional_points": 100.0});
+        let req = authed(TestRequest::put().uri(&uri).set_json(&award), alice).to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+        let req = authed(TestRequest::put().uri(&uri).set_json(&award), admin).to_request();
+        let res = call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: Value = read_body_json(res).await;
+        assert_eq!(body["data"]["points_earned"].as_f64(), Some(100.0));
+
+        // Owners can still update everything else
+        let update = json!({"connected": false, "network_score": 80.0});
+        let req = authed(TestRequest::put().uri(&uri).set_json(&update), alice).to_request();
+        assert_eq!(call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_missing_connection_is_not_found() {
+        let (users, tokens) = setup().await;
+        let alice = &tokens[1];
+        let app = test_app!(users);
+
+        let update = json!({"connected": true});
+        for req in [
+            TestRequest::get().uri("/networks/999"),
+            TestRequest::put().uri("/networks/999").set_json(&update),
+            TestRequest::delete().uri("/networks/999"),
+        ] {
+            let res = call_service(&app, authed(req, alice).to_request()).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        }
+    }
+}
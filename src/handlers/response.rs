@@ -0,0 +1,181 @@
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use serde::Serialize;
+
+use crate::handlers::extractors::Pagination;
+
+/// Standard success envelope for REST responses: `{"data": ..., "meta": {...}}`
+#[derive(Debug, Serialize)]
+pub struct ApiResponse<T: Serialize> {
+    /// The response payload
+    pub data: T,
+    /// Response metadata such as counts or pagination; an empty object when unused
+    pub meta: serde_json::Value,
+}
+
+impl<T: Serialize> ApiResponse<T> {
+    /// Wrap a payload with empty metadata
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            meta: serde_json::json!({}),
+        }
+    }
+
+    /// Attach metadata to the envelope
+    pub fn with_meta(mut self, meta: serde_json::Value) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    /// `200 OK` with the payload wrapped in the envelope
+    pub fn ok(data: T) -> HttpResponse {
+        Self::new(data).respond(StatusCode::OK)
+    }
+
+    /// `201 Created` with the payload wrapped in the envelope
+    pub fn created(data: T) -> HttpResponse {
+        Self::new(data).respond(StatusCode::CREATED)
+    }
+
+    /// Build a response with the given status code
+    pub fn respond(self, status: StatusCode) -> HttpResponse {
+        HttpResponse::build(status).json(self)
+    }
+}
+
+/// Page details reported in `meta` by list endpoints
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum PageMeta {
+    /// `?limit=&offset=` listings, which know how many items exist in total
+    Offset {
+        count: usize,
+        total: usize,
+        /// `null` when the request asked for everything from `offset`
+        limit: Option<usize>,
+        offset: usize,
+        has_more: bool,
+    },
+    /// `?limit=&cursor=` listings; pass `next_cursor` back to fetch the next page
+    Cursor {
+        count: usize,
+        limit: usize,
+        has_more: bool,
+        next_cursor: Option<String>,
+    },
+}
+
+/// One page of a list endpoint, served as `{"data": [items], "meta": {page details}}`
+#[derive(Debug)]
+pub struct PagedResponse<T: Serialize> {
+    /// Items on this page
+    pub items: Vec<T>,
+    /// Where this page sits in the full listing
+    pub page: PageMeta,
+}
+
+impl<T: Serialize> PagedResponse<T> {
+    /// Select the requested page from a complete, ordered listing
+    pub fn paginate(items: Vec<T>, pagination: &Pagination) -> Self {
+        let total = items.len();
+        Self::offset(pagination.apply(items), total, pagination)
+    }
+
+    /// A page already selected by storage, out of `total` items
+    pub fn offset(items: Vec<T>, total: usize, pagination: &Pagination) -> Self {
+        let offset = pagination.offset.unwrap_or(0);
+        Self {
+            page: PageMeta::Offset {
+                count: items.len(),
+                total,
+                limit: pagination.limit,
+                offset,
+                has_more: offset.saturating_add(items.len()) < total,
+            },
+            items,
+        }
+    }
+
+    /// A cursor page from up to `limit + 1` fetched items; the extra one only tells whether
+    /// another page exists. `cursor_of` encodes the cursor after the last item kept.
+    pub fn cursor(mut items: Vec<T>, limit: usize, cursor_of: impl FnOnce(&T) -> String) -> Self {
+        let has_more = items.len() > limit;
+        items.truncate(limit);
+        let next_cursor = items.last().filter(|_| has_more).map(cursor_of);
+        Self {
+            page: PageMeta::Cursor {
+                count: items.len(),
+                limit,
+                has_more,
+                next_cursor,
+            },
+            items,
+        }
+    }
+
+    /// Wrap the page in the standard envelope
+    pub fn into_api_response(self) -> ApiResponse<Vec<T>> {
+        let meta = serde_json::to_value(&self.page).unwrap_or_else(|_| serde_json::json!({}));
+        ApiResponse::new(self.items).with_meta(meta)
+    }
+
+    /// `200 OK` with the page wrapped in the envelope
+    pub fn ok(self) -> HttpResponse {
+        self.into_api_response().respond(StatusCode::OK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_shape() {
+        let body = serde_json::to_value(ApiResponse::new(serde_json::json!({"id": 1}))).unwrap();
+        assert_eq!(body, serde_json::json!({"data": {"id": 1}, "meta": {}}));
+
+        let body = serde_json::to_value(ApiResponse::new(vec![1, 2]).with_meta(serde_json::json!({"count": 2})))
+            .unwrap();
+        assert_eq!(body, serde_json::json!({"data": [1, 2], "meta": {"count": 2}}));
+    }
+
+    #[test]
+    fn test_status_codes() {
+        assert_eq!(ApiResponse::ok(1).status(), StatusCode::OK);
+        assert_eq!(ApiResponse::created(1).status(), StatusCode::CREATED);
+    }
+
+    #[test]
+    fn test_offset_page_meta() {
+        let pagination = Pagination { limit: Some(2), offset: Some(1) };
+        let body = serde_json::to_value(PagedResponse::paginate(vec![1, 2, 3, 4], &pagination).into_api_response())
+            .unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "data": [2, 3],
+                "meta": {"count": 2, "total": 4, "limit": 2, "offset": 1, "has_more": true}
+            })
+        );
+
+        let last = PagedResponse::offset(vec![4], 4, &Pagination { limit: Some(2), offset: Some(3) });
+        assert!(matches!(last.page, PageMeta::Offset { has_more: false, .. }));
+    }
+
+    #[test]
+    fn test_cursor_page_meta() {
+        let page = PagedResponse::cursor(vec![1, 2, 3], 2, |last| format!("after-{}", last));
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(
+            serde_json::to_value(&page.page).unwrap(),
+            serde_json::json!({"count": 2, "limit": 2, "has_more": true, "next_cursor": "after-2"})
+        );
+
+        let last = PagedResponse::cursor(vec![3], 2, |last| format!("after-{}", last));
+        assert_eq!(
+            last.page,
+            PageMeta::Cursor { count: 1, limit: 2, has_more: false, next_cursor: None }
+        );
+    }
+}
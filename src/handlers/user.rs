@@ -1,10 +1,14 @@
+use actix_web::http::StatusCode;
 use actix_web::{web, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
 use crate::config::Config;
-use crate::errors::DashboardResult;
-use crate::models::user::{CreateUserDto, UpdateUserDto, User};
+use crate::errors::{DashboardError, DashboardResult};
+use crate::handlers::extractors::{AuthenticatedUser, JsonBody, Pagination};
+use crate::handlers::response::{ApiResponse, PagedResponse};
+use crate::handlers::websocket::SessionServices;
+use crate::models::user::{mask_public_key, CreateUserDto, NotificationPreferences, UpdateUserDto, User};
 use crate::services::UserService;
 use crate::storage::UserStorage;
 
@@ -15,9 +19,23 @@ pub struct AddPublicKeyRequest {
     pub public_key: String,
 }
 
+/// Request for adding several public keys to a user
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddPublicKeysRequest {
+    /// The public keys to add (hex-encoded)
+    pub public_keys: Vec<String>,
+}
+
+/// Request for changing the authenticated user's password
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
 /// Register a new user
 pub async fn register_user<T: UserStorage>(
-    user_data: web::Json<CreateUserDto>,
+    user_data: JsonBody<CreateUserDto>,
     user_service: web::Data<UserService<T>>,
 ) -> DashboardResult<impl Responder> {
     info!("Registering new user with email: {}", user_data.email);
@@ -25,7 +43,7 @@ pub async fn register_user<T: UserStorage>(
     let user = user_service.register_user(user_data.into_inner()).await?;
     
     info!("User registered successfully: {}", user.id);
-    Ok(HttpResponse::Created().json(user))
+    Ok(ApiResponse::created(user))
 }
 
 /// Get user by ID
@@ -38,13 +56,104 @@ pub async fn get_user<T: UserStorage>(
     
     let user = user_service.get_user(user_id).await?;
     
-    Ok(HttpResponse::Ok().json(user))
+    Ok(ApiResponse::ok(user))
+}
+
+/// Export everything stored about the authenticated user
+pub async fn export_my_data<T: UserStorage>(
+    auth: AuthenticatedUser,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    info!("Exporting data for user: {}", auth.user_id);
+
+    let export = user_service.export_user_data(auth.user_id).await?;
+
+    Ok(ApiResponse::ok(export))
+}
+
+/// Get the authenticated user's notification preferences
+pub async fn get_my_notification_preferences<T: UserStorage>(
+    auth: AuthenticatedUser,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    let preferences = user_service.get_notification_preferences(auth.user_id).await?;
+    Ok(ApiResponse::ok(preferences))
+}
+
+/// Replace the authenticated user's notification preferences
+pub async fn update_my_notification_preferences<T: UserStorage>(
+    auth: AuthenticatedUser,
+    body: JsonBody<NotificationPreferences>,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    let preferences = user_service
+        .update_notification_preferences(auth.user_id, body.into_inner())
+        .await?;
+    Ok(ApiResponse::ok(preferences))
+}
+
+/// Query for self-service account deletion
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteAccountQuery {
+    /// Token from a previous unconfirmed `DELETE /api/users/me`
+    pub confirmation_token: Option<String>,
+}
+
+/// Change the authenticated user's password
+pub async fn change_my_password<T: UserStorage>(
+    auth: AuthenticatedUser,
+    body: JsonBody<ChangePasswordRequest>,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    auth.ensure_not_impersonated("password change")?;
+    let body = body.into_inner();
+    user_service
+        .change_password(auth.user_id, &body.current_password, &body.new_password)
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Delete the authenticated user's account.
+///
+/// Without a token this only issues a confirmation token (`202 Accepted`); repeating the
+/// request with it deletes the account and ends the user's live sessions.
+pub async fn delete_me<T: UserStorage>(
+    auth: AuthenticatedUser,
+    query: web::Query<DeleteAccountQuery>,
+    user_service: web::Data<UserService<T>>,
+    sessions: web::Data<SessionServices>,
+) -> DashboardResult<impl Responder> {
+    auth.ensure_not_impersonated("account deletion")?;
+    let user_id = auth.user_id;
+    let token = match query.into_inner().confirmation_token {
+        Some(token) => token,
+        None => {
+            let (confirmation_token, expires_at) = user_service.request_account_deletion(user_id).await?;
+            info!("Account deletion requested by user: {}", user_id);
+            return Ok(ApiResponse::new(serde_json::json!({
+                "confirmation_token": confirmation_token,
+                "expires_at": expires_at
+            }))
+            .respond(StatusCode::ACCEPTED));
+        }
+    };
+
+    let deleted = user_service.confirm_account_deletion(user_id, &token).await?;
+    sessions.reconnect_tokens.revoke_user(user_id);
+    sessions.ws_tickets.revoke_user(user_id);
+    let disconnected = sessions.broadcast.disconnect_user(user_id, "Account deleted");
+
+    info!("User {} deleted their account, closed {} WebSocket sessions", user_id, disconnected);
+    Ok(ApiResponse::ok(serde_json::json!({
+        "deleted": deleted,
+        "disconnected_sessions": disconnected
+    })))
 }
 
 /// Update user
 pub async fn update_user<T: UserStorage>(
     path: web::Path<i64>,
-    update_data: web::Json<UpdateUserDto>,
+    update_data: JsonBody<UpdateUserDto>,
     user_service: web::Data<UserService<T>>,
 ) -> DashboardResult<impl Responder> {
     let user_id = path.into_inner();
@@ -55,15 +164,18 @@ pub async fn update_user<T: UserStorage>(
         .await?;
     
     info!("User updated successfully: {}", user_id);
-    Ok(HttpResponse::Ok().json(user))
+    Ok(ApiResponse::ok(user))
 }
 
 /// Delete user
 pub async fn delete_user<T: UserStorage>(
+    auth: AuthenticatedUser,
     path: web::Path<i64>,
     user_service: web::Data<UserService<T>>,
 ) -> DashboardResult<impl Responder> {
     let user_id = path.into_inner();
+    auth.ensure_not_impersonated("account deletion")?;
+    auth.ensure_self_or_admin(user_id)?;
     info!("Deleting user with ID: {}", user_id);
     
     let deleted = user_service.delete_user(user_id).await?;
@@ -73,17 +185,19 @@ pub async fn delete_user<T: UserStorage>(
         Ok(HttpResponse::NoContent().finish())
     } else {
         error!("Failed to delete user: {}", user_id);
-        Ok(HttpResponse::InternalServerError().finish())
+        Err(DashboardError::internal_server("Failed to delete user"))
     }
 }
 
 /// Add a public key to a user
 pub async fn add_public_key<T: UserStorage>(
+    auth: AuthenticatedUser,
     path: web::Path<i64>,
-    key_data: web::Json<AddPublicKeyRequest>,
+    key_data: JsonBody<AddPublicKeyRequest>,
     user_service: web::Data<UserService<T>>,
 ) -> DashboardResult<impl Responder> {
     let user_id = path.into_inner();
+    auth.ensure_self_or_admin(user_id)?;
     info!("Adding public key for user: {}", user_id);
     
     user_service
@@ -91,49 +205,144 @@ pub async fn add_public_key<T: UserStorage>(
         .await?;
     
     info!("Public key added successfully for user: {}", user_id);
-    Ok(HttpResponse::Created().json(serde_json::json!({
-        "status": "success",
-        "message": "Public key added successfully"
+    Ok(ApiResponse::created(serde_json::json!({
+        "user_id": user_id,
+        "public_key": key_data.public_key
+    })))
+}
+
+/// Add several public keys to a user
+pub async fn add_public_keys<T: UserStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<i64>,
+    key_data: JsonBody<AddPublicKeysRequest>,
+    user_service: web::Data<UserService<T>>,
+    config: web::Data<Config>,
+) -> DashboardResult<impl Responder> {
+    config.require_feature("bulk_key_registration")?;
+    let user_id = path.into_inner();
+    auth.ensure_self_or_admin(user_id)?;
+    info!("Adding {} public keys for user: {}", key_data.public_keys.len(), user_id);
+    
+    let results = user_service
+        .add_public_keys(user_id, key_data.into_inner().public_keys)
+        .await?;
+    
+    Ok(ApiResponse::ok(serde_json::json!({
+        "user_id": user_id,
+        "results": results
     })))
 }
 
-/// Get user's public keys
+/// Query flags for endpoints that return public keys
+#[derive(Debug, Default, Deserialize)]
+pub struct KeyDisplayQuery {
+    /// Return complete keys instead of `abcd…wxyz`; owner or admin only
+    #[serde(default)]
+    pub full: bool,
+}
+
+/// Get user's public keys, masked unless the owner or an admin asks for `?full=true`
 pub async fn get_public_keys<T: UserStorage>(
+    auth: Option<AuthenticatedUser>,
     path: web::Path<i64>,
+    query: web::Query<KeyDisplayQuery>,
+    pagination: Pagination,
     user_service: web::Data<UserService<T>>,
 ) -> DashboardResult<impl Responder> {
     let user_id = path.into_inner();
     info!("Getting public keys for user: {}", user_id);
     
+    if query.full {
+        auth.ok_or_else(|| DashboardError::authentication("Authentication required for full keys"))?
+            .ensure_self_or_admin(user_id)?;
+    }
+    
     let keys = user_service.get_public_keys(user_id).await?;
+    let keys: Vec<String> = if query.full {
+        keys
+    } else {
+        keys.iter().map(|key| mask_public_key(key)).collect()
+    };
     
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "user_id": user_id,
-        "public_keys": keys
-    })))
+    Ok(PagedResponse::paginate(keys, &pagination).ok())
+}
+
+/// List a user's stored login sessions, oldest first
+pub async fn list_user_sessions<T: UserStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<i64>,
+    pagination: Pagination,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    let user_id = path.into_inner();
+    auth.ensure_self_or_admin(user_id)?;
+    info!("Listing sessions for user: {}", user_id);
+
+    let sessions = user_service.get_user_sessions(user_id).await?;
+    Ok(PagedResponse::paginate(sessions, &pagination).ok())
+}
+
+/// Get metadata for one of a user's public keys (for diagnosing auth failures)
+pub async fn get_public_key_info<T: UserStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<(i64, String)>,
+    query: web::Query<KeyDisplayQuery>,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    let (user_id, public_key) = path.into_inner();
+    auth.ensure_self_or_admin(user_id)?;
+    info!("Getting public key info for user: {}", user_id);
+    
+    let key_info = user_service.get_public_key_info(user_id, &public_key).await?;
+    let key_info = if query.full { key_info } else { key_info.masked() };
+    
+    Ok(ApiResponse::ok(key_info))
 }
 
 /// Revoke a public key from a user
 pub async fn revoke_public_key<T: UserStorage>(
+    auth: AuthenticatedUser,
     path: web::Path<(i64, String)>,
     user_service: web::Data<UserService<T>>,
 ) -> DashboardResult<impl Responder> {
     let (user_id, public_key) = path.into_inner();
+    auth.ensure_self_or_admin(user_id)?;
     info!("Revoking public key for user: {}", user_id);
     
     let revoked = user_service.revoke_public_key(user_id, &public_key).await?;
     
     if revoked {
         info!("Public key revoked successfully for user: {}", user_id);
-        Ok(HttpResponse::Ok().json(serde_json::json!({
-            "status": "success",
-            "message": "Public key revoked successfully"
+        Ok(ApiResponse::ok(serde_json::json!({
+            "user_id": user_id,
+            "public_key": public_key,
+            "revoked": true
         })))
     } else {
         info!("Public key not found or already revoked for user: {}", user_id);
-        Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "status": "error",
-            "message": "Public key not found or already revoked"
+        Err(DashboardError::not_found("Public key not found or already revoked"))
+    }
+}
+
+/// Reactivate a previously revoked public key
+pub async fn reactivate_public_key<T: UserStorage>(
+    auth: AuthenticatedUser,
+    path: web::Path<(i64, String)>,
+    user_service: web::Data<UserService<T>>,
+) -> DashboardResult<impl Responder> {
+    let (user_id, public_key) = path.into_inner();
+    auth.ensure_self_or_admin(user_id)?;
+    info!("Reactivating public key for user: {}", user_id);
+    
+    if user_service.reactivate_public_key(user_id, &public_key).await? {
+        info!("Public key reactivated for user: {}", user_id);
+        Ok(ApiResponse::ok(serde_json::json!({
+            "user_id": user_id,
+            "public_key": public_key,
+            "revoked": false
         })))
+    } else {
+        Err(DashboardError::not_found("Public key not found or not revoked"))
     }
-} 
\ No newline at end of file
+}
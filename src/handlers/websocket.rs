@@ -1,18 +1,42 @@
-use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix::{Actor, ActorContext, AsyncContext, Handler, StreamHandler};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::web::Bytes;
 use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
 use chrono::{DateTime, Utc};
 use nanoid::nanoid;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::{BTreeSet, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
+#[cfg(debug_assertions)]
+use crate::dev::ws_trace::{self, Direction};
+use crate::clock::{has_elapsed, warning_due, Clock, SystemClock};
 use crate::config::Config;
-use crate::models::websocket::{WebSocketAuthMessage, WebSocketMessage};
-use crate::services::SignatureService;
+use crate::json_case::FieldCase;
+use crate::metrics::Metrics;
+use crate::models::user::{RateLimitTier, User, UserSession};
+use crate::models::websocket::{WebSocketAuthMessage, WebSocketAuthRequest, WebSocketConnectionInfo, WebSocketMessage};
+use crate::services::connection_gate::{GateRejection, GateRequest};
+use crate::services::broadcast::{all_channels, is_known_channel, Disconnect, RawFrame, KNOWN_CHANNELS};
+use crate::services::rate_limit::{RateLimitKey, CONNECTION_RETRY_AFTER};
+use crate::services::network_updates::NetworkUpdate;
+use crate::services::reconnect::{ReconnectGrant, ReconnectRejection};
+use crate::services::session_hooks::OnAuthenticated;
+use crate::services::signature::KeyAuthentication;
+use crate::errors::DashboardError;
+use crate::services::{
+    AuditEvent, AuditSink, BroadcastHub, ConnectionGates, ExternalAuthService, MaintenanceMode, NetworkService, NetworkUpdateCoalescer, RateLimits, ReadOnlyMode, ReconnectTokens, SessionRegistry, SignatureService,
+    WsTickets,
+};
 use crate::storage::UserStorage;
-use crate::storage::memory::InMemoryUserStorage;
+use crate::storage::{AppNetworkStorage, AppUserStorage};
 
 /// Tracks the authentication state of a WebSocket connection
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -27,6 +51,215 @@ pub enum AuthState {
     Failed,
 }
 
+impl AuthState {
+    /// Move into `Authenticating` unless a verification is already running.
+    ///
+    /// Returns `false` when an attempt is already in flight, so only one verification
+    /// future runs per session at a time.
+    pub fn try_begin_authentication(&mut self) -> bool {
+        if *self == AuthState::Authenticating {
+            return false;
+        }
+        *self = AuthState::Authenticating;
+        true
+    }
+}
+
+/// Whether a verified `Auth` on a session authenticated as `user_id` may add its key.
+///
+/// Only an active key of the same user escalates a session; keys of other accounts and keys
+/// accepted only because they were revoked within the grace period are refused.
+pub fn check_escalation(user_id: Option<i64>, authentication: Option<&KeyAuthentication>) -> Result<(), String> {
+    match authentication {
+        None => Err("No user is associated with this public key".to_string()),
+        Some(authentication) if Some(authentication.user_id) != user_id => {
+            Err("The key belongs to another account".to_string())
+        }
+        Some(authentication) if authentication.revoked_in_grace => {
+            Err("Revoked keys cannot be used to escalate a session".to_string())
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Largest ping payload allowed by RFC 6455 for control frames
+pub const MAX_PING_PAYLOAD: usize = 125;
+
+/// Whether a ping payload is small enough to echo back in a pong
+pub fn ping_payload_allowed(payload: &[u8]) -> bool {
+    payload.len() <= MAX_PING_PAYLOAD
+}
+
+/// Largest close reason that fits in a close frame after its 2-byte status code
+pub const MAX_CLOSE_REASON: usize = MAX_PING_PAYLOAD - 2;
+
+/// Cut a close reason to `MAX_CLOSE_REASON` bytes without splitting a UTF-8 character
+pub fn truncate_close_reason(reason: &str) -> String {
+    if reason.len() <= MAX_CLOSE_REASON {
+        return reason.to_string();
+    }
+    let mut end = MAX_CLOSE_REASON;
+    while !reason.is_char_boundary(end) {
+        end -= 1;
+    }
+    reason[..end].to_string()
+}
+
+/// Close frame sent in reply to a client's close.
+///
+/// The client's code and description are not mirrored: its description may not fit in a
+/// control frame, and its code may be one that must never be sent on the wire.
+pub fn close_reply() -> ws::CloseReason {
+    ws::CloseReason {
+        code: ws::CloseCode::Normal,
+        description: Some("Connection closed".to_string()),
+    }
+}
+
+/// Banner frame re-sent to sessions while maintenance mode is active
+pub fn maintenance_frame(maintenance: &MaintenanceMode) -> Option<serde_json::Value> {
+    maintenance.message().map(|message| json!({
+        "type": "maintenance",
+        "message": message
+    }))
+}
+
+/// Whether a WebSocket handshake from `origin` may proceed.
+///
+/// Browsers always send `Origin`, so a missing header means a non-browser client, which
+/// cross-site WebSocket hijacking can't involve; those are allowed.
+pub fn origin_allowed(origin: Option<&str>, allowed_origins: &[String], allow_any: bool) -> bool {
+    let origin = match origin {
+        Some(origin) => origin.trim().trim_end_matches('/'),
+        None => return true,
+    };
+    allow_any || allowed_origins.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin))
+}
+
+/// Upper bound on remembered message IDs per session
+const MAX_RECENT_MESSAGE_IDS: usize = 1024;
+
+/// Client message IDs seen recently on a session, used to drop retransmitted messages
+#[derive(Debug)]
+pub struct RecentMessageIds {
+    window: Duration,
+    capacity: usize,
+    ids: HashSet<String>,
+    order: VecDeque<(String, Instant)>,
+}
+
+impl RecentMessageIds {
+    /// Remember IDs for `window`, keeping at most `capacity` of them
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity,
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Whether `id` was recorded within the window; checking does not record it
+    pub fn contains(&mut self, id: &str, now: Instant) -> bool {
+        self.evict_expired(now);
+        self.ids.contains(id)
+    }
+
+    /// Record `id` as seen at `now`, evicting the oldest ID when full
+    pub fn insert(&mut self, id: &str, now: Instant) {
+        self.evict_expired(now);
+        if !self.ids.insert(id.to_owned()) {
+            return;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some((oldest, _)) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        self.order.push_back((id.to_owned(), now));
+    }
+
+    /// Drop IDs older than the window
+    fn evict_expired(&mut self, now: Instant) {
+        while let Some((_, seen_at)) = self.order.front() {
+            if now.saturating_duration_since(*seen_at) <= self.window {
+                break;
+            }
+            if let Some((id, _)) = self.order.pop_front() {
+                self.ids.remove(&id);
+            }
+        }
+    }
+}
+
+/// Extract the optional client-supplied `id` from a raw message frame
+pub fn client_message_id(text: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(text)
+        .ok()?
+        .get("id")?
+        .as_str()
+        .map(str::to_owned)
+}
+
+/// Messages processed by a session, by type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MessageCounters {
+    /// Authentication and reconnect attempts
+    pub auth: u64,
+    /// Heartbeat messages
+    pub heartbeat: u64,
+    /// Connection status updates
+    pub connection_update: u64,
+    /// Network status updates
+    pub network_update: u64,
+    /// Error frames sent to the client
+    pub errors: u64,
+}
+
+/// When a client last showed signs of life, tracked separately for the transport and the
+/// application so a socket that still answers pings but whose app has frozen can be told apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Liveness {
+    /// When tracking started; stands in for the app heartbeat until the first one arrives
+    pub since: Instant,
+    /// Last frame of any kind from the client, including protocol ping/pong
+    pub last_transport_activity: Instant,
+    /// Last application `Heartbeat` message, if any
+    pub last_app_heartbeat: Option<Instant>,
+}
+
+impl Liveness {
+    /// Start tracking at `now`
+    pub fn new(now: Instant) -> Self {
+        Self { since: now, last_transport_activity: now, last_app_heartbeat: None }
+    }
+
+    /// Record a frame received from the client
+    pub fn record_transport(&mut self, now: Instant) {
+        self.last_transport_activity = now;
+    }
+
+    /// Record an application `Heartbeat` message
+    pub fn record_app_heartbeat(&mut self, now: Instant) {
+        self.last_app_heartbeat = Some(now);
+    }
+
+    /// Start of the current stretch without an application heartbeat
+    pub fn app_heartbeat_since(&self) -> Instant {
+        self.last_app_heartbeat.unwrap_or(self.since)
+    }
+
+    /// Whether the transport has been silent for longer than `timeout`
+    pub fn transport_expired(&self, now: Instant, timeout: Duration) -> bool {
+        has_elapsed(self.last_transport_activity, now, timeout)
+    }
+
+    /// Whether the application has gone without a heartbeat for longer than `timeout`
+    pub fn app_heartbeat_expired(&self, now: Instant, timeout: Duration) -> bool {
+        has_elapsed(self.app_heartbeat_since(), now, timeout)
+    }
+}
+
 /// WebSocket session data structure
 pub struct WebSocketSession<T: UserStorage> {
     /// Unique session id
@@ -35,24 +268,139 @@ pub struct WebSocketSession<T: UserStorage> {
     pub user_id: Option<i64>,
     /// Client IP address
     pub client_ip: String,
-    /// Last heartbeat timestamp
-    pub last_heartbeat: Instant,
+    /// Transport and application heartbeat activity
+    pub liveness: Liveness,
+    /// Last application (text or binary) message timestamp
+    pub last_app_message: Instant,
     /// Authentication state
     pub auth_state: AuthState,
     /// When the connection was established
     pub connected_at: DateTime<Utc>,
     /// Public key used for authentication
     pub public_key: Option<String>,
+    /// Every key this session has proven, starting with `public_key`; a second `Auth` on an
+    /// authenticated session adds to it
+    pub verified_keys: BTreeSet<String>,
+    /// Whether a key escalation is being verified
+    pub escalating: bool,
     /// Heartbeat interval from config
     pub heartbeat_interval: Duration,
-    /// Client timeout from config
+    /// Delay before the first heartbeat, after which pings follow every `heartbeat_interval`
+    pub initial_ping_delay: Duration,
+    /// Close the connection after this long without any frame from the client
     pub client_timeout: Duration,
+    /// Close the connection after this long without an application `Heartbeat` (zero disables)
+    pub app_heartbeat_timeout: Duration,
+    /// Close the connection after this long without an application message (zero disables)
+    pub app_idle_timeout: Duration,
+    /// Send an `idle_warning` frame this long before an idle close (zero disables)
+    pub idle_warning: Duration,
+    /// Whether a warning was sent for the current idle stretch
+    pub idle_warning_sent: bool,
+    /// Time source for heartbeat and idle checks
+    pub clock: Arc<dyn Clock>,
     /// Authentication timeout for initial auth
     pub auth_timeout: Duration,
     /// Signature service for verification
     pub signature_service: Option<Arc<SignatureService<T>>>,
+    /// Verifies identity provider tokens; `None` refuses token auth
+    pub external_auth: Option<Arc<ExternalAuthService<T>>>,
     /// Time to wait before closing after auth failure
     pub close_delay: Duration,
+    /// Bytes received from the client in text and binary frames
+    pub bytes_received: u64,
+    /// Bytes sent to the client in text and binary frames
+    pub bytes_sent: u64,
+    /// Shared metrics for aggregate counters
+    pub metrics: Arc<Metrics>,
+    /// Registry of live sessions on this instance
+    pub registry: SessionRegistry,
+    /// Shared maintenance banner state
+    pub maintenance: MaintenanceMode,
+    /// Refuses state-changing messages while enabled
+    pub read_only: ReadOnlyMode,
+    /// Interval for re-sending the maintenance banner (zero disables)
+    pub maintenance_broadcast_interval: Duration,
+    /// Recently processed client message IDs
+    pub recent_message_ids: RecentMessageIds,
+    /// Shared store of reconnect tokens
+    pub reconnect_tokens: ReconnectTokens,
+    /// Reconnect token presented in the handshake query, redeemed on start
+    pub pending_reconnect_token: Option<String>,
+    /// User resolved from a handshake `ticket`, authenticated on start without a signature
+    pub ticket_user_id: Option<i64>,
+    /// Per-user fan-out of server events
+    pub broadcast: BroadcastHub,
+    /// Broadcast channels this session receives events on
+    pub channels: BTreeSet<String>,
+    /// Network service for `stats` requests
+    pub network_service: Option<Arc<NetworkService<AppNetworkStorage>>>,
+    /// Writes `NetworkUpdate` messages to storage, coalesced per connection
+    pub network_updates: Option<NetworkUpdateCoalescer<AppNetworkStorage>>,
+    /// Messages processed by this session, reported in `stats` frames
+    pub message_counters: MessageCounters,
+    /// Public endpoint: no authentication, every message goes to `handle_normal_message`
+    pub anonymous: bool,
+    /// Deployment hook run after authentication
+    pub on_authenticated: Arc<dyn OnAuthenticated>,
+    /// Destination for security events such as reconnects from a changed IP
+    pub audit: Arc<dyn AuditSink>,
+    /// Close the session when the hook fails instead of only logging
+    pub close_on_hook_error: bool,
+    /// Shared message and connection limiters
+    pub rate_limits: RateLimits,
+    /// Admission checks run on every auth path once the user is resolved
+    pub connection_gates: ConnectionGates,
+    /// What this session's limits are counted against: the client IP, then the user once authenticated
+    pub rate_limit_key: RateLimitKey,
+    /// Tier whose limits apply; the strictest until the user's own tier is known
+    pub rate_limit_tier: RateLimitTier,
+    /// Instance serving this session, reported in the welcome frame and logs
+    pub instance_id: String,
+    /// Key naming applied to every frame sent to the client
+    pub field_case: FieldCase,
+}
+
+/// Shared services handed to every WebSocket session
+#[derive(Clone)]
+pub struct SessionServices {
+    /// Aggregate counters
+    pub metrics: Arc<Metrics>,
+    /// Registry of live sessions on this instance
+    pub registry: SessionRegistry,
+    /// Maintenance banner state
+    pub maintenance: MaintenanceMode,
+    /// Read-only mode shared with the HTTP services
+    pub read_only: ReadOnlyMode,
+    /// Reconnect token store
+    pub reconnect_tokens: ReconnectTokens,
+    /// Handshake ticket store
+    pub ws_tickets: WsTickets,
+    /// Per-user fan-out of server events
+    pub broadcast: BroadcastHub,
+    /// Network statistics for `stats` requests
+    pub network_service: Arc<NetworkService<AppNetworkStorage>>,
+    /// Coalesced writes of client `NetworkUpdate` messages
+    pub network_updates: NetworkUpdateCoalescer<AppNetworkStorage>,
+    /// Deployment hook run after authentication
+    pub on_authenticated: Arc<dyn OnAuthenticated>,
+    /// Audit event sink
+    pub audit: Arc<dyn AuditSink>,
+    /// Tier-aware message and connection limiters
+    pub rate_limits: RateLimits,
+    /// Mandatory gates plus those from `WS_CONNECTION_GATES`, run on every auth path
+    pub connection_gates: ConnectionGates,
+    /// Identity provider token auth, when `EXTERNAL_AUTH_JWKS_URL` is set
+    pub external_auth: Option<Arc<ExternalAuthService<AppUserStorage>>>,
+}
+
+/// Handshake query parameters
+#[derive(Debug, Default, Deserialize)]
+pub struct HandshakeQuery {
+    /// Reconnect token from a previous session's `auth_success` frame
+    pub reconnect_token: Option<String>,
+    /// Single-use ticket from `POST /api/ws-ticket`
+    pub ticket: Option<String>,
 }
 
 impl<T: UserStorage> Actor for WebSocketSession<T> {
@@ -60,22 +408,67 @@ impl<T: UserStorage> Actor for WebSocketSession<T> {
 
     /// Start the heartbeat and authentication timeout process on actor start
     fn started(&mut self, ctx: &mut Self::Context) {
+        match self.registry.register(self.connection_info()) {
+            Ok(id) => self.id = id,
+            Err(e) => {
+                error!("Failed to register WebSocket session: {}", e);
+                // Never registered; keep `stopping` from unregistering whoever owns the id
+                self.id.clear();
+                ctx.close(Some(ws::CloseReason {
+                    code: ws::CloseCode::Error,
+                    description: Some("Internal server error".to_string()),
+                }));
+                ctx.stop();
+                return;
+            }
+        }
         self.start_heartbeat(ctx);
-        self.start_auth_timeout(ctx);
-        info!("WebSocket connection established: {}", self.id);
+        if !self.anonymous {
+            self.start_auth_timeout(ctx);
+        }
+        self.start_maintenance_broadcast(ctx);
+        info!("WebSocket connection established: {} on instance {}", self.id, self.instance_id);
         
-        // Send a welcome message that requests authentication
-        ctx.text(json!({
+        // Send a welcome message that requests authentication, unless the endpoint is public
+        // or the handshake already carried a ticket
+        let message = if self.anonymous {
+            "Connected to a public endpoint; no authentication required"
+        } else if self.ticket_user_id.is_some() {
+            "Authenticated with a WebSocket ticket"
+        } else if self.external_auth.is_some() {
+            "Please authenticate with an ed25519 signature or an identity provider token"
+        } else {
+            "Please authenticate with an ed25519 signature"
+        };
+        let welcome = json!({
             "type": "connection_established",
             "session_id": self.id,
-            "auth_required": true,
-            "message": "Please authenticate with an ed25519 signature"
-        }).to_string());
+            "instance_id": self.instance_id,
+            "auth_required": !self.anonymous && self.ticket_user_id.is_none(),
+            "message": message,
+            "maintenance_message": self.maintenance.message()
+        });
+        self.send_json(ctx, welcome);
+
+        if self.anonymous {
+            return;
+        }
+        if let Some(user_id) = self.ticket_user_id.take() {
+            info!("WebSocket ticket redeemed for user {}: {}", user_id, self.id);
+            self.admit_user_id(ctx, user_id, None);
+        } else if let Some(token) = self.pending_reconnect_token.take() {
+            self.handle_reconnect(&token, ctx);
+        }
     }
 
     /// Log when the actor is stopping
     fn stopping(&mut self, _: &mut Self::Context) -> actix::Running {
+        self.metrics
+            .observe_ws_connection_lifetime((Utc::now() - self.connected_at).to_std().unwrap_or_default());
+        self.registry.unregister(&self.id);
+        self.rate_limits.disconnect(&self.rate_limit_key);
         if let Some(user_id) = self.user_id {
+            self.broadcast.unsubscribe(user_id, &self.id);
             info!("WebSocket connection closed for user {}: {}", user_id, self.id);
         } else {
             info!("WebSocket connection closed: {}", self.id);
@@ -84,40 +477,99 @@ impl<T: UserStorage> Actor for WebSocketSession<T> {
     }
 }
 
+/// Deliver server-pushed events such as `network_event` frames, already serialized by the hub
+impl<T: UserStorage> Handler<RawFrame> for WebSocketSession<T> {
+    type Result = ();
+
+    fn handle(&mut self, frame: RawFrame, ctx: &mut Self::Context) {
+        // Frames are shared across sessions; only copy one when its keys need renaming
+        if self.field_case == FieldCase::SnakeCase {
+            #[cfg(debug_assertions)]
+            ws_trace::trace_text(&self.id, Direction::Outbound, &frame.0);
+            self.record_sent(frame.0.len());
+            ctx.text(frame.0);
+        } else {
+            self.send_text(ctx, frame.0.to_string());
+        }
+    }
+}
+
+/// Close the session on request from the server, e.g. when its user is suspended or the
+/// server shuts down
+impl<T: UserStorage> Handler<Disconnect> for WebSocketSession<T> {
+    type Result = ();
+
+    fn handle(&mut self, msg: Disconnect, ctx: &mut Self::Context) {
+        info!("Closing WebSocket session {}: {}", self.id, msg.reason);
+        ctx.close(Some(ws::CloseReason {
+            code: msg.code,
+            description: Some(truncate_close_reason(&msg.reason)),
+        }));
+        ctx.stop();
+    }
+}
+
 /// Handler for WebSocket messages
 impl<T: UserStorage> StreamHandler<Result<ws::Message, ws::ProtocolError>> for WebSocketSession<T> {
     fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        #[cfg(debug_assertions)]
+        self.trace_inbound(&msg);
+        if msg.is_ok() {
+            self.liveness.record_transport(self.clock.now());
+        }
         match msg {
             Ok(ws::Message::Ping(msg)) => {
-                self.last_heartbeat = Instant::now();
+                if !ping_payload_allowed(&msg) {
+                    warn!("WebSocket ping payload of {} bytes exceeds limit, closing: {}", msg.len(), self.id);
+                    ctx.close(Some(ws::CloseReason {
+                        code: ws::CloseCode::Protocol,
+                        description: Some("Ping payload exceeds 125 bytes".to_string()),
+                    }));
+                    ctx.stop();
+                    return;
+                }
                 ctx.pong(&msg);
             }
-            Ok(ws::Message::Pong(_)) => {
-                self.last_heartbeat = Instant::now();
-            }
+            Ok(ws::Message::Pong(_)) => {}
             Ok(ws::Message::Text(text)) => {
                 debug!("WebSocket text message received: {:?}", text);
-                if self.auth_state != AuthState::Authenticated {
+                self.record_received(text.len());
+                self.last_app_message = self.clock.now();
+                if !self.allow_message(ctx) {
+                    return;
+                }
+                let started = Instant::now();
+                if !self.accepts_messages() {
                     self.handle_authentication_message(&text, ctx);
                 } else {
                     self.handle_normal_message(&text, ctx);
                 }
+                self.metrics.observe_ws_message_handling(started.elapsed());
             }
             Ok(ws::Message::Binary(bin)) => {
                 debug!("WebSocket binary message received: {} bytes", bin.len());
-                if self.auth_state != AuthState::Authenticated {
-                    ctx.text(json!({
-                        "type": "error",
-                        "code": "unauthorized",
-                        "message": "Authentication required"
-                    }).to_string());
+                self.record_received(bin.len());
+                self.last_app_message = self.clock.now();
+                if !self.allow_message(ctx) {
                     return;
                 }
-                ctx.binary(bin);
+                if !self.accepts_messages() {
+                    self.send_error(ctx, "unauthorized", "Authentication required");
+                    return;
+                }
+                let started = Instant::now();
+                self.send_binary(ctx, bin);
+                self.metrics.observe_ws_message_handling(started.elapsed());
             }
             Ok(ws::Message::Close(reason)) => {
-                info!("WebSocket closed with reason: {:?}", reason);
-                ctx.close(reason);
+                info!(
+                    "WebSocket closed by client with code {:?} and reason {:?}: {}",
+                    reason.as_ref().map(|r| r.code),
+                    reason.as_ref().and_then(|r| r.description.as_deref()).map(truncate_close_reason),
+                    self.id
+                );
+                ctx.close(Some(close_reply()));
+                ctx.stop();
             }
             Ok(ws::Message::Continuation(_)) => {
                 warn!("WebSocket continuation frame received, not supported yet");
@@ -132,17 +584,199 @@ impl<T: UserStorage> StreamHandler<Result<ws::Message, ws::ProtocolError>> for W
 }
 
 impl<T: UserStorage> WebSocketSession<T> {
-    /// Start the heartbeat process
+    /// Whether application messages are processed: always on public endpoints, otherwise
+    /// only once authenticated
+    fn accepts_messages(&self) -> bool {
+        self.anonymous || self.auth_state == AuthState::Authenticated
+    }
+
+    /// Count an application message against the session's rate limit, sending a
+    /// `rate_limited` error and returning `false` if it should be dropped
+    fn allow_message(&mut self, ctx: &mut ws::WebsocketContext<Self>) -> bool {
+        let now = self.clock.now();
+        if self.rate_limits.allow_message(&self.rate_limit_key, self.rate_limit_tier, now) {
+            return true;
+        }
+        debug!("Dropping rate-limited message on session {}", self.id);
+        self.send_error(ctx, "rate_limited", "Message rate limit exceeded, slow down");
+        false
+    }
+
+    /// Send a text frame to the client, counting outbound bytes
+    fn send_text(&mut self, ctx: &mut ws::WebsocketContext<Self>, text: String) {
+        let text = self.field_case.apply_to_text(text);
+        #[cfg(debug_assertions)]
+        ws_trace::trace_text(&self.id, Direction::Outbound, &text);
+        self.record_sent(text.len());
+        ctx.text(text);
+    }
+
+    /// Serialize a JSON value and send it as a text frame
+    fn send_json(&mut self, ctx: &mut ws::WebsocketContext<Self>, value: serde_json::Value) {
+        self.send_text(ctx, value.to_string());
+    }
+
+    /// Send an `error` frame, counting it in the session stats
+    fn send_error(&mut self, ctx: &mut ws::WebsocketContext<Self>, code: &str, message: impl Into<String>) {
+        self.message_counters.errors += 1;
+        self.send_json(ctx, json!({
+            "type": "error",
+            "code": code,
+            "message": message.into()
+        }));
+    }
+
+    /// Send a binary frame to the client, counting outbound bytes
+    fn send_binary(&mut self, ctx: &mut ws::WebsocketContext<Self>, bin: Bytes) {
+        #[cfg(debug_assertions)]
+        ws_trace::trace_frame(&self.id, Direction::Outbound, "binary", bin.len());
+        self.record_sent(bin.len());
+        ctx.binary(bin);
+    }
+
+    /// Emit a `ws_trace` event for a frame received from the client
+    #[cfg(debug_assertions)]
+    fn trace_inbound(&self, msg: &Result<ws::Message, ws::ProtocolError>) {
+        match msg {
+            Ok(ws::Message::Text(text)) => ws_trace::trace_text(&self.id, Direction::Inbound, text),
+            Ok(ws::Message::Binary(bin)) => ws_trace::trace_frame(&self.id, Direction::Inbound, "binary", bin.len()),
+            Ok(ws::Message::Ping(msg)) => ws_trace::trace_frame(&self.id, Direction::Inbound, "ping", msg.len()),
+            Ok(ws::Message::Pong(msg)) => ws_trace::trace_frame(&self.id, Direction::Inbound, "pong", msg.len()),
+            Ok(ws::Message::Close(_)) => ws_trace::trace_frame(&self.id, Direction::Inbound, "close", 0),
+            Ok(ws::Message::Continuation(_)) => ws_trace::trace_frame(&self.id, Direction::Inbound, "continuation", 0),
+            Ok(ws::Message::Nop) | Err(_) => {}
+        }
+    }
+
+    /// Count bytes received in a text or binary frame
+    fn record_received(&mut self, len: usize) {
+        self.bytes_received += len as u64;
+        self.metrics.record_ws_bytes_received(len as u64);
+        let bytes_received = self.bytes_received;
+        self.registry.update(&self.id, |info| info.bytes_received = bytes_received);
+    }
+
+    /// Count bytes sent in a text or binary frame
+    fn record_sent(&mut self, len: usize) {
+        self.bytes_sent += len as u64;
+        self.metrics.record_ws_bytes_sent(len as u64);
+        let bytes_sent = self.bytes_sent;
+        self.registry.update(&self.id, |info| info.bytes_sent = bytes_sent);
+    }
+
+    /// Snapshot of this session for the connection registry
+    fn connection_info(&self) -> WebSocketConnectionInfo {
+        WebSocketConnectionInfo {
+            session_id: self.id.clone(),
+            user_id: self.user_id,
+            client_ip: self.client_ip.clone(),
+            created_at: self.connected_at,
+            last_active: Utc::now(),
+            authenticated: self.auth_state == AuthState::Authenticated,
+            bytes_received: self.bytes_received,
+            bytes_sent: self.bytes_sent,
+            last_transport_activity: self.wall_time(self.liveness.last_transport_activity),
+            last_app_heartbeat: self.liveness.last_app_heartbeat.map(|at| self.wall_time(at)),
+        }
+    }
+
+    /// Publish this session's liveness to the connection registry; byte counters are
+    /// published as they change
+    fn sync_registry(&self) {
+        let last_transport_activity = self.wall_time(self.liveness.last_transport_activity);
+        let last_app_heartbeat = self.liveness.last_app_heartbeat.map(|at| self.wall_time(at));
+        self.registry.update(&self.id, |info| {
+            info.last_active = Utc::now();
+            info.last_transport_activity = last_transport_activity;
+            info.last_app_heartbeat = last_app_heartbeat;
+        });
+    }
+
+    /// Wall-clock time of an instant read from the session clock
+    fn wall_time(&self, at: Instant) -> DateTime<Utc> {
+        let ago = self.clock.now().saturating_duration_since(at);
+        Utc::now() - chrono::Duration::from_std(ago).unwrap_or_else(|_| chrono::Duration::zero())
+    }
+
+    /// Start the heartbeat process: the first tick after `initial_ping_delay`, then one every
+    /// `heartbeat_interval`
     fn start_heartbeat(&self, ctx: &mut ws::WebsocketContext<Self>) {
-        ctx.run_interval(self.heartbeat_interval, |act, ctx| {
-            // Check if client has been responsive
-            if Instant::now().duration_since(act.last_heartbeat) > act.client_timeout {
-                warn!("WebSocket client timeout, disconnecting: {}", act.id);
-                ctx.stop();
-                return;
+        ctx.run_later(self.initial_ping_delay, |act, ctx| {
+            act.heartbeat(ctx);
+            ctx.run_interval(act.heartbeat_interval, |act, ctx| act.heartbeat(ctx));
+        });
+    }
+
+    /// Close unresponsive or idle connections, otherwise ping the client
+    fn heartbeat(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        let now = self.clock.now();
+        // Check if client has been responsive
+        if self.liveness.transport_expired(now, self.client_timeout) {
+            warn!("WebSocket client timeout, disconnecting: {}", self.id);
+            ctx.stop();
+            return;
+        }
+        // Check if the socket is alive but the client app has stopped sending heartbeats
+        if self.liveness.app_heartbeat_expired(now, self.app_heartbeat_timeout) {
+            warn!("WebSocket application heartbeat timeout, disconnecting: {}", self.id);
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Normal,
+                description: Some("Application heartbeat timeout".to_string()),
+            }));
+            ctx.stop();
+            return;
+        }
+        // Check if the client is alive but has gone quiet at the application level
+        if has_elapsed(self.last_app_message, now, self.app_idle_timeout) {
+            warn!("WebSocket application idle timeout, disconnecting: {}", self.id);
+            ctx.close(Some(ws::CloseReason {
+                code: ws::CloseCode::Normal,
+                description: Some("Application idle timeout".to_string()),
+            }));
+            ctx.stop();
+            return;
+        }
+        self.check_idle_warning(now, ctx);
+        self.sync_registry();
+        // Send ping
+        ctx.ping(b"");
+    }
+    
+    /// Warn the client once per idle stretch when a timeout is about to close the connection
+    fn check_idle_warning(&mut self, now: Instant, ctx: &mut ws::WebsocketContext<Self>) {
+        let due = [
+            ("heartbeat", warning_due(self.liveness.last_transport_activity, now, self.client_timeout, self.idle_warning)),
+            ("app_heartbeat", warning_due(self.liveness.app_heartbeat_since(), now, self.app_heartbeat_timeout, self.idle_warning)),
+            ("app_idle", warning_due(self.last_app_message, now, self.app_idle_timeout, self.idle_warning)),
+        ]
+        .into_iter()
+        .filter_map(|(reason, remaining)| remaining.map(|r| (reason, r)))
+        .min_by_key(|(_, remaining)| *remaining);
+
+        match due {
+            Some((reason, remaining)) if !self.idle_warning_sent => {
+                self.idle_warning_sent = true;
+                self.send_json(ctx, json!({
+                    "type": "idle_warning",
+                    "reason": reason,
+                    "seconds_remaining": remaining.as_secs()
+                }));
+            }
+            Some(_) => {}
+            // Client activity moved the deadline out of the warning window
+            None => self.idle_warning_sent = false,
+        }
+    }
+
+    /// Periodically re-send the maintenance banner while maintenance mode is active
+    fn start_maintenance_broadcast(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        if self.maintenance_broadcast_interval.is_zero() {
+            return;
+        }
+        ctx.run_interval(self.maintenance_broadcast_interval, |act, ctx| {
+            if let Some(frame) = maintenance_frame(&act.maintenance) {
+                act.send_json(ctx, frame);
             }
-            // Send ping
-            ctx.ping(b"");
         });
     }
     
@@ -154,11 +788,7 @@ impl<T: UserStorage> WebSocketSession<T> {
         ctx.run_later(self.auth_timeout, |act, ctx| {
             if act.auth_state != AuthState::Authenticated {
                 warn!("WebSocket authentication timeout, disconnecting: {}", act.id);
-                ctx.text(json!({
-                    "type": "error",
-                    "code": "auth_timeout",
-                    "message": "Authentication timeout"
-                }).to_string());
+                act.send_error(ctx, "auth_timeout", "Authentication timeout");
                 // Give client time to receive the message before closing
                 ctx.run_later(act.close_delay, |_, ctx| ctx.stop());
             }
@@ -168,38 +798,164 @@ impl<T: UserStorage> WebSocketSession<T> {
     /// Handle authentication message
     fn handle_authentication_message(&mut self, text: &str, ctx: &mut ws::WebsocketContext<Self>) {
         let auth_result = match serde_json::from_str::<WebSocketMessage>(text) {
-            Ok(WebSocketMessage::Auth(auth_msg)) => {
-                self.auth_state = AuthState::Authenticating;
-                self.verify_authentication(auth_msg, ctx)
+            Ok(WebSocketMessage::Auth(request)) => {
+                self.message_counters.auth += 1;
+                if !self.auth_state.try_begin_authentication() {
+                    self.send_error(ctx, "auth_in_progress", "An authentication attempt is already in progress");
+                    return;
+                }
+                match request {
+                    WebSocketAuthRequest::Signature(auth_msg) => self.verify_authentication(auth_msg, ctx),
+                    WebSocketAuthRequest::ExternalToken { token } => self.verify_external_token(token, ctx),
+                }
+            },
+            Ok(WebSocketMessage::Reconnect { token }) => {
+                self.message_counters.auth += 1;
+                self.handle_reconnect(&token, ctx);
+                return;
             },
             Ok(_) => {
-                ctx.text(json!({
-                    "type": "error",
-                    "code": "auth_required",
-                    "message": "Authentication required as first message"
-                }).to_string());
+                self.send_error(ctx, "auth_required", "Authentication required as first message");
                 return;
             },
             Err(e) => {
-                ctx.text(json!({
-                    "type": "error",
-                    "code": "invalid_message",
-                    "message": format!("Failed to parse message: {}", e)
-                }).to_string());
+                self.send_error(ctx, "invalid_message", format!("Failed to parse message: {}", e));
                 return;
             }
         };
         if let Err(e) = auth_result {
             self.auth_state = AuthState::Failed;
-            ctx.text(json!({
-                "type": "error",
-                "code": "auth_failed",
-                "message": format!("Authentication failed: {}", e)
-            }).to_string());
+            self.send_error(ctx, "auth_failed", format!("Authentication failed: {}", e));
             ctx.run_later(self.close_delay, |_, ctx| ctx.stop());
         }
     }
     
+    /// Mark the session authenticated and send `auth_success` with a fresh reconnect token
+    fn complete_authentication(
+        &mut self,
+        ctx: &mut ws::WebsocketContext<Self>,
+        user_id: i64,
+        public_key: Option<String>,
+    ) {
+        self.auth_state = AuthState::Authenticated;
+        self.user_id = Some(user_id);
+        self.public_key = public_key.clone();
+        self.verified_keys = public_key.iter().cloned().collect();
+        self.registry.update(&self.id, |info| {
+            info.user_id = Some(user_id);
+            info.authenticated = true;
+        });
+        self.broadcast
+            .subscribe_channels(user_id, &self.id, &ctx.address(), self.channels.clone());
+
+        let reconnect_token = self
+            .reconnect_tokens
+            .issue(user_id, public_key, &self.client_ip, self.clock.now());
+        let session_id = self.id.clone();
+        self.send_json(ctx, json!({
+            "type": "auth_success",
+            "user_id": user_id,
+            "session_id": session_id,
+            "reconnect_token": reconnect_token
+        }));
+        self.run_on_authenticated(ctx, user_id);
+        self.apply_user_rate_limits(ctx, user_id);
+    }
+
+    /// Look up the user's tier and move the session's limits from the client IP to the user.
+    ///
+    /// Until the lookup finishes the strictest tier still applies; a user already at their
+    /// connection limit is closed.
+    fn apply_user_rate_limits(&self, ctx: &mut ws::WebsocketContext<Self>, user_id: i64) {
+        use actix::fut::wrap_future;
+        use actix::ActorFutureExt;
+        let user_storage = match &self.signature_service {
+            Some(s) => s.user_storage(),
+            None => return,
+        };
+        let fut = wrap_future(async move {
+            user_storage.find_user_by_id(user_id).await
+        })
+        .map(move |res, act: &mut WebSocketSession<T>, ctx| {
+            let tier = match res {
+                Ok(Some(user)) => user.rate_limit_tier,
+                Ok(None) => RateLimitTier::Basic,
+                Err(e) => {
+                    error!("Failed to look up rate-limit tier for user {}: {}: {}", user_id, e, act.id);
+                    RateLimitTier::Basic
+                }
+            };
+            let key = RateLimitKey::User(user_id);
+            if !act.rate_limits.rekey_connection(&act.rate_limit_key, &key, tier) {
+                warn!("User {} is at their WebSocket connection limit, closing: {}", user_id, act.id);
+                act.send_error(ctx, "too_many_connections", "Too many open connections for this account");
+                ctx.run_later(act.close_delay, |_, ctx| ctx.stop());
+                return;
+            }
+            act.rate_limit_key = key;
+            act.rate_limit_tier = tier;
+        });
+        ctx.spawn(fut);
+    }
+
+    /// Run the deployment's `OnAuthenticated` hook without blocking message handling
+    fn run_on_authenticated(&self, ctx: &mut ws::WebsocketContext<Self>, user_id: i64) {
+        use actix::fut::wrap_future;
+        use actix::ActorFutureExt;
+        let hook = self.on_authenticated.clone();
+        let client_ip = self.client_ip.clone();
+        let fut = wrap_future(async move {
+            hook.on_authenticated(user_id, &client_ip).await
+        })
+        .map(move |res, act: &mut WebSocketSession<T>, ctx| {
+            if let Err(e) = res {
+                error!("OnAuthenticated hook failed for user {}: {}: {}", user_id, e, act.id);
+                if act.close_on_hook_error {
+                    act.send_error(ctx, "session_setup_failed", "Session setup failed");
+                    ctx.run_later(act.close_delay, |_, ctx| ctx.stop());
+                }
+            }
+        });
+        ctx.spawn(fut);
+    }
+
+    /// Authenticate from a reconnect token instead of a signature
+    fn handle_reconnect(&mut self, token: &str, ctx: &mut ws::WebsocketContext<Self>) {
+        if self.auth_state == AuthState::Authenticating {
+            self.send_error(ctx, "auth_in_progress", "An authentication attempt is already in progress");
+            return;
+        }
+
+        // Redeeming consumes the token; success issues a new one
+        match self.reconnect_tokens.redeem(token, &self.client_ip, self.clock.now()) {
+            Ok(grant) => {
+                if grant.ip_changed(&self.client_ip) {
+                    self.audit_ip_change(&grant, true);
+                }
+                info!("WebSocket reconnect token redeemed for user {}: {}", grant.user_id, self.id);
+                self.admit_user_id(ctx, grant.user_id, grant.public_key);
+            }
+            Err(rejection) => {
+                if let ReconnectRejection::IpChanged(grant) = &rejection {
+                    self.audit_ip_change(grant, false);
+                }
+                warn!("WebSocket reconnect token rejected: {}", self.id);
+                self.send_error(ctx, "reconnect_failed", "Reconnect token is invalid, expired or bound to another address; authenticate with a signature");
+            }
+        }
+    }
+
+    /// Record a reconnect token presented from another IP than it was issued to
+    fn audit_ip_change(&self, grant: &ReconnectGrant, resumed: bool) {
+        self.audit.record(AuditEvent::ReconnectIpMismatch {
+            user_id: grant.user_id,
+            session_id: self.id.clone(),
+            original_ip: grant.client_ip.clone(),
+            client_ip: self.client_ip.clone(),
+            resumed,
+        });
+    }
+
     /// Verify authentication message asynchronously
     fn verify_authentication(&mut self, auth_msg: WebSocketAuthMessage, ctx: &mut ws::WebsocketContext<Self>) -> Result<(), String> {
         // Ensure we have a signature service
@@ -210,43 +966,52 @@ impl<T: UserStorage> WebSocketSession<T> {
         let auth_clone = auth_msg.clone();
         let session_id = self.id.clone();
         let public_key = auth_msg.public_key.clone();
+        let started = Instant::now();
         // Spawn asynchronous verification future
         use actix::fut::wrap_future;
         use actix::ActorFutureExt;
         let fut = wrap_future(async move {
-            signature_service.verify_websocket_auth(&auth_clone).await
+            let denied = signature_service.denies_key(&auth_clone.public_key);
+            let res = match signature_service.authenticate_websocket(&auth_clone).await {
+                // The key's owner is resolved here so the gates can judge the account
+                Ok(Some(authentication)) => signature_service
+                    .user_storage()
+                    .find_user_by_id(authentication.user_id)
+                    .await
+                    .map(|user| user.map(|user| (authentication, user))),
+                Ok(None) => Ok(None),
+                Err(e) => Err(e),
+            };
+            (res, denied)
         })
-        .map(move |res, act: &mut WebSocketSession<T>, ctx| {
+        .map(move |(res, denied), act: &mut WebSocketSession<T>, ctx| {
+            act.metrics.observe_ws_auth_verification(started.elapsed());
             match res {
-                Ok(Some(user_id)) => {
-                    act.auth_state = AuthState::Authenticated;
-                    act.user_id = Some(user_id);
-                    act.public_key = Some(public_key.clone());
-                    info!("WebSocket authenticated for user {}: {}", user_id, session_id);
-                    ctx.text(json!({
-                        "type": "auth_success",
-                        "user_id": user_id,
-                        "session_id": session_id
-                    }).to_string());
+                Ok(Some((authentication, user))) => {
+                    if !act.admit(ctx, &user, Some(public_key.clone())) {
+                        return;
+                    }
+                    act.record_key_used(ctx, user.id, public_key.clone());
+                    info!("WebSocket authenticated for user {}: {}", user.id, session_id);
+                    if authentication.revoked_in_grace && act.auth_state == AuthState::Authenticated {
+                        act.send_json(ctx, json!({
+                            "type": "key_rotation_required",
+                            "message": "This key has been revoked; authenticate again with your current key"
+                        }));
+                    }
                 }
                 Ok(None) => {
                     act.auth_state = AuthState::Failed;
                     warn!("WebSocket valid signature but no user: {}", session_id);
-                    ctx.text(json!({
-                        "type": "error",
-                        "code": "unknown_key",
-                        "message": "Valid signature but no user associated with this public key"
-                    }).to_string());
+                    act.send_error(ctx, "unknown_key", "Valid signature but no user associated with this public key");
                     ctx.run_later(act.close_delay, |_, ctx| ctx.stop());
                 }
+                // The signature service refuses denied keys before verifying; report them as the gate would
+                Err(DashboardError::Authentication(_)) if denied => act.reject_connection(ctx, GateRejection::KeyDenied),
                 Err(e) => {
                     act.auth_state = AuthState::Failed;
                     error!("WebSocket authentication error: {}: {}", e, session_id);
-                    ctx.text(json!({
-                        "type": "error",
-                        "code": "auth_failed",
-                        "message": format!("Authentication failed: {}", e)
-                    }).to_string());
+                    act.send_error(ctx, "auth_failed", format!("Authentication failed: {}", e));
                     ctx.run_later(act.close_delay, |_, ctx| ctx.stop());
                 }
             }
@@ -255,95 +1020,487 @@ impl<T: UserStorage> WebSocketSession<T> {
         Ok(())
     }
     
+    /// Fail authentication with the gate's error code and close the connection
+    fn reject_connection(&mut self, ctx: &mut ws::WebsocketContext<Self>, rejection: GateRejection) {
+        self.auth_state = AuthState::Failed;
+        self.send_error(ctx, rejection.code(), rejection.message());
+        ctx.run_later(self.close_delay, |_, ctx| ctx.stop());
+    }
+
+    /// Run the connection gates for `user` and complete authentication if they all pass.
+    ///
+    /// Every auth path goes through here, so a refused user can't get in by another route.
+    /// Returns whether the user was admitted; a refusal closes the connection.
+    fn admit(&mut self, ctx: &mut ws::WebsocketContext<Self>, user: &User, public_key: Option<String>) -> bool {
+        let request = GateRequest {
+            user,
+            public_key: public_key.as_deref(),
+        };
+        if let Err(rejection) = self.connection_gates.check(&request) {
+            warn!("WebSocket connection for user {} refused by gate: {}: {}", user.id, rejection.code(), self.id);
+            self.reject_connection(ctx, rejection);
+            return false;
+        }
+        self.complete_authentication(ctx, user.id, public_key);
+        true
+    }
+
+    /// Stamp a key as used once its session is admitted; a failure only costs the timestamp
+    fn record_key_used(&self, ctx: &mut ws::WebsocketContext<Self>, user_id: i64, public_key: String) {
+        use actix::fut::wrap_future;
+        let signature_service = match &self.signature_service {
+            Some(s) => s.clone(),
+            None => return,
+        };
+        let session_id = self.id.clone();
+        ctx.spawn(wrap_future(async move {
+            if let Err(e) = signature_service.record_key_used(user_id, &public_key).await {
+                warn!("Failed to record key use for user {}: {}: {}", user_id, e, session_id);
+            }
+        }));
+    }
+
+    /// Look up a user known only by ID, e.g. from a ticket or reconnect token, and `admit` them
+    fn admit_user_id(&mut self, ctx: &mut ws::WebsocketContext<Self>, user_id: i64, public_key: Option<String>) {
+        use actix::fut::wrap_future;
+        use actix::ActorFutureExt;
+        let user_storage = match &self.signature_service {
+            Some(s) => s.user_storage(),
+            None => {
+                self.auth_state = AuthState::Failed;
+                self.send_error(ctx, "auth_failed", "Authentication failed: user storage not configured");
+                ctx.run_later(self.close_delay, |_, ctx| ctx.stop());
+                return;
+            }
+        };
+        self.auth_state = AuthState::Authenticating;
+        let fut = wrap_future(async move {
+            user_storage.find_user_by_id(user_id).await
+        })
+        .map(move |res, act: &mut WebSocketSession<T>, ctx| {
+            match res {
+                Ok(Some(user)) => {
+                    if act.admit(ctx, &user, public_key) {
+                        info!("WebSocket authenticated for user {}: {}", user_id, act.id);
+                    }
+                }
+                Ok(None) => {
+                    act.auth_state = AuthState::Failed;
+                    warn!("WebSocket credential names missing user {}: {}", user_id, act.id);
+                    act.send_error(ctx, "auth_failed", "Authentication failed: user not found");
+                    ctx.run_later(act.close_delay, |_, ctx| ctx.stop());
+                }
+                Err(e) => {
+                    act.auth_state = AuthState::Failed;
+                    error!("WebSocket user lookup failed for user {}: {}: {}", user_id, e, act.id);
+                    act.send_error(ctx, "auth_failed", format!("Authentication failed: {}", e));
+                    ctx.run_later(act.close_delay, |_, ctx| ctx.stop());
+                }
+            }
+        });
+        ctx.spawn(fut);
+    }
+
+    /// Verify an identity provider token asynchronously
+    fn verify_external_token(&mut self, token: String, ctx: &mut ws::WebsocketContext<Self>) -> Result<(), String> {
+        let external_auth = match &self.external_auth {
+            Some(s) => s.clone(),
+            None => return Err("Identity provider tokens are not accepted".to_string()),
+        };
+        let started = Instant::now();
+        use actix::fut::wrap_future;
+        use actix::ActorFutureExt;
+        let fut = wrap_future(async move {
+            external_auth.authenticate(&token).await
+        })
+        .map(move |res, act: &mut WebSocketSession<T>, ctx| {
+            act.metrics.observe_ws_auth_verification(started.elapsed());
+            match res {
+                Ok(user) => {
+                    if act.admit(ctx, &user, None) {
+                        info!("WebSocket authenticated via identity provider for user {}: {}", user.id, act.id);
+                    }
+                }
+                Err(e) => {
+                    act.auth_state = AuthState::Failed;
+                    warn!("WebSocket identity provider token rejected: {}: {}", e, act.id);
+                    act.send_error(ctx, "auth_failed", format!("Authentication failed: {}", e));
+                    ctx.run_later(act.close_delay, |_, ctx| ctx.stop());
+                }
+            }
+        });
+        ctx.spawn(fut);
+        Ok(())
+    }
+
+    /// Verify an `Auth` sent on an authenticated session and add its key to `verified_keys`.
+    ///
+    /// The session keeps its current keys whatever the outcome; a rejected escalation is
+    /// answered with an `escalation_failed` error and does not close the connection.
+    fn escalate_authentication(&mut self, auth_msg: WebSocketAuthMessage, ctx: &mut ws::WebsocketContext<Self>) {
+        if self.escalating {
+            self.send_error(ctx, "auth_in_progress", "An authentication attempt is already in progress");
+            return;
+        }
+        if self.verified_keys.contains(&auth_msg.public_key) {
+            self.send_json(ctx, json!({
+                "type": "info",
+                "message": "Already authenticated with this key"
+            }));
+            return;
+        }
+        let signature_service = match &self.signature_service {
+            Some(s) => s.clone(),
+            None => {
+                self.send_error(ctx, "escalation_failed", "Signature service not configured");
+                return;
+            }
+        };
+
+        self.escalating = true;
+        let public_key = auth_msg.public_key.clone();
+        let started = Instant::now();
+        use actix::fut::wrap_future;
+        use actix::ActorFutureExt;
+        let fut = wrap_future(async move {
+            signature_service.authenticate_websocket(&auth_msg).await
+        })
+        .map(move |res, act: &mut WebSocketSession<T>, ctx| {
+            act.escalating = false;
+            act.metrics.observe_ws_auth_verification(started.elapsed());
+            let outcome = match res {
+                Ok(authentication) => check_escalation(act.user_id, authentication.as_ref()),
+                Err(e) => Err(format!("Invalid signature: {}", e)),
+            };
+            match outcome {
+                Ok(()) => {
+                    info!("WebSocket session escalated with an additional key for user {}: {}", act.user_id.unwrap_or(0), act.id);
+                    act.verified_keys.insert(public_key.clone());
+                    if let Some(user_id) = act.user_id {
+                        act.record_key_used(ctx, user_id, public_key.clone());
+                    }
+                    let verified_keys: Vec<String> = act.verified_keys.iter().cloned().collect();
+                    act.send_json(ctx, json!({
+                        "type": "auth_escalated",
+                        "public_key": public_key,
+                        "verified_keys": verified_keys
+                    }));
+                }
+                Err(reason) => {
+                    warn!("WebSocket key escalation rejected: {}: {}", reason, act.id);
+                    act.send_error(ctx, "escalation_failed", reason);
+                }
+            }
+        });
+        ctx.spawn(fut);
+    }
+
+    /// Send a `stats` frame with the user's network statistics and this session's counters
+    fn send_stats(&mut self, ctx: &mut ws::WebsocketContext<Self>) {
+        let (user_id, network_service) = match (self.user_id, self.network_service.clone()) {
+            (Some(user_id), Some(network_service)) => (user_id, network_service),
+            _ => {
+                self.send_error(ctx, "stats_unavailable", "Statistics are not available");
+                return;
+            }
+        };
+
+        use actix::fut::wrap_future;
+        use actix::ActorFutureExt;
+        let fut = wrap_future(async move {
+            network_service.get_network_statistics(user_id).await
+        })
+        .map(|res, act: &mut WebSocketSession<T>, ctx| match res {
+            Ok(statistics) => {
+                let session_stats = act.message_counters;
+                act.send_json(ctx, json!({
+                    "type": "stats",
+                    "statistics": statistics,
+                    "session_stats": session_stats
+                }));
+            }
+            Err(e) => {
+                error!("Failed to load network statistics for session {}: {}", act.id, e);
+                act.send_error(ctx, "stats_unavailable", format!("Failed to load statistics: {}", e));
+            }
+        });
+        ctx.spawn(fut);
+    }
+
+    /// Add or remove broadcast channels and ack with the resulting subscription set.
+    ///
+    /// Unknown channel names reject the whole request, leaving the set unchanged.
+    fn update_channels(&mut self, ctx: &mut ws::WebsocketContext<Self>, channels: Vec<String>, subscribe: bool) {
+        let unknown: Vec<&str> = channels
+            .iter()
+            .map(String::as_str)
+            .filter(|channel| !is_known_channel(channel))
+            .collect();
+        if !unknown.is_empty() {
+            self.send_error(ctx, "unknown_channel", format!(
+                "Unknown channels: {}; known channels: {}",
+                unknown.join(", "),
+                KNOWN_CHANNELS.join(", ")
+            ));
+            return;
+        }
+
+        for channel in channels {
+            if subscribe {
+                self.channels.insert(channel);
+            } else {
+                self.channels.remove(&channel);
+            }
+        }
+        if let Some(user_id) = self.user_id {
+            self.broadcast.set_channels(user_id, &self.id, self.channels.clone());
+        }
+        let channels = self.channels.clone();
+        self.send_json(ctx, json!({
+            "type": "subscriptions",
+            "channels": channels
+        }));
+    }
+
     /// Handle normal message for authenticated connections
     fn handle_normal_message(&mut self, text: &str, ctx: &mut ws::WebsocketContext<Self>) {
-        if self.auth_state != AuthState::Authenticated {
-            ctx.text(json!({
-                "type": "error",
-                "code": "unauthorized",
-                "message": "Authentication required"
-            }).to_string());
+        if !self.accepts_messages() {
+            self.send_error(ctx, "unauthorized", "Authentication required");
             return;
         }
+
+        // Retransmitted messages are acknowledged again but not reprocessed
+        let message_id = client_message_id(text);
+        if let Some(id) = &message_id {
+            if self.recent_message_ids.contains(id, self.clock.now()) {
+                debug!("Dropping duplicate message {} on session {}", id, self.id);
+                self.send_json(ctx, json!({
+                    "type": "ack",
+                    "id": id,
+                    "duplicate": true
+                }));
+                return;
+            }
+        }
+
         match serde_json::from_str::<WebSocketMessage>(text) {
+            Ok(message) if message.is_state_changing() && self.read_only.is_enabled() => {
+                self.send_error(ctx, "read_only", "The server is read-only; state changes are temporarily refused");
+            },
             Ok(message) => {
+                // Only accepted messages count as seen, so a refused one can be retried later
+                if let Some(id) = &message_id {
+                    self.recent_message_ids.insert(id, self.clock.now());
+                }
                 match message {
                     WebSocketMessage::Heartbeat => {
-                        self.last_heartbeat = Instant::now();
-                        ctx.text(json!({
+                        self.message_counters.heartbeat += 1;
+                        self.liveness.record_app_heartbeat(self.clock.now());
+                        self.send_json(ctx, json!({
                             "type": "heartbeat_ack",
                             "timestamp": chrono::Utc::now().timestamp()
-                        }).to_string());
+                        }));
                     },
                     WebSocketMessage::ConnectionUpdate { connected } => {
+                        self.message_counters.connection_update += 1;
                         debug!("Connection update from user {}: connected={}", self.user_id.unwrap_or(0), connected);
-                        ctx.text(json!({
+                        self.send_json(ctx, json!({
                             "type": "connection_update_ack",
                             "connected": connected
-                        }).to_string());
+                        }));
                     },
-                    WebSocketMessage::NetworkUpdate { status, score } => {
+                    WebSocketMessage::NetworkUpdate { status, score, connection_id } => {
+                        self.message_counters.network_update += 1;
                         debug!("Network update from user {}: status={}, score={}", self.user_id.unwrap_or(0), status, score);
-                        ctx.text(json!({
+                        // Acked right away; the write itself is coalesced with later updates
+                        if let (Some(user_id), Some(connection_id), Some(updates)) =
+                            (self.user_id, connection_id, &self.network_updates)
+                        {
+                            updates.submit(connection_id, NetworkUpdate {
+                                user_id,
+                                status_message: status.clone(),
+                                network_score: score,
+                            });
+                        }
+                        self.send_json(ctx, json!({
                             "type": "network_update_ack",
                             "status": status,
                             "score": score
-                        }).to_string());
+                        }));
                     },
-                    WebSocketMessage::Auth(_) => {
-                        ctx.text(json!({
+                    WebSocketMessage::Auth(_) | WebSocketMessage::Reconnect { .. } if self.anonymous => {
+                        self.send_json(ctx, json!({
                             "type": "info",
-                            "message": "Already authenticated"
-                        }).to_string());
+                            "message": "Authentication is not used on this endpoint"
+                        }));
+                    },
+                    WebSocketMessage::Auth(WebSocketAuthRequest::Signature(auth_msg)) => {
+                        self.message_counters.auth += 1;
+                        self.escalate_authentication(auth_msg, ctx);
+                    },
+                    WebSocketMessage::Auth(WebSocketAuthRequest::ExternalToken { .. }) => {
+                        self.message_counters.auth += 1;
+                        self.send_error(ctx, "escalation_failed", "Only signed keys can be added to an authenticated session");
+                    },
+                    WebSocketMessage::StatsRequest => {
+                        self.send_stats(ctx);
+                    },
+                    WebSocketMessage::Subscribe { channels } => {
+                        self.update_channels(ctx, channels, true);
+                    },
+                    WebSocketMessage::Unsubscribe { channels } => {
+                        self.update_channels(ctx, channels, false);
                     },
                     _ => {
-                        ctx.text(text);
+                        self.send_text(ctx, text.to_owned());
                     }
                 }
             },
             Err(e) => {
-                ctx.text(json!({
-                    "type": "error",
-                    "code": "invalid_message",
-                    "message": format!("Failed to parse message: {}", e)
-                }).to_string());
+                self.send_error(ctx, "invalid_message", format!("Failed to parse message: {}", e));
             }
         }
     }
 }
 
-/// WebSocket connection handler
+/// Answer a WebSocket upgrade that hasn't started within `WS_HANDSHAKE_TIMEOUT` seconds with
+/// `408 Request Timeout` (0 disables). Wraps the WebSocket scope only, so other routes keep
+/// just `SERVER_CLIENT_REQUEST_TIMEOUT`.
+pub async fn handshake_timeout(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let timeout = req
+        .app_data::<web::Data<Config>>()
+        .map(|config| Duration::from_secs(config.websocket.handshake_timeout))
+        .unwrap_or_default();
+    if timeout.is_zero() {
+        return next.call(req).await;
+    }
+    match tokio::time::timeout(timeout, next.call(req)).await {
+        Ok(res) => res,
+        Err(_) => {
+            warn!("WebSocket handshake did not complete within {:?}, aborting", timeout);
+            Err(DashboardError::request_timeout("WebSocket handshake timed out").into())
+        }
+    }
+}
+
+/// WebSocket connection handler; `anonymous` endpoints skip authentication entirely
 pub async fn websocket_route(
     req: HttpRequest,
     stream: web::Payload,
     config: web::Data<Config>,
-    signature_service: web::Data<SignatureService<InMemoryUserStorage>>,
+    signature_service: web::Data<SignatureService<AppUserStorage>>,
+    services: web::Data<SessionServices>,
+    anonymous: bool,
 ) -> Result<HttpResponse, Error> {
+    // Refuse cross-site handshakes before doing any work; an unreadable Origin is refused too
+    let origin = req
+        .headers()
+        .get(header::ORIGIN)
+        .map(|value| value.to_str().unwrap_or(""));
+    if !origin_allowed(origin, &config.server.allowed_origins, config.server.allows_any_origin()) {
+        warn!("WebSocket handshake from disallowed origin: {:?}", origin);
+        return Err(DashboardError::authorization("Origin not allowed").into());
+    }
+    
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+    let now = clock.now();
+    let query = web::Query::<HandshakeQuery>::from_query(req.query_string())
+        .map(web::Query::into_inner)
+        .unwrap_or_default();
+    
+    // A ticket is exchanged for the user identity before the upgrade; a bad one refuses it
+    let ticket_user_id = match query.ticket.as_deref() {
+        Some(ticket) if !anonymous => match services.ws_tickets.redeem(ticket, now) {
+            Some(user_id) => Some(user_id),
+            None => {
+                warn!("WebSocket handshake with invalid or expired ticket");
+                return Err(DashboardError::authentication("Invalid or expired ticket").into());
+            }
+        },
+        _ => None,
+    };
+
+    // Unauthenticated connections are counted per IP under the strictest tier
+    let client_ip = UserSession::sanitize_ip_address(
+        req.connection_info().realip_remote_addr().unwrap_or("unknown"),
+    );
+    let rate_limit_key = RateLimitKey::Ip(client_ip.clone());
+    if !services.rate_limits.try_connect(&rate_limit_key, RateLimitTier::Basic) {
+        warn!("WebSocket connection limit reached for {}", client_ip);
+        return Err(DashboardError::rate_limit("Too many connections", CONNECTION_RETRY_AFTER).into());
+    }
+    
     // Create a new WebSocket session
-    let session = WebSocketSession::<InMemoryUserStorage> {
+    let session = WebSocketSession::<AppUserStorage> {
         id: nanoid!(),
         user_id: None,
-        client_ip: req
-            .connection_info()
-            .realip_remote_addr()
-            .unwrap_or("unknown")
-            .to_owned(),
-        last_heartbeat: Instant::now(),
+        client_ip,
+        liveness: Liveness::new(now),
+        last_app_message: now,
         auth_state: AuthState::NotAuthenticated,
         connected_at: Utc::now(),
         public_key: None,
+        verified_keys: BTreeSet::new(),
+        escalating: false,
         heartbeat_interval: Duration::from_secs(config.websocket.heartbeat_interval),
+        initial_ping_delay: Duration::from_secs(config.websocket.initial_ping_delay),
         client_timeout: Duration::from_secs(config.websocket.client_timeout),
+        app_heartbeat_timeout: Duration::from_secs(config.websocket.app_heartbeat_timeout),
+        app_idle_timeout: Duration::from_secs(config.websocket.app_idle_timeout),
+        idle_warning: Duration::from_secs(config.websocket.idle_warning),
+        idle_warning_sent: false,
+        clock,
         auth_timeout: Duration::from_secs(30), // 30 seconds to authenticate
         signature_service: Some(signature_service.into_inner()),
+        external_auth: services.external_auth.clone(),
         close_delay: Duration::from_secs(2), // 2 seconds before closing after auth failure
+        bytes_received: 0,
+        bytes_sent: 0,
+        metrics: services.metrics.clone(),
+        registry: services.registry.clone(),
+        maintenance: services.maintenance.clone(),
+        read_only: services.read_only.clone(),
+        maintenance_broadcast_interval: if config.feature_enabled("maintenance_broadcast") {
+            Duration::from_secs(config.websocket.maintenance_broadcast_interval)
+        } else {
+            Duration::ZERO
+        },
+        recent_message_ids: RecentMessageIds::new(
+            Duration::from_secs(config.websocket.dedup_window),
+            MAX_RECENT_MESSAGE_IDS,
+        ),
+        reconnect_tokens: services.reconnect_tokens.clone(),
+        pending_reconnect_token: query.reconnect_token,
+        ticket_user_id,
+        broadcast: services.broadcast.clone(),
+        channels: all_channels(),
+        network_service: Some(services.network_service.clone()),
+        network_updates: Some(services.network_updates.clone()),
+        message_counters: MessageCounters::default(),
+        anonymous,
+        on_authenticated: services.on_authenticated.clone(),
+        audit: services.audit.clone(),
+        close_on_hook_error: config.websocket.close_on_auth_hook_error,
+        rate_limits: services.rate_limits.clone(),
+        connection_gates: services.connection_gates.clone(),
+        rate_limit_key: rate_limit_key.clone(),
+        rate_limit_tier: RateLimitTier::Basic,
+        instance_id: config.server.instance_id.clone(),
+        field_case: config.server.json_field_case,
     };
     
     // Start websocket connection
     let resp = ws::start(session, &req, stream);
     match &resp {
         Ok(_) => info!("WebSocket connection started: {}", req.connection_info().realip_remote_addr().unwrap_or("unknown")),
-        Err(e) => error!("WebSocket connection error: {}", e),
+        Err(e) => {
+            error!("WebSocket connection error: {}", e);
+            // The session never started, so release its slot here
+            services.rate_limits.disconnect(&rate_limit_key);
+        }
     }
     resp
 }
@@ -353,9 +1510,10 @@ pub async fn dashboard_ws(
     req: HttpRequest,
     stream: web::Payload,
     config: web::Data<Config>,
-    signature_service: web::Data<SignatureService<InMemoryUserStorage>>,
+    signature_service: web::Data<SignatureService<AppUserStorage>>,
+    services: web::Data<SessionServices>,
 ) -> Result<HttpResponse, Error> {
-    websocket_route(req, stream, config, signature_service).await
+    websocket_route(req, stream, config, signature_service, services, false).await
 }
 
 /// Earnings-specific WebSocket endpoint 
@@ -363,9 +1521,10 @@ pub async fn earnings_ws(
     req: HttpRequest,
     stream: web::Payload,
     config: web::Data<Config>,
-    signature_service: web::Data<SignatureService<InMemoryUserStorage>>,
+    signature_service: web::Data<SignatureService<AppUserStorage>>,
+    services: web::Data<SessionServices>,
 ) -> Result<HttpResponse, Error> {
-    websocket_route(req, stream, config, signature_service).await
+    websocket_route(req, stream, config, signature_service, services, false).await
 }
 
 /// Referrals-specific WebSocket endpoint
@@ -373,7 +1532,338 @@ pub async fn referrals_ws(
     req: HttpRequest,
     stream: web::Payload,
     config: web::Data<Config>,
-    signature_service: web::Data<SignatureService<InMemoryUserStorage>>,
+    signature_service: web::Data<SignatureService<AppUserStorage>>,
+    services: web::Data<SessionServices>,
+) -> Result<HttpResponse, Error> {
+    websocket_route(req, stream, config, signature_service, services, false).await
+}
+
+/// Public status WebSocket endpoint; no authentication required
+pub async fn status_ws(
+    req: HttpRequest,
+    stream: web::Payload,
+    config: web::Data<Config>,
+    signature_service: web::Data<SignatureService<AppUserStorage>>,
+    services: web::Data<SessionServices>,
 ) -> Result<HttpResponse, Error> {
-    websocket_route(req, stream, config, signature_service).await
-} 
\ No newline at end of file
+    websocket_route(req, stream, config, signature_service, services, true).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_messages_parse_and_channels_are_validated() {
+        let message: WebSocketMessage =
+            serde_json::from_str(r#"{"type":"Subscribe","data":{"channels":["network","earnings"]}}"#).unwrap();
+        assert!(matches!(message, WebSocketMessage::Subscribe { ref channels } if channels.len() == 2));
+        let message: WebSocketMessage =
+            serde_json::from_str(r#"{"type":"Unsubscribe","data":{"channels":["referrals"]}}"#).unwrap();
+        assert!(matches!(message, WebSocketMessage::Unsubscribe { .. }));
+
+        assert!(KNOWN_CHANNELS.iter().all(|channel| is_known_channel(channel)));
+        assert!(!is_known_channel("admin"));
+        assert_eq!(all_channels().len(), KNOWN_CHANNELS.len());
+    }
+
+    #[test]
+    fn test_maintenance_banner_is_broadcast_only_while_active() {
+        let maintenance = MaintenanceMode::new(Some("   ".to_string()));
+        assert!(!maintenance.is_active());
+        assert_eq!(maintenance_frame(&maintenance), None);
+
+        // Clones share state, so a toggle by the admin handler reaches every session
+        let session_view = maintenance.clone();
+        maintenance.set_message(Some("Upgrading storage".to_string()));
+        assert!(session_view.is_active());
+        assert_eq!(
+            maintenance_frame(&session_view),
+            Some(json!({ "type": "maintenance", "message": "Upgrading storage" }))
+        );
+
+        maintenance.set_message(Some(String::new()));
+        assert!(!session_view.is_active());
+        assert_eq!(maintenance_frame(&session_view), None);
+
+        maintenance.set_message(Some("Back soon".to_string()));
+        maintenance.set_message(None);
+        assert_eq!(session_view.message(), None);
+    }
+
+    #[test]
+    fn test_stats_request_and_session_stats_shape() {
+        let message: WebSocketMessage = serde_json::from_str(r#"{"type":"StatsRequest"}"#).unwrap();
+        assert!(matches!(message, WebSocketMessage::StatsRequest));
+
+        let counters = MessageCounters {
+            auth: 1,
+            heartbeat: 3,
+            errors: 2,
+            ..MessageCounters::default()
+        };
+        assert_eq!(
+            serde_json::to_value(counters).unwrap(),
+            json!({
+                "auth": 1,
+                "heartbeat": 3,
+                "connection_update": 0,
+                "network_update": 0,
+                "errors": 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_protocol_pings_keep_the_transport_alive_but_not_the_app() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(60);
+        let mut liveness = Liveness::new(start);
+
+        // Only pongs arrive: the socket is alive, the app is frozen
+        for secs in [30, 60, 90] {
+            liveness.record_transport(start + Duration::from_secs(secs));
+        }
+        let now = start + Duration::from_secs(91);
+        assert!(!liveness.transport_expired(now, timeout));
+        assert!(liveness.app_heartbeat_expired(now, timeout));
+        assert_eq!(liveness.last_app_heartbeat, None);
+
+        // An app heartbeat resets the app deadline
+        liveness.record_app_heartbeat(now);
+        assert!(!liveness.app_heartbeat_expired(now + timeout, timeout));
+        assert!(!liveness.app_heartbeat_expired(now + timeout * 10, Duration::ZERO));
+    }
+
+    #[test]
+    fn test_app_heartbeat_does_not_stand_in_for_transport_activity() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(60);
+        let mut liveness = Liveness::new(start);
+
+        liveness.record_app_heartbeat(start + Duration::from_secs(50));
+        let now = start + Duration::from_secs(61);
+        assert!(liveness.transport_expired(now, timeout));
+        assert!(!liveness.app_heartbeat_expired(now, timeout));
+        assert_eq!(liveness.last_transport_activity, start);
+    }
+
+    #[test]
+    fn test_escalation_only_accepts_active_keys_of_the_same_user() {
+        let own = KeyAuthentication { user_id: 7, revoked_in_grace: false };
+        assert!(check_escalation(Some(7), Some(&own)).is_ok());
+
+        let other = KeyAuthentication { user_id: 8, revoked_in_grace: false };
+        assert!(check_escalation(Some(7), Some(&other)).is_err());
+        let revoked = KeyAuthentication { user_id: 7, revoked_in_grace: true };
+        assert!(check_escalation(Some(7), Some(&revoked)).is_err());
+        assert!(check_escalation(Some(7), None).is_err());
+        assert!(check_escalation(None, Some(&own)).is_err());
+    }
+
+    #[test]
+    fn test_second_auth_rejected_while_authenticating() {
+        let mut state = AuthState::NotAuthenticated;
+
+        // First auth message starts verification
+        assert!(state.try_begin_authentication());
+        assert_eq!(state, AuthState::Authenticating);
+
+        // A rapid second auth message is rejected and leaves the state untouched
+        assert!(!state.try_begin_authentication());
+        assert_eq!(state, AuthState::Authenticating);
+    }
+
+    #[test]
+    fn test_allowed_origin_passes() {
+        let allowed = vec!["https://app.example.com".to_string()];
+        assert!(origin_allowed(Some("https://app.example.com"), &allowed, false));
+        assert!(origin_allowed(Some("HTTPS://APP.EXAMPLE.COM/"), &allowed, false));
+    }
+
+    #[test]
+    fn test_disallowed_origin_is_rejected() {
+        let allowed = vec!["https://app.example.com".to_string()];
+        assert!(!origin_allowed(Some("https://evil.example.com"), &allowed, false));
+        assert!(!origin_allowed(Some("https://app.example.com.evil.com"), &allowed, false));
+        assert!(!origin_allowed(Some(""), &allowed, false));
+        assert!(!origin_allowed(Some("https://app.example.com"), &[], false));
+
+        // Development (or `*`) accepts any origin
+        assert!(origin_allowed(Some("https://evil.example.com"), &[], true));
+    }
+
+    #[test]
+    fn test_missing_origin_is_allowed() {
+        assert!(origin_allowed(None, &[], false));
+    }
+
+    #[test]
+    fn test_oversized_ping_is_rejected() {
+        assert!(ping_payload_allowed(&[]));
+        assert!(ping_payload_allowed(&[0u8; MAX_PING_PAYLOAD]));
+        assert!(!ping_payload_allowed(&[0u8; MAX_PING_PAYLOAD + 1]));
+        assert!(!ping_payload_allowed(&vec![0u8; 64 * 1024]));
+    }
+
+    #[test]
+    fn test_oversized_close_reason_is_not_echoed() {
+        let reply = close_reply();
+        assert_eq!(reply.code, ws::CloseCode::Normal);
+        assert!(reply.description.unwrap().len() <= MAX_CLOSE_REASON);
+
+        let oversized = "x".repeat(4096);
+        assert_eq!(truncate_close_reason(&oversized).len(), MAX_CLOSE_REASON);
+        assert_eq!(truncate_close_reason("Account suspended"), "Account suspended");
+
+        // Multi-byte characters are never split
+        let wide = "é".repeat(100);
+        let truncated = truncate_close_reason(&wide);
+        assert!(truncated.len() <= MAX_CLOSE_REASON);
+        assert_eq!(truncated.chars().count(), MAX_CLOSE_REASON / 2);
+    }
+
+    #[test]
+    fn test_duplicate_message_id_processed_once() {
+        let mut recent = RecentMessageIds::new(Duration::from_secs(60), 16);
+        let now = Instant::now();
+        let frame = r#"{"id":"msg-1","type":"heartbeat"}"#;
+
+        let mut processed = 0;
+        for _ in 0..2 {
+            let id = client_message_id(frame).unwrap();
+            if !recent.contains(&id, now) {
+                recent.insert(&id, now);
+                processed += 1;
+            }
+        }
+        assert_eq!(processed, 1);
+    }
+
+    #[test]
+    fn test_refused_message_id_is_not_remembered() {
+        let mut recent = RecentMessageIds::new(Duration::from_secs(60), 16);
+        let now = Instant::now();
+
+        // Checked but refused, e.g. while read-only: the retry is not a duplicate
+        assert!(!recent.contains("msg-1", now));
+        assert!(!recent.contains("msg-1", now + Duration::from_secs(1)));
+        recent.insert("msg-1", now + Duration::from_secs(1));
+        assert!(recent.contains("msg-1", now + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_message_id_forgotten_after_window() {
+        let mut recent = RecentMessageIds::new(Duration::from_secs(60), 16);
+        let start = Instant::now();
+
+        recent.insert("msg-1", start);
+        assert!(recent.contains("msg-1", start + Duration::from_secs(30)));
+        assert!(!recent.contains("msg-1", start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_message_ids_bounded_by_capacity() {
+        let mut recent = RecentMessageIds::new(Duration::from_secs(60), 2);
+        let now = Instant::now();
+
+        for id in ["a", "b", "c"] {
+            recent.insert(id, now);
+        }
+        // "a" was evicted to make room for "c"
+        assert!(!recent.contains("a", now));
+        assert!(recent.contains("b", now) && recent.contains("c", now));
+    }
+
+    #[test]
+    fn test_auth_can_restart_after_completion() {
+        let mut state = AuthState::Failed;
+        assert!(state.try_begin_authentication());
+        assert_eq!(state, AuthState::Authenticating);
+    }
+
+    /// Signature service and session services as `main` wires them, over in-memory storage
+    fn test_session_services(config: &Config) -> (SignatureService<AppUserStorage>, SessionServices) {
+        use crate::services::session_hooks::NoopOnAuthenticated;
+        use crate::services::{CircuitBreaker, LogAuditSink};
+        use crate::storage::memory::{InMemoryNetworkStorage, InMemoryUserStorage};
+        use crate::storage::{CircuitBreakerStorage, TracedStorage, UserBackend};
+
+        let breaker = CircuitBreaker::new(5, Duration::from_secs(30));
+        let users = CircuitBreakerStorage::new(
+            TracedStorage::new(UserBackend::Memory(InMemoryUserStorage::new())),
+            breaker.clone(),
+        );
+        let networks = CircuitBreakerStorage::new(TracedStorage::new(InMemoryNetworkStorage::new()), breaker);
+        let network_service = Arc::new(NetworkService::new(Arc::new(networks)));
+        let rate_limits = RateLimits::new(config.rate_limits.clone());
+        let services = SessionServices {
+            metrics: Arc::new(Metrics::new()),
+            registry: SessionRegistry::new(),
+            maintenance: MaintenanceMode::default(),
+            read_only: ReadOnlyMode::default(),
+            reconnect_tokens: ReconnectTokens::new(Duration::from_secs(60)),
+            ws_tickets: WsTickets::new(Duration::from_secs(60)),
+            broadcast: BroadcastHub::new(),
+            network_service: network_service.clone(),
+            network_updates: NetworkUpdateCoalescer::new(network_service, Duration::ZERO),
+            on_authenticated: Arc::new(NoopOnAuthenticated),
+            audit: Arc::new(LogAuditSink),
+            connection_gates: ConnectionGates::new(),
+            rate_limits,
+            external_auth: None,
+        };
+        (SignatureService::new(Arc::new(users)), services)
+    }
+
+    #[actix_rt::test]
+    async fn test_stalled_upgrade_is_timed_out() {
+        use actix_web::middleware::from_fn;
+        use actix_web::test::{call_service, init_service, try_call_service, TestRequest};
+
+        // Stands in for whatever holds up an upgrade before the session starts
+        async fn stall(
+            req: ServiceRequest,
+            next: Next<impl MessageBody + 'static>,
+        ) -> Result<ServiceResponse<impl MessageBody>, Error> {
+            if req.query_string().contains("stall") {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            next.call(req).await
+        }
+
+        let mut config = Config::from_env().unwrap();
+        config.websocket.handshake_timeout = 1;
+        let (signature_service, services) = test_session_services(&config);
+        let app = init_service(
+            actix_web::App::new()
+                .app_data(web::Data::new(config))
+                .app_data(web::Data::new(signature_service))
+                .app_data(web::Data::new(services))
+                .service(crate::routes::websocket_routes().wrap(from_fn(stall)).wrap(from_fn(handshake_timeout))),
+        )
+        .await;
+        let upgrade = |uri: &str| {
+            TestRequest::get()
+                .uri(uri)
+                .insert_header((header::CONNECTION, "upgrade"))
+                .insert_header((header::UPGRADE, "websocket"))
+                .insert_header((header::SEC_WEBSOCKET_VERSION, "13"))
+                .insert_header((header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ=="))
+                .to_request()
+        };
+
+        // A prompt upgrade reaches the real handler and is switched over
+        let res = call_service(&app, upgrade("/ws/status")).await;
+        assert_eq!(res.status(), actix_web::http::StatusCode::SWITCHING_PROTOCOLS);
+
+        // Cut off by WS_HANDSHAKE_TIMEOUT rather than left to the general client timeout
+        let started = Instant::now();
+        let err = match try_call_service(&app, upgrade("/ws/status?stall=1")).await {
+            Ok(res) => panic!("stalled upgrade answered with {}", res.status()),
+            Err(err) => err,
+        };
+        assert_eq!(err.as_response_error().status_code(), actix_web::http::StatusCode::REQUEST_TIMEOUT);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,63 @@
+use nanoid::nanoid;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::errors::{DashboardError, DashboardResult};
+
+/// Source of random identifiers, injectable so tests can force collisions
+pub type IdGenerator = Arc<dyn Fn() -> String + Send + Sync>;
+
+/// Ids drawn before giving up on finding one that is not taken
+pub const MAX_ID_ATTEMPTS: usize = 8;
+
+/// Generator of 21-character nanoids
+pub fn nanoid_generator() -> IdGenerator {
+    Arc::new(|| nanoid!())
+}
+
+/// Draw ids from `generate` until one is not `taken`, failing with an internal error after
+/// `MAX_ID_ATTEMPTS` collisions.
+///
+/// `kind` names the id in logs and the error, e.g. `"session"`.
+pub fn unique_id(
+    generate: &IdGenerator,
+    kind: &str,
+    taken: impl Fn(&str) -> bool,
+) -> DashboardResult<String> {
+    for _ in 0..MAX_ID_ATTEMPTS {
+        let id = generate();
+        if !taken(&id) {
+            return Ok(id);
+        }
+        warn!("Generated {} id collided with an existing one, regenerating", kind);
+    }
+    Err(DashboardError::internal_server(format!(
+        "Could not generate a unique {} id after {} attempts",
+        kind, MAX_ID_ATTEMPTS
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_regenerates_on_collision_and_gives_up_eventually() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let generate: IdGenerator = Arc::new(move || {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            if n < 2 { "taken".to_string() } else { format!("id-{}", n) }
+        });
+
+        assert_eq!(unique_id(&generate, "session", |id| id == "taken").unwrap(), "id-2");
+
+        let constant: IdGenerator = Arc::new(|| "taken".to_string());
+        assert!(matches!(
+            unique_id(&constant, "session", |id| id == "taken"),
+            Err(DashboardError::InternalServer(_))
+        ));
+        assert_eq!(nanoid_generator()().len(), 21);
+    }
+}
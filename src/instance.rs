@@ -0,0 +1,53 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::{web, Error};
+use nanoid::nanoid;
+use tracing::Span;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
+
+use crate::config::Config;
+
+/// Response header naming the instance that served the request
+pub const INSTANCE_HEADER: &str = "X-Server-Instance";
+
+/// Pick this process's instance ID: `configured` if set and non-empty, otherwise a random one.
+///
+/// Set `SERVER_INSTANCE_ID` to something stable (e.g. the pod name) to match logs across
+/// restarts; the random fallback only has to tell live instances apart.
+pub fn instance_id(configured: Option<String>) -> String {
+    configured
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| format!("dashboard-{}", nanoid!(10)))
+}
+
+/// Request spans carrying the instance ID alongside the default request fields
+pub struct InstanceRootSpan;
+
+impl RootSpanBuilder for InstanceRootSpan {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let instance = request
+            .app_data::<web::Data<Config>>()
+            .map(|config| config.server.instance_id.clone())
+            .unwrap_or_default();
+        tracing_actix_web::root_span!(request, instance = %instance)
+    }
+
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_instance_id_wins_and_fallbacks_are_unique() {
+        assert_eq!(instance_id(Some(" pod-7 ".to_string())), "pod-7");
+
+        let generated = instance_id(Some("  ".to_string()));
+        assert!(generated.starts_with("dashboard-"));
+        assert_ne!(generated, instance_id(None));
+    }
+}
@@ -0,0 +1,322 @@
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header;
+use actix_web::middleware::Next;
+use actix_web::web::Bytes;
+use actix_web::{error, web, Error};
+use serde::de::{self, DeserializeOwned, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+use crate::config::Config;
+
+/// Naming convention for object keys in JSON responses and server-sent WebSocket frames.
+///
+/// Models serialize with their Rust field names (`snake_case`); `CamelCase` rewrites every key
+/// on the way out. Values, including enum values such as `"pending_verification"` and frame
+/// types, are never changed, and request bodies and client frames are always read as
+/// `snake_case`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum FieldCase {
+    /// Rust field names, e.g. `wallet_address`
+    #[default]
+    #[serde(rename = "snake_case")]
+    SnakeCase,
+    /// e.g. `walletAddress`
+    #[serde(rename = "camelCase")]
+    CamelCase,
+}
+
+impl FromStr for FieldCase {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "snake_case" => Ok(FieldCase::SnakeCase),
+            "camelCase" => Ok(FieldCase::CamelCase),
+            other => Err(format!(
+                "Unknown JSON field case '{}'; expected snake_case or camelCase",
+                other
+            )),
+        }
+    }
+}
+
+impl FieldCase {
+    /// Rename the keys of every object in `value`, recursively
+    pub fn apply(self, value: Value) -> Value {
+        match self {
+            FieldCase::SnakeCase => value,
+            FieldCase::CamelCase => rename_keys(value),
+        }
+    }
+
+    /// Rename the keys of a serialized JSON text; text that is not JSON is returned as is
+    pub fn apply_to_text(self, text: String) -> String {
+        if self == FieldCase::SnakeCase {
+            return text;
+        }
+        match serde_json::from_str::<Value>(&text) {
+            Ok(value) => self.apply(value).to_string(),
+            Err(_) => text,
+        }
+    }
+}
+
+fn rename_keys(value: Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (to_camel_case(&key), rename_keys(value)))
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(rename_keys).collect()),
+        other => other,
+    }
+}
+
+/// `wallet_address` -> `walletAddress`; keys without underscores are unchanged
+fn to_camel_case(key: &str) -> String {
+    let mut camel = String::with_capacity(key.len());
+    let mut upper_next = false;
+    for c in key.chars() {
+        if c == '_' && !camel.is_empty() {
+            upper_next = true;
+        } else if upper_next {
+            camel.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel
+}
+
+/// What to do with request body fields a DTO doesn't declare
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnknownFieldPolicy {
+    /// Fail with a `400` naming the first unexpected field, so typos don't go unnoticed
+    Reject,
+    /// Drop unexpected fields, as serde does; the default, so existing clients that send
+    /// extra metadata keep working
+    #[default]
+    Ignore,
+}
+
+impl FromStr for UnknownFieldPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "reject" => Ok(UnknownFieldPolicy::Reject),
+            "ignore" => Ok(UnknownFieldPolicy::Ignore),
+            other => Err(format!(
+                "Unknown JSON unknown-field policy '{}'; expected reject or ignore",
+                other
+            )),
+        }
+    }
+}
+
+impl UnknownFieldPolicy {
+    /// Check the top-level keys of a request body against the fields `T` declares, naming the
+    /// first one it doesn't. Types that aren't plain structs are not checked.
+    pub fn check<T: DeserializeOwned>(self, body: &Value) -> Result<(), String> {
+        let (UnknownFieldPolicy::Reject, Some(fields), Value::Object(object)) =
+            (self, struct_fields::<T>(), body)
+        else {
+            return Ok(());
+        };
+        match object.keys().find(|key| !fields.contains(&key.as_str())) {
+            Some(key) => Err(format!(
+                "unknown field `{}`, expected one of {}",
+                key,
+                fields.iter().map(|field| format!("`{}`", field)).collect::<Vec<_>>().join(", ")
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Field names a derived `Deserialize` struct accepts, read from what it passes to
+/// `deserialize_struct`, which includes `#[serde(alias)]` names; `None` for other types,
+/// including structs with `#[serde(flatten)]` fields
+fn struct_fields<T: DeserializeOwned>() -> Option<&'static [&'static str]> {
+    let mut fields = None;
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+/// Deserializer that records the field list of the struct asked of it, then fails
+struct FieldNames<'a>(&'a mut Option<&'static [&'static str]>);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = Some(fields);
+        Err(de::Error::custom("fields recorded"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+/// Middleware applying the configured `FieldCase` to `application/json` response bodies.
+///
+/// Other responses, such as NDJSON exports and WebSocket upgrades, pass through untouched.
+pub async fn rewrite_json_fields(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let case = req
+        .app_data::<web::Data<Config>>()
+        .map(|config| config.server.json_field_case)
+        .unwrap_or_default();
+    let res = next.call(req).await?;
+
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    if case == FieldCase::SnakeCase || !is_json {
+        return Ok(res.map_into_boxed_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = body::to_bytes(body).await.map_err(|e| {
+        let e: Box<dyn std::error::Error> = e.into();
+        error::ErrorInternalServerError(e.to_string())
+    })?;
+    let bytes = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(value) => serde_json::to_vec(&case.apply(value)).map(Bytes::from).unwrap_or(bytes),
+        Err(_) => bytes,
+    };
+
+    Ok(ServiceResponse::new(req, res.set_body(bytes).map_into_boxed_body()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::response::ApiResponse;
+    use actix_web::test::{call_and_read_body, call_and_read_body_json, init_service, TestRequest};
+    use actix_web::{middleware, App};
+    use serde_json::json;
+
+    #[test]
+    fn test_camel_case_renames_nested_keys_but_not_values() {
+        let frame = json!({
+            "type": "auth_success",
+            "user_id": 1,
+            "session_stats": {"connection_update": 2},
+            "networks": [{"network_score": 50.0, "status": "pending_verification"}]
+        });
+
+        assert_eq!(FieldCase::SnakeCase.apply(frame.clone()), frame);
+        assert_eq!(
+            FieldCase::CamelCase.apply(frame),
+            json!({
+                "type": "auth_success",
+                "userId": 1,
+                "sessionStats": {"connectionUpdate": 2},
+                "networks": [{"networkScore": 50.0, "status": "pending_verification"}]
+            })
+        );
+        assert_eq!(FieldCase::CamelCase.apply_to_text("not json".to_string()), "not json");
+        assert_eq!("camelCase".parse(), Ok(FieldCase::CamelCase));
+        assert!("kebab-case".parse::<FieldCase>().is_err());
+    }
+
+    #[test]
+    fn test_unknown_field_policy() {
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Signup {
+            email: String,
+            password: String,
+        }
+
+        let body = json!({"email": "a@example.com", "passwrd": "secret"});
+        assert_eq!(
+            UnknownFieldPolicy::Reject.check::<Signup>(&body),
+            Err("unknown field `passwrd`, expected one of `email`, `password`".to_string())
+        );
+        assert_eq!(UnknownFieldPolicy::Ignore.check::<Signup>(&body), Ok(()));
+        assert_eq!(
+            UnknownFieldPolicy::Reject.check::<Signup>(&json!({"email": "a", "password": "b"})),
+            Ok(())
+        );
+        // Only structs declare their fields
+        assert_eq!(UnknownFieldPolicy::Reject.check::<Value>(&body), Ok(()));
+        assert_eq!("ignore".parse(), Ok(UnknownFieldPolicy::Ignore));
+        assert_eq!(UnknownFieldPolicy::default(), UnknownFieldPolicy::Ignore);
+    }
+
+    #[test]
+    fn test_unknown_field_policy_accepts_aliases() {
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Wallet {
+            #[serde(alias = "wallet")]
+            wallet_address: String,
+        }
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct Tagged {
+            name: String,
+            #[serde(flatten)]
+            extra: std::collections::HashMap<String, Value>,
+        }
+
+        assert_eq!(UnknownFieldPolicy::Reject.check::<Wallet>(&json!({"wallet": "0xabc"})), Ok(()));
+        assert_eq!(UnknownFieldPolicy::Reject.check::<Wallet>(&json!({"wallet_address": "0xabc"})), Ok(()));
+        assert!(UnknownFieldPolicy::Reject.check::<Wallet>(&json!({"walet": "0xabc"})).is_err());
+        // Flattened structs take any field, so they are not checked
+        assert_eq!(UnknownFieldPolicy::Reject.check::<Tagged>(&json!({"name": "a", "color": "red"})), Ok(()));
+    }
+
+    #[actix_rt::test]
+    async fn test_middleware_rewrites_json_responses_only() {
+        let mut config = Config::from_env().unwrap();
+        config.server.json_field_case = FieldCase::CamelCase;
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(config))
+                .wrap(middleware::from_fn(rewrite_json_fields))
+                .route("/json", web::get().to(|| async { ApiResponse::ok(json!({"wallet_address": "0xabc"})) }))
+                .route(
+                    "/ndjson",
+                    web::get().to(|| async {
+                        actix_web::HttpResponse::Ok()
+                            .content_type("application/x-ndjson")
+                            .body("{\"wallet_address\":\"0xabc\"}\n")
+                    }),
+                ),
+        )
+        .await;
+
+        let body: Value = call_and_read_body_json(&app, TestRequest::get().uri("/json").to_request()).await;
+        assert_eq!(body, json!({"data": {"walletAddress": "0xabc"}, "meta": {}}));
+
+        let body = call_and_read_body(&app, TestRequest::get().uri("/ndjson").to_request()).await;
+        assert_eq!(body, "{\"wallet_address\":\"0xabc\"}\n");
+    }
+}
@@ -1,11 +1,20 @@
 // Export modules for external use
+pub mod affinity;
+pub mod cache;
+pub mod clock;
 pub mod config;
 pub mod errors;
+pub mod freshness;
 pub mod genesis;
+pub mod ids;
 pub mod handlers;
+pub mod instance;
+pub mod json_case;
+pub mod metrics;
 pub mod models;
 pub mod routes;
 pub mod services;
+pub mod shutdown;
 pub mod storage;
 #[cfg(debug_assertions)]
 pub mod dev; 
\ No newline at end of file
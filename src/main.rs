@@ -1,40 +1,149 @@
 // Main modules
+mod affinity;
+mod cache;
+mod clock;
 mod config;
 mod errors;
+mod freshness;
 mod genesis;
+mod ids;
 mod handlers;
+mod instance;
+mod json_case;
+mod metrics;
 mod models;
 mod routes;
 mod services;
+mod shutdown;
 mod storage;
 #[cfg(debug_assertions)]
 mod dev;
 
 use actix_web::{web, App, HttpServer, Responder, HttpResponse, get, middleware};
 use actix_cors::Cors;
-use tracing::{info, Level, warn};
+use tracing::{error, info, Level, warn};
+use tracing_actix_web::TracingLogger;
 use tracing_subscriber::FmtSubscriber;
 use std::time::Duration;
 use std::sync::Arc;
+use crate::affinity::{check_affinity, AffinitySigner};
+use crate::handlers::cursor::CursorSigner;
+use crate::handlers::websocket::{handshake_timeout, SessionServices};
+use crate::instance::{InstanceRootSpan, INSTANCE_HEADER};
+use crate::json_case::rewrite_json_fields;
+use crate::metrics::Metrics;
+use crate::models::user::{NotificationRoute, UserFieldLimits};
+use crate::services::{MaintenanceMode, ReadOnlyMode};
+use crate::services::BroadcastHub;
+use crate::services::LogAuditSink;
+use crate::services::CircuitBreaker;
+use crate::services::CompositeNotifier;
+use crate::services::ConnectionGates;
+use crate::services::AuthChallenges;
+use crate::services::broadcast::ServerEvent;
+use crate::services::EmailVerificationTokens;
+use crate::services::ExternalAuthService;
+use crate::services::external_auth::{HttpJwksSource, JwksCache};
+use crate::services::KeyDenyList;
+use crate::services::NotificationPreferenceCache;
+use crate::services::notification_preferences::{DEFAULT_PREFERENCES_CACHE_CAPACITY, DEFAULT_PREFERENCES_CACHE_TTL};
+use crate::services::NetworkService;
+use crate::services::NetworkUpdateCoalescer;
+use crate::services::network::PointsAccrual;
+use crate::services::RateLimits;
+use crate::services::RedisBroadcastBridge;
+use crate::services::HealthState;
+use crate::services::health::ProcessStart;
+use crate::services::Readiness;
+use crate::services::ReconnectTokens;
+use crate::services::SessionRegistry;
 use crate::services::SignatureService;
 use crate::services::UserService;
-use crate::storage::memory::InMemoryUserStorage;
+use crate::services::Webhooks;
+use crate::services::WsTickets;
+use crate::services::email_verification::LogVerificationNotifier;
+use crate::services::session_hooks::NoopOnAuthenticated;
+use crate::shutdown::ShutdownCoordinator;
+use crate::storage::memory::{InMemoryNetworkStorage, InMemoryUserStorage};
+use crate::storage::postgres::PostgresUserStorage;
+use crate::storage::{AppUserStorage, CircuitBreakerStorage, TracedStorage, UserBackend};
 
 #[get("/")]
 async fn hello() -> impl Responder {
     HttpResponse::Ok().body("WebSocket Dashboard System")
 }
 
+/// Readiness probe: `503` until startup tasks (database, seeding, storage) have finished
+#[get("/ready")]
+async fn readiness_check(readiness: web::Data<Readiness>) -> impl Responder {
+    if readiness.is_ready() {
+        HttpResponse::Ok().json(serde_json::json!({ "status": "ready" }))
+    } else {
+        HttpResponse::ServiceUnavailable().json(serde_json::json!({ "status": "starting" }))
+    }
+}
+
+/// Liveness probe: `200` whenever the process is serving requests
 #[get("/health")]
-async fn health_check() -> impl Responder {
-    HttpResponse::Ok().json(serde_json::json!({
+async fn health_check(
+    config: web::Data<config::Config>,
+    maintenance: web::Data<MaintenanceMode>,
+    read_only: web::Data<ReadOnlyMode>,
+    db_breaker: web::Data<CircuitBreaker>,
+    health: web::Data<HealthState>,
+) -> impl Responder {
+    let mut body = serde_json::json!({
         "status": "ok",
-        "version": env!("CARGO_PKG_VERSION")
-    }))
+        "version": env!("CARGO_PKG_VERSION"),
+        "maintenance": maintenance.is_active(),
+        "maintenance_message": maintenance.message(),
+        "read_only": read_only.is_enabled(),
+        "database_breaker": db_breaker.state().as_str()
+    });
+    if config.server.health_details {
+        if let (Some(body), Ok(serde_json::Value::Object(details))) =
+            (body.as_object_mut(), serde_json::to_value(health.details()))
+        {
+            body.extend(details);
+        }
+    }
+    HttpResponse::Ok().json(body)
+}
+
+#[get("/metrics")]
+async fn metrics_handler(
+    config: web::Data<config::Config>,
+    metrics: web::Data<Metrics>,
+    signature_service: web::Data<SignatureService<AppUserStorage>>,
+    user_service: web::Data<UserService<AppUserStorage>>,
+    db_breaker: web::Data<CircuitBreaker>,
+    user_backend: web::Data<UserBackend>,
+    network_storage: web::Data<InMemoryNetworkStorage>,
+) -> impl Responder {
+    if !config.feature_enabled("metrics") {
+        return HttpResponse::NotFound().finish();
+    }
+
+    // Cache sizes are sampled at scrape time
+    metrics.set_nonce_cache_entries(signature_service.nonce_cache_len());
+    metrics.set_failed_login_cache_entries(user_service.failed_login_cache_len());
+    metrics.set_db_circuit_breaker_state(db_breaker.state());
+    // Users in PostgreSQL aren't counted; the user and session gauges only track the in-memory store
+    if let UserBackend::Memory(user_storage) = user_backend.get_ref() {
+        metrics.set_memory_user_store_sizes(user_storage.user_count(), user_storage.session_count());
+    }
+    metrics.set_memory_connection_store_size(network_storage.connection_count());
+
+    HttpResponse::Ok()
+        .content_type("application/openmetrics-text; version=1.0.0; charset=utf-8")
+        .body(metrics.encode())
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // Uptime in /health counts from here
+    let started = ProcessStart::now();
+    
     // Load configuration from environment
     let config = config::Config::from_env().expect("Failed to load configuration");
     
@@ -53,7 +162,14 @@ async fn main() -> std::io::Result<()> {
     tracing::subscriber::set_global_default(subscriber)
         .expect("Failed to set up the logger");
     
-    info!("Starting server on port {}", config.server.port);
+    info!("Starting server instance {} on port {}", config.server.instance_id, config.server.port);
+    
+    for flag in config.features.unknown_flags() {
+        warn!("Ignoring unknown feature flag: {}", flag);
+    }
+
+    // Flipped to ready once every startup task below has completed
+    let readiness = web::Data::new(Readiness::new());
 
     // Initialize database connection
     let pool = match &config.database.url {
@@ -104,19 +220,42 @@ async fn main() -> std::io::Result<()> {
 
     let config_data = web::Data::new(config.clone());
     let config_port = config.server.port;
+    let server_config = config.server.clone();
     
     // Initialize in-memory storage for development
-    let user_storage_instance = InMemoryUserStorage::new();
-    let user_storage = web::Data::new(user_storage_instance.clone());
+    let memory_limits = &config.memory_storage;
+    let network_storage_instance = InMemoryNetworkStorage::new().with_max_connections(memory_limits.max_connections);
+    let network_storage = web::Data::new(network_storage_instance.clone());
+    
+    // Users live in PostgreSQL when a database is configured; the schema comes from `migrations/`
+    let user_backend = match &pool {
+        Some(pool) => {
+            info!("Using PostgreSQL user storage");
+            UserBackend::Postgres(PostgresUserStorage::new(pool.clone()))
+        }
+        None => UserBackend::Memory(
+            InMemoryUserStorage::new()
+                .with_max_users(memory_limits.max_users)
+                .with_max_sessions(memory_limits.max_sessions, memory_limits.session_eviction),
+        ),
+    };
+    let user_backend_data = web::Data::new(user_backend.clone());
     
     // Seed in-memory storage with genesis data in development mode
     #[cfg(debug_assertions)]
     if config.server.environment == "development" {
-        info!("Seeding in-memory storage with genesis data");
-        if let Err(e) = genesis::memory_seed::seed_storage(&user_storage_instance).await {
-            warn!("Failed to seed in-memory storage: {}", e);
-        } else {
-            info!("In-memory storage seeded successfully");
+        // A configured database is seeded above instead
+        if let UserBackend::Memory(user_storage) = &user_backend {
+            info!("Seeding in-memory storage with genesis data");
+            if let Err(e) = genesis::memory_seed::seed_storage(user_storage).await {
+                warn!("Failed to seed in-memory storage: {}", e);
+            } else {
+                info!("In-memory storage seeded successfully");
+            }
+        }
+        
+        if let Err(e) = genesis::memory_seed::seed_network_storage(&network_storage_instance).await {
+            warn!("Failed to seed in-memory network storage: {}", e);
         }
         
         // Initialize and register test keys after seeding
@@ -124,32 +263,268 @@ async fn main() -> std::io::Result<()> {
         dev::test_keys::initialize_test_keys();
         
         // Register test keys with users if they weren't part of genesis data
-        if let Err(e) = dev::test_keys::register_test_keys_with_users(&user_storage_instance).await {
+        if let Err(e) = dev::test_keys::register_test_keys_with_users(&user_backend).await {
             warn!("Failed to register test keys with users: {}", e);
         }
     }
     
+    // Storage calls short-circuit with a 503 while the database keeps failing
+    let db_breaker = web::Data::new(
+        CircuitBreaker::new(
+            config.database.breaker_failure_threshold,
+            Duration::from_secs(config.database.breaker_cooldown),
+        )
+        .with_call_timeout(Duration::from_millis(config.database.call_timeout_ms)),
+    );
+    // Storage calls run in `storage` spans timing each operation
+    let app_user_storage = CircuitBreakerStorage::new(TracedStorage::new(user_backend), db_breaker.get_ref().clone());
+    let app_network_storage = CircuitBreakerStorage::new(TracedStorage::new(network_storage_instance), db_breaker.get_ref().clone());
+    
+    // Globally denied public keys, reloadable via the admin API
+    let key_deny_list = web::Data::new(KeyDenyList::new(config.auth.denied_public_keys.clone()));
+    
+    // Server-issued nonces for WebSocket signature auth
+    let auth_challenges = web::Data::new(AuthChallenges::new(
+        Duration::from_secs(config.auth.auth_challenge_ttl),
+        config.auth.auth_challenge_max_entries,
+    ));
+    
     // Create and register SignatureService
-    let signature_service = web::Data::new(SignatureService::new(Arc::new(user_storage_instance.clone())));
+    let mut signature_service =
+        SignatureService::new(Arc::new(app_user_storage.clone()))
+            .with_blocking_verification(config.auth.signature_verify_concurrency)
+            .with_nonce_cache_capacity(config.auth.nonce_cache_max_entries)
+            .with_key_cache_capacity(config.auth.public_key_cache_max_entries)
+            .with_timestamp_window(config.auth.auth_message_max_age, config.auth.auth_message_max_future)
+            .with_revoked_key_grace(Duration::from_secs(config.auth.revoked_key_grace_period));
+    // Denied keys are refused before verifying; the `key_deny_list` gate catches ones denied mid-auth
+    signature_service = signature_service.with_key_deny_list(key_deny_list.get_ref().clone());
+    if config.auth.require_server_challenge {
+        signature_service = signature_service.with_required_challenges(auth_challenges.get_ref().clone());
+        info!("WebSocket signature auth requires server-issued challenges");
+    }
+    let signature_service = web::Data::new(signature_service);
+    if config.auth.public_key_cache_warmup {
+        match signature_service.warm_key_cache().await {
+            Ok(warmed) => info!("Warmed public key cache with {} keys", warmed),
+            Err(e) => warn!("Failed to warm public key cache: {}", e),
+        }
+    }
+
+    // Write freeze for migrations and failovers, toggleable via the admin API
+    let read_only = web::Data::new(ReadOnlyMode::new(config.server.read_only));
+    if config.server.read_only {
+        warn!("Starting in read-only mode, writes are refused");
+    }
+
+    // WebSocket auth with identity provider tokens, alongside signature auth
+    let external_auth = match (&config.auth.external_auth_jwks_url, &config.auth.external_auth_issuer) {
+        (Some(jwks_url), Some(issuer)) => {
+            info!("WebSocket token auth enabled for issuer {}", issuer);
+            let jwks = JwksCache::new(
+                Arc::new(HttpJwksSource::new(jwks_url.clone())),
+                Duration::from_secs(config.auth.external_auth_jwks_refresh),
+            );
+            Some(Arc::new(
+                ExternalAuthService::new(Arc::new(app_user_storage.clone()), jwks, issuer.clone())
+                    .with_audience(config.auth.external_auth_audience.clone())
+                    .with_auto_provision(config.auth.external_auth_auto_provision)
+                    .with_field_limits(UserFieldLimits {
+                        max_email_len: config.auth.max_email_length,
+                        max_username_len: config.auth.max_username_length,
+                    })
+                    .with_read_only(read_only.get_ref().clone()),
+            ))
+        }
+        (Some(_), None) => {
+            error!("EXTERNAL_AUTH_JWKS_URL is set without EXTERNAL_AUTH_ISSUER; token auth disabled");
+            None
+        }
+        _ => None,
+    };
+
+    // Users' muted notification channels, applied as events are delivered
+    let notification_preferences = NotificationPreferenceCache::new(
+        Arc::new(app_user_storage.clone()),
+        DEFAULT_PREFERENCES_CACHE_TTL,
+        DEFAULT_PREFERENCES_CACHE_CAPACITY,
+    );
 
     // Create and register UserService
     let user_service = web::Data::new(UserService::new(
-        Arc::new(user_storage_instance.clone()),
+        Arc::new(app_user_storage),
         config.auth.jwt_secret.clone(),
         config.auth.jwt_expiration as i64,
-    ));
+    )
+    .with_max_public_keys(config.auth.max_public_keys_per_user)
+    .with_admin_user_ids(config.auth.admin_user_ids.clone())
+    .with_failed_login_cache_capacity(config.auth.failed_login_cache_max_entries)
+    .with_max_failed_logins(config.auth.max_failed_logins)
+    .with_password_history(config.auth.password_history)
+    .with_session_max_lifetime(config.auth.session_max_lifetime)
+    .with_session_write_retries(config.auth.login_session_retries)
+    .with_network_storage(Arc::new(app_network_storage.clone()))
+    .with_data_export_interval(config.auth.data_export_interval)
+    .with_account_status_cache_ttl(config.auth.account_status_cache_ttl)
+    .with_field_limits(UserFieldLimits {
+        max_email_len: config.auth.max_email_length,
+        max_username_len: config.auth.max_username_length,
+    })
+    .with_key_deny_list(key_deny_list.get_ref().clone())
+    .with_email_verification(
+        EmailVerificationTokens::new(
+            Duration::from_secs(config.auth.email_verification_ttl),
+            Duration::from_secs(config.auth.verification_resend_interval),
+        ),
+        Arc::new(LogVerificationNotifier),
+    )
+    .with_require_email_verification(config.auth.require_email_verification)
+    .with_read_only(read_only.get_ref().clone())
+    .with_notification_preferences(notification_preferences.clone())
+    .with_impersonation(config.auth.impersonation_ttl, config.auth.max_impersonations_per_hour)
+    .with_jwt_issuer(config.auth.jwt_issuer.clone(), config.auth.jwt_audience.clone()));
+    
+    // Shared metrics
+    let metrics = web::Data::new(Metrics::new());
+    
+    // Signed delivery of network and earnings events to external receivers
+    let webhooks = match Webhooks::spawn(config.webhooks.clone(), metrics.clone().into_inner()) {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            error!("Webhooks disabled: {}", e);
+            Webhooks::disabled()
+        }
+    };
+    
+    // Per-user fan-out of server events to WebSocket sessions, relayed to the other instances
+    // through Redis when it is configured
+    let mut redis_status = None;
+    let broadcast = match &config.redis.url {
+        Some(url) => match RedisBroadcastBridge::new(url, config.redis.broadcast_channel.clone(), config.server.instance_id.clone()) {
+            Ok(bridge) => {
+                redis_status = Some(bridge.status());
+                let (relay, outbound) = tokio::sync::mpsc::unbounded_channel();
+                let hub = BroadcastHub::new().with_relay(relay);
+                bridge.spawn(hub.clone(), outbound);
+                info!("Relaying broadcasts between instances via Redis");
+                hub
+            }
+            Err(e) => {
+                error!("Invalid REDIS_URL, broadcasts stay on this instance: {}", e);
+                BroadcastHub::new()
+            }
+        },
+        None => BroadcastHub::new(),
+    };
+    
+    // User events reach WebSocket sessions and webhooks, minus the channels each user muted
+    // there; relayed events are delivered to webhooks by the instance that published them
+    let notifier = CompositeNotifier::new()
+        .with(Arc::new(notification_preferences.filter(NotificationRoute::WebSocket, Arc::new(broadcast.clone()))))
+        .with(Arc::new(notification_preferences.filter(NotificationRoute::Webhook, Arc::new(webhooks.clone()))));
+    let webhooks = web::Data::new(webhooks);
+    
+    // Create and register NetworkService
+    let mut network_service =
+        NetworkService::new(Arc::new(app_network_storage))
+        .with_notifier(Arc::new(notifier))
+        .with_read_only(read_only.get_ref().clone());
+    if config.feature_enabled("points_accrual") {
+        network_service = network_service.with_points_accrual(PointsAccrual {
+            points_per_minute: config.earnings.points_per_minute,
+            max_points_per_update: config.earnings.max_points_per_update,
+        });
+    }
+    let network_service = web::Data::new(network_service);
+    
+    // Live WebSocket session registry
+    let session_registry = web::Data::new(SessionRegistry::new());
+    
+    // Uptime, live connections and dependency states for /health
+    let mut health = HealthState::new(started, session_registry.get_ref().clone(), db_breaker.get_ref().clone());
+    if let Some(pool) = &pool {
+        health = health.with_database(pool.clone());
+    }
+    if let Some(status) = redis_status {
+        health = health.with_redis(status);
+    }
+    let health = web::Data::new(health);
+    
+    // Maintenance banner, updatable at runtime via the admin API
+    let maintenance = web::Data::new(MaintenanceMode::new(config.server.maintenance_message.clone()));
+    
+    // Signs opaque pagination cursors for admin listings
+    let cursor_signer = web::Data::new(CursorSigner::new(&config.auth.jwt_secret));
+    
+    // Signs the instance affinity hints handed out at login for sticky load balancing
+    let affinity_signer = (config.server.affinity_ttl > 0).then(|| {
+        web::Data::new(AffinitySigner::new(
+            &config.auth.jwt_secret,
+            &config.server.instance_id,
+            Duration::from_secs(config.server.affinity_ttl),
+        ))
+    });
+    
+    // Single-use handshake tickets for browser WebSocket clients
+    let ws_tickets = web::Data::new(WsTickets::new(Duration::from_secs(config.websocket.ticket_ttl)));
+    
+    // Admission checks for signature-authenticated WebSocket sessions
+    let rate_limits = RateLimits::new(config.rate_limits.clone());
+    let connection_gates = ConnectionGates::from_kinds(&config.auth.connection_gates, key_deny_list.get_ref(), &rate_limits);
+    
+    // Services shared by every WebSocket session
+    let session_services = web::Data::new(SessionServices {
+        metrics: metrics.clone().into_inner(),
+        registry: session_registry.get_ref().clone(),
+        maintenance: maintenance.get_ref().clone(),
+        read_only: read_only.get_ref().clone(),
+        reconnect_tokens: ReconnectTokens::new(Duration::from_secs(config.websocket.reconnect_grace))
+            .with_ip_change_policy(config.websocket.reconnect_ip_change),
+        ws_tickets: ws_tickets.get_ref().clone(),
+        broadcast,
+        network_service: network_service.clone().into_inner(),
+        network_updates: NetworkUpdateCoalescer::new(
+            network_service.clone().into_inner(),
+            Duration::from_millis(config.websocket.network_update_coalesce_ms),
+        ),
+        on_authenticated: Arc::new(NoopOnAuthenticated),
+        audit: Arc::new(LogAuditSink),
+        rate_limits,
+        connection_gates,
+        external_auth,
+    });
     
     // If we have genesis data, make it available to the application
     let genesis_data = genesis_data.map(web::Data::new);
     
+    // Kept for closing the pool during shutdown
+    let shutdown_pool = pool.clone();
+    let shutdown_broadcast = session_services.broadcast.clone();
+    let shutdown_network_updates = session_services.network_updates.clone();
+    
     // Database pool as app data if available
     let pool_data = pool.map(web::Data::new);
     
+    // Startup tasks are done; /ready starts returning 200
+    readiness.set_ready(true);
+    info!("Startup complete, server is ready");
+    
+    let shutdown_readiness = readiness.get_ref().clone();
+    let shutdown_config = config.shutdown.clone();
+    
     // Start HTTP server with WebSocket support
-    HttpServer::new(move || {
-        // CORS configuration
-        let cors = Cors::default()
-            .allow_any_origin()
+    let server = HttpServer::new(move || {
+        // CORS configuration; the same allowlist gates WebSocket handshakes
+        let cors = if config_data.server.allows_any_origin() {
+            Cors::default().allow_any_origin()
+        } else {
+            config_data
+                .server
+                .allowed_origins
+                .iter()
+                .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        };
+        let cors = cors
             .allow_any_method()
             .allow_any_header()
             .max_age(3600);
@@ -158,22 +533,35 @@ async fn main() -> std::io::Result<()> {
             // Add shared configuration
             .app_data(config_data.clone())
             // Add storage and services
-            .app_data(user_storage.clone())
+            .app_data(user_backend_data.clone())
+            .app_data(network_storage.clone())
             .app_data(signature_service.clone())
             .app_data(user_service.clone())
+            .app_data(network_service.clone())
+            .app_data(metrics.clone())
+            .app_data(session_registry.clone())
+            .app_data(maintenance.clone())
+            .app_data(read_only.clone())
+            .app_data(db_breaker.clone())
+            .app_data(key_deny_list.clone())
+            .app_data(ws_tickets.clone())
+            .app_data(auth_challenges.clone())
+            .app_data(cursor_signer.clone())
+            .app_data(readiness.clone())
+            .app_data(health.clone())
+            .app_data(webhooks.clone())
+            .app_data(session_services.clone())
             // Configure request timeouts
             .app_data(
                 web::JsonConfig::default()
                     .limit(4194304) // 4MB JSON payload limit
-                    .error_handler(|err, _| {
-                        let err_msg = format!("JSON error: {}", err);
-                        actix_web::error::InternalError::from_response(
-                            err, 
-                            HttpResponse::BadRequest().body(err_msg)
-                        ).into()
-                    })
+                    .error_handler(errors::json_error_handler)
             )
             // Add middleware
+            .wrap(middleware::from_fn(rewrite_json_fields))
+            .wrap(middleware::from_fn(check_affinity))
+            .wrap(middleware::DefaultHeaders::new().add((INSTANCE_HEADER, config_data.server.instance_id.as_str())))
+            .wrap(TracingLogger::<InstanceRootSpan>::new())
             .wrap(middleware::Logger::default())
             .wrap(middleware::Compress::default())
             .wrap(middleware::NormalizePath::trim())
@@ -181,16 +569,23 @@ async fn main() -> std::io::Result<()> {
             // Register basic services
             .service(hello)
             .service(health_check)
+            .service(readiness_check)
+            .service(metrics_handler)
             // Register API routes
             .service(routes::api_routes())
             // Register WebSocket routes
-            .service(routes::websocket_routes());
+            .service(routes::websocket_routes().wrap(middleware::from_fn(handshake_timeout)));
             
         // Add database pool if available
         if let Some(ref pool) = pool_data {
             app = app.app_data(pool.clone());
         }
         
+        // Affinity hints are only issued and checked when enabled
+        if let Some(ref signer) = affinity_signer {
+            app = app.app_data(signer.clone());
+        }
+        
         // Add genesis data if available (dev mode)
         if let Some(ref genesis) = genesis_data {
             app = app.app_data(genesis.clone());
@@ -198,13 +593,78 @@ async fn main() -> std::io::Result<()> {
         
         app
     })
-    .keep_alive(Duration::from_secs(60))
-    .client_request_timeout(Duration::from_secs(60))
-    .client_disconnect_timeout(Duration::from_secs(5))
+    .keep_alive(Duration::from_secs(server_config.keep_alive))
+    .client_request_timeout(Duration::from_secs(server_config.client_request_timeout))
+    .client_disconnect_timeout(Duration::from_secs(server_config.client_disconnect_timeout))
     .server_hostname(format!("dashboard-server-{}", env!("CARGO_PKG_VERSION")))
     .workers(num_cpus::get())
-    .shutdown_timeout(30) // Graceful shutdown timeout in seconds
+    .shutdown_timeout(server_config.shutdown_timeout) // Graceful shutdown timeout in seconds
+    // Signals are handled by the shutdown sequence below
+    .disable_signals()
     .bind(("0.0.0.0", config_port))?
-    .run()
-    .await
+    .run();
+    
+    let handle = server.handle();
+    let shutdown = actix_rt::spawn(async move {
+        let signal = shutdown::wait_for_signal().await;
+        info!("Received {}, shutting down", signal);
+        
+        let drain_timeout = Duration::from_secs(server_config.shutdown_timeout + 1);
+        ShutdownCoordinator::new()
+            // Fail readiness so load balancers stop routing here, and stop accepting connections
+            .phase(
+                "stop_accepting",
+                Duration::from_secs(shutdown_config.stop_accepting_timeout),
+                {
+                    let handle = handle.clone();
+                    move || async move {
+                        shutdown_readiness.set_ready(false);
+                        handle.pause().await;
+                    }
+                },
+            )
+            // Tell authenticated sockets to reconnect elsewhere, then close them with Going Away
+            .phase(
+                "notify_sockets",
+                Duration::from_secs(shutdown_config.notify_timeout),
+                move || async move {
+                    let notified = shutdown_broadcast.publish_all(ServerEvent(serde_json::json!({
+                        "type": "server_shutdown",
+                        "reconnect": true
+                    })));
+                    shutdown_broadcast.disconnect_all("Server shutting down");
+                    info!("Notified {} WebSocket sessions of shutdown", notified);
+                },
+            )
+            // Let in-flight requests, and the accrual writes they make, finish
+            .phase("drain_requests", drain_timeout, move || async move {
+                handle.stop(true).await;
+            })
+            // Write the network updates still waiting out their coalescing window
+            .phase(
+                "flush_network_updates",
+                Duration::from_secs(shutdown_config.flush_network_updates_timeout),
+                move || async move {
+                    let written = shutdown_network_updates.flush_all().await;
+                    info!("Flushed {} pending network updates", written);
+                },
+            )
+            .phase(
+                "close_pools",
+                Duration::from_secs(shutdown_config.close_pools_timeout),
+                move || async move {
+                    if let Some(pool) = shutdown_pool {
+                        pool.close().await;
+                    }
+                },
+            )
+            .run()
+            .await;
+        info!("Shutdown complete");
+    });
+    
+    server.await?;
+    // The server stops during `drain_requests`; wait for the remaining phases
+    let _ = shutdown.await;
+    Ok(())
 }
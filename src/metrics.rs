@@ -0,0 +1,368 @@
+use prometheus_client::encoding::text::encode;
+use prometheus_client::encoding::{EncodeMetric, MetricEncoder};
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::MetricType;
+use prometheus_client::registry::{Registry, Unit};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::error;
+
+use crate::services::circuit_breaker::BreakerState;
+
+/// Upper bounds, in seconds, for request-scale latencies: 1ms to 5s
+const LATENCY_BUCKETS: [f64; 12] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Upper bounds, in seconds, for WebSocket connection lifetimes: 1s to 1 day
+const LIFETIME_BUCKETS: [f64; 10] = [1.0, 10.0, 60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0, 21600.0, 86400.0];
+
+/// Histogram of durations built on atomics, so observing never takes a lock.
+///
+/// `prometheus_client`'s `Histogram` write-locks on every observation; this one is hit on
+/// every WebSocket message. The sum is kept in nanoseconds and the count is the total of the
+/// buckets, so a scrape racing an observation may be off by that one observation.
+#[derive(Debug, Clone)]
+pub struct DurationHistogram {
+    inner: Arc<HistogramInner>,
+}
+
+#[derive(Debug)]
+struct HistogramInner {
+    /// Bucket upper bounds in seconds, ascending
+    bounds: Vec<f64>,
+    /// Observations per bucket (not cumulative), with a final `+Inf` bucket
+    buckets: Vec<AtomicU64>,
+    sum_nanos: AtomicU64,
+}
+
+impl DurationHistogram {
+    /// Create a histogram with the given ascending bucket upper bounds, in seconds
+    pub fn new(bounds: &[f64]) -> Self {
+        Self {
+            inner: Arc::new(HistogramInner {
+                bounds: bounds.to_vec(),
+                buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+                sum_nanos: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Record one observation
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = self.inner.bounds.iter().position(|bound| seconds <= *bound).unwrap_or(self.inner.bounds.len());
+        self.inner.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.inner.sum_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    /// Sum in seconds, count, and per-bucket counts keyed by upper bound (`f64::MAX` for `+Inf`)
+    fn snapshot(&self) -> (f64, u64, Vec<(f64, u64)>) {
+        let buckets: Vec<(f64, u64)> = self
+            .inner
+            .bounds
+            .iter()
+            .copied()
+            .chain(std::iter::once(f64::MAX))
+            .zip(self.inner.buckets.iter().map(|count| count.load(Ordering::Relaxed)))
+            .collect();
+        let count = buckets.iter().map(|(_, count)| count).sum();
+        let sum = self.inner.sum_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        (sum, count, buckets)
+    }
+}
+
+impl EncodeMetric for DurationHistogram {
+    fn encode(&self, mut encoder: MetricEncoder) -> Result<(), std::fmt::Error> {
+        let (sum, count, buckets) = self.snapshot();
+        encoder.encode_histogram::<()>(sum, count, &buckets, None)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        MetricType::Histogram
+    }
+}
+
+/// Application-wide Prometheus metrics
+pub struct Metrics {
+    registry: Registry,
+    /// Total bytes received in WebSocket text and binary frames
+    ws_bytes_received: Counter,
+    /// Total bytes sent in WebSocket text and binary frames
+    ws_bytes_sent: Counter,
+    /// Entries in the nonce replay cache
+    nonce_cache_entries: Gauge,
+    /// Entries in the failed-login cache
+    failed_login_cache_entries: Gauge,
+    /// Database circuit breaker state: 0 closed, 1 open, 2 half-open
+    db_circuit_breaker_state: Gauge,
+    /// Entries in the in-memory user, session and network connection stores
+    memory_store_users: Gauge,
+    memory_store_sessions: Gauge,
+    memory_store_connections: Gauge,
+    /// Webhook deliveries acknowledged, given up on, and retried
+    webhook_deliveries_succeeded: Counter,
+    webhook_deliveries_failed: Counter,
+    webhook_delivery_retries: Counter,
+    /// Requests whose affinity hint named this instance, and ones that named another
+    affinity_hits: Counter,
+    affinity_misses: Counter,
+    /// Time from receiving a WebSocket `Auth` to its signature or token being verified
+    ws_auth_verification_duration: DurationHistogram,
+    /// Time spent handling one WebSocket text or binary message
+    ws_message_handling_duration: DurationHistogram,
+    /// How long WebSocket connections stayed open
+    ws_connection_lifetime: DurationHistogram,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// Create a new metrics registry with all application metrics registered
+    pub fn new() -> Self {
+        let mut registry = Registry::default();
+
+        let ws_bytes_received = Counter::default();
+        registry.register(
+            "ws_bytes_received",
+            "Bytes received in WebSocket text and binary frames",
+            ws_bytes_received.clone(),
+        );
+
+        let ws_bytes_sent = Counter::default();
+        registry.register(
+            "ws_bytes_sent",
+            "Bytes sent in WebSocket text and binary frames",
+            ws_bytes_sent.clone(),
+        );
+
+        let nonce_cache_entries = Gauge::default();
+        registry.register(
+            "nonce_cache_entries",
+            "Entries in the WebSocket auth nonce replay cache",
+            nonce_cache_entries.clone(),
+        );
+
+        let failed_login_cache_entries = Gauge::default();
+        registry.register(
+            "failed_login_cache_entries",
+            "Emails tracked in the failed-login cache",
+            failed_login_cache_entries.clone(),
+        );
+
+        let db_circuit_breaker_state = Gauge::default();
+        registry.register(
+            "db_circuit_breaker_state",
+            "Database circuit breaker state (0 closed, 1 open, 2 half-open)",
+            db_circuit_breaker_state.clone(),
+        );
+
+        let memory_store_users = Gauge::default();
+        registry.register("memory_store_users", "Users in the in-memory store", memory_store_users.clone());
+
+        let memory_store_sessions = Gauge::default();
+        registry.register(
+            "memory_store_sessions",
+            "Login sessions in the in-memory store",
+            memory_store_sessions.clone(),
+        );
+
+        let memory_store_connections = Gauge::default();
+        registry.register(
+            "memory_store_connections",
+            "Network connections in the in-memory store",
+            memory_store_connections.clone(),
+        );
+
+        let webhook_deliveries_succeeded = Counter::default();
+        registry.register(
+            "webhook_deliveries_succeeded",
+            "Webhook deliveries acknowledged by the receiver",
+            webhook_deliveries_succeeded.clone(),
+        );
+
+        let webhook_deliveries_failed = Counter::default();
+        registry.register(
+            "webhook_deliveries_failed",
+            "Webhook deliveries given up on after every attempt failed",
+            webhook_deliveries_failed.clone(),
+        );
+
+        let webhook_delivery_retries = Counter::default();
+        registry.register(
+            "webhook_delivery_retries",
+            "Failed webhook attempts that were retried",
+            webhook_delivery_retries.clone(),
+        );
+
+        let affinity_hits = Counter::default();
+        registry.register(
+            "affinity_hits",
+            "Requests whose affinity hint named this instance",
+            affinity_hits.clone(),
+        );
+
+        let affinity_misses = Counter::default();
+        registry.register(
+            "affinity_misses",
+            "Requests whose affinity hint named another instance",
+            affinity_misses.clone(),
+        );
+
+        let ws_auth_verification_duration = DurationHistogram::new(&LATENCY_BUCKETS);
+        registry.register_with_unit(
+            "ws_auth_verification_duration",
+            "Time to verify a WebSocket auth signature or identity provider token",
+            Unit::Seconds,
+            ws_auth_verification_duration.clone(),
+        );
+
+        let ws_message_handling_duration = DurationHistogram::new(&LATENCY_BUCKETS);
+        registry.register_with_unit(
+            "ws_message_handling_duration",
+            "Time spent handling a WebSocket text or binary message",
+            Unit::Seconds,
+            ws_message_handling_duration.clone(),
+        );
+
+        let ws_connection_lifetime = DurationHistogram::new(&LIFETIME_BUCKETS);
+        registry.register_with_unit(
+            "ws_connection_lifetime",
+            "How long WebSocket connections stayed open",
+            Unit::Seconds,
+            ws_connection_lifetime.clone(),
+        );
+
+        Self {
+            registry,
+            ws_bytes_received,
+            ws_bytes_sent,
+            nonce_cache_entries,
+            failed_login_cache_entries,
+            db_circuit_breaker_state,
+            memory_store_users,
+            memory_store_sessions,
+            memory_store_connections,
+            webhook_deliveries_succeeded,
+            webhook_deliveries_failed,
+            webhook_delivery_retries,
+            affinity_hits,
+            affinity_misses,
+            ws_auth_verification_duration,
+            ws_message_handling_duration,
+            ws_connection_lifetime,
+        }
+    }
+
+    /// Record bytes received from a WebSocket client
+    pub fn record_ws_bytes_received(&self, bytes: u64) {
+        self.ws_bytes_received.inc_by(bytes);
+    }
+
+    /// Record bytes sent to a WebSocket client
+    pub fn record_ws_bytes_sent(&self, bytes: u64) {
+        self.ws_bytes_sent.inc_by(bytes);
+    }
+
+    /// Record the current size of the nonce replay cache
+    pub fn set_nonce_cache_entries(&self, entries: usize) {
+        self.nonce_cache_entries.set(entries as i64);
+    }
+
+    /// Record the current size of the failed-login cache
+    pub fn set_failed_login_cache_entries(&self, entries: usize) {
+        self.failed_login_cache_entries.set(entries as i64);
+    }
+
+    /// Record the current state of the database circuit breaker
+    pub fn set_db_circuit_breaker_state(&self, state: BreakerState) {
+        self.db_circuit_breaker_state.set(state.as_gauge());
+    }
+
+    /// Record the current sizes of the in-memory user store
+    pub fn set_memory_user_store_sizes(&self, users: usize, sessions: usize) {
+        self.memory_store_users.set(users as i64);
+        self.memory_store_sessions.set(sessions as i64);
+    }
+
+    /// Record the current size of the in-memory network store
+    pub fn set_memory_connection_store_size(&self, connections: usize) {
+        self.memory_store_connections.set(connections as i64);
+    }
+
+    /// Record a webhook delivery the receiver acknowledged
+    pub fn record_webhook_delivered(&self) {
+        self.webhook_deliveries_succeeded.inc();
+    }
+
+    /// Record a webhook delivery whose attempts all failed
+    pub fn record_webhook_failed(&self) {
+        self.webhook_deliveries_failed.inc();
+    }
+
+    /// Record a failed webhook attempt that will be retried
+    pub fn record_webhook_retry(&self) {
+        self.webhook_delivery_retries.inc();
+    }
+
+    /// Record whether a request's affinity hint named this instance
+    pub fn record_affinity(&self, hit: bool) {
+        if hit {
+            self.affinity_hits.inc();
+        } else {
+            self.affinity_misses.inc();
+        }
+    }
+
+    /// Record how long verifying a WebSocket auth took
+    pub fn observe_ws_auth_verification(&self, duration: Duration) {
+        self.ws_auth_verification_duration.observe(duration);
+    }
+
+    /// Record how long handling a WebSocket message took
+    pub fn observe_ws_message_handling(&self, duration: Duration) {
+        self.ws_message_handling_duration.observe(duration);
+    }
+
+    /// Record how long a closed WebSocket connection was open
+    pub fn observe_ws_connection_lifetime(&self, duration: Duration) {
+        self.ws_connection_lifetime.observe(duration);
+    }
+
+    /// Encode all metrics in the Prometheus text exposition format
+    pub fn encode(&self) -> String {
+        let mut buffer = String::new();
+        if let Err(e) = encode(&mut buffer, &self.registry) {
+            error!("Failed to encode metrics: {}", e);
+        }
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_and_exposition() {
+        let metrics = Metrics::new();
+        metrics.observe_ws_message_handling(Duration::from_micros(500));
+        metrics.observe_ws_message_handling(Duration::from_millis(30));
+        metrics.observe_ws_message_handling(Duration::from_secs(10));
+
+        let text = metrics.encode();
+        assert!(text.contains("# TYPE ws_message_handling_duration_seconds histogram"), "{}", text);
+        assert!(text.contains("ws_message_handling_duration_seconds_count 3"), "{}", text);
+        // Buckets are cumulative in the exposition
+        assert!(text.contains("ws_message_handling_duration_seconds_bucket{le=\"0.001\"} 1"), "{}", text);
+        assert!(text.contains("ws_message_handling_duration_seconds_bucket{le=\"0.05\"} 2"), "{}", text);
+        assert!(text.contains("ws_message_handling_duration_seconds_bucket{le=\"5.0\"} 2"), "{}", text);
+        assert!(text.contains("ws_message_handling_duration_seconds_bucket{le=\"+Inf\"} 3"), "{}", text);
+        assert!(text.contains("ws_connection_lifetime_seconds_count 0"), "{}", text);
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where earned points came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", try_from = "String")]
+pub enum EarningSource {
+    /// Accrued for connected time
+    Uptime,
+    /// Earned through a referred user
+    Referral,
+    /// Awarded outside the regular accrual, e.g. a promotion
+    Bonus,
+    /// Earned for contributing network quality
+    Network,
+}
+
+impl EarningSource {
+    /// Every source, in the order they are listed to clients
+    pub const ALL: [EarningSource; 4] = [
+        EarningSource::Uptime,
+        EarningSource::Referral,
+        EarningSource::Bonus,
+        EarningSource::Network,
+    ];
+
+    /// Wire name of the source
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EarningSource::Uptime => "uptime",
+            EarningSource::Referral => "referral",
+            EarningSource::Bonus => "bonus",
+            EarningSource::Network => "network",
+        }
+    }
+}
+
+impl fmt::Display for EarningSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EarningSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        EarningSource::ALL
+            .into_iter()
+            .find(|source| source.as_str() == s)
+            .ok_or_else(|| {
+                let known: Vec<&str> = EarningSource::ALL.iter().map(EarningSource::as_str).collect();
+                format!("Unknown earning source '{}'; expected one of: {}", s, known.join(", "))
+            })
+    }
+}
+
+impl TryFrom<String> for EarningSource {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
@@ -1,9 +1,11 @@
 // Export all model submodules
 pub mod user;
 pub mod network;
+pub mod earnings;
 pub mod websocket;
 
 // Re-export common models for easier importing
 pub use user::User;
 pub use network::NetworkConnection;
-pub use websocket::{WebSocketAuthMessage, WebSocketAuthResponse, WebSocketMessage, WebSocketConnectionInfo}; 
\ No newline at end of file
+pub use earnings::EarningSource;
+pub use websocket::{SigningMode, WebSocketAuthMessage, WebSocketAuthRequest, WebSocketAuthResponse, WebSocketMessage, WebSocketConnectionInfo}; 
\ No newline at end of file
@@ -46,6 +46,25 @@ pub struct NetworkStatus {
     pub updated_at: DateTime<Utc>,
 }
 
+/// A recorded status transition of a network connection, with the values before and after
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkEvent {
+    /// Network connection ID
+    pub connection_id: i64,
+    /// Whether the connection was active before; `None` for its first status
+    pub previous_connected: Option<bool>,
+    /// Whether the connection is active after the update
+    pub connected: bool,
+    /// Network score before the update; `None` for its first status
+    pub previous_score: Option<f64>,
+    /// Network score after the update
+    pub network_score: f64,
+    /// Status message set by the update
+    pub status_message: String,
+    /// When the update happened
+    pub recorded_at: DateTime<Utc>,
+}
+
 /// Network statistics for a user
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkStatistics {
@@ -59,12 +78,51 @@ pub struct NetworkStatistics {
     pub total_connection_time: i64,
     /// Average network score
     pub average_network_score: f64,
+    /// Average network score weighted by each connection's connected time
+    pub weighted_network_score: f64,
     /// Total points earned from all networks
     pub total_points_earned: f64,
     /// Timestamp when the statistics were last updated
     pub last_updated: DateTime<Utc>,
 }
 
+impl NetworkStatistics {
+    /// Plain average of the connections' scores; 0 without connections
+    pub fn average_score(connections: &[NetworkConnection]) -> f64 {
+        if connections.is_empty() {
+            return 0.0;
+        }
+        connections.iter().map(|c| c.network_score).sum::<f64>() / connections.len() as f64
+    }
+
+    /// Average of the connections' scores weighted by connected time, so long-running
+    /// connections count for more; the plain average when none has any connected time
+    pub fn time_weighted_score(connections: &[NetworkConnection]) -> f64 {
+        let weight = |c: &NetworkConnection| c.connection_time.unwrap_or(0).max(0) as f64;
+        let total_weight: f64 = connections.iter().map(weight).sum();
+        if total_weight == 0.0 {
+            return Self::average_score(connections);
+        }
+        connections.iter().map(|c| c.network_score * weight(c)).sum::<f64>() / total_weight
+    }
+}
+
+/// Network score range for listing connections; both bounds are inclusive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionScoreFilter {
+    /// Only connections scoring at least this
+    pub min_score: Option<f64>,
+    /// Only connections scoring at most this
+    pub max_score: Option<f64>,
+}
+
+impl ConnectionScoreFilter {
+    /// Whether `score` falls within the range
+    pub fn matches(&self, score: f64) -> bool {
+        self.min_score.map_or(true, |min| score >= min) && self.max_score.map_or(true, |max| score <= max)
+    }
+}
+
 /// Data needed to create a new network connection
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateNetworkConnectionDto {
@@ -76,6 +134,9 @@ pub struct CreateNetworkConnectionDto {
     pub ip_address: String,
     /// Initial network score
     pub initial_score: Option<f64>,
+    /// Always create a new row, even if the user already has an active connection on this network
+    #[serde(default)]
+    pub allow_duplicate: bool,
 }
 
 /// Data needed to update a network connection
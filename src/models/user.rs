@@ -1,8 +1,13 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
 use uuid::Uuid;
 
+use crate::errors::{DashboardError, DashboardResult};
+use crate::models::network::NetworkConnection;
+
 /// Represents a user in the system
 #[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct User {
@@ -18,6 +23,47 @@ pub struct User {
     pub created_at: DateTime<Utc>,
     /// Timestamp of the user's last activity
     pub last_active: DateTime<Utc>,
+    /// Account status; suspended users cannot authenticate
+    #[serde(default)]
+    pub status: UserStatus,
+    /// Rate-limit tier applied to the user's WebSocket sessions
+    #[serde(default)]
+    pub rate_limit_tier: RateLimitTier,
+}
+
+/// Rate-limit tier; limits for each tier come from config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum RateLimitTier {
+    /// Strictest limits; also applied to connections before they authenticate
+    Basic,
+    /// Default for registered users
+    #[default]
+    Standard,
+    /// Raised limits for premium users
+    Premium,
+}
+
+/// Lifecycle state of a user account
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum UserStatus {
+    /// Normal account
+    #[default]
+    Active,
+    /// Blocked by an administrator; login and WebSocket auth are refused
+    Suspended,
+    /// Registered but the email address has not been confirmed yet
+    PendingVerification,
+}
+
+impl UserStatus {
+    /// Whether the account is blocked from authenticating
+    pub fn is_suspended(self) -> bool {
+        self == UserStatus::Suspended
+    }
 }
 
 /// Represents a user's authentication credentials
@@ -46,6 +92,19 @@ pub struct CreateUserDto {
     pub wallet_address: Option<String>,
 }
 
+/// A new user together with everything written alongside them at registration
+#[derive(Debug, Clone)]
+pub struct NewUserAccount {
+    /// Profile of the new user
+    pub user: CreateUserDto,
+    /// Argon2 hash of the password
+    pub password_hash: String,
+    /// Salt the hash was computed with
+    pub salt: String,
+    /// Status the account starts in
+    pub status: UserStatus,
+}
+
 /// Data needed to update a user's profile
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateUserDto {
@@ -57,8 +116,69 @@ pub struct UpdateUserDto {
     pub wallet_address: Option<String>,
 }
 
+/// Default maximum characters of an email address (the RFC 5321 path limit)
+pub const DEFAULT_MAX_EMAIL_LEN: usize = 254;
+
+/// Default maximum characters of a username
+pub const DEFAULT_MAX_USERNAME_LEN: usize = 64;
+
+/// Length limits for user profile fields; both fit the `VARCHAR(255)` columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserFieldLimits {
+    /// Maximum characters of an email address
+    pub max_email_len: usize,
+    /// Maximum characters of a username
+    pub max_username_len: usize,
+}
+
+impl Default for UserFieldLimits {
+    fn default() -> Self {
+        Self {
+            max_email_len: DEFAULT_MAX_EMAIL_LEN,
+            max_username_len: DEFAULT_MAX_USERNAME_LEN,
+        }
+    }
+}
+
+impl UserFieldLimits {
+    /// Fail with one `field: problem` entry for each given value longer than its limit
+    fn check(&self, email: Option<&str>, username: Option<&str>) -> DashboardResult<()> {
+        let mut problems = Vec::new();
+        for (field, value, max) in [
+            ("email", email, self.max_email_len),
+            ("username", username, self.max_username_len),
+        ] {
+            if let Some(value) = value {
+                if value.chars().count() > max {
+                    problems.push(format!("{}: must be at most {} characters", field, max));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(DashboardError::validation(problems.join("; ")))
+        }
+    }
+}
+
+impl CreateUserDto {
+    /// Check field lengths, reporting every field over its limit
+    pub fn validate(&self, limits: &UserFieldLimits) -> DashboardResult<()> {
+        limits.check(Some(&self.email), Some(&self.username))
+    }
+}
+
+impl UpdateUserDto {
+    /// Check the lengths of the fields being changed, reporting every field over its limit
+    pub fn validate(&self, limits: &UserFieldLimits) -> DashboardResult<()> {
+        limits.check(self.email.as_deref(), self.username.as_deref())
+    }
+}
+
 /// User session information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
 pub struct UserSession {
     /// Unique session identifier
     pub id: String,
@@ -74,6 +194,111 @@ pub struct UserSession {
     pub user_agent: String,
 }
 
+/// Maximum characters of a client user agent kept in storage
+pub const MAX_USER_AGENT_LEN: usize = 512;
+
+/// Maximum characters of a client IP address kept in storage (fits IPv6 with zone)
+pub const MAX_IP_ADDRESS_LEN: usize = 64;
+
+/// Strip control characters from a client-supplied string and truncate it to `max_chars`
+pub fn sanitize_client_string(value: &str, max_chars: usize) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(max_chars)
+        .collect()
+}
+
+impl UserSession {
+    /// Sanitize a client user agent for storage
+    pub fn sanitize_user_agent(user_agent: &str) -> String {
+        sanitize_client_string(user_agent, MAX_USER_AGENT_LEN)
+    }
+
+    /// Sanitize a client IP address for storage
+    pub fn sanitize_ip_address(ip_address: &str) -> String {
+        sanitize_client_string(ip_address, MAX_IP_ADDRESS_LEN)
+    }
+}
+
+/// Characters of a public key left visible at each end when masked
+pub const MASKED_KEY_VISIBLE_CHARS: usize = 4;
+
+/// Mask a public key for display as `abcd…wxyz`.
+///
+/// Keys too short to hide anything are returned unchanged.
+pub fn mask_public_key(public_key: &str) -> String {
+    let chars: Vec<char> = public_key.chars().collect();
+    if chars.len() <= MASKED_KEY_VISIBLE_CHARS * 2 {
+        return public_key.to_string();
+    }
+    let head: String = chars[..MASKED_KEY_VISIBLE_CHARS].iter().collect();
+    let tail: String = chars[chars.len() - MASKED_KEY_VISIBLE_CHARS..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+/// Metadata about a public key registered to a user
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct PublicKeyInfo {
+    /// User ID that this key belongs to
+    pub user_id: i64,
+    /// Hex-encoded ed25519 public key
+    pub public_key: String,
+    /// Time when the key was registered
+    pub created_at: DateTime<Utc>,
+    /// Time when the key was last used to authenticate
+    pub last_used: Option<DateTime<Utc>>,
+    /// Whether the key has been revoked
+    pub revoked: bool,
+    /// When the key was revoked; cleared on reactivation
+    #[serde(default)]
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl PublicKeyInfo {
+    /// Copy of this metadata with the key masked for display
+    pub fn masked(&self) -> Self {
+        Self {
+            public_key: mask_public_key(&self.public_key),
+            ..self.clone()
+        }
+    }
+
+    /// Order in which a user's keys are listed: active before revoked, then newest first,
+    /// then by key so keys registered in the same instant still have a stable order.
+    ///
+    /// Matches `PUBLIC_KEY_LIST_ORDER_BY` for SQL-backed storage.
+    pub fn cmp_listing_order(&self, other: &Self) -> Ordering {
+        self.revoked
+            .cmp(&other.revoked)
+            .then_with(|| other.created_at.cmp(&self.created_at))
+            .then_with(|| self.public_key.cmp(&other.public_key))
+    }
+}
+
+/// Outcome of registering a single key in a bulk registration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PublicKeyRegistrationStatus {
+    /// The key was newly registered
+    Added,
+    /// The key was already registered to this user
+    AlreadyExists,
+    /// The key could not be registered
+    Error,
+}
+
+/// Per-key result of a bulk public key registration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicKeyRegistrationResult {
+    /// The public key this result refers to
+    pub public_key: String,
+    /// Registration outcome
+    pub status: PublicKeyRegistrationStatus,
+    /// Error details when the status is `Error`
+    pub message: Option<String>,
+}
+
 /// User login response with token
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserLoginResponse {
@@ -85,6 +310,120 @@ pub struct UserLoginResponse {
     pub expires_at: DateTime<Utc>,
 }
 
+/// Token letting an admin act as a user, from `POST /api/admin/users/{id}/impersonate`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpersonationToken {
+    /// JWT carrying the user as subject and the admin as `impersonated_by`
+    pub token: String,
+    /// User being impersonated
+    pub user: User,
+    /// Token expiration time
+    pub expires_at: DateTime<Utc>,
+    /// Admin the token was issued to
+    pub impersonated_by: i64,
+}
+
+/// Records removed by an account deletion, per subsystem
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountDeletion {
+    /// Whether the user record itself was removed
+    pub user_deleted: bool,
+    /// Login sessions removed
+    pub sessions: i64,
+    /// Public keys removed, including revoked ones
+    pub public_keys: usize,
+    /// Network connections removed, with their statuses and earned points
+    pub network_connections: i64,
+}
+
+/// Everything stored about a user, for data-portability requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserDataExport {
+    /// When the export was assembled
+    pub exported_at: DateTime<Utc>,
+    /// Profile
+    pub user: User,
+    /// Public keys with registration, usage and revocation metadata
+    pub public_keys: Vec<PublicKeyInfo>,
+    /// Login sessions
+    pub sessions: Vec<UserSession>,
+    /// Network connections, including per-connection points
+    pub network_connections: Vec<NetworkConnection>,
+    /// Earnings totals
+    pub earnings: EarningsSummary,
+}
+
+/// Earnings totals in a data export.
+///
+/// Points are accrued on network connections, so these are summed from them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EarningsSummary {
+    /// Points earned across all network connections
+    pub total_points_earned: f64,
+    /// Connected time across all network connections, in seconds
+    pub total_connection_time: i64,
+}
+
+impl EarningsSummary {
+    /// Sum the points and connected time of a user's connections
+    pub fn from_connections(connections: &[NetworkConnection]) -> Self {
+        Self {
+            total_points_earned: connections.iter().map(|c| c.points_earned).sum(),
+            total_connection_time: connections.iter().map(|c| c.connection_time.unwrap_or(0)).sum(),
+        }
+    }
+}
+
+/// Where a user notification is delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotificationRoute {
+    /// The user's live WebSocket sessions
+    WebSocket,
+    /// Configured webhook receivers
+    Webhook,
+}
+
+/// Which notification channels a user has muted, per delivery route.
+///
+/// Everything is delivered by default; security-critical notifications are delivered even
+/// on muted channels.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    /// Channels not pushed to the user's WebSocket sessions
+    #[serde(default)]
+    pub muted_websocket_channels: BTreeSet<String>,
+    /// Channels not delivered to webhooks
+    #[serde(default)]
+    pub muted_webhook_channels: BTreeSet<String>,
+}
+
+impl NotificationPreferences {
+    /// Reject channel names outside `known_channels`
+    pub fn validate(&self, known_channels: &[&str]) -> DashboardResult<()> {
+        let unknown = self
+            .muted_websocket_channels
+            .iter()
+            .chain(&self.muted_webhook_channels)
+            .find(|channel| !known_channels.contains(&channel.as_str()));
+        match unknown {
+            Some(channel) => Err(DashboardError::validation(format!(
+                "Unknown notification channel '{}'; expected one of {}",
+                channel,
+                known_channels.join(", ")
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the user muted `channel` on `route`
+    pub fn mutes(&self, route: NotificationRoute, channel: &str) -> bool {
+        match route {
+            NotificationRoute::WebSocket => self.muted_websocket_channels.contains(channel),
+            NotificationRoute::Webhook => self.muted_webhook_channels.contains(channel),
+        }
+    }
+}
+
 impl User {
     /// Create a new user with the given details
     pub fn new(email: String, username: String, wallet_address: Option<String>) -> Self {
@@ -96,6 +435,8 @@ impl User {
             wallet_address,
             created_at: now,
             last_active: now,
+            status: UserStatus::default(),
+            rate_limit_tier: RateLimitTier::default(),
         }
     }
 } 
\ No newline at end of file
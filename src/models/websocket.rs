@@ -1,6 +1,20 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::freshness;
+use crate::models::earnings::EarningSource;
+
+/// How the signed message is derived from an authentication message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningMode {
+    /// The signed message is the string `timestamp:nonce`
+    #[default]
+    Plain,
+    /// The signed message is the canonical JSON of `{public_key, timestamp, nonce}`
+    CanonicalJson,
+}
+
 /// Message for WebSocket authentication using ed25519 signatures
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebSocketAuthMessage {
@@ -12,6 +26,22 @@ pub struct WebSocketAuthMessage {
     pub nonce: String,
     /// Ed25519 signature of the message (timestamp + nonce)
     pub signature: String,
+    /// How the signed message was constructed by the client
+    #[serde(default)]
+    pub signing_mode: SigningMode,
+}
+
+/// Payload of an `Auth` message; the fields present select how the client authenticates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WebSocketAuthRequest {
+    /// An ed25519 signature by one of the user's registered keys
+    Signature(WebSocketAuthMessage),
+    /// A bearer JWT from the external identity provider
+    ExternalToken {
+        /// The provider-issued JWT
+        token: String,
+    },
 }
 
 /// Response to a WebSocket authentication attempt
@@ -29,20 +59,51 @@ pub struct WebSocketAuthResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "data")]
 pub enum WebSocketMessage {
-    /// Authentication message
-    Auth(WebSocketAuthMessage),
+    /// Authentication message, by signature or external identity token
+    Auth(WebSocketAuthRequest),
     /// Heartbeat message to keep connection alive
     Heartbeat,
     /// Connection status update
     ConnectionUpdate { connected: bool },
-    /// Network status update
-    NetworkUpdate { status: String, score: f64 },
-    /// Earnings update
-    EarningsUpdate { amount: f64, source: String },
+    /// Network status update; with a `connection_id` it is recorded as that connection's status
+    NetworkUpdate {
+        status: String,
+        score: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        connection_id: Option<i64>,
+    },
+    /// Earnings update; pushed by the server with the user's running `total`
+    EarningsUpdate {
+        amount: f64,
+        source: EarningSource,
+        #[serde(default)]
+        total: f64,
+    },
     /// Generic error message
     Error { code: String, message: String },
     /// Custom data message
     Data { content: serde_json::Value },
+    /// Resume an authenticated session with a reconnect token
+    Reconnect { token: String },
+    /// Request network statistics and this session's message counters
+    StatsRequest,
+    /// Add channels to the session's subscription set
+    Subscribe { channels: Vec<String> },
+    /// Remove channels from the session's subscription set
+    Unsubscribe { channels: Vec<String> },
+}
+
+impl WebSocketMessage {
+    /// Whether the message reports client state the server would record, as opposed to
+    /// session control (auth, heartbeats, subscriptions, stats)
+    pub fn is_state_changing(&self) -> bool {
+        matches!(
+            self,
+            WebSocketMessage::ConnectionUpdate { .. }
+                | WebSocketMessage::NetworkUpdate { .. }
+                | WebSocketMessage::EarningsUpdate { .. }
+        )
+    }
 }
 
 /// WebSocket connection information
@@ -60,6 +121,14 @@ pub struct WebSocketConnectionInfo {
     pub last_active: DateTime<Utc>,
     /// Authentication status
     pub authenticated: bool,
+    /// Bytes received from the client in text and binary frames
+    pub bytes_received: u64,
+    /// Bytes sent to the client in text and binary frames
+    pub bytes_sent: u64,
+    /// When the client last sent any frame, including protocol ping/pong
+    pub last_transport_activity: DateTime<Utc>,
+    /// When the client last sent an application `Heartbeat`, if ever
+    pub last_app_heartbeat: Option<DateTime<Utc>>,
 }
 
 impl WebSocketAuthMessage {
@@ -70,16 +139,40 @@ impl WebSocketAuthMessage {
             timestamp,
             nonce,
             signature,
+            signing_mode: SigningMode::Plain,
         }
     }
 
-    /// Get the message that was signed (timestamp + nonce)
+    /// Use the given signing mode
+    pub fn with_signing_mode(mut self, signing_mode: SigningMode) -> Self {
+        self.signing_mode = signing_mode;
+        self
+    }
+
+    /// Get the message that was signed, according to the signing mode
     pub fn get_signed_message(&self) -> String {
-        format!("{}:{}", self.timestamp, self.nonce)
+        match self.signing_mode {
+            SigningMode::Plain => format!("{}:{}", self.timestamp, self.nonce),
+            SigningMode::CanonicalJson => canonical_json(&serde_json::json!({
+                "public_key": self.public_key,
+                "timestamp": self.timestamp,
+                "nonce": self.nonce
+            })),
+        }
     }
 
-    /// Validate the basic structure of the message
+    /// Validate the basic structure of the message using the default timestamp window
     pub fn validate(&self) -> Result<(), String> {
+        self.validate_with(&TimestampWindow::default())
+    }
+
+    /// Validate the basic structure of the message, accepting timestamps inside `window`
+    pub fn validate_with(&self, window: &TimestampWindow) -> Result<(), String> {
+        self.validate_at(window, chrono::Utc::now().timestamp())
+    }
+
+    /// Validate against the server clock reading `now` (unix seconds)
+    pub fn validate_at(&self, window: &TimestampWindow, now: i64) -> Result<(), String> {
         // Check public key format (should be a valid hex string)
         if self.public_key.len() != 64 && self.public_key.len() != 128 {
             return Err("Invalid public key length".to_string());
@@ -90,16 +183,8 @@ impl WebSocketAuthMessage {
         }
 
         // Ensure the timestamp is reasonable (not too old or in the future)
-        let now = chrono::Utc::now().timestamp();
-        let time_diff = now - self.timestamp;
-        
-        if time_diff < -60 { // Allow 1 minute of clock skew
-            return Err("Timestamp is in the future".to_string());
-        }
-        
-        if time_diff > 300 { // 5 minutes expiration
-            return Err("Authentication message has expired".to_string());
-        }
+        freshness::check(self.timestamp, now, window.max_future_secs, window.max_age_secs)
+            .map_err(|err| err.to_string())?;
 
         // Verify nonce is present and reasonable length
         if self.nonce.is_empty() || self.nonce.len() < 8 || self.nonce.len() > 64 {
@@ -117,4 +202,56 @@ impl WebSocketAuthMessage {
 
         Ok(())
     }
-} 
\ No newline at end of file
+}
+
+/// How far a signed auth timestamp may drift from the server clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampWindow {
+    /// Seconds a message stays valid after it was signed
+    pub max_age_secs: i64,
+    /// Seconds a client clock may run ahead of the server's
+    pub max_future_secs: i64,
+}
+
+/// Default lifetime of a signed auth message
+pub const DEFAULT_AUTH_MAX_AGE_SECS: i64 = 300;
+
+/// Default tolerance for client clocks running ahead of the server
+pub const DEFAULT_AUTH_MAX_FUTURE_SECS: i64 = 60;
+
+impl Default for TimestampWindow {
+    fn default() -> Self {
+        Self {
+            max_age_secs: DEFAULT_AUTH_MAX_AGE_SECS,
+            max_future_secs: DEFAULT_AUTH_MAX_FUTURE_SECS,
+        }
+    }
+}
+
+/// Serialize a JSON value canonically: object keys sorted, no insignificant whitespace.
+///
+/// Clients signing JSON challenges must produce exactly these bytes.
+pub fn canonical_json(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .into_iter()
+                .map(|key| {
+                    format!(
+                        "{}:{}",
+                        serde_json::Value::String(key.clone()),
+                        canonical_json(&map[key])
+                    )
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        serde_json::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_json).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
@@ -1,10 +1,23 @@
-use actix_web::{web, Scope, get, HttpResponse, Responder};
-use crate::handlers::websocket::{dashboard_ws, earnings_ws, referrals_ws};
+use actix_web::{web, Scope, get, post, HttpResponse, Responder};
+use crate::handlers::websocket::{dashboard_ws, earnings_ws, referrals_ws, status_ws};
 use crate::handlers::user::{
-    register_user, get_user, update_user, delete_user,
-    add_public_key, get_public_keys, revoke_public_key
+    register_user, get_user, update_user, delete_user, delete_me, change_my_password, export_my_data,
+    get_my_notification_preferences, update_my_notification_preferences,
+    add_public_key, add_public_keys, get_public_keys, get_public_key_info, revoke_public_key,
+    reactivate_public_key, list_user_sessions,
+};
+use crate::handlers::auth::{
+    issue_auth_challenge, issue_ws_ticket, login, refresh_token, resend_verification, verify_email,
+};
+use crate::handlers::admin::{
+    delete_public_key, impersonate_user, list_connections, list_users, list_webhook_deliveries,
+    reload_denied_keys, set_maintenance, set_read_only, set_user_status,
+};
+use crate::handlers::debug::verify_signature;
+use crate::handlers::network::{
+    create_connection, delete_connection, export_user_connections, get_connection, list_connection_events,
+    list_network_connections, list_user_connections, update_connection,
 };
-use crate::handlers::auth::login;
 
 pub fn api_routes() -> Scope {
     web::scope("/api")
@@ -18,6 +31,12 @@ pub fn api_routes() -> Scope {
         .service(earnings_routes())
         // Referral routes will go here
         .service(referral_routes())
+        // Admin routes
+        .service(admin_routes())
+        // Single-use tickets for browser WebSocket auth
+        .route("/ws-ticket", web::post().to(issue_ws_ticket))
+        // Diagnostics (development or admin only)
+        .service(debug_routes())
         // Development routes (only in debug builds)
         .service(dev_routes())
 }
@@ -25,28 +44,60 @@ pub fn api_routes() -> Scope {
 pub fn auth_routes() -> Scope {
     web::scope("/auth")
         // Login endpoint
-        .route("/login", web::post().to(login::<crate::storage::memory::InMemoryUserStorage>))
+        .route("/login", web::post().to(login::<crate::storage::AppUserStorage>))
+        // Token refresh within the session's absolute lifetime
+        .route("/refresh", web::post().to(refresh_token::<crate::storage::AppUserStorage>))
+        // Nonces for challenge-response WebSocket signature auth
+        .route("/challenge", web::get().to(issue_auth_challenge))
+        // Email verification for new registrations
+        .route("/verify-email", web::post().to(verify_email::<crate::storage::AppUserStorage>))
+        .route("/resend-verification", web::post().to(resend_verification::<crate::storage::AppUserStorage>))
 }
 
 pub fn user_routes() -> Scope {
     web::scope("/users")
         // User registration
-        .route("", web::post().to(register_user::<crate::storage::memory::InMemoryUserStorage>))
+        .route("", web::post().to(register_user::<crate::storage::AppUserStorage>))
+        // Data export for the authenticated user
+        .route("/me/export", web::get().to(export_my_data::<crate::storage::AppUserStorage>))
+        // Password change, refusing recently used passwords
+        .route("/me/password", web::put().to(change_my_password::<crate::storage::AppUserStorage>))
+        // Channels the authenticated user muted per delivery route
+        .route("/me/notification-preferences", web::get().to(get_my_notification_preferences::<crate::storage::AppUserStorage>))
+        .route("/me/notification-preferences", web::put().to(update_my_notification_preferences::<crate::storage::AppUserStorage>))
+        // Self-service account deletion, confirmed with a token
+        .route("/me", web::delete().to(delete_me::<crate::storage::AppUserStorage>))
         // Get user by ID
-        .route("/{id}", web::get().to(get_user::<crate::storage::memory::InMemoryUserStorage>))
+        .route("/{id}", web::get().to(get_user::<crate::storage::AppUserStorage>))
         // Update user
-        .route("/{id}", web::put().to(update_user::<crate::storage::memory::InMemoryUserStorage>))
+        .route("/{id}", web::put().to(update_user::<crate::storage::AppUserStorage>))
         // Delete user
-        .route("/{id}", web::delete().to(delete_user::<crate::storage::memory::InMemoryUserStorage>))
+        .route("/{id}", web::delete().to(delete_user::<crate::storage::AppUserStorage>))
         // Public key management
-        .route("/{id}/keys", web::post().to(add_public_key::<crate::storage::memory::InMemoryUserStorage>))
-        .route("/{id}/keys", web::get().to(get_public_keys::<crate::storage::memory::InMemoryUserStorage>))
-        .route("/{id}/keys/{key}", web::delete().to(revoke_public_key::<crate::storage::memory::InMemoryUserStorage>))
+        .route("/{id}/keys", web::post().to(add_public_key::<crate::storage::AppUserStorage>))
+        .route("/{id}/keys/bulk", web::post().to(add_public_keys::<crate::storage::AppUserStorage>))
+        .route("/{id}/keys", web::get().to(get_public_keys::<crate::storage::AppUserStorage>))
+        .route("/{id}/keys/{key}", web::get().to(get_public_key_info::<crate::storage::AppUserStorage>))
+        .route("/{id}/keys/{key}", web::delete().to(revoke_public_key::<crate::storage::AppUserStorage>))
+        .route("/{id}/keys/{key}/reactivate", web::post().to(reactivate_public_key::<crate::storage::AppUserStorage>))
+        // Stored login sessions
+        .route("/{id}/sessions", web::get().to(list_user_sessions::<crate::storage::AppUserStorage>))
+        // Network connections, optionally filtered by score
+        .route("/{id}/networks", web::get().to(list_user_connections::<crate::storage::AppNetworkStorage>))
+        // Network connection export (NDJSON)
+        .route("/{id}/networks/export", web::get().to(export_user_connections::<crate::storage::AppNetworkStorage>))
 }
 
 pub fn network_routes() -> Scope {
     web::scope("/networks")
-        // Network information, status, etc.
+        // Network connection CRUD, scoped to the caller unless they are an admin
+        .route("", web::post().to(create_connection::<crate::storage::AppNetworkStorage>))
+        .route("", web::get().to(list_network_connections::<crate::storage::AppNetworkStorage>))
+        .route("/{id}", web::get().to(get_connection::<crate::storage::AppNetworkStorage>))
+        .route("/{id}", web::put().to(update_connection::<crate::storage::AppNetworkStorage>))
+        .route("/{id}", web::delete().to(delete_connection::<crate::storage::AppNetworkStorage>))
+        // Status transition history of a connection
+        .route("/{id}/events", web::get().to(list_connection_events::<crate::storage::AppNetworkStorage>))
 }
 
 pub fn earnings_routes() -> Scope {
@@ -59,6 +110,34 @@ pub fn referral_routes() -> Scope {
         // Referral generation, tracking, etc.
 }
 
+pub fn admin_routes() -> Scope {
+    web::scope("/admin")
+        // Live WebSocket connections on this instance
+        .route("/connections", web::get().to(list_connections))
+        // Users, cursor-paginated
+        .route("/users", web::get().to(list_users::<crate::storage::AppUserStorage>))
+        // Suspend or reactivate a user account
+        .route("/users/{id}/status", web::put().to(set_user_status::<crate::storage::AppUserStorage>))
+        // Short-lived, audited token for acting as a user
+        .route("/users/{id}/impersonate", web::post().to(impersonate_user::<crate::storage::AppUserStorage>))
+        // Maintenance banner
+        .route("/maintenance", web::put().to(set_maintenance))
+        // Refuse writes while storage is being migrated or failed over
+        .route("/read-only", web::put().to(set_read_only))
+        // Recent webhook deliveries and their outcome
+        .route("/webhooks/deliveries", web::get().to(list_webhook_deliveries))
+        // Reload the global public key deny-list
+        .route("/denied-keys/reload", web::post().to(reload_denied_keys))
+        // Hard-delete a user's public key
+        .route("/users/{id}/keys/{key}", web::delete().to(delete_public_key::<crate::storage::AppUserStorage>))
+}
+
+pub fn debug_routes() -> Scope {
+    web::scope("/debug")
+        // Check a signature without authenticating
+        .route("/verify-signature", web::post().to(verify_signature::<crate::storage::AppUserStorage>))
+}
+
 pub fn websocket_routes() -> Scope {
     web::scope("/ws")
         // Dashboard WebSocket endpoint
@@ -67,6 +146,8 @@ pub fn websocket_routes() -> Scope {
         .route("/earnings", web::get().to(earnings_ws))
         // Referrals WebSocket endpoint
         .route("/referrals", web::get().to(referrals_ws))
+        // Public status WebSocket endpoint (no authentication)
+        .route("/status", web::get().to(status_ws))
 }
 
 // Development routes - only available in debug builds
@@ -77,6 +158,7 @@ pub fn dev_routes() -> Scope {
         .service(get_test_keys)
         .service(get_test_key)
         .service(get_test_auth_message)
+        .service(regenerate_test_keys)
 }
 
 // Empty scope for production builds
@@ -87,11 +169,28 @@ pub fn dev_routes() -> Scope {
 
 // Development endpoints for test keys
 
+/// Query flags for the test keys listing
+#[cfg(debug_assertions)]
+#[derive(serde::Deserialize)]
+struct TestKeysQuery {
+    /// Omit private keys from the response
+    #[serde(default)]
+    public_only: bool,
+}
+
 #[cfg(debug_assertions)]
 #[get("/test-keys")]
-async fn get_test_keys() -> impl Responder {
-    let keys = crate::dev::test_keys::get_test_keys();
-    HttpResponse::Ok().json(keys)
+async fn get_test_keys(
+    pagination: crate::handlers::extractors::Pagination,
+    query: web::Query<TestKeysQuery>,
+) -> impl Responder {
+    let keys = pagination.apply(crate::dev::test_keys::get_test_keys());
+    if query.public_only {
+        let keys: Vec<crate::dev::test_keys::TestPublicKey> = keys.into_iter().map(Into::into).collect();
+        HttpResponse::Ok().json(keys)
+    } else {
+        HttpResponse::Ok().json(keys)
+    }
 }
 
 #[cfg(debug_assertions)]
@@ -118,4 +217,29 @@ async fn get_test_auth_message(path: web::Path<usize>) -> impl Responder {
             "error": error
         }))
     }
-} 
\ No newline at end of file
+} 
+
+/// Request for regenerating the test keys
+#[cfg(debug_assertions)]
+#[derive(serde::Deserialize)]
+struct RegenerateKeysRequest {
+    /// Seed the keys are derived from; omitted restores the default keys
+    seed: Option<String>,
+}
+
+#[cfg(debug_assertions)]
+#[post("/regenerate-keys")]
+async fn regenerate_test_keys(
+    body: Option<web::Json<RegenerateKeysRequest>>,
+    signature_service: web::Data<crate::services::SignatureService<crate::storage::AppUserStorage>>,
+) -> impl Responder {
+    let seed = body.and_then(|body| body.into_inner().seed);
+    let keys = crate::dev::test_keys::regenerate_test_keys(seed.as_deref());
+    
+    // Register the new keys with the active user backend so they can authenticate straight away
+    if let Err(e) = crate::dev::test_keys::register_test_keys_with_users(signature_service.user_storage().as_ref()).await {
+        tracing::warn!("Failed to register regenerated test keys: {}", e);
+    }
+    
+    HttpResponse::Ok().json(keys)
+}
\ No newline at end of file
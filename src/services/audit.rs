@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{error, warn};
+
+/// Security-relevant event kept for later review
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// A reconnect token was presented from a different IP than it was issued to
+    ReconnectIpMismatch {
+        user_id: i64,
+        /// WebSocket session that presented the token
+        session_id: String,
+        /// IP the token was issued to
+        original_ip: String,
+        /// IP the token was presented from
+        client_ip: String,
+        /// Whether the session was resumed anyway (`flag` policy) or had to re-authenticate
+        resumed: bool,
+    },
+    /// An admin minted a token to act as a user
+    ImpersonationStarted {
+        admin_id: i64,
+        user_id: i64,
+        /// Why support needed to act as the user
+        reason: String,
+        /// When the token stops working
+        expires_at: DateTime<Utc>,
+        /// IP the admin requested it from
+        client_ip: String,
+    },
+    /// A request was made with an impersonation token
+    ImpersonatedRequest {
+        admin_id: i64,
+        user_id: i64,
+        method: String,
+        path: String,
+    },
+}
+
+/// Destination for audit events; deployments can forward them to a SIEM or a database
+pub trait AuditSink: Send + Sync {
+    /// Record `event`; must not block, since it is called from request and session handlers
+    fn record(&self, event: AuditEvent);
+}
+
+/// Default sink writing each event as JSON to the log under the `audit` target
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogAuditSink;
+
+impl AuditSink for LogAuditSink {
+    fn record(&self, event: AuditEvent) {
+        match serde_json::to_string(&event) {
+            Ok(json) => warn!(target: "audit", "{}", json),
+            Err(e) => error!("Failed to serialize audit event {:?}: {}", event, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_event_serializes_with_its_name() {
+        let event = AuditEvent::ReconnectIpMismatch {
+            user_id: 7,
+            session_id: "s1".to_string(),
+            original_ip: "10.0.0.1".to_string(),
+            client_ip: "10.0.0.2".to_string(),
+            resumed: false,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            json!({
+                "event": "reconnect_ip_mismatch",
+                "user_id": 7,
+                "session_id": "s1",
+                "original_ip": "10.0.0.1",
+                "client_ip": "10.0.0.2",
+                "resumed": false
+            })
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use nanoid::nanoid;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+use crate::cache::BoundedCache;
+
+/// Default number of outstanding challenges kept
+pub const DEFAULT_AUTH_CHALLENGE_CAPACITY: usize = 100_000;
+
+/// Server-issued nonces for WebSocket signature auth.
+///
+/// With `REQUIRE_SERVER_CHALLENGE` only nonces from `GET /api/auth/challenge` are accepted, so
+/// a signature can't have been prepared ahead of time. Each challenge is single-use and
+/// expires after `ttl`; when the store is full the oldest outstanding challenge is dropped.
+#[derive(Clone)]
+pub struct AuthChallenges {
+    ttl: Duration,
+    /// nonce -> expires_at
+    challenges: Arc<Mutex<BoundedCache<String, Instant>>>,
+}
+
+impl AuthChallenges {
+    /// Create a store of at most `capacity` challenges that expire `ttl` after issue
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            challenges: Arc::new(Mutex::new(BoundedCache::new(capacity))),
+        }
+    }
+
+    /// How long a challenge stays valid
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Mint a challenge nonce for the client to sign
+    pub fn issue(&self, now: Instant) -> Option<String> {
+        // Expired challenges are refused on redeem and are the first evicted, so no sweep here
+        let nonce = nanoid!(32);
+        match self.challenges.lock() {
+            Ok(mut challenges) => challenges.insert(nonce.clone(), now + self.ttl),
+            Err(e) => {
+                error!("Failed to lock auth challenges: {}", e);
+                return None;
+            }
+        }
+        Some(nonce)
+    }
+
+    /// Consume a challenge, returning whether it was issued here and had not expired
+    pub fn redeem(&self, nonce: &str, now: Instant) -> bool {
+        let expires_at = match self.challenges.lock() {
+            Ok(mut challenges) => challenges.remove(&nonce.to_string()),
+            Err(e) => {
+                error!("Failed to lock auth challenges: {}", e);
+                return false;
+            }
+        };
+        matches!(expires_at, Some(expires_at) if expires_at > now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_challenge_is_single_use_and_expires() {
+        let challenges = AuthChallenges::new(Duration::from_secs(60), 10);
+        let now = Instant::now();
+
+        let nonce = challenges.issue(now).unwrap();
+        assert!(challenges.redeem(&nonce, now));
+        assert!(!challenges.redeem(&nonce, now));
+
+        let nonce = challenges.issue(now).unwrap();
+        assert!(!challenges.redeem(&nonce, now + Duration::from_secs(60)));
+        assert!(!challenges.redeem("client-invented", now));
+    }
+
+    #[test]
+    fn test_store_is_bounded() {
+        let challenges = AuthChallenges::new(Duration::from_secs(60), 2);
+        let now = Instant::now();
+
+        let oldest = challenges.issue(now).unwrap();
+        let middle = challenges.issue(now).unwrap();
+        let newest = challenges.issue(now).unwrap();
+        assert!(!challenges.redeem(&oldest, now));
+        assert!(challenges.redeem(&middle, now));
+        assert!(challenges.redeem(&newest, now));
+    }
+}
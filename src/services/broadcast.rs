@@ -0,0 +1,575 @@
+use actix::dev::ToEnvelope;
+use actix::{Actor, Addr, Handler, Message, Recipient};
+use actix_web_actors::ws::CloseCode;
+use bytestring::ByteString;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error, warn};
+
+/// Channel carrying `network_event` and `network_status` frames
+pub const NETWORK_CHANNEL: &str = "network";
+
+/// Channel carrying `EarningsUpdate` frames
+pub const EARNINGS_CHANNEL: &str = "earnings";
+
+/// Channels a session can subscribe to; new sessions start subscribed to all of them
+pub const KNOWN_CHANNELS: &[&str] = &[NETWORK_CHANNEL, EARNINGS_CHANNEL, "referrals"];
+
+/// Whether `name` is one of `KNOWN_CHANNELS`
+pub fn is_known_channel(name: &str) -> bool {
+    KNOWN_CHANNELS.contains(&name)
+}
+
+/// Every known channel, the default subscription set
+pub fn all_channels() -> BTreeSet<String> {
+    KNOWN_CHANNELS.iter().map(|channel| channel.to_string()).collect()
+}
+
+/// A JSON frame pushed from the server to a WebSocket session
+#[derive(Debug, Clone)]
+pub struct ServerEvent(pub serde_json::Value);
+
+impl ServerEvent {
+    /// Serialize once into a frame that can be shared by every recipient
+    pub fn to_frame(&self) -> RawFrame {
+        RawFrame(ByteString::from(self.0.to_string()))
+    }
+}
+
+/// A pre-serialized JSON text frame; clones share the same buffer
+#[derive(Debug, Clone)]
+pub struct RawFrame(pub ByteString);
+
+impl Message for RawFrame {
+    type Result = ();
+}
+
+/// Ask a session to close, e.g. because its user was suspended
+#[derive(Debug, Clone)]
+pub struct Disconnect {
+    /// Close code sent to the client
+    pub code: CloseCode,
+    /// Close reason sent to the client
+    pub reason: String,
+}
+
+impl Message for Disconnect {
+    type Result = ();
+}
+
+/// A user event or disconnect forwarded between the instances of a deployment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RelayedBroadcast {
+    /// `publish_to_user` on another instance
+    Event {
+        user_id: i64,
+        channel: String,
+        event: serde_json::Value,
+    },
+    /// `disconnect_user` on another instance
+    Disconnect { user_id: i64, reason: String },
+}
+
+/// Mailboxes of one subscribed session
+#[derive(Clone)]
+struct Subscriber {
+    frames: Recipient<RawFrame>,
+    disconnect: Recipient<Disconnect>,
+    /// Channels whose events this session receives
+    channels: BTreeSet<String>,
+}
+
+/// Fan-out of server events to every authenticated session of a user on this instance.
+///
+/// Sessions all speak JSON text frames, so each event is serialized once per publish and the
+/// same buffer is handed to every session. With a relay, user events and disconnects are also
+/// forwarded to the other instances; `publish_all` and `disconnect_all` stay local.
+#[derive(Clone, Default)]
+pub struct BroadcastHub {
+    /// user_id -> session_id -> session mailbox
+    subscribers: Arc<Mutex<HashMap<i64, HashMap<String, Subscriber>>>>,
+    /// Forwards user events and disconnects to other instances
+    relay: Option<UnboundedSender<RelayedBroadcast>>,
+}
+
+impl BroadcastHub {
+    /// Create a new hub with no subscribers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forward user events and disconnects to `relay` as well as delivering them locally
+    pub fn with_relay(mut self, relay: UnboundedSender<RelayedBroadcast>) -> Self {
+        self.relay = Some(relay);
+        self
+    }
+
+    /// Hand a broadcast to the relay, if there is one
+    fn relay(&self, broadcast: RelayedBroadcast) {
+        if let Some(relay) = &self.relay {
+            if relay.send(broadcast).is_err() {
+                warn!("Broadcast relay has stopped; event delivered on this instance only");
+            }
+        }
+    }
+
+    /// Deliver a broadcast relayed from another instance to the sessions on this one, without
+    /// relaying it again.
+    ///
+    /// Returns the number of sessions it was queued for.
+    pub fn deliver_relayed(&self, broadcast: RelayedBroadcast) -> usize {
+        match broadcast {
+            RelayedBroadcast::Event {
+                user_id,
+                channel,
+                event,
+            } => self.publish_local(user_id, &channel, ServerEvent(event), None),
+            RelayedBroadcast::Disconnect { user_id, reason } => self.disconnect_local(user_id, &reason),
+        }
+    }
+
+    /// Subscribe an authenticated session to its user's events on every channel
+    pub fn subscribe<A>(&self, user_id: i64, session_id: &str, session: &Addr<A>)
+    where
+        A: Actor + Handler<RawFrame> + Handler<Disconnect>,
+        A::Context: ToEnvelope<A, RawFrame> + ToEnvelope<A, Disconnect>,
+    {
+        self.subscribe_channels(user_id, session_id, session, all_channels());
+    }
+
+    /// Subscribe an authenticated session to its user's events on `channels`
+    pub fn subscribe_channels<A>(
+        &self,
+        user_id: i64,
+        session_id: &str,
+        session: &Addr<A>,
+        channels: BTreeSet<String>,
+    ) where
+        A: Actor + Handler<RawFrame> + Handler<Disconnect>,
+        A::Context: ToEnvelope<A, RawFrame> + ToEnvelope<A, Disconnect>,
+    {
+        let subscriber = Subscriber {
+            frames: session.clone().recipient(),
+            disconnect: session.clone().recipient(),
+            channels,
+        };
+        match self.subscribers.lock() {
+            Ok(mut subscribers) => {
+                subscribers
+                    .entry(user_id)
+                    .or_default()
+                    .insert(session_id.to_owned(), subscriber);
+            }
+            Err(e) => error!("Failed to lock broadcast hub: {}", e),
+        }
+    }
+
+    /// Replace the channels of a subscribed session, returning `false` if it isn't subscribed
+    pub fn set_channels(&self, user_id: i64, session_id: &str, channels: BTreeSet<String>) -> bool {
+        match self.subscribers.lock() {
+            Ok(mut subscribers) => match subscribers
+                .get_mut(&user_id)
+                .and_then(|sessions| sessions.get_mut(session_id))
+            {
+                Some(subscriber) => {
+                    subscriber.channels = channels;
+                    true
+                }
+                None => false,
+            },
+            Err(e) => {
+                error!("Failed to lock broadcast hub: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Remove a session's subscription
+    pub fn unsubscribe(&self, user_id: i64, session_id: &str) {
+        match self.subscribers.lock() {
+            Ok(mut subscribers) => {
+                if let Some(sessions) = subscribers.get_mut(&user_id) {
+                    sessions.remove(session_id);
+                    if sessions.is_empty() {
+                        subscribers.remove(&user_id);
+                    }
+                }
+            }
+            Err(e) => error!("Failed to lock broadcast hub: {}", e),
+        }
+    }
+
+    /// Number of sessions subscribed for a user
+    pub fn subscriber_count(&self, user_id: i64) -> usize {
+        self.subscribers
+            .lock()
+            .map(|subscribers| subscribers.get(&user_id).map_or(0, HashMap::len))
+            .unwrap_or(0)
+    }
+
+    /// Deliver an event on `channel` to all of a user's sessions subscribed to it, optionally
+    /// skipping the originating one.
+    ///
+    /// Returns the number of sessions on this instance the event was queued for.
+    pub fn publish_to_user(
+        &self,
+        user_id: i64,
+        channel: &str,
+        event: ServerEvent,
+        exclude_session: Option<&str>,
+    ) -> usize {
+        if self.relay.is_some() {
+            self.relay(RelayedBroadcast::Event {
+                user_id,
+                channel: channel.to_string(),
+                event: event.0.clone(),
+            });
+        }
+        self.publish_local(user_id, channel, event, exclude_session)
+    }
+
+    fn publish_local(
+        &self,
+        user_id: i64,
+        channel: &str,
+        event: ServerEvent,
+        exclude_session: Option<&str>,
+    ) -> usize {
+        let recipients: Vec<Recipient<RawFrame>> = match self.subscribers.lock() {
+            Ok(subscribers) => subscribers
+                .get(&user_id)
+                .map(|sessions| {
+                    sessions
+                        .iter()
+                        .filter(|(session_id, _)| Some(session_id.as_str()) != exclude_session)
+                        .filter(|(_, subscriber)| subscriber.channels.contains(channel))
+                        .map(|(_, subscriber)| subscriber.frames.clone())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Err(e) => {
+                error!("Failed to lock broadcast hub: {}", e);
+                return 0;
+            }
+        };
+
+        if recipients.is_empty() {
+            return 0;
+        }
+
+        // Serialize once and send outside the lock; a closed mailbox is cleaned up when its
+        // session stops
+        let frame = event.to_frame();
+        for recipient in &recipients {
+            recipient.do_send(frame.clone());
+        }
+        debug!("Published event to {} sessions of user {}", recipients.len(), user_id);
+        recipients.len()
+    }
+
+    /// Close every session of a user, returning how many on this instance were asked to close
+    pub fn disconnect_user(&self, user_id: i64, reason: &str) -> usize {
+        self.relay(RelayedBroadcast::Disconnect {
+            user_id,
+            reason: reason.to_string(),
+        });
+        self.disconnect_local(user_id, reason)
+    }
+
+    fn disconnect_local(&self, user_id: i64, reason: &str) -> usize {
+        let sessions = match self.subscribers.lock() {
+            Ok(mut subscribers) => subscribers.remove(&user_id).unwrap_or_default(),
+            Err(e) => {
+                error!("Failed to lock broadcast hub: {}", e);
+                return 0;
+            }
+        };
+
+        for subscriber in sessions.values() {
+            subscriber.disconnect.do_send(Disconnect {
+                code: CloseCode::Policy,
+                reason: reason.to_string(),
+            });
+        }
+        debug!("Disconnected {} sessions of user {}", sessions.len(), user_id);
+        sessions.len()
+    }
+
+    /// Deliver an event to every subscribed session regardless of channel, returning how many
+    /// it was queued for
+    pub fn publish_all(&self, event: ServerEvent) -> usize {
+        let recipients: Vec<Recipient<RawFrame>> = match self.subscribers.lock() {
+            Ok(subscribers) => subscribers
+                .values()
+                .flat_map(|sessions| sessions.values().map(|subscriber| subscriber.frames.clone()))
+                .collect(),
+            Err(e) => {
+                error!("Failed to lock broadcast hub: {}", e);
+                return 0;
+            }
+        };
+
+        let frame = event.to_frame();
+        for recipient in &recipients {
+            recipient.do_send(frame.clone());
+        }
+        recipients.len()
+    }
+
+    /// Close every subscribed session with `Going Away`, e.g. on server shutdown.
+    ///
+    /// Frames published earlier are delivered first, since each session handles its mailbox
+    /// in order.
+    pub fn disconnect_all(&self, reason: &str) -> usize {
+        let subscribers = match self.subscribers.lock() {
+            Ok(mut subscribers) => std::mem::take(&mut *subscribers),
+            Err(e) => {
+                error!("Failed to lock broadcast hub: {}", e);
+                return 0;
+            }
+        };
+
+        let mut count = 0;
+        for subscriber in subscribers.values().flat_map(HashMap::values) {
+            subscriber.disconnect.do_send(Disconnect {
+                code: CloseCode::Away,
+                reason: reason.to_string(),
+            });
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix::Context;
+
+    /// Test actor that records received events
+    #[derive(Default)]
+    struct Collector {
+        events: Vec<serde_json::Value>,
+        disconnected: bool,
+    }
+
+    impl Actor for Collector {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<RawFrame> for Collector {
+        type Result = ();
+
+        fn handle(&mut self, frame: RawFrame, _: &mut Self::Context) {
+            self.events.push(serde_json::from_str(&frame.0).unwrap());
+        }
+    }
+
+    impl Handler<Disconnect> for Collector {
+        type Result = ();
+
+        fn handle(&mut self, _: Disconnect, _: &mut Self::Context) {
+            self.disconnected = true;
+        }
+    }
+
+    /// Whether the collector was asked to disconnect
+    struct WasDisconnected;
+
+    impl Message for WasDisconnected {
+        type Result = bool;
+    }
+
+    impl Handler<WasDisconnected> for Collector {
+        type Result = bool;
+
+        fn handle(&mut self, _: WasDisconnected, _: &mut Self::Context) -> bool {
+            self.disconnected
+        }
+    }
+
+    /// Returns the number of events received; mailbox order ensures earlier sends are handled
+    struct Received;
+
+    impl Message for Received {
+        type Result = usize;
+    }
+
+    impl Handler<Received> for Collector {
+        type Result = usize;
+
+        fn handle(&mut self, _: Received, _: &mut Self::Context) -> usize {
+            self.events.len()
+        }
+    }
+
+    fn start_collector() -> Addr<Collector> {
+        Collector::default().start()
+    }
+
+    #[actix_rt::test]
+    async fn test_publish_reaches_all_user_sessions_except_origin() {
+        let hub = BroadcastHub::new();
+        let (phone, laptop, other_user) = (start_collector(), start_collector(), start_collector());
+        hub.subscribe(1, "phone", &phone);
+        hub.subscribe(1, "laptop", &laptop);
+        hub.subscribe(2, "other", &other_user);
+
+        let event = ServerEvent(serde_json::json!({"type": "network_event"}));
+        assert_eq!(hub.publish_to_user(1, NETWORK_CHANNEL, event.clone(), None), 2);
+        assert_eq!(hub.publish_to_user(1, NETWORK_CHANNEL, event, Some("phone")), 1);
+
+        assert_eq!(phone.send(Received).await.unwrap(), 1);
+        assert_eq!(laptop.send(Received).await.unwrap(), 2);
+        assert_eq!(other_user.send(Received).await.unwrap(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_unsubscribe_removes_session() {
+        let hub = BroadcastHub::new();
+        hub.subscribe(1, "phone", &start_collector());
+        assert_eq!(hub.subscriber_count(1), 1);
+
+        hub.unsubscribe(1, "phone");
+        assert_eq!(hub.subscriber_count(1), 0);
+        assert_eq!(hub.publish_to_user(1, NETWORK_CHANNEL, ServerEvent(serde_json::json!({})), None), 0);
+    }
+
+    #[actix_rt::test]
+    async fn test_events_only_reach_sessions_subscribed_to_the_channel() {
+        let hub = BroadcastHub::new();
+        let (phone, laptop) = (start_collector(), start_collector());
+        hub.subscribe(1, "phone", &phone);
+        hub.subscribe_channels(1, "laptop", &laptop, BTreeSet::from(["earnings".to_string()]));
+
+        let event = ServerEvent(serde_json::json!({"type": "network_event"}));
+        assert_eq!(hub.publish_to_user(1, NETWORK_CHANNEL, event.clone(), None), 1);
+
+        assert!(hub.set_channels(1, "laptop", all_channels()));
+        assert!(hub.set_channels(1, "phone", BTreeSet::new()));
+        assert!(!hub.set_channels(1, "tablet", all_channels()));
+        assert_eq!(hub.publish_to_user(1, NETWORK_CHANNEL, event, None), 1);
+
+        assert_eq!(phone.send(Received).await.unwrap(), 1);
+        assert_eq!(laptop.send(Received).await.unwrap(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_disconnect_user_closes_only_that_users_sessions() {
+        let hub = BroadcastHub::new();
+        let (phone, other_user) = (start_collector(), start_collector());
+        hub.subscribe(1, "phone", &phone);
+        hub.subscribe(2, "other", &other_user);
+
+        assert_eq!(hub.disconnect_user(1, "Account suspended"), 1);
+        assert_eq!(hub.subscriber_count(1), 0);
+        assert!(phone.send(WasDisconnected).await.unwrap());
+        assert!(!other_user.send(WasDisconnected).await.unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn test_publish_all_then_disconnect_all_reaches_every_session() {
+        let hub = BroadcastHub::new();
+        let (phone, other_user) = (start_collector(), start_collector());
+        hub.subscribe(1, "phone", &phone);
+        hub.subscribe(2, "other", &other_user);
+
+        let event = ServerEvent(serde_json::json!({"type": "server_shutdown"}));
+        assert_eq!(hub.publish_all(event), 2);
+        assert_eq!(hub.disconnect_all("Server shutting down"), 2);
+        assert_eq!(hub.subscriber_count(1) + hub.subscriber_count(2), 0);
+
+        for session in [phone, other_user] {
+            assert_eq!(session.send(Received).await.unwrap(), 1);
+            assert!(session.send(WasDisconnected).await.unwrap());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_user_broadcasts_are_relayed_but_relayed_ones_are_not() {
+        let (relay, mut outbound) = tokio::sync::mpsc::unbounded_channel();
+        let hub = BroadcastHub::new().with_relay(relay);
+        let phone = start_collector();
+        hub.subscribe(1, "phone", &phone);
+
+        let event = serde_json::json!({"type": "network_event"});
+        assert_eq!(hub.publish_to_user(1, NETWORK_CHANNEL, ServerEvent(event.clone()), None), 1);
+        assert_eq!(hub.publish_to_user(2, NETWORK_CHANNEL, ServerEvent(event.clone()), None), 0);
+        hub.publish_all(ServerEvent(event.clone()));
+        assert_eq!(
+            outbound.try_recv().unwrap(),
+            RelayedBroadcast::Event {
+                user_id: 1,
+                channel: NETWORK_CHANNEL.to_string(),
+                event: event.clone(),
+            }
+        );
+        assert!(matches!(outbound.try_recv().unwrap(), RelayedBroadcast::Event { user_id: 2, .. }));
+        assert!(outbound.try_recv().is_err());
+
+        // Broadcasts from other instances reach local sessions and go no further
+        let remote = RelayedBroadcast::Event {
+            user_id: 1,
+            channel: NETWORK_CHANNEL.to_string(),
+            event,
+        };
+        assert_eq!(hub.deliver_relayed(remote), 1);
+        let disconnect = RelayedBroadcast::Disconnect {
+            user_id: 1,
+            reason: "Account suspended".to_string(),
+        };
+        assert_eq!(hub.deliver_relayed(disconnect), 1);
+        assert!(outbound.try_recv().is_err());
+        assert_eq!(phone.send(Received).await.unwrap(), 3);
+        assert!(phone.send(WasDisconnected).await.unwrap());
+
+        hub.disconnect_user(1, "Account deleted");
+        assert!(matches!(outbound.try_recv().unwrap(), RelayedBroadcast::Disconnect { user_id: 1, .. }));
+    }
+
+    #[test]
+    fn test_frame_clones_share_buffer() {
+        let frame = ServerEvent(serde_json::json!({"type": "network_event"})).to_frame();
+        let copy = frame.clone();
+        assert_eq!(frame.0.as_bytes().as_ptr(), copy.0.as_bytes().as_ptr());
+        assert_eq!(&*copy.0, r#"{"type":"network_event"}"#);
+    }
+
+    /// Compare serializing a broadcast per session with serializing it once.
+    /// Run with `cargo test --release bench_broadcast_serialization -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_broadcast_serialization() {
+        let event = ServerEvent(serde_json::json!({
+            "type": "network_status",
+            "status": {
+                "connection_id": 1,
+                "user_id": 1,
+                "network_name": "home",
+                "connected": true,
+                "status_message": "Connected",
+                "network_score": 87.5,
+                "updated_at": "2024-01-01T00:00:00Z"
+            }
+        }));
+        let sessions = 10_000;
+
+        let start = std::time::Instant::now();
+        let per_session: Vec<String> = (0..sessions).map(|_| event.0.to_string()).collect();
+        let per_session_elapsed = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let frame = event.to_frame();
+        let shared: Vec<RawFrame> = (0..sessions).map(|_| frame.clone()).collect();
+        let shared_elapsed = start.elapsed();
+
+        assert_eq!(per_session.len(), shared.len());
+        println!(
+            "{} sessions: per-session serialization {:?}, serialize once {:?}",
+            sessions, per_session_elapsed, shared_elapsed
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::clock::{Clock, SystemClock};
+use crate::errors::{DashboardError, DashboardResult, StorageError};
+
+/// Externally visible state of a `CircuitBreaker`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls go through; consecutive failures are counted
+    Closed,
+    /// Calls are refused until the cooldown has passed
+    Open,
+    /// The cooldown has passed; the next call is let through to test recovery
+    HalfOpen,
+}
+
+impl BreakerState {
+    /// Name reported by `/health`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BreakerState::Closed => "closed",
+            BreakerState::Open => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+
+    /// Value of the `db_circuit_breaker_state` gauge
+    pub fn as_gauge(self) -> i64 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::Open => 1,
+            BreakerState::HalfOpen => 2,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Circuit {
+    Closed { failures: u32 },
+    Open { since: Instant },
+    /// `probing` while the single trial call is in flight
+    HalfOpen { probing: bool },
+}
+
+/// Circuit breaker for storage calls, shared by every store that talks to the same backend.
+///
+/// After `failure_threshold` consecutive transient failures (see `StorageError::classify`),
+/// including calls exceeding the call timeout, the breaker opens and refuses calls with
+/// `DatabaseUnavailable` (`503`) for `cooldown` instead of letting them queue on an unreachable
+/// backend. Then one trial call is let through: success closes the breaker, failure opens it
+/// for another cooldown. Errors that prove the backend answered, such as not found or
+/// validation errors, count as success.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    circuit: Arc<Mutex<Circuit>>,
+    failure_threshold: u32,
+    cooldown: Duration,
+    call_timeout: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+}
+
+impl CircuitBreaker {
+    /// Create a closed breaker; a zero `failure_threshold` never opens it
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            circuit: Arc::new(Mutex::new(Circuit::Closed { failures: 0 })),
+            failure_threshold,
+            cooldown,
+            call_timeout: Duration::ZERO,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Fail calls taking longer than `timeout` with a transient error (zero disables)
+    pub fn with_call_timeout(mut self, timeout: Duration) -> Self {
+        self.call_timeout = timeout;
+        self
+    }
+
+    /// Use a different clock for the cooldown, e.g. a `ManualClock` in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Current state; an open breaker whose cooldown has passed reports `HalfOpen`
+    pub fn state(&self) -> BreakerState {
+        let circuit = self.circuit.lock().unwrap_or_else(|e| e.into_inner());
+        match *circuit {
+            Circuit::Closed { .. } => BreakerState::Closed,
+            Circuit::Open { since } if !self.cooldown_passed(since) => BreakerState::Open,
+            Circuit::Open { .. } | Circuit::HalfOpen { .. } => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Run a storage call through the breaker
+    pub async fn call<T, F>(&self, call: F) -> DashboardResult<T>
+    where
+        F: Future<Output = DashboardResult<T>>,
+    {
+        let mut permit = self.acquire()?;
+        let result = if self.call_timeout.is_zero() {
+            call.await
+        } else {
+            match tokio::time::timeout(self.call_timeout, call).await {
+                Ok(result) => result,
+                Err(_) => Err(DashboardError::database(format!(
+                    "Storage call timed out after {}ms",
+                    self.call_timeout.as_millis()
+                ))),
+            }
+        };
+        permit.settle(&result);
+        result
+    }
+
+    fn cooldown_passed(&self, since: Instant) -> bool {
+        self.clock.now().saturating_duration_since(since) >= self.cooldown
+    }
+
+    fn acquire(&self) -> DashboardResult<Permit<'_>> {
+        if self.failure_threshold == 0 {
+            return Ok(Permit { breaker: self, probe: false, settled: false });
+        }
+
+        let mut circuit = self.circuit.lock().unwrap_or_else(|e| e.into_inner());
+        let probe = match *circuit {
+            Circuit::Closed { .. } => false,
+            Circuit::Open { since } if self.cooldown_passed(since) => {
+                *circuit = Circuit::HalfOpen { probing: true };
+                true
+            }
+            Circuit::HalfOpen { probing: false } => {
+                *circuit = Circuit::HalfOpen { probing: true };
+                true
+            }
+            Circuit::Open { .. } | Circuit::HalfOpen { probing: true } => {
+                return Err(DashboardError::database_unavailable(
+                    "Storage calls are paused after repeated failures, try again later",
+                ));
+            }
+        };
+        Ok(Permit { breaker: self, probe, settled: false })
+    }
+
+    fn record(&self, probe: bool, failed: bool) {
+        let mut circuit = self.circuit.lock().unwrap_or_else(|e| e.into_inner());
+        match (&mut *circuit, failed) {
+            (Circuit::Closed { failures }, false) => *failures = 0,
+            (Circuit::Closed { failures }, true) => {
+                *failures += 1;
+                if *failures >= self.failure_threshold {
+                    warn!(
+                        "Opening the database circuit breaker after {} consecutive failures",
+                        failures
+                    );
+                    *circuit = Circuit::Open { since: self.clock.now() };
+                }
+            }
+            // Only the trial call decides; stragglers from before the breaker opened are ignored
+            (Circuit::HalfOpen { .. }, false) if probe => {
+                info!("Database recovered, closing the circuit breaker");
+                *circuit = Circuit::Closed { failures: 0 };
+            }
+            (Circuit::HalfOpen { .. }, true) if probe => {
+                warn!("Database trial call failed, reopening the circuit breaker");
+                *circuit = Circuit::Open { since: self.clock.now() };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Admission of one call; a trial call dropped before finishing lets the next call try instead
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    settled: bool,
+}
+
+impl Permit<'_> {
+    fn settle<T>(&mut self, result: &DashboardResult<T>) {
+        self.settled = true;
+        if self.breaker.failure_threshold == 0 {
+            return;
+        }
+        let failed =
+            matches!(result, Err(e) if StorageError::classify(e) == StorageError::Transient);
+        self.breaker.record(self.probe, failed);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.settled {
+            let mut circuit = self.breaker.circuit.lock().unwrap_or_else(|e| e.into_inner());
+            if let Circuit::HalfOpen { probing } = &mut *circuit {
+                *probing = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn breaker(clock: &ManualClock) -> CircuitBreaker {
+        CircuitBreaker::new(2, Duration::from_secs(30)).with_clock(Arc::new(clock.clone()))
+    }
+
+    async fn fail(breaker: &CircuitBreaker) -> DashboardResult<()> {
+        breaker.call(async { Err(DashboardError::database("connection refused")) }).await
+    }
+
+    #[tokio::test]
+    async fn test_opens_after_consecutive_failures_and_recovers_through_half_open() {
+        let clock = ManualClock::new();
+        let breaker = breaker(&clock);
+
+        // A non-transient error proves the backend answered and resets the count
+        fail(&breaker).await.unwrap_err();
+        let _ = breaker.call(async { Err::<(), _>(DashboardError::not_found("user")) }).await;
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // Short-circuited without running the call
+        let result = breaker.call(async { Ok(()) }).await;
+        assert!(matches!(result, Err(DashboardError::DatabaseUnavailable(_))));
+
+        // A failed trial call reopens for another cooldown
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(breaker.call(async { Ok(1) }).await.unwrap(), 1);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_count_as_failures_and_abandoned_probe_is_released() {
+        let clock = ManualClock::new();
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(30))
+            .with_call_timeout(Duration::from_millis(10))
+            .with_clock(Arc::new(clock.clone()));
+
+        let result = breaker.call(std::future::pending::<DashboardResult<()>>()).await;
+        assert!(matches!(result, Err(DashboardError::Database(_))));
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // The trial call is cancelled by its caller; another caller may try
+        clock.advance(Duration::from_secs(30));
+        let abandoned = breaker.call(std::future::pending::<DashboardResult<()>>());
+        let _ = tokio::time::timeout(Duration::from_millis(1), abandoned).await;
+        assert!(breaker.call(async { Ok(()) }).await.is_ok());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_zero_threshold_never_opens() {
+        let breaker = CircuitBreaker::default();
+        for _ in 0..10 {
+            fail(&breaker).await.unwrap_err();
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
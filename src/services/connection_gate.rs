@@ -0,0 +1,328 @@
+use serde::Deserialize;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::models::user::{User, UserStatus};
+use crate::services::key_deny_list::KeyDenyList;
+use crate::services::rate_limit::{RateLimitKey, RateLimits};
+
+/// Why a gate refused a WebSocket connection for an otherwise authenticated user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateRejection {
+    /// The account is suspended
+    AccountSuspended,
+    /// The account's email address is still pending verification
+    EmailUnverified,
+    /// The public key is on the global deny-list
+    KeyDenied,
+    /// The user already has as many open connections as their tier allows
+    TooManyConnections,
+}
+
+impl GateRejection {
+    /// `code` of the error frame sent to the client
+    pub fn code(self) -> &'static str {
+        match self {
+            GateRejection::AccountSuspended => "account_suspended",
+            GateRejection::EmailUnverified => "email_unverified",
+            GateRejection::KeyDenied => "key_denied",
+            GateRejection::TooManyConnections => "too_many_connections",
+        }
+    }
+
+    /// `message` of the error frame sent to the client
+    pub fn message(self) -> &'static str {
+        match self {
+            GateRejection::AccountSuspended => "Account suspended",
+            GateRejection::EmailUnverified => "Email address not verified",
+            GateRejection::KeyDenied => "This public key has been revoked globally",
+            GateRejection::TooManyConnections => "Too many open connections for this account",
+        }
+    }
+}
+
+/// A user who has proven their identity and wants to open a WebSocket session
+pub struct GateRequest<'a> {
+    pub user: &'a User,
+    /// Key the user signed with; `None` for auth without a key
+    pub public_key: Option<&'a str>,
+}
+
+/// One check a user must pass before their WebSocket session is established
+pub trait ConnectionGate: Send + Sync {
+    fn check(&self, request: &GateRequest) -> Result<(), GateRejection>;
+}
+
+/// Refuses suspended accounts
+pub struct StatusGate;
+
+impl ConnectionGate for StatusGate {
+    fn check(&self, request: &GateRequest) -> Result<(), GateRejection> {
+        if request.user.status.is_suspended() {
+            return Err(GateRejection::AccountSuspended);
+        }
+        Ok(())
+    }
+}
+
+/// Refuses accounts whose email address is not verified yet
+pub struct VerificationGate;
+
+impl ConnectionGate for VerificationGate {
+    fn check(&self, request: &GateRequest) -> Result<(), GateRejection> {
+        if request.user.status == UserStatus::PendingVerification {
+            return Err(GateRejection::EmailUnverified);
+        }
+        Ok(())
+    }
+}
+
+/// Refuses keys on the shared deny-list, including ones denied while the auth was in flight
+pub struct KeyDenyListGate {
+    deny_list: KeyDenyList,
+}
+
+impl KeyDenyListGate {
+    pub fn new(deny_list: KeyDenyList) -> Self {
+        Self { deny_list }
+    }
+}
+
+impl ConnectionGate for KeyDenyListGate {
+    fn check(&self, request: &GateRequest) -> Result<(), GateRejection> {
+        match request.public_key {
+            Some(public_key) if self.deny_list.contains(public_key) => Err(GateRejection::KeyDenied),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Refuses users already at their tier's connection limit.
+///
+/// Only a pre-check: the slot is taken when the session moves onto the user's budget, which
+/// still refuses the session if another one took the last slot in between.
+pub struct SessionLimitGate {
+    rate_limits: RateLimits,
+}
+
+impl SessionLimitGate {
+    pub fn new(rate_limits: RateLimits) -> Self {
+        Self { rate_limits }
+    }
+}
+
+impl ConnectionGate for SessionLimitGate {
+    fn check(&self, request: &GateRequest) -> Result<(), GateRejection> {
+        let key = RateLimitKey::User(request.user.id);
+        if !self.rate_limits.has_connection_capacity(&key, request.user.rate_limit_tier) {
+            return Err(GateRejection::TooManyConnections);
+        }
+        Ok(())
+    }
+}
+
+/// Built-in gates, as named in `WS_CONNECTION_GATES`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionGateKind {
+    Status,
+    Verification,
+    KeyDenyList,
+    SessionLimit,
+}
+
+impl FromStr for ConnectionGateKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "status" => Ok(ConnectionGateKind::Status),
+            "verification" => Ok(ConnectionGateKind::Verification),
+            "key_deny_list" => Ok(ConnectionGateKind::KeyDenyList),
+            "session_limit" => Ok(ConnectionGateKind::SessionLimit),
+            other => Err(format!(
+                "Unknown connection gate '{}'; expected status, verification, key_deny_list or session_limit",
+                other
+            )),
+        }
+    }
+}
+
+impl ConnectionGateKind {
+    /// Parse a comma-separated list of gate names; an empty list disables every gate
+    pub fn parse_list(value: &str) -> Result<Vec<Self>, String> {
+        value
+            .split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Gates that run whatever `WS_CONNECTION_GATES` says: `status`, `key_deny_list`, and
+    /// `verification` when email verification is required
+    pub fn mandatory(require_email_verification: bool) -> Vec<Self> {
+        let mut kinds = vec![ConnectionGateKind::Status];
+        if require_email_verification {
+            kinds.push(ConnectionGateKind::Verification);
+        }
+        kinds.push(ConnectionGateKind::KeyDenyList);
+        kinds
+    }
+
+    /// The mandatory gates followed by the configured ones not already among them
+    pub fn with_mandatory(configured: Vec<Self>, require_email_verification: bool) -> Vec<Self> {
+        let mut kinds = Self::mandatory(require_email_verification);
+        for kind in configured {
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        kinds
+    }
+
+    /// Gates enabled when `WS_CONNECTION_GATES` is unset
+    pub fn defaults(require_email_verification: bool) -> Vec<Self> {
+        Self::with_mandatory(vec![ConnectionGateKind::SessionLimit], require_email_verification)
+    }
+}
+
+/// Checks run, in order, once a WebSocket signature has been verified and its user resolved.
+///
+/// The first gate to refuse decides the rejection, so each refusal carries its own code.
+#[derive(Clone, Default)]
+pub struct ConnectionGates {
+    gates: Vec<Arc<dyn ConnectionGate>>,
+}
+
+impl ConnectionGates {
+    /// A pipeline with no gates, admitting every authenticated user
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build the built-in gates named in `kinds`
+    pub fn from_kinds(kinds: &[ConnectionGateKind], deny_list: &KeyDenyList, rate_limits: &RateLimits) -> Self {
+        kinds.iter().fold(Self::new(), |gates, kind| {
+            let gate: Arc<dyn ConnectionGate> = match kind {
+                ConnectionGateKind::Status => Arc::new(StatusGate),
+                ConnectionGateKind::Verification => Arc::new(VerificationGate),
+                ConnectionGateKind::KeyDenyList => Arc::new(KeyDenyListGate::new(deny_list.clone())),
+                ConnectionGateKind::SessionLimit => Arc::new(SessionLimitGate::new(rate_limits.clone())),
+            };
+            gates.with(gate)
+        })
+    }
+
+    /// Append a gate, e.g. a deployment-specific one, after the existing ones
+    pub fn with(mut self, gate: Arc<dyn ConnectionGate>) -> Self {
+        self.gates.push(gate);
+        self
+    }
+
+    /// Run every gate, stopping at the first refusal
+    pub fn check(&self, request: &GateRequest) -> Result<(), GateRejection> {
+        self.gates.iter().try_for_each(|gate| gate.check(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{RateLimitConfig, TierLimits};
+    use crate::models::user::RateLimitTier;
+
+    fn rate_limits() -> RateLimits {
+        let tier = TierLimits {
+            messages_per_minute: 0,
+            max_connections: 1,
+        };
+        RateLimits::new(RateLimitConfig {
+            basic: tier,
+            standard: tier,
+            premium: tier,
+        })
+    }
+
+    fn user(status: UserStatus) -> User {
+        let mut user = User::new("alice@example.com".to_string(), "alice".to_string(), None);
+        user.id = 7;
+        user.status = status;
+        user
+    }
+
+    #[test]
+    fn test_each_gate_has_its_own_rejection() {
+        let deny_list = KeyDenyList::new(vec!["ab".repeat(32)]);
+        let limits = rate_limits();
+        let all = [
+            ConnectionGateKind::Status,
+            ConnectionGateKind::Verification,
+            ConnectionGateKind::KeyDenyList,
+            ConnectionGateKind::SessionLimit,
+        ];
+        let gates = ConnectionGates::from_kinds(&all, &deny_list, &limits);
+        let check = |user: &User, public_key: &str| {
+            gates.check(&GateRequest {
+                user,
+                public_key: Some(public_key),
+            })
+        };
+        let good_key = "cd".repeat(32);
+
+        assert_eq!(check(&user(UserStatus::Active), &good_key), Ok(()));
+        assert_eq!(check(&user(UserStatus::Suspended), &good_key), Err(GateRejection::AccountSuspended));
+        assert_eq!(check(&user(UserStatus::PendingVerification), &good_key), Err(GateRejection::EmailUnverified));
+        assert_eq!(check(&user(UserStatus::Active), &"AB".repeat(32)), Err(GateRejection::KeyDenied));
+
+        assert!(limits.try_connect(&RateLimitKey::User(7), RateLimitTier::Basic));
+        assert_eq!(check(&user(UserStatus::Active), &good_key), Err(GateRejection::TooManyConnections));
+        // Gates run in order, so a suspended user at their limit is reported as suspended
+        assert_eq!(check(&user(UserStatus::Suspended), &good_key), Err(GateRejection::AccountSuspended));
+    }
+
+    #[test]
+    fn test_account_and_deny_list_gates_cannot_be_configured_away() {
+        let kinds = ConnectionGateKind::with_mandatory(ConnectionGateKind::parse_list("").unwrap(), false);
+        assert_eq!(kinds, vec![ConnectionGateKind::Status, ConnectionGateKind::KeyDenyList]);
+        let kinds = ConnectionGateKind::with_mandatory(ConnectionGateKind::parse_list("session_limit,status").unwrap(), true);
+        assert_eq!(
+            kinds,
+            vec![
+                ConnectionGateKind::Status,
+                ConnectionGateKind::Verification,
+                ConnectionGateKind::KeyDenyList,
+                ConnectionGateKind::SessionLimit
+            ]
+        );
+
+        let deny_list = KeyDenyList::new(vec!["ab".repeat(32)]);
+        let gates = ConnectionGates::from_kinds(&kinds, &deny_list, &rate_limits());
+        let suspended = user(UserStatus::Suspended);
+        assert_eq!(
+            gates.check(&GateRequest { user: &suspended, public_key: None }),
+            Err(GateRejection::AccountSuspended)
+        );
+    }
+
+    #[test]
+    fn test_only_configured_gates_run() {
+        let deny_list = KeyDenyList::default();
+        let limits = rate_limits();
+        let kinds = ConnectionGateKind::parse_list("status, session_limit").unwrap();
+        let gates = ConnectionGates::from_kinds(&kinds, &deny_list, &limits);
+        let pending = user(UserStatus::PendingVerification);
+        assert_eq!(gates.check(&GateRequest { user: &pending, public_key: None }), Ok(()));
+
+        let none = ConnectionGates::from_kinds(&ConnectionGateKind::parse_list("").unwrap(), &deny_list, &limits);
+        let suspended = user(UserStatus::Suspended);
+        assert_eq!(none.check(&GateRequest { user: &suspended, public_key: None }), Ok(()));
+
+        assert!(ConnectionGateKind::parse_list("status,stauts").is_err());
+        assert_eq!(
+            ConnectionGateKind::defaults(false),
+            vec![ConnectionGateKind::Status, ConnectionGateKind::KeyDenyList, ConnectionGateKind::SessionLimit]
+        );
+        assert!(!ConnectionGateKind::defaults(false).contains(&ConnectionGateKind::Verification));
+        assert!(ConnectionGateKind::defaults(true).contains(&ConnectionGateKind::Verification));
+    }
+}
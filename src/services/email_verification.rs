@@ -0,0 +1,145 @@
+use async_trait::async_trait;
+use nanoid::nanoid;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::errors::{DashboardError, DashboardResult};
+use crate::models::user::User;
+
+/// Default lifetime of an email verification token
+pub const DEFAULT_VERIFICATION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default minimum time between verification emails to the same user
+pub const DEFAULT_VERIFICATION_RESEND_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Single-use tokens that confirm ownership of a registration email address.
+///
+/// Each user has at most one outstanding token; issuing a new one replaces the old, and is
+/// refused within `resend_interval` of the previous issue.
+#[derive(Clone)]
+pub struct EmailVerificationTokens {
+    ttl: Duration,
+    resend_interval: Duration,
+    /// token -> (user_id, expires_at)
+    tokens: Arc<Mutex<HashMap<String, (i64, Instant)>>>,
+    /// user_id -> (current token, issued_at)
+    issued: Arc<Mutex<HashMap<i64, (String, Instant)>>>,
+}
+
+impl Default for EmailVerificationTokens {
+    fn default() -> Self {
+        Self::new(DEFAULT_VERIFICATION_TTL, DEFAULT_VERIFICATION_RESEND_INTERVAL)
+    }
+}
+
+impl EmailVerificationTokens {
+    /// Create a token store whose tokens expire `ttl` after issue
+    pub fn new(ttl: Duration, resend_interval: Duration) -> Self {
+        Self {
+            ttl,
+            resend_interval,
+            tokens: Arc::new(Mutex::new(HashMap::new())),
+            issued: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Mint a token for a user, replacing any earlier one.
+    ///
+    /// Returns `Err(RateLimit)` if the previous token was issued less than `resend_interval` ago.
+    pub fn issue(&self, user_id: i64, now: Instant) -> DashboardResult<String> {
+        let mut tokens = self.tokens.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut issued = self.issued.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        if let Some((previous, issued_at)) = issued.get(&user_id) {
+            let elapsed = now.saturating_duration_since(*issued_at);
+            if elapsed < self.resend_interval {
+                return Err(DashboardError::rate_limit(
+                    "Verification email sent recently, try again later",
+                    self.resend_interval - elapsed,
+                ));
+            }
+            tokens.remove(previous);
+        }
+        tokens.retain(|_, (_, expires_at)| *expires_at > now);
+
+        let token = nanoid!(32);
+        tokens.insert(token.clone(), (user_id, now + self.ttl));
+        issued.insert(user_id, (token.clone(), now));
+        Ok(token)
+    }
+
+    /// Consume a token, returning its user if it had not expired
+    pub fn redeem(&self, token: &str, now: Instant) -> DashboardResult<Option<i64>> {
+        let mut tokens = self.tokens.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let (user_id, expires_at) = match tokens.remove(token) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        drop(tokens);
+
+        self.issued
+            .lock()
+            .map_err(|e| DashboardError::internal_server(e.to_string()))?
+            .remove(&user_id);
+
+        if expires_at <= now {
+            return Ok(None);
+        }
+        Ok(Some(user_id))
+    }
+}
+
+/// Delivers verification tokens to users, e.g. by email
+#[async_trait]
+pub trait VerificationNotifier: Send + Sync {
+    /// Send `token` to the user's email address
+    async fn send_verification(&self, user: &User, token: &str) -> DashboardResult<()>;
+}
+
+/// Default notifier that only logs the token; deployments plug in a real mailer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogVerificationNotifier;
+
+#[async_trait]
+impl VerificationNotifier for LogVerificationNotifier {
+    async fn send_verification(&self, user: &User, token: &str) -> DashboardResult<()> {
+        info!("Email verification token for user {} <{}>: {}", user.id, user.email, token);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_is_single_use_and_expires() {
+        let tokens = EmailVerificationTokens::new(Duration::from_secs(60), Duration::ZERO);
+        let now = Instant::now();
+
+        let token = tokens.issue(7, now).unwrap();
+        assert_eq!(tokens.redeem(&token, now).unwrap(), Some(7));
+        assert_eq!(tokens.redeem(&token, now).unwrap(), None);
+
+        let token = tokens.issue(7, now).unwrap();
+        assert_eq!(tokens.redeem(&token, now + Duration::from_secs(60)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_reissue_is_rate_limited_and_replaces_token() {
+        let tokens = EmailVerificationTokens::new(Duration::from_secs(600), Duration::from_secs(60));
+        let now = Instant::now();
+
+        let first = tokens.issue(7, now).unwrap();
+        assert!(matches!(
+            tokens.issue(7, now + Duration::from_secs(30)),
+            Err(DashboardError::RateLimit(..))
+        ));
+
+        let second = tokens.issue(7, now + Duration::from_secs(60)).unwrap();
+        assert_eq!(tokens.redeem(&first, now).unwrap(), None);
+        assert_eq!(tokens.redeem(&second, now).unwrap(), Some(7));
+    }
+}
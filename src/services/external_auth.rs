@@ -0,0 +1,469 @@
+use async_trait::async_trait;
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use nanoid::nanoid;
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::errors::{DashboardError, DashboardResult};
+use crate::models::user::{CreateUserDto, User, UserFieldLimits};
+use crate::services::ReadOnlyMode;
+use crate::storage::UserStorage;
+
+/// Shortest interval between fetches forced by tokens naming a key the cache doesn't have,
+/// so tokens with made-up `kid`s can't hammer the identity provider
+const MIN_UNKNOWN_KID_REFETCH: Duration = Duration::from_secs(30);
+
+/// Where the identity provider's signing keys come from
+#[async_trait]
+pub trait JwksSource: Send + Sync {
+    /// Fetch the current key set
+    async fn fetch(&self) -> DashboardResult<JwkSet>;
+}
+
+/// Fetches the key set from the provider's `jwks_uri`
+pub struct HttpJwksSource {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpJwksSource {
+    /// Fetch keys from `url`
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl JwksSource for HttpJwksSource {
+    async fn fetch(&self) -> DashboardResult<JwkSet> {
+        let response = self
+            .client
+            .get(&self.url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| DashboardError::service_unavailable(format!("Failed to fetch JWKS: {}", e)))?;
+        response
+            .json::<JwkSet>()
+            .await
+            .map_err(|e| DashboardError::service_unavailable(format!("Invalid JWKS: {}", e)))
+    }
+}
+
+/// A fixed key set, for pinned keys and tests
+pub struct StaticJwks(pub JwkSet);
+
+#[async_trait]
+impl JwksSource for StaticJwks {
+    async fn fetch(&self) -> DashboardResult<JwkSet> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Fetched keys and when they were fetched
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Caches the provider's key set, refetching it once `refresh` has passed or when a token
+/// names a key it doesn't have (for key rotation), at most once per `MIN_UNKNOWN_KID_REFETCH`.
+///
+/// If a refresh fails the previous keys keep being used.
+pub struct JwksCache {
+    source: Arc<dyn JwksSource>,
+    refresh: Duration,
+    cached: Mutex<Option<CachedJwks>>,
+}
+
+impl JwksCache {
+    /// Cache keys from `source` for `refresh`
+    pub fn new(source: Arc<dyn JwksSource>, refresh: Duration) -> Self {
+        Self {
+            source,
+            refresh,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// The key a token signed with `kid` should verify against.
+    ///
+    /// A token without a `kid` is only accepted when the set holds a single key.
+    pub async fn key(&self, kid: Option<&str>, now: Instant) -> DashboardResult<Jwk> {
+        let (found, fetched_at) = self.lookup(kid)?;
+        let stale = fetched_at.map_or(true, |at| now.saturating_duration_since(at) >= self.refresh);
+        let may_refetch = fetched_at.map_or(true, |at| now.saturating_duration_since(at) >= MIN_UNKNOWN_KID_REFETCH);
+        match found {
+            Some(key) if !stale => return Ok(key),
+            None if !stale && !may_refetch => {
+                return Err(DashboardError::authentication("Token signed with an unknown key"));
+            }
+            _ => {}
+        }
+
+        match self.source.fetch().await {
+            Ok(keys) => {
+                info!("Fetched {} keys from the identity provider", keys.keys.len());
+                let mut cached = self.cached.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+                *cached = Some(CachedJwks { keys, fetched_at: now });
+            }
+            Err(e) if fetched_at.is_some() => warn!("Keeping cached JWKS after refresh failed: {}", e),
+            Err(e) => return Err(e),
+        }
+        self.lookup(kid)?
+            .0
+            .ok_or_else(|| DashboardError::authentication("Token signed with an unknown key"))
+    }
+
+    /// The cached key for `kid` and when the cache was filled, if it was
+    fn lookup(&self, kid: Option<&str>) -> DashboardResult<(Option<Jwk>, Option<Instant>)> {
+        let cached = self.cached.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        Ok(match cached.as_ref() {
+            Some(cached) => {
+                let key = match kid {
+                    Some(kid) => cached.keys.find(kid),
+                    None if cached.keys.keys.len() == 1 => cached.keys.keys.first(),
+                    None => None,
+                };
+                (key.cloned(), Some(cached.fetched_at))
+            }
+            None => (None, None),
+        })
+    }
+}
+
+/// Claims read from an identity provider token
+#[derive(Debug, Deserialize)]
+struct ExternalClaims {
+    sub: String,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    email_verified: Option<bool>,
+    #[serde(default)]
+    preferred_username: Option<String>,
+}
+
+/// Signature algorithms accepted from the identity provider; shared-secret algorithms are
+/// refused so a public key can never be used as an HMAC secret
+const ACCEPTED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// WebSocket authentication with bearer JWTs from an external OIDC/JWT identity provider.
+///
+/// Tokens are verified against the provider's JWKS, must carry its issuer (and audience, when
+/// configured), and map to a local user through the `(issuer, sub)` link stored with the
+/// user. With auto-provisioning, the first token of an unknown subject creates a user from its
+/// verified `email` claim.
+pub struct ExternalAuthService<T: UserStorage> {
+    user_storage: Arc<T>,
+    jwks: JwksCache,
+    issuer: String,
+    audience: Option<String>,
+    auto_provision: bool,
+    field_limits: UserFieldLimits,
+    read_only: ReadOnlyMode,
+}
+
+impl<T: UserStorage> ExternalAuthService<T> {
+    /// Accept tokens issued by `issuer` and signed by keys from `jwks`
+    pub fn new(user_storage: Arc<T>, jwks: JwksCache, issuer: impl Into<String>) -> Self {
+        Self {
+            user_storage,
+            jwks,
+            issuer: issuer.into(),
+            audience: None,
+            auto_provision: false,
+            field_limits: UserFieldLimits::default(),
+            read_only: ReadOnlyMode::default(),
+        }
+    }
+
+    /// Require tokens to name `audience` in their `aud` claim
+    pub fn with_audience(mut self, audience: Option<String>) -> Self {
+        self.audience = audience;
+        self
+    }
+
+    /// Create a local user for subjects seen for the first time
+    pub fn with_auto_provision(mut self, auto_provision: bool) -> Self {
+        self.auto_provision = auto_provision;
+        self
+    }
+
+    /// Length limits applied to provisioned users' email and username
+    pub fn with_field_limits(mut self, limits: UserFieldLimits) -> Self {
+        self.field_limits = limits;
+        self
+    }
+
+    /// Refuse provisioning while `read_only` is enabled
+    pub fn with_read_only(mut self, read_only: ReadOnlyMode) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Verify `token` and return the local user it maps to
+    pub async fn authenticate(&self, token: &str) -> DashboardResult<User> {
+        let claims = self.verify(token, Instant::now()).await?;
+
+        let user = match self.user_storage.find_user_by_external_identity(&self.issuer, &claims.sub).await? {
+            Some(user) => user,
+            None if self.auto_provision => self.provision(&claims).await?,
+            None => {
+                warn!("External token for unlinked subject {} of {}", claims.sub, self.issuer);
+                return Err(DashboardError::authentication("No user is linked to this identity"));
+            }
+        };
+        // Account status is judged by the session's connection gates, like every other auth path
+        Ok(user)
+    }
+
+    /// Check the token's signature, issuer, audience and expiry
+    async fn verify(&self, token: &str, now: Instant) -> DashboardResult<ExternalClaims> {
+        let header = decode_header(token)
+            .map_err(|e| DashboardError::authentication(format!("Invalid token: {}", e)))?;
+        if !ACCEPTED_ALGORITHMS.contains(&header.alg) {
+            return Err(DashboardError::authentication(format!("Unsupported token algorithm {:?}", header.alg)));
+        }
+
+        let jwk = self.jwks.key(header.kid.as_deref(), now).await?;
+        if jwk.common.algorithm.map_or(false, |alg| alg != header.alg) {
+            return Err(DashboardError::authentication("Token algorithm does not match its key"));
+        }
+        let key = DecodingKey::from_jwk(&jwk)
+            .map_err(|e| DashboardError::authentication(format!("Unusable signing key: {}", e)))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_required_spec_claims(&["exp", "iss", "sub"]);
+        if let Some(audience) = &self.audience {
+            validation.set_audience(&[audience]);
+            validation.required_spec_claims.insert("aud".to_string());
+        }
+        decode::<ExternalClaims>(token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| DashboardError::authentication(format!("Invalid token: {}", e)))
+    }
+
+    /// Create and link a user for a subject seen for the first time
+    async fn provision(&self, claims: &ExternalClaims) -> DashboardResult<User> {
+        self.read_only.ensure_writable()?;
+        let email = match (&claims.email, claims.email_verified) {
+            (Some(email), Some(true)) => email.clone(),
+            _ => {
+                return Err(DashboardError::authentication(
+                    "A verified email claim is required to create an account",
+                ))
+            }
+        };
+        if self.user_storage.find_user_by_email(&email).await?.is_some() {
+            // Linking by email would hand an existing account to whoever controls the IdP entry
+            warn!("External subject {} of {} matches the email of an existing account", claims.sub, self.issuer);
+            return Err(DashboardError::authentication("An account with this email already exists"));
+        }
+        let username = claims
+            .preferred_username
+            .clone()
+            .unwrap_or_else(|| email.split('@').next().unwrap_or_default().to_string());
+        let new_user = CreateUserDto {
+            email,
+            username,
+            // Never stored: provisioned users have no password and sign in through the provider
+            password: nanoid!(32),
+            wallet_address: None,
+        };
+        new_user.validate(&self.field_limits)?;
+
+        let user = self.user_storage.create_user(new_user).await?;
+        if let Err(e) = self.user_storage.link_external_identity(user.id, &self.issuer, &claims.sub).await {
+            // Another connection provisioned the subject first; use its user
+            if let Err(e) = self.user_storage.delete_user(user.id).await {
+                error!("Failed to remove duplicate provisioned user {}: {}", user.id, e);
+            }
+            return match self.user_storage.find_user_by_external_identity(&self.issuer, &claims.sub).await? {
+                Some(user) => Ok(user),
+                None => Err(e),
+            };
+        }
+        info!("Provisioned user {} for subject {} of {}", user.id, claims.sub, self.issuer);
+        Ok(user)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use chrono::Utc;
+    use ed25519_dalek::SigningKey;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    use crate::storage::memory::InMemoryUserStorage;
+
+    const ISSUER: &str = "https://idp.example.com";
+
+    /// PKCS#8 v1 prefix of an Ed25519 private key, followed by the 32-byte seed
+    const ED25519_PKCS8_PREFIX: [u8; 16] = [
+        0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+    ];
+
+    struct Provider {
+        signing_key: SigningKey,
+        kid: &'static str,
+    }
+
+    impl Provider {
+        fn new(seed: u8, kid: &'static str) -> Self {
+            Self {
+                signing_key: SigningKey::from_bytes(&[seed; 32]),
+                kid,
+            }
+        }
+
+        fn jwk(&self) -> serde_json::Value {
+            json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "alg": "EdDSA",
+                "kid": self.kid,
+                "x": URL_SAFE_NO_PAD.encode(self.signing_key.verifying_key().to_bytes()),
+            })
+        }
+
+        fn token(&self, claims: serde_json::Value) -> String {
+            let mut der = ED25519_PKCS8_PREFIX.to_vec();
+            der.extend_from_slice(&self.signing_key.to_bytes());
+            let mut header = Header::new(Algorithm::EdDSA);
+            header.kid = Some(self.kid.to_string());
+            encode(&header, &claims, &EncodingKey::from_ed_der(&der)).unwrap()
+        }
+    }
+
+    fn jwks(providers: &[&Provider]) -> JwkSet {
+        serde_json::from_value(json!({ "keys": providers.iter().map(|p| p.jwk()).collect::<Vec<_>>() })).unwrap()
+    }
+
+    fn claims(sub: &str) -> serde_json::Value {
+        json!({
+            "iss": ISSUER,
+            "sub": sub,
+            "exp": Utc::now().timestamp() + 300,
+            "email": format!("{}@example.com", sub),
+            "email_verified": true,
+        })
+    }
+
+    fn service(provider: &Provider) -> (ExternalAuthService<InMemoryUserStorage>, Arc<InMemoryUserStorage>) {
+        let storage = Arc::new(InMemoryUserStorage::new());
+        let jwks = JwksCache::new(Arc::new(StaticJwks(jwks(&[provider]))), Duration::from_secs(3600));
+        (ExternalAuthService::new(storage.clone(), jwks, ISSUER), storage)
+    }
+
+    #[tokio::test]
+    async fn test_token_maps_subject_to_linked_user() {
+        let provider = Provider::new(1, "k1");
+        let (service, storage) = service(&provider);
+        let user = storage
+            .create_user(CreateUserDto {
+                email: "alice@example.com".to_string(),
+                username: "alice".to_string(),
+                password: "unused".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap();
+
+        let token = provider.token(claims("idp-alice"));
+        assert!(matches!(
+            service.authenticate(&token).await.unwrap_err(),
+            DashboardError::Authentication(_)
+        ));
+
+        storage.link_external_identity(user.id, ISSUER, "idp-alice").await.unwrap();
+        assert_eq!(service.authenticate(&token).await.unwrap().id, user.id);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_foreign_issuer_expired_and_unknown_keys() {
+        let provider = Provider::new(1, "k1");
+        let (service, _) = service(&provider);
+        let service = service.with_auto_provision(true);
+
+        let mut foreign = claims("bob");
+        foreign["iss"] = json!("https://evil.example.com");
+        let mut expired = claims("bob");
+        expired["exp"] = json!(Utc::now().timestamp() - 3600);
+        let impostor = Provider::new(2, "k2");
+
+        for token in [provider.token(foreign), provider.token(expired), impostor.token(claims("bob"))] {
+            assert!(matches!(
+                service.authenticate(&token).await.unwrap_err(),
+                DashboardError::Authentication(_)
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_auto_provisions_once_per_subject() {
+        let provider = Provider::new(1, "k1");
+        let (service, storage) = service(&provider);
+        let service = service.with_auto_provision(true);
+
+        let first = service.authenticate(&provider.token(claims("carol"))).await.unwrap();
+        assert_eq!(first.email, "carol@example.com");
+        let again = service.authenticate(&provider.token(claims("carol"))).await.unwrap();
+        assert_eq!(again.id, first.id);
+        assert_eq!(storage.user_count(), 1);
+
+        let mut unverified = claims("dave");
+        unverified["email_verified"] = json!(false);
+        assert!(service.authenticate(&provider.token(unverified)).await.is_err());
+        assert_eq!(storage.user_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_jwks_cache_refetches_for_rotated_keys() {
+        struct Rotating {
+            sets: Mutex<Vec<JwkSet>>,
+        }
+
+        #[async_trait]
+        impl JwksSource for Rotating {
+            async fn fetch(&self) -> DashboardResult<JwkSet> {
+                let mut sets = self.sets.lock().unwrap();
+                Ok(if sets.len() > 1 { sets.remove(0) } else { sets[0].clone() })
+            }
+        }
+
+        let old = Provider::new(1, "old");
+        let new = Provider::new(2, "new");
+        let source = Arc::new(Rotating { sets: Mutex::new(vec![jwks(&[&old]), jwks(&[&new])]) });
+        let cache = JwksCache::new(source, Duration::from_secs(3600));
+        let now = Instant::now();
+
+        assert!(cache.key(Some("old"), now).await.is_ok());
+        // Too soon after the last fetch to look for a key the cache doesn't know
+        assert!(cache.key(Some("new"), now + Duration::from_secs(1)).await.is_err());
+        assert!(cache.key(Some("new"), now + MIN_UNKNOWN_KID_REFETCH).await.is_ok());
+        assert!(cache.key(Some("old"), now + MIN_UNKNOWN_KID_REFETCH).await.is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Instant;
+
+use crate::services::circuit_breaker::BreakerState;
+use crate::services::{CircuitBreaker, RedisLinkStatus, SessionRegistry};
+
+/// Commit the binary was built from, taken from `BUILD_GIT_SHA` at compile time
+pub const BUILD_GIT_SHA: &str = match option_env!("BUILD_GIT_SHA") {
+    Some(sha) => sha,
+    None => "unknown",
+};
+
+/// When the process started, captured at the top of `main`
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessStart {
+    instant: Instant,
+    at: DateTime<Utc>,
+}
+
+impl ProcessStart {
+    /// Record the current moment as the start
+    pub fn now() -> Self {
+        Self {
+            instant: Instant::now(),
+            at: Utc::now(),
+        }
+    }
+}
+
+/// State of one dependency as reported by `/health`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyStatus {
+    /// Reachable and serving calls
+    Up,
+    /// Recovering; calls may fail
+    Degraded,
+    /// Unreachable, or calls are being refused
+    Down,
+    /// Not configured for this instance
+    Disabled,
+}
+
+impl DependencyStatus {
+    /// Storage health as seen by its circuit breaker
+    pub fn from_breaker(state: BreakerState) -> Self {
+        match state {
+            BreakerState::Closed => DependencyStatus::Up,
+            BreakerState::HalfOpen => DependencyStatus::Degraded,
+            BreakerState::Open => DependencyStatus::Down,
+        }
+    }
+}
+
+/// Dependencies reported by `/health`
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyStatuses {
+    /// Storage calls, by the database circuit breaker
+    pub storage: DependencyStatus,
+    /// The Postgres pool, `disabled` without `DATABASE_URL`
+    pub database: DependencyStatus,
+    /// The cross-instance broadcast subscription, `disabled` without `REDIS_URL`
+    pub redis: DependencyStatus,
+}
+
+/// Detailed `/health` fields for dashboards
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthDetails {
+    pub started_at: DateTime<Utc>,
+    pub uptime_seconds: u64,
+    pub build_git_sha: &'static str,
+    pub active_connections: usize,
+    pub dependencies: DependencyStatuses,
+}
+
+/// Process start time and dependency handles behind the detailed `/health` response.
+///
+/// Every check reads in-memory state only, so the probe never waits on a dependency.
+#[derive(Clone)]
+pub struct HealthState {
+    started: ProcessStart,
+    registry: SessionRegistry,
+    db_breaker: CircuitBreaker,
+    database: Option<sqlx::PgPool>,
+    redis: Option<RedisLinkStatus>,
+}
+
+impl HealthState {
+    /// Report uptime since `started`
+    pub fn new(started: ProcessStart, registry: SessionRegistry, db_breaker: CircuitBreaker) -> Self {
+        Self {
+            started,
+            registry,
+            db_breaker,
+            database: None,
+            redis: None,
+        }
+    }
+
+    /// Report the state of the Postgres pool
+    pub fn with_database(mut self, pool: sqlx::PgPool) -> Self {
+        self.database = Some(pool);
+        self
+    }
+
+    /// Report the state of the Redis broadcast subscription
+    pub fn with_redis(mut self, status: RedisLinkStatus) -> Self {
+        self.redis = Some(status);
+        self
+    }
+
+    /// Collect the current details
+    pub fn details(&self) -> HealthDetails {
+        HealthDetails {
+            started_at: self.started.at,
+            uptime_seconds: self.started.instant.elapsed().as_secs(),
+            build_git_sha: BUILD_GIT_SHA,
+            active_connections: self.registry.count(),
+            dependencies: DependencyStatuses {
+                storage: DependencyStatus::from_breaker(self.db_breaker.state()),
+                database: match &self.database {
+                    Some(pool) if pool.is_closed() => DependencyStatus::Down,
+                    Some(_) => DependencyStatus::Up,
+                    None => DependencyStatus::Disabled,
+                },
+                redis: match &self.redis {
+                    Some(status) if status.is_subscribed() => DependencyStatus::Up,
+                    Some(_) => DependencyStatus::Down,
+                    None => DependencyStatus::Disabled,
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_details_without_optional_dependencies() {
+        let health = HealthState::new(
+            ProcessStart::now(),
+            SessionRegistry::new(),
+            CircuitBreaker::new(3, Duration::from_secs(30)),
+        );
+        let details = health.details();
+
+        assert_eq!(details.active_connections, 0);
+        assert!(details.started_at <= Utc::now());
+        assert_eq!(details.dependencies.storage, DependencyStatus::Up);
+        assert_eq!(details.dependencies.database, DependencyStatus::Disabled);
+        assert_eq!(details.dependencies.redis, DependencyStatus::Disabled);
+
+        let json = serde_json::to_value(&details).unwrap();
+        assert_eq!(json["dependencies"]["redis"], "disabled");
+        assert!(json["uptime_seconds"].is_u64());
+    }
+
+    #[test]
+    fn test_unsubscribed_redis_is_down() {
+        let health = HealthState::new(
+            ProcessStart::now(),
+            SessionRegistry::new(),
+            CircuitBreaker::new(3, Duration::from_secs(30)),
+        )
+        .with_redis(RedisLinkStatus::default());
+        assert_eq!(health.details().dependencies.redis, DependencyStatus::Down);
+    }
+
+    #[test]
+    fn test_breaker_states_map_to_dependency_status() {
+        assert_eq!(DependencyStatus::from_breaker(BreakerState::Closed), DependencyStatus::Up);
+        assert_eq!(DependencyStatus::from_breaker(BreakerState::HalfOpen), DependencyStatus::Degraded);
+        assert_eq!(DependencyStatus::from_breaker(BreakerState::Open), DependencyStatus::Down);
+    }
+}
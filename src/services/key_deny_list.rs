@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use tracing::error;
+
+/// Runtime-reloadable set of public keys blocked for every user, e.g. leaked or compromised keys
+#[derive(Clone, Default)]
+pub struct KeyDenyList {
+    keys: Arc<RwLock<HashSet<String>>>,
+}
+
+impl KeyDenyList {
+    /// Create a deny-list from hex-encoded public keys
+    pub fn new<I: IntoIterator<Item = String>>(keys: I) -> Self {
+        let list = Self::default();
+        list.replace(keys);
+        list
+    }
+
+    /// Whether a public key is denied; hex case is ignored
+    pub fn contains(&self, public_key: &str) -> bool {
+        match self.keys.read() {
+            Ok(keys) => keys.contains(&public_key.trim().to_ascii_lowercase()),
+            Err(e) => {
+                error!("Failed to read key deny-list: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Number of denied keys
+    pub fn len(&self) -> usize {
+        self.keys.read().map(|keys| keys.len()).unwrap_or(0)
+    }
+
+    /// Whether no keys are denied
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replace the whole list, returning the new number of denied keys
+    pub fn replace<I: IntoIterator<Item = String>>(&self, keys: I) -> usize {
+        let keys: HashSet<String> = keys
+            .into_iter()
+            .map(|key| key.trim().to_ascii_lowercase())
+            .filter(|key| !key.is_empty())
+            .collect();
+        let count = keys.len();
+        match self.keys.write() {
+            Ok(mut current) => *current = keys,
+            Err(e) => error!("Failed to update key deny-list: {}", e),
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_ignores_case_and_replace_swaps_list() {
+        let list = KeyDenyList::new(vec!["ABCD".to_string(), " ".to_string()]);
+        assert_eq!(list.len(), 1);
+        assert!(list.contains("abcd"));
+
+        assert_eq!(list.replace(vec!["ef01".to_string()]), 1);
+        assert!(!list.contains("abcd"));
+        assert!(list.contains("EF01"));
+    }
+}
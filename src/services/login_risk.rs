@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+
+/// Header a client uses to present a solved challenge when retrying a login
+pub const CHALLENGE_TOKEN_HEADER: &str = "x-challenge-token";
+
+/// Outcome of a login risk assessment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginRiskDecision {
+    /// Continue with password verification
+    Allow,
+    /// Refuse until the client retries with a valid challenge token
+    RequireChallenge,
+    /// Refuse the attempt outright
+    Block,
+}
+
+/// Context for a login attempt, available before the password is checked
+#[derive(Debug, Clone)]
+pub struct LoginAttempt<'a> {
+    /// Email the client is logging in as
+    pub email: &'a str,
+    /// Client IP address
+    pub ip_address: &'a str,
+    /// Client user agent
+    pub user_agent: &'a str,
+    /// Failed attempts for this email within the lockout window
+    pub recent_failures: u32,
+    /// Challenge token from the `X-Challenge-Token` header, if any
+    pub challenge_token: Option<&'a str>,
+}
+
+/// Pluggable check run by `UserService::login` before the password is verified.
+///
+/// Implementations decide whether to allow the attempt, demand a challenge (e.g. a CAPTCHA
+/// token, which they are responsible for validating), or block it.
+#[async_trait]
+pub trait LoginRiskPolicy: Send + Sync {
+    /// Assess a login attempt
+    async fn assess(&self, attempt: &LoginAttempt<'_>) -> LoginRiskDecision;
+}
+
+/// Default policy that allows every attempt
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllowAllPolicy;
+
+#[async_trait]
+impl LoginRiskPolicy for AllowAllPolicy {
+    async fn assess(&self, _: &LoginAttempt<'_>) -> LoginRiskDecision {
+        LoginRiskDecision::Allow
+    }
+}
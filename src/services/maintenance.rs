@@ -0,0 +1,78 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::error;
+
+use crate::errors::{DashboardError, DashboardResult};
+
+/// Runtime-updatable maintenance banner shared across workers and sessions
+#[derive(Clone, Default)]
+pub struct MaintenanceMode {
+    message: Arc<RwLock<Option<String>>>,
+}
+
+impl MaintenanceMode {
+    /// Create maintenance state with an optional initial message
+    pub fn new(message: Option<String>) -> Self {
+        Self {
+            message: Arc::new(RwLock::new(message.filter(|m| !m.trim().is_empty()))),
+        }
+    }
+
+    /// Current maintenance message, if maintenance mode is active
+    pub fn message(&self) -> Option<String> {
+        match self.message.read() {
+            Ok(message) => message.clone(),
+            Err(e) => {
+                error!("Failed to read maintenance state: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Whether maintenance mode is active
+    pub fn is_active(&self) -> bool {
+        self.message().is_some()
+    }
+
+    /// Set or clear the maintenance message; an empty message clears it
+    pub fn set_message(&self, message: Option<String>) {
+        match self.message.write() {
+            Ok(mut current) => *current = message.filter(|m| !m.trim().is_empty()),
+            Err(e) => error!("Failed to update maintenance state: {}", e),
+        }
+    }
+}
+
+/// Runtime-toggleable read-only mode, e.g. during migrations: mutating service calls fail
+/// with `SERVICE_READ_ONLY` while reads, logins and WebSocket auth keep working
+#[derive(Clone, Default)]
+pub struct ReadOnlyMode {
+    enabled: Arc<AtomicBool>,
+}
+
+impl ReadOnlyMode {
+    /// Create read-only state, initially `enabled` or not
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(enabled)),
+        }
+    }
+
+    /// Whether writes are currently refused
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Turn read-only mode on or off
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Fail while read-only mode is on; checked first by every mutating service method
+    pub fn ensure_writable(&self) -> DashboardResult<()> {
+        if self.is_enabled() {
+            return Err(DashboardError::read_only("Writes are temporarily disabled, try again later"));
+        }
+        Ok(())
+    }
+}
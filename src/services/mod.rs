@@ -2,8 +2,50 @@
 pub mod user;
 pub mod network;
 pub mod signature;
+pub mod session_registry;
+pub mod maintenance;
+pub mod reconnect;
+pub mod broadcast;
+pub mod login_risk;
+pub mod key_deny_list;
+pub mod ws_ticket;
+pub mod session_hooks;
+pub mod readiness;
+pub mod email_verification;
+pub mod rate_limit;
+pub mod redis_bridge;
+pub mod circuit_breaker;
+pub mod audit;
+pub mod health;
+pub mod webhook;
+pub mod notifier;
+pub mod notification_preferences;
+pub mod network_updates;
+pub mod auth_challenge;
+pub mod external_auth;
+pub mod connection_gate;
 
 // Re-export services for easier importing
 pub use user::UserService;
 pub use network::NetworkService;
-pub use signature::SignatureService; 
\ No newline at end of file
+pub use network_updates::NetworkUpdateCoalescer;
+pub use signature::SignatureService;
+pub use session_registry::SessionRegistry;
+pub use maintenance::{MaintenanceMode, ReadOnlyMode};
+pub use reconnect::ReconnectTokens;
+pub use broadcast::BroadcastHub;
+pub use key_deny_list::KeyDenyList;
+pub use ws_ticket::WsTickets;
+pub use auth_challenge::AuthChallenges;
+pub use external_auth::ExternalAuthService;
+pub use connection_gate::ConnectionGates;
+pub use readiness::Readiness;
+pub use email_verification::EmailVerificationTokens;
+pub use rate_limit::RateLimits;
+pub use redis_bridge::{RedisBroadcastBridge, RedisLinkStatus};
+pub use circuit_breaker::CircuitBreaker;
+pub use audit::{AuditEvent, AuditSink, LogAuditSink};
+pub use health::HealthState;
+pub use webhook::Webhooks;
+pub use notifier::CompositeNotifier;
+pub use notification_preferences::NotificationPreferenceCache;
\ No newline at end of file
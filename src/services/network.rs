@@ -1,21 +1,152 @@
 use crate::errors::{DashboardError, DashboardResult};
 use crate::models::network::{
-    CreateNetworkConnectionDto, NetworkConnection, NetworkStatistics, NetworkStatus,
-    UpdateNetworkConnectionDto,
+    ConnectionScoreFilter, CreateNetworkConnectionDto, NetworkConnection, NetworkEvent, NetworkStatistics,
+    NetworkStatus, UpdateNetworkConnectionDto,
 };
+use crate::models::earnings::EarningSource;
+use crate::models::websocket::WebSocketMessage;
+use crate::services::broadcast::{BroadcastHub, EARNINGS_CHANNEL, NETWORK_CHANNEL};
+use crate::services::maintenance::ReadOnlyMode;
+use crate::services::notifier::{Notification, Notifier};
 use crate::storage::NetworkStorage;
+use serde_json::json;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+/// Lowest network score
+pub const MIN_NETWORK_SCORE: f64 = 0.0;
+
+/// Highest network score; a connection at this score earns the full per-minute rate
+pub const MAX_NETWORK_SCORE: f64 = 100.0;
+
+/// Passive points earned for connected time, scaled by network score
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointsAccrual {
+    /// Points per connected minute at `MAX_NETWORK_SCORE`
+    pub points_per_minute: f64,
+    /// Upper bound on points awarded by a single connection-time update
+    pub max_points_per_update: f64,
+}
+
+impl PointsAccrual {
+    /// Points earned for `seconds` of connected time at `network_score`.
+    ///
+    /// The score is clamped to 0..=`MAX_NETWORK_SCORE` and the result to
+    /// 0..=`max_points_per_update`, so bad inputs can't mint runaway or negative points.
+    pub fn points_for(&self, seconds: i64, network_score: f64) -> f64 {
+        if seconds <= 0 || !network_score.is_finite() || !self.points_per_minute.is_finite() {
+            return 0.0;
+        }
+        let score_factor = network_score.clamp(MIN_NETWORK_SCORE, MAX_NETWORK_SCORE) / MAX_NETWORK_SCORE;
+        let points = seconds as f64 / 60.0 * self.points_per_minute.max(0.0) * score_factor;
+        points.min(self.max_points_per_update.max(0.0))
+    }
+}
 
 /// Network service for handling network-related operations
 pub struct NetworkService<T: NetworkStorage> {
     storage: Arc<T>,
+    notifier: Option<Arc<dyn Notifier>>,
+    points_accrual: Option<PointsAccrual>,
+    read_only: ReadOnlyMode,
 }
 
 impl<T: NetworkStorage> NetworkService<T> {
     /// Create a new NetworkService with the given storage
     pub fn new(storage: Arc<T>) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            notifier: None,
+            points_accrual: None,
+            read_only: ReadOnlyMode::default(),
+        }
+    }
+
+    /// Award points for connected time recorded via `record_connection_time`
+    pub fn with_points_accrual(mut self, accrual: PointsAccrual) -> Self {
+        self.points_accrual = Some(accrual);
+        self
+    }
+
+    /// Refuse every write while `read_only` is enabled
+    pub fn with_read_only(mut self, read_only: ReadOnlyMode) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Send connection changes and earnings to the owning user through `notifier`
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Publish connection changes and earnings to the owning user's WebSocket sessions only
+    pub fn with_broadcast(self, broadcast: BroadcastHub) -> Self {
+        self.with_notifier(Arc::new(broadcast))
+    }
+
+    /// Notify the user of a status change with `network_event` and typed `network_status`
+    /// frames
+    fn publish_status(&self, status: &NetworkStatus, origin_session: Option<&str>) {
+        if let Some(notifier) = &self.notifier {
+            let event = json!({
+                "type": "network_event",
+                "connection_id": status.connection_id,
+                "network_name": status.network_name,
+                "connected": status.connected,
+                "status_message": status.status_message,
+                "network_score": status.network_score,
+                "updated_at": status.updated_at
+            });
+            notifier.notify(
+                status.user_id,
+                &Notification::new(NETWORK_CHANNEL, event).from_session(origin_session),
+            );
+
+            let status_frame = json!({
+                "type": "network_status",
+                "status": status
+            });
+            notifier.notify(
+                status.user_id,
+                &Notification::new(NETWORK_CHANNEL, status_frame).from_session(origin_session),
+            );
+        }
+    }
+
+    /// Record points for a connection and notify the user with an `EarningsUpdate` carrying
+    /// their running total.
+    ///
+    /// Returns the connection's new points total.
+    async fn award_points(
+        &self,
+        connection: &NetworkConnection,
+        points: f64,
+        source: EarningSource,
+    ) -> DashboardResult<f64> {
+        let connection_total = self.storage.record_earned_points(connection.id, points).await?;
+
+        if let Some(notifier) = &self.notifier {
+            // The points are already stored, so a failed total lookup only skips the push
+            match self.storage.find_connections_by_user_id(connection.user_id).await {
+                Ok(connections) => {
+                    let update = WebSocketMessage::EarningsUpdate {
+                        amount: points,
+                        source,
+                        total: connections.iter().map(|c| c.points_earned).sum(),
+                    };
+                    match serde_json::to_value(&update) {
+                        Ok(event) => {
+                            notifier.notify(connection.user_id, &Notification::new(EARNINGS_CHANNEL, event));
+                        }
+                        Err(e) => error!("Failed to serialize earnings update: {}", e),
+                    }
+                }
+                Err(e) => warn!("Skipping earnings push for user {}: {}", connection.user_id, e),
+            }
+        }
+
+        Ok(connection_total)
     }
 
     /// Get a network connection by ID
@@ -33,6 +164,44 @@ impl<T: NetworkStorage> NetworkService<T> {
         self.storage.find_connections_by_user_id(user_id).await
     }
 
+    /// Get a page of a user's network connections ordered by ID, starting after `after_id`
+    pub async fn get_user_connections_page(
+        &self,
+        user_id: i64,
+        after_id: Option<i64>,
+        limit: usize,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        self.storage.find_connections_page(user_id, after_id, limit).await
+    }
+
+    /// Get a user's network connections scoring within `filter`, lowest score first.
+    ///
+    /// Each bound must lie within `MIN_NETWORK_SCORE..=MAX_NETWORK_SCORE`, and `min_score`
+    /// must not exceed `max_score`.
+    pub async fn get_user_connections_by_score(
+        &self,
+        user_id: i64,
+        filter: &ConnectionScoreFilter,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        for (name, bound) in [("min_score", filter.min_score), ("max_score", filter.max_score)] {
+            if let Some(bound) = bound {
+                if !(MIN_NETWORK_SCORE..=MAX_NETWORK_SCORE).contains(&bound) {
+                    return Err(DashboardError::validation(format!(
+                        "{} must be between {} and {}",
+                        name, MIN_NETWORK_SCORE, MAX_NETWORK_SCORE
+                    )));
+                }
+            }
+        }
+        if let (Some(min), Some(max)) = (filter.min_score, filter.max_score) {
+            if min > max {
+                return Err(DashboardError::validation("min_score must not exceed max_score"));
+            }
+        }
+
+        self.storage.find_connections_by_score(user_id, filter).await
+    }
+
     /// Get active network connections for a user
     pub async fn get_active_user_connections(
         &self,
@@ -41,11 +210,13 @@ impl<T: NetworkStorage> NetworkService<T> {
         self.storage.find_active_connections_by_user_id(user_id).await
     }
 
-    /// Create a new network connection
+    /// Create a new network connection, or refresh the user's active connection on the same
+    /// network unless the DTO sets `allow_duplicate`
     pub async fn create_connection(
         &self,
         connection: CreateNetworkConnectionDto,
     ) -> DashboardResult<NetworkConnection> {
+        self.read_only.ensure_writable()?;
         let connection = self.storage.create_connection(connection).await?;
 
         // Initialize network status
@@ -67,8 +238,28 @@ impl<T: NetworkStorage> NetworkService<T> {
         id: i64,
         update: UpdateNetworkConnectionDto,
     ) -> DashboardResult<NetworkConnection> {
+        self.update_connection_from(id, update, None).await
+    }
+
+    /// Update a network connection, skipping `origin_session` when broadcasting the change
+    pub async fn update_connection_from(
+        &self,
+        id: i64,
+        update: UpdateNetworkConnectionDto,
+        origin_session: Option<&str>,
+    ) -> DashboardResult<NetworkConnection> {
+        self.read_only.ensure_writable()?;
         // Check if connection exists
-        self.get_connection(id).await?;
+        let existing = self.get_connection(id).await?;
+
+        // Connected time reported with the update accrues points like `record_connection_time`
+        let mut update = update;
+        if let (Some(accrual), Some(seconds)) = (&self.points_accrual, update.additional_time) {
+            let points = accrual.points_for(seconds, existing.network_score);
+            if points > 0.0 {
+                update.additional_points = Some(update.additional_points.unwrap_or(0.0) + points);
+            }
+        }
 
         let connection = self.storage.update_connection(id, update.clone()).await?;
 
@@ -82,9 +273,11 @@ impl<T: NetworkStorage> NetworkService<T> {
 
             let network_score = update.clone().network_score;
 
-            self.storage
+            let status = self
+                .storage
                 .update_network_status(id, connected, status_message, network_score)
                 .await?;
+            self.publish_status(&status, origin_session);
         }
 
         Ok(connection)
@@ -92,6 +285,7 @@ impl<T: NetworkStorage> NetworkService<T> {
 
     /// Delete a network connection
     pub async fn delete_connection(&self, id: i64) -> DashboardResult<bool> {
+        self.read_only.ensure_writable()?;
         // Check if connection exists
         self.get_connection(id).await?;
 
@@ -119,12 +313,34 @@ impl<T: NetworkStorage> NetworkService<T> {
         status_message: &str,
         network_score: Option<f64>,
     ) -> DashboardResult<NetworkStatus> {
+        self.read_only.ensure_writable()?;
         // Check if connection exists
         self.get_connection(connection_id).await?;
 
-        self.storage
+        let status = self
+            .storage
             .update_network_status(connection_id, connected, status_message, network_score)
-            .await
+            .await?;
+        self.publish_status(&status, None);
+
+        Ok(status)
+    }
+
+    /// Get a connection's status history, newest first
+    pub async fn get_connection_events(
+        &self,
+        connection_id: i64,
+        offset: usize,
+        limit: usize,
+    ) -> DashboardResult<Vec<NetworkEvent>> {
+        self.get_connection(connection_id).await?;
+        self.storage.list_events(connection_id, offset, limit).await
+    }
+
+    /// Count a connection's recorded status transitions
+    pub async fn count_connection_events(&self, connection_id: i64) -> DashboardResult<usize> {
+        self.get_connection(connection_id).await?;
+        self.storage.count_events(connection_id).await
     }
 
     /// Get network statistics for a user
@@ -132,32 +348,49 @@ impl<T: NetworkStorage> NetworkService<T> {
         self.storage.get_network_statistics(user_id).await
     }
 
-    /// Record connection time
+    /// Record connection time, accruing points for it when accrual is enabled
     pub async fn record_connection_time(
         &self,
         connection_id: i64,
         seconds: i64,
     ) -> DashboardResult<i64> {
+        self.read_only.ensure_writable()?;
         // Check if connection exists
-        self.get_connection(connection_id).await?;
+        let connection = self.get_connection(connection_id).await?;
+
+        let total = self.storage.record_connection_time(connection_id, seconds).await?;
+
+        if let Some(accrual) = &self.points_accrual {
+            let points = accrual.points_for(seconds, connection.network_score);
+            if points > 0.0 {
+                self.award_points(&connection, points, EarningSource::Uptime).await?;
+                info!(
+                    "Accrued {:.2} points for {}s on connection {} (score {:.1})",
+                    points, seconds, connection_id, connection.network_score
+                );
+            }
+        }
 
-        self.storage.record_connection_time(connection_id, seconds).await
+        Ok(total)
     }
 
-    /// Record earned points
+    /// Record points earned on a connection from `source`
     pub async fn record_earned_points(
         &self,
         connection_id: i64,
         points: f64,
+        source: EarningSource,
     ) -> DashboardResult<f64> {
+        self.read_only.ensure_writable()?;
         // Check if connection exists
-        self.get_connection(connection_id).await?;
+        let connection = self.get_connection(connection_id).await?;
 
-        self.storage.record_earned_points(connection_id, points).await
+        self.award_points(&connection, points, source).await
     }
 
     /// Calculate network score based on connection metrics
     pub async fn calculate_network_score(&self, connection_id: i64) -> DashboardResult<f64> {
+        self.read_only.ensure_writable()?;
         // This is a placeholder for the actual scoring algorithm
         // In a real implementation, this would incorporate various metrics
         let connection = self.get_connection(connection_id).await?;
@@ -185,4 +418,351 @@ impl<T: NetworkStorage> NetworkService<T> {
         
         Ok(score)
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::broadcast::{Disconnect, RawFrame};
+    use crate::storage::memory::network::MAX_EVENTS_PER_CONNECTION;
+    use crate::storage::memory::InMemoryNetworkStorage;
+    use actix::{Actor, Context, Handler, Message};
+
+    /// Test session that records pushed frames
+    #[derive(Default)]
+    struct Collector {
+        frames: Vec<serde_json::Value>,
+    }
+
+    impl Actor for Collector {
+        type Context = Context<Self>;
+    }
+
+    impl Handler<RawFrame> for Collector {
+        type Result = ();
+
+        fn handle(&mut self, frame: RawFrame, _: &mut Self::Context) {
+            self.frames.push(serde_json::from_str(&frame.0).unwrap());
+        }
+    }
+
+    impl Handler<Disconnect> for Collector {
+        type Result = ();
+
+        fn handle(&mut self, _: Disconnect, _: &mut Self::Context) {}
+    }
+
+    struct Frames;
+
+    impl Message for Frames {
+        type Result = Vec<serde_json::Value>;
+    }
+
+    impl Handler<Frames> for Collector {
+        type Result = Vec<serde_json::Value>;
+
+        fn handle(&mut self, _: Frames, _: &mut Self::Context) -> Self::Result {
+            self.frames.clone()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_update_network_status_pushes_typed_frame() {
+        let hub = BroadcastHub::new();
+        let service = NetworkService::new(Arc::new(InMemoryNetworkStorage::new())).with_broadcast(hub.clone());
+        let connection = service
+            .create_connection(CreateNetworkConnectionDto {
+                user_id: 7,
+                network_name: "home".to_string(),
+                ip_address: "10.0.0.1".to_string(),
+                initial_score: Some(50.0),
+                allow_duplicate: false,
+            })
+            .await
+            .unwrap();
+
+        let session = Collector::default().start();
+        hub.subscribe(7, "session", &session);
+
+        service
+            .update_network_status(connection.id, false, "Signal lost", Some(12.5))
+            .await
+            .unwrap();
+
+        let frames = session.send(Frames).await.unwrap();
+        let status_frame = frames
+            .iter()
+            .find(|frame| frame["type"] == "network_status")
+            .expect("network_status frame");
+        assert_eq!(status_frame["status"]["connection_id"], connection.id);
+        assert_eq!(status_frame["status"]["connected"], false);
+        assert_eq!(status_frame["status"]["status_message"], "Signal lost");
+        assert_eq!(status_frame["status"]["network_score"], 12.5);
+    }
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        received: std::sync::Mutex<Vec<(i64, Notification)>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn notify(&self, user_id: i64, notification: &Notification) {
+            self.received.lock().unwrap().push((user_id, notification.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status_changes_and_earnings_go_through_the_notifier() {
+        let notifier = Arc::new(RecordingNotifier::default());
+        let service = NetworkService::new(Arc::new(InMemoryNetworkStorage::new())).with_notifier(notifier.clone());
+        let connection = service
+            .create_connection(CreateNetworkConnectionDto {
+                user_id: 7,
+                network_name: "home".to_string(),
+                ip_address: "10.0.0.1".to_string(),
+                initial_score: Some(50.0),
+                allow_duplicate: false,
+            })
+            .await
+            .unwrap();
+
+        let update = UpdateNetworkConnectionDto {
+            connected: Some(false),
+            network_score: None,
+            additional_time: None,
+            additional_points: None,
+        };
+        service.update_connection_from(connection.id, update, Some("s1")).await.unwrap();
+        service.record_earned_points(connection.id, 2.5, EarningSource::Bonus).await.unwrap();
+
+        let received = notifier.received.lock().unwrap();
+        let summary: Vec<_> = received
+            .iter()
+            .map(|(user_id, n)| (*user_id, n.channel, n.event["type"].clone(), n.origin_session.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (7, NETWORK_CHANNEL, json!("network_event"), Some("s1")),
+                (7, NETWORK_CHANNEL, json!("network_status"), Some("s1")),
+                (7, EARNINGS_CHANNEL, json!("EarningsUpdate"), None),
+            ]
+        );
+        assert_eq!(received[2].1.event["data"]["total"], 2.5);
+    }
+
+    #[test]
+    fn test_points_scale_with_time_and_score_and_are_clamped() {
+        let accrual = PointsAccrual {
+            points_per_minute: 1.0,
+            max_points_per_update: 30.0,
+        };
+        assert_eq!(accrual.points_for(600, 100.0), 10.0);
+        assert_eq!(accrual.points_for(600, 50.0), 5.0);
+        assert_eq!(accrual.points_for(600, 250.0), 10.0);
+        assert_eq!(accrual.points_for(3600, 100.0), 30.0);
+        assert_eq!(accrual.points_for(-600, 100.0), 0.0);
+        assert_eq!(accrual.points_for(600, -10.0), 0.0);
+        assert_eq!(accrual.points_for(600, f64::NAN), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_time_accrues_points_only_when_enabled() {
+        let dto = || CreateNetworkConnectionDto {
+            user_id: 7,
+            network_name: "home".to_string(),
+            ip_address: "10.0.0.1".to_string(),
+            initial_score: Some(50.0),
+            allow_duplicate: false,
+        };
+
+        let plain = NetworkService::new(Arc::new(InMemoryNetworkStorage::new()));
+        let connection = plain.create_connection(dto()).await.unwrap();
+        plain.record_connection_time(connection.id, 600).await.unwrap();
+        assert_eq!(plain.get_connection(connection.id).await.unwrap().points_earned, 0.0);
+
+        let accruing = NetworkService::new(Arc::new(InMemoryNetworkStorage::new())).with_points_accrual(
+            PointsAccrual {
+                points_per_minute: 2.0,
+                max_points_per_update: 100.0,
+            },
+        );
+        let connection = accruing.create_connection(dto()).await.unwrap();
+        assert_eq!(accruing.record_connection_time(connection.id, 600).await.unwrap(), 600);
+        assert_eq!(accruing.get_connection(connection.id).await.unwrap().points_earned, 10.0);
+    }
+
+    #[actix_rt::test]
+    async fn test_earned_points_push_earnings_update_with_running_total() {
+        let hub = BroadcastHub::new();
+        let service = NetworkService::new(Arc::new(InMemoryNetworkStorage::new()))
+            .with_broadcast(hub.clone())
+            .with_points_accrual(PointsAccrual {
+                points_per_minute: 1.0,
+                max_points_per_update: 100.0,
+            });
+        let dto = |network_name: &str| CreateNetworkConnectionDto {
+            user_id: 7,
+            network_name: network_name.to_string(),
+            ip_address: "10.0.0.1".to_string(),
+            initial_score: Some(100.0),
+            allow_duplicate: false,
+        };
+        let home = service.create_connection(dto("home")).await.unwrap();
+        let office = service.create_connection(dto("office")).await.unwrap();
+
+        let earnings = Collector::default().start();
+        hub.subscribe(7, "earnings", &earnings);
+        let network_only = Collector::default().start();
+        hub.subscribe_channels(7, "network-only", &network_only, [NETWORK_CHANNEL.to_string()].into());
+
+        service.record_connection_time(home.id, 300).await.unwrap();
+        assert_eq!(service.record_earned_points(office.id, 2.5, EarningSource::Bonus).await.unwrap(), 2.5);
+
+        let frames = earnings.send(Frames).await.unwrap();
+        assert_eq!(
+            frames,
+            vec![
+                json!({"type": "EarningsUpdate", "data": {"amount": 5.0, "source": "uptime", "total": 5.0}}),
+                json!({"type": "EarningsUpdate", "data": {"amount": 2.5, "source": "bonus", "total": 7.5}}),
+            ]
+        );
+        assert!(network_only.send(Frames).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_connection_refreshes_active_connection_on_same_network() {
+        let service = NetworkService::new(Arc::new(InMemoryNetworkStorage::new()));
+        let dto = |network_name: &str, ip_address: &str, allow_duplicate| CreateNetworkConnectionDto {
+            user_id: 7,
+            network_name: network_name.to_string(),
+            ip_address: ip_address.to_string(),
+            initial_score: Some(50.0),
+            allow_duplicate,
+        };
+
+        let first = service.create_connection(dto("home", "10.0.0.1", false)).await.unwrap();
+        let again = service.create_connection(dto("home", "10.0.0.2", false)).await.unwrap();
+        assert_eq!(again.id, first.id);
+        assert_eq!(again.ip_address, "10.0.0.2");
+
+        // Other networks, and networks whose connection has ended, get a new row
+        let office = service.create_connection(dto("office", "10.0.0.1", false)).await.unwrap();
+        assert_ne!(office.id, first.id);
+        service
+            .update_connection(first.id, UpdateNetworkConnectionDto {
+                connected: Some(false),
+                network_score: None,
+                additional_time: None,
+                additional_points: None,
+            })
+            .await
+            .unwrap();
+        let reconnected = service.create_connection(dto("home", "10.0.0.3", false)).await.unwrap();
+        assert_ne!(reconnected.id, first.id);
+        assert_eq!(service.get_network_statistics(7).await.unwrap().total_networks, 3);
+    }
+
+    #[tokio::test]
+    async fn test_create_connection_allows_explicit_duplicates() {
+        let service = NetworkService::new(Arc::new(InMemoryNetworkStorage::new()));
+        let dto = || CreateNetworkConnectionDto {
+            user_id: 7,
+            network_name: "home".to_string(),
+            ip_address: "10.0.0.1".to_string(),
+            initial_score: None,
+            allow_duplicate: true,
+        };
+
+        let first = service.create_connection(dto()).await.unwrap();
+        let second = service.create_connection(dto()).await.unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(service.get_active_user_connections(7).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_connections_filtered_by_score_include_boundaries_and_sort_ascending() {
+        let service = NetworkService::new(Arc::new(InMemoryNetworkStorage::new()));
+        for (network_name, score) in [("a", 80.0), ("b", 20.0), ("c", 50.0), ("d", 19.9), ("e", 50.0), ("f", 100.0)] {
+            service
+                .create_connection(CreateNetworkConnectionDto {
+                    user_id: 7,
+                    network_name: network_name.to_string(),
+                    ip_address: "10.0.0.1".to_string(),
+                    initial_score: Some(score),
+                    allow_duplicate: false,
+                })
+                .await
+                .unwrap();
+        }
+        let names = |connections: Vec<NetworkConnection>| {
+            connections.into_iter().map(|c| c.network_name).collect::<Vec<_>>()
+        };
+        let filter = |min_score, max_score| ConnectionScoreFilter { min_score, max_score };
+
+        let all = service.get_user_connections_by_score(7, &filter(None, None)).await.unwrap();
+        assert_eq!(names(all), ["d", "b", "c", "e", "a", "f"]);
+        let low = service.get_user_connections_by_score(7, &filter(None, Some(20.0))).await.unwrap();
+        assert_eq!(names(low), ["d", "b"]);
+        let mid = service.get_user_connections_by_score(7, &filter(Some(20.0), Some(50.0))).await.unwrap();
+        assert_eq!(names(mid), ["b", "c", "e"]);
+        let top = service.get_user_connections_by_score(7, &filter(Some(100.0), Some(100.0))).await.unwrap();
+        assert_eq!(names(top), ["f"]);
+        assert!(service.get_user_connections_by_score(8, &filter(None, None)).await.unwrap().is_empty());
+
+        for (min_score, max_score) in [(Some(60.0), Some(40.0)), (Some(-0.1), None), (None, Some(100.1)), (Some(f64::NAN), None)] {
+            let err = service
+                .get_user_connections_by_score(7, &filter(min_score, max_score))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, DashboardError::Validation(_)), "{:?}..{:?}", min_score, max_score);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_status_updates_are_recorded_as_events_with_old_and_new_values() {
+        let service = NetworkService::new(Arc::new(InMemoryNetworkStorage::new()));
+        let connection = service
+            .create_connection(CreateNetworkConnectionDto {
+                user_id: 7,
+                network_name: "home".to_string(),
+                ip_address: "10.0.0.1".to_string(),
+                initial_score: Some(50.0),
+                allow_duplicate: false,
+            })
+            .await
+            .unwrap();
+
+        // Creating the connection records its first status
+        service.update_network_status(connection.id, false, "Signal lost", Some(12.5)).await.unwrap();
+
+        let events = service.get_connection_events(connection.id, 0, 10).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].previous_connected, Some(true));
+        assert!(!events[0].connected);
+        assert_eq!(events[0].previous_score, Some(50.0));
+        assert_eq!(events[0].network_score, 12.5);
+        assert_eq!(events[0].status_message, "Signal lost");
+        assert_eq!(events[1].previous_connected, None);
+        assert_eq!(events[1].previous_score, None);
+        assert!(events[1].connected);
+        assert_eq!(service.get_connection_events(connection.id, 1, 10).await.unwrap(), events[1..]);
+        assert_eq!(service.count_connection_events(connection.id).await.unwrap(), 2);
+
+        // The history is capped, dropping the oldest events first
+        for i in 0..MAX_EVENTS_PER_CONNECTION {
+            service
+                .update_network_status(connection.id, true, &format!("tick {}", i), None)
+                .await
+                .unwrap();
+        }
+        let events = service.get_connection_events(connection.id, 0, usize::MAX).await.unwrap();
+        assert_eq!(events.len(), MAX_EVENTS_PER_CONNECTION);
+        assert_eq!(events.last().unwrap().status_message, "tick 0");
+        assert_eq!(service.count_connection_events(connection.id).await.unwrap(), MAX_EVENTS_PER_CONNECTION);
+
+        assert!(matches!(
+            service.get_connection_events(connection.id + 1, 0, 10).await.unwrap_err(),
+            DashboardError::NotFound(_)
+        ));
+    }
+}
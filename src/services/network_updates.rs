@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+use crate::services::NetworkService;
+use crate::storage::NetworkStorage;
+
+/// Latest state a client reported for one of its connections
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkUpdate {
+    /// User the reporting session is authenticated as; must own the connection
+    pub user_id: i64,
+    pub status_message: String,
+    pub network_score: f64,
+}
+
+/// Coalesces `NetworkUpdate` messages per connection so a flood of them costs one storage
+/// write per window instead of one per message.
+///
+/// The first update for a connection starts a window; updates arriving before it closes
+/// replace the pending one, and the latest is written when it does. Updates arriving during
+/// that write start the next window, so the last reported state is always written.
+pub struct NetworkUpdateCoalescer<T: NetworkStorage> {
+    service: Arc<NetworkService<T>>,
+    window: Duration,
+    /// connection_id -> update waiting for the window to close; an entry means a flush task
+    /// is running for the connection
+    pending: Arc<Mutex<HashMap<i64, Option<NetworkUpdate>>>>,
+}
+
+impl<T: NetworkStorage> Clone for NetworkUpdateCoalescer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            service: self.service.clone(),
+            window: self.window,
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<T: NetworkStorage + 'static> NetworkUpdateCoalescer<T> {
+    /// Write updates through `service`, at most once per `window` for each connection
+    pub fn new(service: Arc<NetworkService<T>>, window: Duration) -> Self {
+        Self {
+            service,
+            window,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Queue `update` for `connection_id`, replacing any update still waiting for its window
+    pub fn submit(&self, connection_id: i64, update: NetworkUpdate) {
+        let start_flush = match self.pending.lock() {
+            Ok(mut pending) => pending.insert(connection_id, Some(update)).is_none(),
+            Err(e) => {
+                error!("Failed to lock pending network updates: {}", e);
+                return;
+            }
+        };
+        if start_flush {
+            actix_rt::spawn(self.clone().flush_loop(connection_id));
+        }
+    }
+
+    /// Connections with an update waiting to be written
+    pub fn pending_len(&self) -> usize {
+        match self.pending.lock() {
+            Ok(pending) => pending.values().filter(|update| update.is_some()).count(),
+            Err(_) => 0,
+        }
+    }
+
+    /// Write every waiting update now, e.g. during shutdown; returns how many were written
+    pub async fn flush_all(&self) -> usize {
+        let updates: Vec<(i64, NetworkUpdate)> = match self.pending.lock() {
+            Ok(mut pending) => pending
+                .iter_mut()
+                .filter_map(|(connection_id, update)| update.take().map(|update| (*connection_id, update)))
+                .collect(),
+            Err(e) => {
+                error!("Failed to lock pending network updates: {}", e);
+                return 0;
+            }
+        };
+        let mut written = 0;
+        for (connection_id, update) in updates {
+            if self.apply(connection_id, update).await {
+                written += 1;
+            }
+        }
+        written
+    }
+
+    /// Write the latest update once per window until none arrives during a window
+    async fn flush_loop(self, connection_id: i64) {
+        loop {
+            if !self.window.is_zero() {
+                tokio::time::sleep(self.window).await;
+            }
+            let update = match self.pending.lock() {
+                Ok(mut pending) => match pending.get_mut(&connection_id).and_then(Option::take) {
+                    Some(update) => update,
+                    None => {
+                        pending.remove(&connection_id);
+                        return;
+                    }
+                },
+                Err(e) => {
+                    error!("Failed to lock pending network updates: {}", e);
+                    return;
+                }
+            };
+            self.apply(connection_id, update).await;
+        }
+    }
+
+    /// Write one update, refusing connections the reporting user doesn't own
+    async fn apply(&self, connection_id: i64, update: NetworkUpdate) -> bool {
+        match self.service.get_connection(connection_id).await {
+            Ok(connection) if connection.user_id == update.user_id => {}
+            Ok(_) => {
+                warn!("User {} sent a network update for connection {} they don't own", update.user_id, connection_id);
+                return false;
+            }
+            Err(e) => {
+                warn!("Dropping network update for connection {}: {}", connection_id, e);
+                return false;
+            }
+        }
+        match self
+            .service
+            .update_network_status(connection_id, true, &update.status_message, Some(update.network_score))
+            .await
+        {
+            Ok(_) => {
+                debug!("Wrote coalesced network update for connection {}", connection_id);
+                true
+            }
+            Err(e) => {
+                warn!("Failed to write network update for connection {}: {}", connection_id, e);
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::network::CreateNetworkConnectionDto;
+    use crate::storage::memory::InMemoryNetworkStorage;
+
+    type Service = NetworkService<InMemoryNetworkStorage>;
+
+    async fn setup(window: Duration) -> (NetworkUpdateCoalescer<InMemoryNetworkStorage>, Arc<Service>, i64) {
+        let service = Arc::new(NetworkService::new(Arc::new(InMemoryNetworkStorage::new())));
+        let connection = service
+            .create_connection(CreateNetworkConnectionDto {
+                user_id: 7,
+                network_name: "home".to_string(),
+                ip_address: "10.0.0.1".to_string(),
+                initial_score: Some(50.0),
+                allow_duplicate: false,
+            })
+            .await
+            .unwrap();
+        (NetworkUpdateCoalescer::new(service.clone(), window), service, connection.id)
+    }
+
+    fn update(user_id: i64, i: usize) -> NetworkUpdate {
+        NetworkUpdate {
+            user_id,
+            status_message: format!("tick {}", i),
+            network_score: i as f64,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_flood_of_updates_is_written_once_with_the_latest_state() {
+        let (coalescer, service, connection_id) = setup(Duration::from_millis(50)).await;
+
+        for i in 0..100 {
+            coalescer.submit(connection_id, update(7, i));
+        }
+        assert_eq!(coalescer.pending_len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(coalescer.pending_len(), 0);
+
+        // One event for the creation, one for the single coalesced write
+        let events = service.get_connection_events(connection_id, 0, usize::MAX).await.unwrap();
+        assert_eq!(events.len(), 2);
+        let status = service.get_network_status(connection_id).await.unwrap();
+        assert_eq!(status.status_message, "tick 99");
+        assert_eq!(status.network_score, 99.0);
+    }
+
+    #[actix_rt::test]
+    async fn test_flush_all_writes_pending_updates_before_the_window_closes() {
+        let (coalescer, service, connection_id) = setup(Duration::from_secs(60)).await;
+
+        coalescer.submit(connection_id, update(7, 1));
+        coalescer.submit(connection_id, update(7, 2));
+        assert_eq!(coalescer.flush_all().await, 1);
+        assert_eq!(service.get_network_status(connection_id).await.unwrap().status_message, "tick 2");
+
+        // Another user's session can't write to the connection
+        coalescer.submit(connection_id, update(8, 3));
+        assert_eq!(coalescer.flush_all().await, 0);
+        assert_eq!(service.get_network_status(connection_id).await.unwrap().status_message, "tick 2");
+    }
+}
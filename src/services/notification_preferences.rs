@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+
+use crate::cache::BoundedCache;
+use crate::models::user::{NotificationPreferences, NotificationRoute};
+use crate::services::notifier::{Notification, Notifier};
+use crate::storage::UserStorage;
+
+/// How long loaded preferences are trusted before being read from storage again
+pub const DEFAULT_PREFERENCES_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Default number of users whose preferences are kept in memory
+pub const DEFAULT_PREFERENCES_CACHE_CAPACITY: usize = 100_000;
+
+/// A notification waiting for its user's preferences to load
+struct Pending {
+    route: NotificationRoute,
+    notifier: Arc<dyn Notifier>,
+    notification: Notification,
+}
+
+struct CacheState {
+    /// user_id -> preferences and when they were loaded
+    loaded: BoundedCache<i64, (NotificationPreferences, Instant)>,
+    /// user_id -> notifications that arrived while the user's preferences were loading, in
+    /// arrival order
+    loading: HashMap<i64, Vec<Pending>>,
+}
+
+/// Users' notification preferences, cached so `Notifier::notify` can apply them without
+/// waiting on storage.
+///
+/// The first notification for a user whose preferences aren't cached starts a load; it and
+/// any that follow are queued and delivered in order once the load finishes. Preferences
+/// saved on this instance apply immediately; ones saved on another instance apply within
+/// `ttl`. If storage fails, the queued notifications are delivered as if nothing was muted.
+pub struct NotificationPreferenceCache<T: UserStorage> {
+    storage: Arc<T>,
+    ttl: Duration,
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl<T: UserStorage> Clone for NotificationPreferenceCache<T> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            ttl: self.ttl,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T: UserStorage> NotificationPreferenceCache<T> {
+    /// Cache the preferences of at most `capacity` users, re-reading them after `ttl`
+    pub fn new(storage: Arc<T>, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            storage,
+            ttl,
+            state: Arc::new(Mutex::new(CacheState {
+                loaded: BoundedCache::new(capacity),
+                loading: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Apply preferences a user just saved, without waiting for the cached ones to expire
+    pub fn put(&self, user_id: i64, preferences: NotificationPreferences) {
+        match self.state.lock() {
+            Ok(mut state) => state.loaded.insert(user_id, (preferences, Instant::now())),
+            Err(e) => error!("Failed to lock notification preferences: {}", e),
+        }
+    }
+
+    /// A notifier delivering to `inner` on `route` only what users haven't muted there
+    pub fn filter(&self, route: NotificationRoute, inner: Arc<dyn Notifier>) -> PreferenceFilter<T> {
+        PreferenceFilter {
+            cache: self.clone(),
+            route,
+            inner,
+        }
+    }
+
+    fn deliver(&self, user_id: i64, route: NotificationRoute, notifier: &Arc<dyn Notifier>, notification: &Notification) {
+        if notification.critical {
+            notifier.notify(user_id, notification);
+            return;
+        }
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Failed to lock notification preferences, delivering unfiltered: {}", e);
+                notifier.notify(user_id, notification);
+                return;
+            }
+        };
+        if let Some(queue) = state.loading.get_mut(&user_id) {
+            queue.push(Pending {
+                route,
+                notifier: notifier.clone(),
+                notification: notification.clone(),
+            });
+            return;
+        }
+        let muted = match state.loaded.get_mut(&user_id) {
+            Some((preferences, loaded_at)) if loaded_at.elapsed() < self.ttl => {
+                Some(preferences.mutes(route, notification.channel))
+            }
+            _ => None,
+        };
+        match muted {
+            Some(muted) => {
+                drop(state);
+                if !muted {
+                    notifier.notify(user_id, notification);
+                }
+            }
+            None => {
+                state.loading.insert(
+                    user_id,
+                    vec![Pending {
+                        route,
+                        notifier: notifier.clone(),
+                        notification: notification.clone(),
+                    }],
+                );
+                drop(state);
+                actix_rt::spawn(self.clone().load(user_id));
+            }
+        }
+    }
+
+    /// Read a user's preferences and deliver the notifications queued meanwhile
+    async fn load(self, user_id: i64) {
+        let started = Instant::now();
+        let preferences = match self.storage.get_notification_preferences(user_id).await {
+            Ok(preferences) => Some(preferences.unwrap_or_default()),
+            Err(e) => {
+                warn!("Failed to load notification preferences for user {}, delivering everything: {}", user_id, e);
+                None
+            }
+        };
+        let mut state = match self.state.lock() {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Failed to lock notification preferences: {}", e);
+                return;
+            }
+        };
+        let queue = state.loading.remove(&user_id).unwrap_or_default();
+        // Preferences saved while this load was in flight are newer than what it read
+        let saved_meanwhile = matches!(state.loaded.get_mut(&user_id), Some((_, loaded_at)) if *loaded_at >= started);
+        let effective = if saved_meanwhile {
+            state.loaded.get_mut(&user_id).map(|(preferences, _)| preferences.clone()).unwrap_or_default()
+        } else {
+            if let Some(preferences) = &preferences {
+                state.loaded.insert(user_id, (preferences.clone(), Instant::now()));
+            }
+            preferences.unwrap_or_default()
+        };
+        // Delivered under the lock so notifications arriving now can't overtake the queue
+        for pending in queue {
+            if !effective.mutes(pending.route, pending.notification.channel) {
+                pending.notifier.notify(user_id, &pending.notification);
+            }
+        }
+    }
+}
+
+/// Delivers to one route, e.g. the WebSocket hub, skipping channels the user muted there
+pub struct PreferenceFilter<T: UserStorage> {
+    cache: NotificationPreferenceCache<T>,
+    route: NotificationRoute,
+    inner: Arc<dyn Notifier>,
+}
+
+impl<T: UserStorage> Notifier for PreferenceFilter<T> {
+    fn notify(&self, user_id: i64, notification: &Notification) {
+        self.cache.deliver(user_id, self.route, &self.inner, notification);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::models::user::CreateUserDto;
+    use crate::services::broadcast::{EARNINGS_CHANNEL, NETWORK_CHANNEL};
+    use crate::services::notifier::CompositeNotifier;
+    use crate::storage::memory::InMemoryUserStorage;
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        received: Mutex<Vec<(i64, &'static str)>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn notify(&self, user_id: i64, notification: &Notification) {
+            self.received.lock().unwrap().push((user_id, notification.channel));
+        }
+    }
+
+    async fn setup() -> (Arc<InMemoryUserStorage>, i64) {
+        let storage = Arc::new(InMemoryUserStorage::new());
+        let user = storage
+            .create_user(CreateUserDto {
+                email: "alice@example.com".to_string(),
+                username: "alice".to_string(),
+                password: "password123".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap();
+        (storage, user.id)
+    }
+
+    fn muting_earnings_on_websocket() -> NotificationPreferences {
+        NotificationPreferences {
+            muted_websocket_channels: [EARNINGS_CHANNEL.to_string()].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_muted_channels_skip_their_route_but_not_critical_events() {
+        let (storage, user_id) = setup().await;
+        storage.set_notification_preferences(user_id, &muting_earnings_on_websocket()).await.unwrap();
+        let cache = NotificationPreferenceCache::new(storage, DEFAULT_PREFERENCES_CACHE_TTL, 10);
+        let websocket = Arc::new(RecordingNotifier::default());
+        let webhook = Arc::new(RecordingNotifier::default());
+        let notifier = CompositeNotifier::new()
+            .with(Arc::new(cache.filter(NotificationRoute::WebSocket, websocket.clone())))
+            .with(Arc::new(cache.filter(NotificationRoute::Webhook, webhook.clone())));
+
+        // Queued until the preferences load, then delivered in order
+        notifier.notify(user_id, &Notification::new(NETWORK_CHANNEL, json!({})));
+        notifier.notify(user_id, &Notification::new(EARNINGS_CHANNEL, json!({})));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // Served from the cache
+        notifier.notify(user_id, &Notification::new(EARNINGS_CHANNEL, json!({})).critical());
+        notifier.notify(user_id, &Notification::new(EARNINGS_CHANNEL, json!({})));
+
+        assert_eq!(
+            *websocket.received.lock().unwrap(),
+            vec![(user_id, NETWORK_CHANNEL), (user_id, EARNINGS_CHANNEL)]
+        );
+        assert_eq!(
+            *webhook.received.lock().unwrap(),
+            vec![
+                (user_id, NETWORK_CHANNEL),
+                (user_id, EARNINGS_CHANNEL),
+                (user_id, EARNINGS_CHANNEL),
+                (user_id, EARNINGS_CHANNEL)
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_saved_preferences_apply_immediately() {
+        let (storage, user_id) = setup().await;
+        let cache = NotificationPreferenceCache::new(storage.clone(), DEFAULT_PREFERENCES_CACHE_TTL, 10);
+        let websocket = Arc::new(RecordingNotifier::default());
+        let notifier = cache.filter(NotificationRoute::WebSocket, websocket.clone());
+
+        // Users who never saved preferences get everything
+        notifier.notify(user_id, &Notification::new(EARNINGS_CHANNEL, json!({})));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(websocket.received.lock().unwrap().len(), 1);
+
+        storage.set_notification_preferences(user_id, &muting_earnings_on_websocket()).await.unwrap();
+        cache.put(user_id, muting_earnings_on_websocket());
+        notifier.notify(user_id, &Notification::new(EARNINGS_CHANNEL, json!({})));
+        assert_eq!(websocket.received.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::services::broadcast::{BroadcastHub, ServerEvent};
+use crate::services::webhook::Webhooks;
+
+/// An event for one user, on one of the broadcast channels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// Channel the event belongs to, e.g. `NETWORK_CHANNEL`
+    pub channel: &'static str,
+    /// The JSON frame delivered to the user
+    pub event: serde_json::Value,
+    /// WebSocket session that caused the event; it already knows and is skipped
+    pub origin_session: Option<String>,
+    /// Security-critical events are delivered even on channels the user muted
+    pub critical: bool,
+}
+
+impl Notification {
+    /// An event on `channel`
+    pub fn new(channel: &'static str, event: serde_json::Value) -> Self {
+        Self {
+            channel,
+            event,
+            origin_session: None,
+            critical: false,
+        }
+    }
+
+    /// Deliver regardless of the user's notification preferences
+    pub fn critical(mut self) -> Self {
+        self.critical = true;
+        self
+    }
+
+    /// Skip `session` when delivering to WebSocket sessions
+    pub fn from_session(mut self, session: Option<&str>) -> Self {
+        self.origin_session = session.map(str::to_owned);
+        self
+    }
+}
+
+/// A delivery channel for user notifications.
+///
+/// Services produce events through a `Notifier` without knowing where they end up; deployments
+/// compose WebSocket, webhook and other channels with `CompositeNotifier`.
+pub trait Notifier: Send + Sync {
+    /// Deliver `notification` to `user_id`; must not block, since services call it inline
+    fn notify(&self, user_id: i64, notification: &Notification);
+}
+
+/// Default notifier that only logs each notification
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, user_id: i64, notification: &Notification) {
+        debug!("Notification for user {} on {}: {}", user_id, notification.channel, notification.event);
+    }
+}
+
+/// Pushes notifications to the user's WebSocket sessions subscribed to the channel
+impl Notifier for BroadcastHub {
+    fn notify(&self, user_id: i64, notification: &Notification) {
+        self.publish_to_user(
+            user_id,
+            notification.channel,
+            ServerEvent(notification.event.clone()),
+            notification.origin_session.as_deref(),
+        );
+    }
+}
+
+/// Queues notifications on `WEBHOOK_CHANNELS` for signed webhook delivery
+impl Notifier for Webhooks {
+    fn notify(&self, user_id: i64, notification: &Notification) {
+        self.enqueue(user_id, notification.channel, &notification.event);
+    }
+}
+
+/// Hands every notification to each of its notifiers, in the order they were added
+#[derive(Clone, Default)]
+pub struct CompositeNotifier {
+    notifiers: Vec<Arc<dyn Notifier>>,
+}
+
+impl CompositeNotifier {
+    /// Create a notifier with no channels
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also deliver to `notifier`
+    pub fn with(mut self, notifier: Arc<dyn Notifier>) -> Self {
+        self.notifiers.push(notifier);
+        self
+    }
+}
+
+impl Notifier for CompositeNotifier {
+    fn notify(&self, user_id: i64, notification: &Notification) {
+        for notifier in &self.notifiers {
+            notifier.notify(user_id, notification);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    use crate::services::broadcast::{EARNINGS_CHANNEL, NETWORK_CHANNEL};
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        received: Mutex<Vec<(i64, Notification)>>,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn notify(&self, user_id: i64, notification: &Notification) {
+            self.received.lock().unwrap().push((user_id, notification.clone()));
+        }
+    }
+
+    #[test]
+    fn test_composite_delivers_to_every_channel() {
+        let first = Arc::new(RecordingNotifier::default());
+        let second = Arc::new(RecordingNotifier::default());
+        let notifier = CompositeNotifier::new()
+            .with(first.clone())
+            .with(Arc::new(LogNotifier))
+            .with(second.clone());
+
+        let earnings = Notification::new(EARNINGS_CHANNEL, json!({ "type": "EarningsUpdate" }));
+        let network = Notification::new(NETWORK_CHANNEL, json!({ "type": "network_event" })).from_session(Some("s1"));
+        notifier.notify(7, &earnings);
+        notifier.notify(8, &network);
+
+        for recorder in [&first, &second] {
+            assert_eq!(
+                *recorder.received.lock().unwrap(),
+                vec![(7, earnings.clone()), (8, network.clone())]
+            );
+        }
+        assert_eq!(network.origin_session.as_deref(), Some("s1"));
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+use crate::cache::BoundedCache;
+use crate::config::{RateLimitConfig, TierLimits};
+use crate::models::user::RateLimitTier;
+
+/// Length of the message-rate window
+const MESSAGE_WINDOW: Duration = Duration::from_secs(60);
+
+/// How long a client refused a connection slot is told to wait; slots free up when another
+/// session closes rather than on a window, so this is only a back-off hint
+pub const CONNECTION_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Upper bound on tracked message windows; the least recently active keys are dropped first
+const MAX_TRACKED_KEYS: usize = 100_000;
+
+/// What a WebSocket limit is counted against: the client IP until the session authenticates,
+/// then the user, so all of a user's sessions share one budget
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    /// Unauthenticated connection, counted per client IP
+    Ip(String),
+    /// Authenticated connection, counted per user
+    User(i64),
+}
+
+/// Tier-aware WebSocket message and connection limits shared by every session on this instance
+#[derive(Clone)]
+pub struct RateLimits {
+    tiers: RateLimitConfig,
+    /// key -> (window start, messages in window)
+    messages: Arc<Mutex<BoundedCache<RateLimitKey, (Instant, u32)>>>,
+    /// key -> live connections
+    connections: Arc<Mutex<HashMap<RateLimitKey, usize>>>,
+}
+
+impl RateLimits {
+    /// Create limiters using the per-tier limits from config
+    pub fn new(tiers: RateLimitConfig) -> Self {
+        Self {
+            tiers,
+            messages: Arc::new(Mutex::new(BoundedCache::new(MAX_TRACKED_KEYS))),
+            connections: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Limits configured for a tier
+    pub fn limits(&self, tier: RateLimitTier) -> TierLimits {
+        match tier {
+            RateLimitTier::Basic => self.tiers.basic,
+            RateLimitTier::Standard => self.tiers.standard,
+            RateLimitTier::Premium => self.tiers.premium,
+        }
+    }
+
+    /// Count a message against `key`, returning `false` if the tier's per-minute budget is spent
+    pub fn allow_message(&self, key: &RateLimitKey, tier: RateLimitTier, now: Instant) -> bool {
+        let limit = self.limits(tier).messages_per_minute;
+        if limit == 0 {
+            return true;
+        }
+
+        let mut windows = match self.messages.lock() {
+            Ok(windows) => windows,
+            Err(e) => {
+                error!("Failed to lock message rate limiter: {}", e);
+                return true;
+            }
+        };
+        match windows.get_mut(key) {
+            Some((started, count)) if now.saturating_duration_since(*started) < MESSAGE_WINDOW => {
+                if *count >= limit {
+                    return false;
+                }
+                *count += 1;
+            }
+            _ => windows.insert(key.clone(), (now, 1)),
+        }
+        true
+    }
+
+    /// Register a new connection for `key`, returning `false` if the tier's limit is reached
+    pub fn try_connect(&self, key: &RateLimitKey, tier: RateLimitTier) -> bool {
+        let limit = self.limits(tier).max_connections;
+        let mut connections = match self.connections.lock() {
+            Ok(connections) => connections,
+            Err(e) => {
+                error!("Failed to lock connection limiter: {}", e);
+                return true;
+            }
+        };
+
+        let count = connections.entry(key.clone()).or_insert(0);
+        if limit != 0 && *count >= limit {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    /// Whether `key` is below the tier's connection limit, without registering a connection
+    pub fn has_connection_capacity(&self, key: &RateLimitKey, tier: RateLimitTier) -> bool {
+        let limit = self.limits(tier).max_connections;
+        if limit == 0 {
+            return true;
+        }
+        match self.connections.lock() {
+            Ok(connections) => connections.get(key).copied().unwrap_or(0) < limit,
+            Err(e) => {
+                error!("Failed to lock connection limiter: {}", e);
+                true
+            }
+        }
+    }
+
+    /// Release a connection registered with `try_connect`
+    pub fn disconnect(&self, key: &RateLimitKey) {
+        match self.connections.lock() {
+            Ok(mut connections) => {
+                if let Some(count) = connections.get_mut(key) {
+                    *count = count.saturating_sub(1);
+                    if *count == 0 {
+                        connections.remove(key);
+                    }
+                }
+            }
+            Err(e) => error!("Failed to lock connection limiter: {}", e),
+        }
+    }
+
+    /// Move a connection from `from` to `to` under `tier`'s limit, e.g. from the client IP to
+    /// the user once authenticated.
+    ///
+    /// Returns `false`, leaving the connection counted under `from`, if `to` is at its limit.
+    pub fn rekey_connection(&self, from: &RateLimitKey, to: &RateLimitKey, tier: RateLimitTier) -> bool {
+        if from == to {
+            return true;
+        }
+        if !self.try_connect(to, tier) {
+            return false;
+        }
+        self.disconnect(from);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> RateLimits {
+        let tier = |messages_per_minute, max_connections| TierLimits {
+            messages_per_minute,
+            max_connections,
+        };
+        RateLimits::new(RateLimitConfig {
+            basic: tier(2, 1),
+            standard: tier(5, 2),
+            premium: tier(0, 0),
+        })
+    }
+
+    #[test]
+    fn test_message_budget_depends_on_tier_and_resets_each_window() {
+        let limits = limits();
+        let now = Instant::now();
+        let ip = RateLimitKey::Ip("10.0.0.1".to_string());
+
+        assert!(limits.allow_message(&ip, RateLimitTier::Basic, now));
+        assert!(limits.allow_message(&ip, RateLimitTier::Basic, now));
+        assert!(!limits.allow_message(&ip, RateLimitTier::Basic, now));
+        assert!(limits.allow_message(&ip, RateLimitTier::Basic, now + MESSAGE_WINDOW));
+
+        // A separate key has its own budget; premium is unlimited here
+        let user = RateLimitKey::User(7);
+        for _ in 0..100 {
+            assert!(limits.allow_message(&user, RateLimitTier::Premium, now));
+        }
+    }
+
+    #[test]
+    fn test_connection_rekeyed_from_ip_to_user() {
+        let limits = limits();
+        let ip = RateLimitKey::Ip("10.0.0.1".to_string());
+        let user = RateLimitKey::User(7);
+
+        assert!(limits.try_connect(&ip, RateLimitTier::Basic));
+        assert!(!limits.try_connect(&ip, RateLimitTier::Basic));
+
+        // Authenticating frees the IP slot and counts against the user's tier instead
+        assert!(limits.rekey_connection(&ip, &user, RateLimitTier::Standard));
+        assert!(limits.try_connect(&ip, RateLimitTier::Basic));
+        assert!(limits.rekey_connection(&ip, &user, RateLimitTier::Standard));
+
+        // The user is now at the standard limit; the IP connection stays where it was
+        assert!(limits.try_connect(&ip, RateLimitTier::Basic));
+        assert!(!limits.rekey_connection(&ip, &user, RateLimitTier::Standard));
+        assert!(!limits.try_connect(&ip, RateLimitTier::Basic));
+
+        limits.disconnect(&user);
+        assert!(limits.rekey_connection(&ip, &user, RateLimitTier::Standard));
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Readiness flag for the `/ready` probe, set once startup tasks have finished
+#[derive(Clone, Default)]
+pub struct Readiness {
+    ready: Arc<AtomicBool>,
+}
+
+impl Readiness {
+    /// Create a flag in the not-ready state
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether startup has finished and the server should receive traffic
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    /// Mark the server ready (or not ready again, e.g. while draining)
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starts_not_ready_and_is_shared() {
+        let readiness = Readiness::new();
+        let probe = readiness.clone();
+        assert!(!probe.is_ready());
+
+        readiness.set_ready(true);
+        assert!(probe.is_ready());
+    }
+}
@@ -0,0 +1,221 @@
+use nanoid::nanoid;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+/// What happens when a reconnect token is presented from a different IP than it was issued to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpChangePolicy {
+    /// Refuse the token; the client must authenticate with a signature
+    #[default]
+    Reject,
+    /// Accept the token, e.g. for mobile clients switching networks, but audit the change
+    Flag,
+}
+
+impl FromStr for IpChangePolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "reject" => Ok(IpChangePolicy::Reject),
+            "flag" => Ok(IpChangePolicy::Flag),
+            other => Err(format!("Unknown IP change policy '{}'; expected reject or flag", other)),
+        }
+    }
+}
+
+/// Identity restored by redeeming a reconnect token
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectGrant {
+    /// User the token was issued to
+    pub user_id: i64,
+    /// Public key the original session authenticated with
+    pub public_key: Option<String>,
+    /// IP address the token is bound to
+    pub client_ip: String,
+    /// When the token stops being accepted
+    pub expires_at: Instant,
+}
+
+impl ReconnectGrant {
+    /// Whether the token is being redeemed from another IP than it was issued to
+    pub fn ip_changed(&self, client_ip: &str) -> bool {
+        self.client_ip != client_ip
+    }
+}
+
+/// Why a reconnect token was refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReconnectRejection {
+    /// Unknown, already used or expired
+    Invalid,
+    /// Presented from another IP under `IpChangePolicy::Reject`; carries the refused grant
+    IpChanged(ReconnectGrant),
+}
+
+/// Short-lived, single-use, IP-bound tokens that let a dropped client skip signature auth
+#[derive(Clone)]
+pub struct ReconnectTokens {
+    grace: Duration,
+    ip_change_policy: IpChangePolicy,
+    grants: Arc<Mutex<HashMap<String, ReconnectGrant>>>,
+}
+
+impl ReconnectTokens {
+    /// Create a token store accepting tokens for `grace` after issue (zero disables)
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            ip_change_policy: IpChangePolicy::default(),
+            grants: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Set what happens when a token is redeemed from another IP
+    pub fn with_ip_change_policy(mut self, policy: IpChangePolicy) -> Self {
+        self.ip_change_policy = policy;
+        self
+    }
+
+    /// Whether reconnect tokens are issued at all
+    pub fn is_enabled(&self) -> bool {
+        !self.grace.is_zero()
+    }
+
+    /// Issue a token for an authenticated session, or `None` when disabled
+    pub fn issue(
+        &self,
+        user_id: i64,
+        public_key: Option<String>,
+        client_ip: &str,
+        now: Instant,
+    ) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let mut grants = match self.grants.lock() {
+            Ok(grants) => grants,
+            Err(e) => {
+                error!("Failed to lock reconnect tokens: {}", e);
+                return None;
+            }
+        };
+        grants.retain(|_, grant| grant.expires_at > now);
+
+        let token = nanoid!(32);
+        grants.insert(
+            token.clone(),
+            ReconnectGrant {
+                user_id,
+                public_key,
+                client_ip: client_ip.to_owned(),
+                expires_at: now + self.grace,
+            },
+        );
+        Some(token)
+    }
+
+    /// Consume a token; it is valid only once and before expiry.
+    ///
+    /// A token presented from another IP than it was issued to is refused under
+    /// `IpChangePolicy::Reject`; under `Flag` it is accepted and callers should check
+    /// `ReconnectGrant::ip_changed`.
+    pub fn redeem(
+        &self,
+        token: &str,
+        client_ip: &str,
+        now: Instant,
+    ) -> Result<ReconnectGrant, ReconnectRejection> {
+        let grant = match self.grants.lock() {
+            Ok(mut grants) => grants.remove(token).ok_or(ReconnectRejection::Invalid)?,
+            Err(e) => {
+                error!("Failed to lock reconnect tokens: {}", e);
+                return Err(ReconnectRejection::Invalid);
+            }
+        };
+
+        if grant.expires_at <= now {
+            return Err(ReconnectRejection::Invalid);
+        }
+        if grant.ip_changed(client_ip) && self.ip_change_policy == IpChangePolicy::Reject {
+            return Err(ReconnectRejection::IpChanged(grant));
+        }
+        Ok(grant)
+    }
+
+    /// Drop every outstanding token of a user, returning how many were removed
+    pub fn revoke_user(&self, user_id: i64) -> usize {
+        match self.grants.lock() {
+            Ok(mut grants) => {
+                let before = grants.len();
+                grants.retain(|_, grant| grant.user_id != user_id);
+                before - grants.len()
+            }
+            Err(e) => {
+                error!("Failed to lock reconnect tokens: {}", e);
+                0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_is_single_use() {
+        let tokens = ReconnectTokens::new(Duration::from_secs(30));
+        let now = Instant::now();
+        let token = tokens.issue(7, None, "10.0.0.1", now).unwrap();
+
+        let grant = tokens.redeem(&token, "10.0.0.1", now).unwrap();
+        assert_eq!(grant.user_id, 7);
+        assert_eq!(tokens.redeem(&token, "10.0.0.1", now), Err(ReconnectRejection::Invalid));
+    }
+
+    #[test]
+    fn test_token_bound_to_ip_and_grace_window() {
+        let tokens = ReconnectTokens::new(Duration::from_secs(30));
+        let now = Instant::now();
+
+        let token = tokens.issue(7, None, "10.0.0.1", now).unwrap();
+        assert!(matches!(
+            tokens.redeem(&token, "10.0.0.2", now),
+            Err(ReconnectRejection::IpChanged(ref grant)) if grant.client_ip == "10.0.0.1"
+        ));
+        // A failed attempt still burns the token
+        assert_eq!(tokens.redeem(&token, "10.0.0.1", now), Err(ReconnectRejection::Invalid));
+
+        let token = tokens.issue(7, None, "10.0.0.1", now).unwrap();
+        assert_eq!(
+            tokens.redeem(&token, "10.0.0.1", now + Duration::from_secs(31)),
+            Err(ReconnectRejection::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_flag_policy_accepts_token_from_another_ip() {
+        let tokens = ReconnectTokens::new(Duration::from_secs(30))
+            .with_ip_change_policy(IpChangePolicy::Flag);
+        let now = Instant::now();
+
+        let token = tokens.issue(7, None, "10.0.0.1", now).unwrap();
+        let grant = tokens.redeem(&token, "10.0.0.2", now).unwrap();
+        assert!(grant.ip_changed("10.0.0.2"));
+        assert_eq!("flag".parse(), Ok(IpChangePolicy::Flag));
+        assert!("warn".parse::<IpChangePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_zero_grace_disables_tokens() {
+        let tokens = ReconnectTokens::new(Duration::ZERO);
+        assert!(tokens.issue(7, None, "10.0.0.1", Instant::now()).is_none());
+    }
+}
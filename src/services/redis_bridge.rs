@@ -0,0 +1,183 @@
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::{debug, error, info, warn};
+
+use crate::errors::DashboardResult;
+use crate::services::broadcast::{BroadcastHub, RelayedBroadcast};
+
+/// Wait before reconnecting after the Redis connection drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Message on the shared Redis channel
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    /// Instance that published the broadcast; it has already delivered it locally
+    origin: String,
+    broadcast: RelayedBroadcast,
+}
+
+/// Whether a bridge is currently subscribed to its Redis channel, as reported by `/health`
+#[derive(Clone, Default)]
+pub struct RedisLinkStatus {
+    subscribed: Arc<AtomicBool>,
+}
+
+impl RedisLinkStatus {
+    /// Whether broadcasts from other instances are currently being received
+    pub fn is_subscribed(&self) -> bool {
+        self.subscribed.load(Ordering::Acquire)
+    }
+
+    fn set_subscribed(&self, subscribed: bool) {
+        self.subscribed.store(subscribed, Ordering::Release);
+    }
+}
+
+/// Relays user broadcasts between instances over Redis pub/sub, so an event published on one
+/// instance reaches the user's sockets on every instance
+pub struct RedisBroadcastBridge {
+    client: redis::Client,
+    channel: String,
+    instance_id: String,
+    status: RedisLinkStatus,
+}
+
+impl RedisBroadcastBridge {
+    /// Create a bridge publishing and subscribing on `channel` at `url`; no connection is made
+    /// until `spawn`
+    pub fn new(url: &str, channel: impl Into<String>, instance_id: impl Into<String>) -> DashboardResult<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            channel: channel.into(),
+            instance_id: instance_id.into(),
+            status: RedisLinkStatus::default(),
+        })
+    }
+
+    /// Handle on the subscription state, which stays valid after `spawn`
+    pub fn status(&self) -> RedisLinkStatus {
+        self.status.clone()
+    }
+
+    /// Publish the hub's outbound broadcasts and deliver other instances' broadcasts to it.
+    ///
+    /// Both directions reconnect on their own after Redis becomes unreachable; broadcasts
+    /// published while it is down reach this instance's sessions only.
+    pub fn spawn(self, hub: BroadcastHub, outbound: UnboundedReceiver<RelayedBroadcast>) {
+        let bridge = Arc::new(self);
+        actix_rt::spawn(bridge.clone().publish_loop(outbound));
+        actix_rt::spawn(bridge.subscribe_loop(hub));
+    }
+
+    async fn publish_loop(self: Arc<Self>, mut outbound: UnboundedReceiver<RelayedBroadcast>) {
+        let mut connection = loop {
+            match self.client.get_connection_manager().await {
+                Ok(connection) => break connection,
+                Err(e) => {
+                    error!("Failed to connect to Redis for broadcast publishing: {}", e);
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        };
+
+        while let Some(broadcast) = outbound.recv().await {
+            let payload = match self.encode(broadcast) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    error!("Failed to serialize relayed broadcast: {}", e);
+                    continue;
+                }
+            };
+            let published: redis::RedisResult<i64> = redis::cmd("PUBLISH")
+                .arg(&self.channel)
+                .arg(payload)
+                .query_async(&mut connection)
+                .await;
+            if let Err(e) = published {
+                warn!("Failed to relay broadcast to other instances: {}", e);
+            }
+        }
+    }
+
+    async fn subscribe_loop(self: Arc<Self>, hub: BroadcastHub) {
+        loop {
+            match self.client.get_tokio_connection().await {
+                Ok(connection) => {
+                    let mut pubsub = connection.into_pubsub();
+                    match pubsub.subscribe(&self.channel).await {
+                        Ok(()) => {
+                            info!("Receiving broadcasts from other instances on Redis channel {}", self.channel);
+                            self.status.set_subscribed(true);
+                            let mut messages = pubsub.on_message();
+                            while let Some(message) = messages.next().await {
+                                match message.get_payload::<String>() {
+                                    Ok(payload) => {
+                                        if let Some(broadcast) = self.decode(&payload) {
+                                            hub.deliver_relayed(broadcast);
+                                        }
+                                    }
+                                    Err(e) => warn!("Ignoring unreadable relayed broadcast: {}", e),
+                                }
+                            }
+                            self.status.set_subscribed(false);
+                            warn!("Redis broadcast subscription ended, reconnecting");
+                        }
+                        Err(e) => error!("Failed to subscribe to Redis channel {}: {}", self.channel, e),
+                    }
+                }
+                Err(e) => error!("Failed to connect to Redis for broadcast delivery: {}", e),
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// Wrap a broadcast with this instance as its origin
+    fn encode(&self, broadcast: RelayedBroadcast) -> serde_json::Result<String> {
+        serde_json::to_string(&Envelope {
+            origin: self.instance_id.clone(),
+            broadcast,
+        })
+    }
+
+    /// Unwrap a broadcast from another instance; our own and malformed messages yield `None`
+    fn decode(&self, payload: &str) -> Option<RelayedBroadcast> {
+        match serde_json::from_str::<Envelope>(payload) {
+            Ok(envelope) if envelope.origin == self.instance_id => None,
+            Ok(envelope) => {
+                debug!("Relayed broadcast from instance {}", envelope.origin);
+                Some(envelope.broadcast)
+            }
+            Err(e) => {
+                warn!("Ignoring malformed relayed broadcast: {}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bridge(instance_id: &str) -> RedisBroadcastBridge {
+        RedisBroadcastBridge::new("redis://127.0.0.1/", "dashboard:broadcast", instance_id).unwrap()
+    }
+
+    #[test]
+    fn test_broadcasts_from_other_instances_decode_and_own_are_skipped() {
+        let (a, b) = (bridge("instance-a"), bridge("instance-b"));
+        let broadcast = RelayedBroadcast::Disconnect {
+            user_id: 7,
+            reason: "Account suspended".to_string(),
+        };
+
+        let payload = a.encode(broadcast.clone()).unwrap();
+        assert_eq!(b.decode(&payload), Some(broadcast));
+        assert_eq!(a.decode(&payload), None);
+        assert_eq!(b.decode("not json"), None);
+    }
+}
@@ -0,0 +1,25 @@
+use async_trait::async_trait;
+
+use crate::errors::DashboardResult;
+
+/// Hook run after a WebSocket session becomes authenticated.
+///
+/// Deployments use it for custom logic such as provisioning a connection row or notifying an
+/// external system. It runs off the message path; errors are logged, and close the session
+/// only when `WS_CLOSE_ON_AUTH_HOOK_ERROR` is set.
+#[async_trait]
+pub trait OnAuthenticated: Send + Sync {
+    /// Called once per successful authentication, including ticket and reconnect resumes
+    async fn on_authenticated(&self, user_id: i64, client_ip: &str) -> DashboardResult<()>;
+}
+
+/// Default hook that does nothing
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopOnAuthenticated;
+
+#[async_trait]
+impl OnAuthenticated for NoopOnAuthenticated {
+    async fn on_authenticated(&self, _: i64, _: &str) -> DashboardResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use crate::errors::{DashboardError, DashboardResult};
+use crate::ids::{nanoid_generator, unique_id, IdGenerator};
+use crate::models::websocket::WebSocketConnectionInfo;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::error;
+
+/// Registry of live WebSocket sessions on this server instance
+#[derive(Clone)]
+pub struct SessionRegistry {
+    sessions: Arc<Mutex<HashMap<String, WebSocketConnectionInfo>>>,
+    ids: IdGenerator,
+}
+
+impl Default for SessionRegistry {
+    fn default() -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            ids: nanoid_generator(),
+        }
+    }
+}
+
+impl SessionRegistry {
+    /// Create a new empty session registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draw replacement session ids from `generator` instead of random nanoids
+    pub fn with_id_generator(mut self, generator: IdGenerator) -> Self {
+        self.ids = generator;
+        self
+    }
+
+    /// Register a newly established session, returning the id it is registered under.
+    ///
+    /// A session whose id is already taken is given a fresh one rather than replacing the
+    /// existing entry; the caller must adopt the returned id.
+    pub fn register(&self, mut info: WebSocketConnectionInfo) -> DashboardResult<String> {
+        let mut sessions =
+            self.sessions.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        if sessions.contains_key(&info.session_id) {
+            info.session_id = unique_id(&self.ids, "WebSocket session", |id| sessions.contains_key(id))?;
+        }
+        let session_id = info.session_id.clone();
+        sessions.insert(session_id.clone(), info);
+        Ok(session_id)
+    }
+
+    /// Apply an update to a registered session
+    pub fn update<F>(&self, session_id: &str, update: F)
+    where
+        F: FnOnce(&mut WebSocketConnectionInfo),
+    {
+        match self.sessions.lock() {
+            Ok(mut sessions) => {
+                if let Some(info) = sessions.get_mut(session_id) {
+                    update(info);
+                }
+            }
+            Err(e) => error!("Failed to lock session registry: {}", e),
+        }
+    }
+
+    /// Remove a session from the registry
+    pub fn unregister(&self, session_id: &str) {
+        match self.sessions.lock() {
+            Ok(mut sessions) => {
+                sessions.remove(session_id);
+            }
+            Err(e) => error!("Failed to lock session registry: {}", e),
+        }
+    }
+
+    /// List all registered sessions
+    pub fn list(&self) -> Vec<WebSocketConnectionInfo> {
+        match self.sessions.lock() {
+            Ok(sessions) => sessions.values().cloned().collect(),
+            Err(e) => {
+                error!("Failed to lock session registry: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Number of registered sessions
+    pub fn count(&self) -> usize {
+        self.sessions.lock().map(|sessions| sessions.len()).unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn info(session_id: &str) -> WebSocketConnectionInfo {
+        WebSocketConnectionInfo {
+            session_id: session_id.to_string(),
+            user_id: None,
+            client_ip: "10.0.0.1".to_string(),
+            created_at: Utc::now(),
+            last_active: Utc::now(),
+            authenticated: false,
+            bytes_received: 0,
+            bytes_sent: 0,
+            last_transport_activity: Utc::now(),
+            last_app_heartbeat: None,
+        }
+    }
+
+    #[test]
+    fn test_colliding_session_gets_a_fresh_id_instead_of_replacing() {
+        let registry = SessionRegistry::new().with_id_generator(Arc::new(|| "fresh".to_string()));
+
+        assert_eq!(registry.register(info("dup")).unwrap(), "dup");
+        assert_eq!(registry.register(info("dup")).unwrap(), "fresh");
+        assert_eq!(registry.count(), 2);
+
+        // No free id left to hand out
+        assert!(registry.register(info("dup")).is_err());
+        assert_eq!(registry.count(), 2);
+    }
+}
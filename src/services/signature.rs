@@ -1,116 +1,426 @@
+use crate::cache::BoundedCache;
 use crate::errors::{DashboardError, DashboardResult};
-use crate::models::websocket::WebSocketAuthMessage;
+use crate::models::user::User;
+use crate::models::websocket::{TimestampWindow, WebSocketAuthMessage};
+use crate::services::auth_challenge::AuthChallenges;
+use crate::services::key_deny_list::KeyDenyList;
 use crate::storage::UserStorage;
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use hex;
-use std::sync::Arc;
+use chrono::Utc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
 use tracing::{debug, error, info, warn};
 
-/// Service for handling ed25519 signature verification
+/// Service for handling ed25519 signature verification.
+///
+/// Result contract shared by every method:
+/// - `Ok(Some(_))` / `Ok(true)`: the operation succeeded
+/// - `Ok(None)` / `Ok(false)`: the input was well-formed and cryptographically valid, but
+///   nothing matched (unknown key, nothing to revoke)
+/// - `Err(Validation)`: the input was malformed
+/// - `Err(Authentication)`: the signature did not verify
+/// - any other `Err`: storage or internal failure
 pub struct SignatureService<T: UserStorage> {
     user_storage: Arc<T>,
-    // Optionally add caching for frequently used public keys
+    /// Bounds verifications running on the blocking pool; `None` verifies inline
+    verification_permits: Option<Arc<Semaphore>>,
+    /// Recently accepted `public_key:nonce` pairs, for replay rejection
+    nonce_cache: Mutex<BoundedCache<String, i64>>,
+    /// Keys rejected for every user, checked before the storage lookup
+    deny_list: KeyDenyList,
+    /// Accepted age and future skew of auth message timestamps
+    timestamp_window: TimestampWindow,
+    /// How long a revoked key keeps authenticating its owner; zero rejects it at once
+    revoked_key_grace: Duration,
+    /// Decoded verifying keys of recently authenticated public keys, by hex.
+    ///
+    /// Only the curve point is cached, never ownership or revocation, so entries can't go stale.
+    key_cache: Mutex<BoundedCache<String, VerifyingKey>>,
+    /// When set, only nonces issued by the challenge endpoint are accepted
+    server_challenges: Option<AuthChallenges>,
 }
 
+/// A successful WebSocket signature authentication
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyAuthentication {
+    /// User the key belongs to
+    pub user_id: i64,
+    /// The key was revoked within the grace period; the client should re-sign with a current key
+    pub revoked_in_grace: bool,
+}
+
+/// Default maximum number of remembered nonces
+pub const DEFAULT_NONCE_CACHE_CAPACITY: usize = 100_000;
+
+/// Default maximum number of decoded public keys kept
+pub const DEFAULT_KEY_CACHE_CAPACITY: usize = 10_000;
+
+/// Users listed per storage call while warming the key cache
+const WARMUP_PAGE_SIZE: usize = 500;
+
 impl<T: UserStorage> SignatureService<T> {
     /// Create a new SignatureService with the given user storage
     pub fn new(user_storage: Arc<T>) -> Self {
-        Self { user_storage }
+        Self {
+            user_storage,
+            verification_permits: None,
+            nonce_cache: Mutex::new(BoundedCache::new(DEFAULT_NONCE_CACHE_CAPACITY)),
+            deny_list: KeyDenyList::default(),
+            timestamp_window: TimestampWindow::default(),
+            revoked_key_grace: Duration::ZERO,
+            key_cache: Mutex::new(BoundedCache::new(DEFAULT_KEY_CACHE_CAPACITY)),
+            server_challenges: None,
+        }
+    }
+
+    /// Accept only auth messages whose nonce was issued by `challenges` and not yet used,
+    /// turning signature auth into a challenge-response
+    pub fn with_required_challenges(mut self, challenges: AuthChallenges) -> Self {
+        self.server_challenges = Some(challenges);
+        self
+    }
+
+    /// Keep up to `capacity` decoded public keys, so repeat authentications skip decoding
+    pub fn with_key_cache_capacity(mut self, capacity: usize) -> Self {
+        self.key_cache = Mutex::new(BoundedCache::new(capacity));
+        self
+    }
+
+    /// Keep accepting a key for `grace` after it is revoked, so clients that are mid-rotation
+    /// can still connect and are asked to re-sign with their current key
+    pub fn with_revoked_key_grace(mut self, grace: Duration) -> Self {
+        self.revoked_key_grace = grace;
+        self
+    }
+
+    /// Accept auth messages signed up to `max_age_secs` ago, or up to `max_future_secs` ahead
+    /// of the server clock.
+    ///
+    /// The nonce cache only needs to remember nonces for this long, so keep its capacity
+    /// large enough for the auth rate over the whole window.
+    pub fn with_timestamp_window(mut self, max_age_secs: i64, max_future_secs: i64) -> Self {
+        self.timestamp_window = TimestampWindow {
+            max_age_secs,
+            max_future_secs,
+        };
+        self
+    }
+
+    /// Reject the keys in `deny_list` for authentication and registration.
+    ///
+    /// The list is shared, so reloading it elsewhere takes effect immediately.
+    pub fn with_key_deny_list(mut self, deny_list: KeyDenyList) -> Self {
+        self.deny_list = deny_list;
+        self
+    }
+
+    /// Bound the nonce replay cache to `capacity` entries.
+    ///
+    /// When full the least recently seen nonce is forgotten; those are the oldest and fall
+    /// outside the message validation window first.
+    pub fn with_nonce_cache_capacity(mut self, capacity: usize) -> Self {
+        self.nonce_cache = Mutex::new(BoundedCache::new(capacity));
+        self
+    }
+
+    /// Whether `public_key` is on the deny-list this service rejects before verifying
+    pub fn denies_key(&self, public_key: &str) -> bool {
+        self.deny_list.contains(public_key)
+    }
+
+    /// User storage backing key lookups
+    pub fn user_storage(&self) -> Arc<T> {
+        self.user_storage.clone()
+    }
+
+    /// Number of nonces currently remembered
+    pub fn nonce_cache_len(&self) -> usize {
+        self.nonce_cache.lock().map(|cache| cache.len()).unwrap_or(0)
+    }
+
+    /// Number of decoded public keys currently cached
+    pub fn key_cache_len(&self) -> usize {
+        self.key_cache.lock().map(|cache| cache.len()).unwrap_or(0)
+    }
+
+    /// Preload the key cache with the registered keys of the most recently active users, up to
+    /// its capacity, so the first auths after a deploy don't pay for decoding. Returns how
+    /// many keys were loaded; keys that fail to decode are skipped.
+    pub async fn warm_key_cache(&self) -> DashboardResult<usize> {
+        let capacity = self
+            .key_cache
+            .lock()
+            .map_err(|e| DashboardError::internal_server(e.to_string()))?
+            .capacity();
+
+        let mut users = Vec::new();
+        let mut after_id = None;
+        loop {
+            let page = self.user_storage.list_users(after_id, WARMUP_PAGE_SIZE, None).await?;
+            after_id = page.last().map(|user| user.id);
+            let done = page.len() < WARMUP_PAGE_SIZE;
+            users.extend(page.into_iter().filter(|user| !user.status.is_suspended()));
+            if done {
+                break;
+            }
+        }
+        users.sort_by(|a, b| b.last_active.cmp(&a.last_active));
+
+        // Least recently active first, so the most active end up most recently used
+        let mut keys = Vec::new();
+        'users: for user in users {
+            if keys.len() >= capacity {
+                break;
+            }
+            for public_key in self.user_storage.get_public_keys_for_user(user.id).await? {
+                if keys.len() >= capacity {
+                    break 'users;
+                }
+                if let Ok(verifying_key) = decode_verifying_key(&public_key) {
+                    keys.push((public_key, verifying_key));
+                }
+            }
+        }
+        let warmed = keys.len();
+        let mut cache = self
+            .key_cache
+            .lock()
+            .map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        for (public_key, verifying_key) in keys.into_iter().rev() {
+            cache.insert(public_key, verifying_key);
+        }
+        Ok(warmed)
+    }
+
+    /// Decoded key for `public_key_hex`, from the cache when present
+    fn verifying_key(&self, public_key_hex: &str) -> DashboardResult<(VerifyingKey, bool)> {
+        let cached = self
+            .key_cache
+            .lock()
+            .map_err(|e| DashboardError::internal_server(e.to_string()))?
+            .get_mut(&public_key_hex.to_string())
+            .copied();
+        match cached {
+            Some(verifying_key) => Ok((verifying_key, true)),
+            None => Ok((decode_verifying_key(public_key_hex)?, false)),
+        }
+    }
+
+    /// Cache a key once it has produced a valid signature, so junk keys can't crowd it out
+    fn cache_verifying_key(&self, public_key_hex: String, verifying_key: VerifyingKey) {
+        match self.key_cache.lock() {
+            Ok(mut cache) => cache.insert(public_key_hex, verifying_key),
+            Err(e) => error!("Failed to lock key cache: {}", e),
+        }
+    }
+
+    /// Remember a nonce, failing if the same key already used it
+    fn record_nonce(&self, auth_msg: &WebSocketAuthMessage) -> DashboardResult<()> {
+        let mut cache = self
+            .nonce_cache
+            .lock()
+            .map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        let key = format!("{}:{}", auth_msg.public_key, auth_msg.nonce);
+        if cache.contains(&key) {
+            warn!("Replayed nonce from {}", auth_msg.public_key);
+            return Err(DashboardError::authentication("Nonce has already been used"));
+        }
+        cache.insert(key, auth_msg.timestamp);
+        Ok(())
+    }
+
+    /// Run signature checks on the blocking thread pool, at most `max_concurrent` at a time.
+    ///
+    /// A single ed25519 verification takes tens of microseconds, so inline verification
+    /// only hurts once auth bursts keep an async worker busy for milliseconds at a time.
+    /// Zero keeps verification inline on the async worker.
+    pub fn with_blocking_verification(mut self, max_concurrent: usize) -> Self {
+        self.verification_permits = if max_concurrent > 0 {
+            Some(Arc::new(Semaphore::new(max_concurrent)))
+        } else {
+            None
+        };
+        self
     }
 
-    /// Verify a WebSocket authentication message
+    /// Verify a WebSocket authentication message.
+    ///
+    /// Returns `Ok(Some(user_id))` on success, `Ok(None)` for a valid signature from a key
+    /// no user owns, and `Err` for malformed messages or bad signatures.
     pub async fn verify_websocket_auth(
         &self,
         auth_msg: &WebSocketAuthMessage,
     ) -> DashboardResult<Option<i64>> {
+        Ok(self
+            .authenticate_websocket(auth_msg)
+            .await?
+            .map(|authentication| authentication.user_id))
+    }
+
+    /// Verify a WebSocket authentication message like `verify_websocket_auth`, also reporting
+    /// whether a recently revoked key was accepted under the grace period
+    pub async fn authenticate_websocket(
+        &self,
+        auth_msg: &WebSocketAuthMessage,
+    ) -> DashboardResult<Option<KeyAuthentication>> {
         // Validate message structure
-        if let Err(validation_error) = auth_msg.validate() {
+        if let Err(validation_error) = auth_msg.validate_with(&self.timestamp_window) {
             return Err(DashboardError::validation(validation_error));
         }
 
+        if self.deny_list.contains(&auth_msg.public_key) {
+            warn!("Rejected globally revoked public key: {}", auth_msg.public_key);
+            return Err(DashboardError::authentication("key revoked globally"));
+        }
+
         // Verify the signature
-        let verified = self.verify_signature(
-            &auth_msg.public_key,
-            &auth_msg.get_signed_message(),
-            &auth_msg.signature,
-        )?;
+        let verified = self
+            .verify_signature_offloaded(
+                auth_msg.public_key.clone(),
+                auth_msg.get_signed_message(),
+                auth_msg.signature.clone(),
+            )
+            .await?;
 
         if !verified {
             return Err(DashboardError::authentication("Invalid signature"));
         }
 
-        // Find user by public key
-        let user = self.user_storage.find_user_by_public_key(&auth_msg.public_key).await?;
+        // Only genuine signatures consume a nonce or challenge, so forgeries can't burn someone else's
+        if let Some(challenges) = &self.server_challenges {
+            if !challenges.redeem(&auth_msg.nonce, Instant::now()) {
+                warn!("Rejected auth with an unissued or expired challenge from {}", auth_msg.public_key);
+                return Err(DashboardError::authentication("Unknown or expired challenge"));
+            }
+        }
+        self.record_nonce(auth_msg)?;
 
-        if let Some(user) = user {
-            // Update last used timestamp
-            self.user_storage
-                .update_public_key_last_used(user.id, &auth_msg.public_key)
-                .await?;
+        // Find user by public key; revoked keys match nobody unless still within the grace
+        // period, and otherwise fall through to `Ok(None)`
+        let (user, revoked_in_grace) = match self.user_storage.find_user_by_public_key(&auth_msg.public_key).await? {
+            Some(user) => (Some(user), false),
+            None => {
+                let user = self.recently_revoked_key_owner(&auth_msg.public_key).await?;
+                let revoked_in_grace = user.is_some();
+                (user, revoked_in_grace)
+            }
+        };
 
+        // Account status and other admission checks are left to the caller's `ConnectionGates`, and
+        // the key's last-used time is left to `record_key_used` once the gates admit the session
+        if let Some(user) = user {
             info!("User {} authenticated via WebSocket", user.id);
-            Ok(Some(user.id))
+            Ok(Some(KeyAuthentication {
+                user_id: user.id,
+                revoked_in_grace,
+            }))
         } else {
             warn!("Valid signature but unknown public key: {}", auth_msg.public_key);
             Ok(None)
         }
     }
 
-    /// Verify an ed25519 signature against a message and public key
-    pub fn verify_signature(
-        &self,
-        public_key_hex: &str,
-        message: &str,
-        signature_hex: &str,
-    ) -> DashboardResult<bool> {
-        // Decode public key
-        let public_key_bytes = hex::decode(public_key_hex)
-            .map_err(|e| DashboardError::validation(format!("Invalid public key format: {}", e)))?;
-
-        if public_key_bytes.len() != 32 {
-            return Err(DashboardError::validation(format!(
-                "Public key must be 32 bytes, got {} bytes",
-                public_key_bytes.len()
-            )));
-        }
-
-        let verifying_key = VerifyingKey::from_bytes(
-            &public_key_bytes
-                .as_slice()
-                .try_into()
-                .expect("slice with incorrect length"),
-        )
-        .map_err(|e| DashboardError::validation(format!("Invalid public key: {}", e)))?;
+    /// Stamp `public_key` as just used by `user_id`, for a session that has been admitted
+    pub async fn record_key_used(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
+        self.user_storage.update_public_key_last_used(user_id, public_key).await
+    }
 
-        // Decode signature
-        let signature_bytes = hex::decode(signature_hex)
-            .map_err(|e| DashboardError::validation(format!("Invalid signature format: {}", e)))?;
+    /// Owner of `public_key` if it was revoked no longer than the grace period ago
+    async fn recently_revoked_key_owner(&self, public_key: &str) -> DashboardResult<Option<User>> {
+        if self.revoked_key_grace.is_zero() {
+            return Ok(None);
+        }
+        let (user_id, revoked_at) = match self.user_storage.find_public_key_info(public_key).await? {
+            Some(info) if info.revoked => match info.revoked_at {
+                Some(revoked_at) => (info.user_id, revoked_at),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
 
-        if signature_bytes.len() != 64 {
-            return Err(DashboardError::validation(format!(
-                "Signature must be 64 bytes, got {} bytes",
-                signature_bytes.len()
-            )));
+        // A revocation stamped in the future (clock skew) counts as just now
+        let revoked_for = Utc::now().signed_duration_since(revoked_at).to_std().unwrap_or_default();
+        if revoked_for > self.revoked_key_grace {
+            warn!("Rejected public key revoked {}s ago: {}", revoked_for.as_secs(), public_key);
+            return Ok(None);
         }
 
-        // Handle the signature creation - ed25519 2.0 has changed how signatures work
-        let signature_array: [u8; 64] = signature_bytes.as_slice().try_into()
-            .map_err(|_| DashboardError::validation("Invalid signature length".to_string()))?;
-        let signature = Signature::from_bytes(&signature_array);
+        warn!(
+            "Accepting public key revoked {}s ago for user {} within the grace period: {}",
+            revoked_for.as_secs(),
+            user_id,
+            public_key
+        );
+        self.user_storage.find_user_by_id(user_id).await
+    }
 
-        match verifying_key.verify(message.as_bytes(), &signature) {
-            Ok(_) => {
-                debug!("Valid signature from {}", public_key_hex);
-                Ok(true)
-            }
-            Err(e) => {
-                debug!("Invalid signature from {}: {}", public_key_hex, e);
-                Ok(false)
+    /// Verify several authentication messages independently.
+    ///
+    /// Each entry follows the `verify_websocket_auth` contract, so an unknown key (`Ok(None)`)
+    /// stays distinguishable from a bad signature (`Err`) per message, and one failure does
+    /// not affect the others.
+    pub async fn verify_websocket_auth_batch(
+        &self,
+        auth_msgs: &[WebSocketAuthMessage],
+    ) -> Vec<DashboardResult<Option<i64>>> {
+        let mut results = Vec::with_capacity(auth_msgs.len());
+        for auth_msg in auth_msgs {
+            results.push(self.verify_websocket_auth(auth_msg).await);
+        }
+        results
+    }
+
+    /// Verify a signature on the blocking pool when configured, otherwise inline
+    async fn verify_signature_offloaded(
+        &self,
+        public_key_hex: String,
+        message: String,
+        signature_hex: String,
+    ) -> DashboardResult<bool> {
+        let (verifying_key, cached) = self.verifying_key(&public_key_hex)?;
+        let verified = match &self.verification_permits {
+            Some(permits) => {
+                let _permit = permits
+                    .acquire()
+                    .await
+                    .map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+                tokio::task::spawn_blocking(move || verify_with_key(&verifying_key, &message, &signature_hex))
+                    .await
+                    .map_err(|e| {
+                        error!("Signature verification task failed: {}", e);
+                        DashboardError::internal_server("Signature verification failed")
+                    })??
             }
+            None => verify_with_key(&verifying_key, &message, &signature_hex)?,
+        };
+
+        if verified && !cached {
+            self.cache_verifying_key(public_key_hex, verifying_key);
         }
+        Ok(verified)
+    }
+
+    /// Verify an ed25519 signature against a message and public key.
+    ///
+    /// Returns `Ok(false)` for a well-formed signature that does not match, and
+    /// `Err(Validation)` when the key or signature cannot be decoded.
+    pub fn verify_signature(
+        &self,
+        public_key_hex: &str,
+        message: &str,
+        signature_hex: &str,
+    ) -> DashboardResult<bool> {
+        verify_ed25519(public_key_hex, message, signature_hex)
     }
 
-    /// Register a new public key for a user
+    /// Register a new public key for a user.
+    ///
+    /// Re-registering a key the user already owns is `Ok(())`; a malformed key, a globally
+    /// denied key, or one owned by another user is `Err(Validation)`.
     pub async fn register_public_key(
         &self,
         user_id: i64,
@@ -119,12 +429,17 @@ impl<T: UserStorage> SignatureService<T> {
         if public_key.len() != 64 || !public_key.chars().all(|c| c.is_ascii_hexdigit()) {
             return Err(DashboardError::validation("Invalid public key format"));
         }
+        if self.deny_list.contains(public_key) {
+            return Err(DashboardError::validation("Public key has been revoked globally"));
+        }
         self.user_storage.store_public_key(user_id, public_key).await?;
         info!("Registered new public key for user {}", user_id);
         Ok(())
     }
 
-    /// Revoke a public key for a user
+    /// Revoke a public key for a user.
+    ///
+    /// Returns `Ok(false)` when the user has no such key, mirroring `Ok(None)` elsewhere.
     pub async fn revoke_public_key(
         &self,
         user_id: i64,
@@ -148,36 +463,465 @@ impl<T: UserStorage> SignatureService<T> {
     }
 }
 
+/// Verify an ed25519 signature over `message` with a hex-encoded public key and signature
+fn verify_ed25519(public_key_hex: &str, message: &str, signature_hex: &str) -> DashboardResult<bool> {
+    verify_with_key(&decode_verifying_key(public_key_hex)?, message, signature_hex)
+}
+
+/// Decode a hex-encoded ed25519 public key
+fn decode_verifying_key(public_key_hex: &str) -> DashboardResult<VerifyingKey> {
+    let public_key_bytes = hex::decode(public_key_hex)
+        .map_err(|e| DashboardError::validation(format!("Invalid public key format: {}", e)))?;
+
+    if public_key_bytes.len() != 32 {
+        return Err(DashboardError::validation(format!(
+            "Public key must be 32 bytes, got {} bytes",
+            public_key_bytes.len()
+        )));
+    }
+
+    VerifyingKey::from_bytes(
+        &public_key_bytes
+            .as_slice()
+            .try_into()
+            .expect("slice with incorrect length"),
+    )
+    .map_err(|e| DashboardError::validation(format!("Invalid public key: {}", e)))
+}
+
+/// Verify a hex-encoded ed25519 signature over `message` with a decoded key
+fn verify_with_key(verifying_key: &VerifyingKey, message: &str, signature_hex: &str) -> DashboardResult<bool> {
+    // Decode signature
+    let signature_bytes = hex::decode(signature_hex)
+        .map_err(|e| DashboardError::validation(format!("Invalid signature format: {}", e)))?;
+
+    if signature_bytes.len() != 64 {
+        return Err(DashboardError::validation(format!(
+            "Signature must be 64 bytes, got {} bytes",
+            signature_bytes.len()
+        )));
+    }
+
+    // Handle the signature creation - ed25519 2.0 has changed how signatures work
+    let signature_array: [u8; 64] = signature_bytes.as_slice().try_into()
+        .map_err(|_| DashboardError::validation("Invalid signature length".to_string()))?;
+    let signature = Signature::from_bytes(&signature_array);
+
+    match verifying_key.verify(message.as_bytes(), &signature) {
+        Ok(_) => {
+            debug!("Valid signature from {}", hex::encode(verifying_key.as_bytes()));
+            Ok(true)
+        }
+        Err(e) => {
+            debug!("Invalid signature from {}: {}", hex::encode(verifying_key.as_bytes()), e);
+            Ok(false)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mockall::predicate::*;
-    use mockall::*;
-    use crate::models::User;
-
-    mock! {
-        UserStorage {}
-        #[async_trait::async_trait]
-        impl UserStorage for UserStorage {
-            async fn find_user_by_id(&self, id: i64) -> DashboardResult<Option<User>>;
-            async fn find_user_by_email(&self, email: &str) -> DashboardResult<Option<User>>;
-            async fn create_user(&self, user: crate::models::user::CreateUserDto) -> DashboardResult<User>;
-            async fn update_user(&self, id: i64, update: crate::models::user::UpdateUserDto) -> DashboardResult<User>;
-            async fn delete_user(&self, id: i64) -> DashboardResult<bool>;
-            async fn store_credentials(&self, user_id: i64, password_hash: &str, salt: &str) -> DashboardResult<()>;
-            async fn get_credentials(&self, user_id: i64) -> DashboardResult<Option<crate::models::user::UserCredentials>>;
-            async fn create_session(&self, user_id: i64, ip_address: &str, user_agent: &str, expires_in_seconds: i64) -> DashboardResult<crate::models::user::UserSession>;
-            async fn find_session_by_id(&self, session_id: &str) -> DashboardResult<Option<crate::models::user::UserSession>>;
-            async fn delete_session(&self, session_id: &str) -> DashboardResult<bool>;
-            async fn delete_user_sessions(&self, user_id: i64) -> DashboardResult<i64>;
-            async fn update_last_active(&self, user_id: i64) -> DashboardResult<()>;
-            async fn find_user_by_public_key(&self, public_key: &str) -> DashboardResult<Option<User>>;
-            async fn store_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<()>;
-            async fn revoke_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool>;
-            async fn get_public_keys_for_user(&self, user_id: i64) -> DashboardResult<Vec<String>>;
-            async fn update_public_key_last_used(&self, user_id: i64, public_key: &str) -> DashboardResult<()>;
-        }
-    }
-
-    // TODO: Add unit tests for the SignatureService
-} 
\ No newline at end of file
+    use crate::models::websocket::{canonical_json, SigningMode};
+    use crate::storage::memory::InMemoryUserStorage;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn test_signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn test_service() -> SignatureService<InMemoryUserStorage> {
+        SignatureService::new(Arc::new(InMemoryUserStorage::new()))
+    }
+
+    #[test]
+    fn test_canonical_json_ignores_key_order() {
+        let a: serde_json::Value =
+            serde_json::from_str(r#"{"public_key":"ab","timestamp":1,"nonce":"n1"}"#).unwrap();
+        let b: serde_json::Value =
+            serde_json::from_str(r#"{ "nonce": "n1", "timestamp": 1, "public_key": "ab" }"#).unwrap();
+
+        assert_eq!(canonical_json(&a), canonical_json(&b));
+        assert_eq!(canonical_json(&a), r#"{"nonce":"n1","public_key":"ab","timestamp":1}"#);
+    }
+
+    #[test]
+    fn test_canonical_json_signature_verifies_for_reordered_keys() {
+        let signing_key = test_signing_key();
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+
+        let client_json = format!(
+            r#"{{"timestamp":1700000000,"public_key":"{}","nonce":"abcdefgh"}}"#,
+            public_key
+        );
+        let client_value: serde_json::Value = serde_json::from_str(&client_json).unwrap();
+        let signature = hex::encode(signing_key.sign(canonical_json(&client_value).as_bytes()).to_bytes());
+
+        let auth_msg = WebSocketAuthMessage::new(
+            public_key.clone(),
+            1700000000,
+            "abcdefgh".to_string(),
+            signature.clone(),
+        )
+        .with_signing_mode(SigningMode::CanonicalJson);
+
+        let service = test_service();
+        assert!(service
+            .verify_signature(&public_key, &auth_msg.get_signed_message(), &signature)
+            .unwrap());
+
+        // The plain `timestamp:nonce` message must not verify against a JSON signature
+        let plain = auth_msg.clone().with_signing_mode(SigningMode::Plain);
+        assert!(!service
+            .verify_signature(&public_key, &plain.get_signed_message(), &signature)
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_blocking_verification_matches_inline() {
+        let signing_key = test_signing_key();
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+        let signature = hex::encode(signing_key.sign(b"1700000000:abcdefgh").to_bytes());
+
+        let inline = test_service();
+        let offloaded = test_service().with_blocking_verification(2);
+
+        for message in ["1700000000:abcdefgh", "1700000000:tampered"] {
+            let expected = inline
+                .verify_signature_offloaded(public_key.clone(), message.to_string(), signature.clone())
+                .await
+                .unwrap();
+            let actual = offloaded
+                .verify_signature_offloaded(public_key.clone(), message.to_string(), signature.clone())
+                .await
+                .unwrap();
+            assert_eq!(expected, actual);
+        }
+
+        // Malformed input is still a validation error on the blocking path
+        assert!(offloaded
+            .verify_signature_offloaded("zz".to_string(), "m".to_string(), signature.clone())
+            .await
+            .is_err());
+    }
+
+    /// Rough cost of inline vs offloaded verification; run with `--ignored --nocapture`
+    #[tokio::test]
+    #[ignore]
+    async fn bench_inline_vs_blocking_verification() {
+        let signing_key = test_signing_key();
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+        let signature = hex::encode(signing_key.sign(b"1700000000:abcdefgh").to_bytes());
+        let iterations = 2_000;
+
+        for (label, service) in [
+            ("inline", test_service()),
+            ("blocking", test_service().with_blocking_verification(num_cpus::get())),
+        ] {
+            let start = std::time::Instant::now();
+            for _ in 0..iterations {
+                service
+                    .verify_signature_offloaded(
+                        public_key.clone(),
+                        "1700000000:abcdefgh".to_string(),
+                        signature.clone(),
+                    )
+                    .await
+                    .unwrap();
+            }
+            println!("{}: {:?} per verification", label, start.elapsed() / iterations);
+        }
+    }
+
+    fn signed_auth_message(signing_key: &SigningKey) -> WebSocketAuthMessage {
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+        let timestamp = chrono::Utc::now().timestamp();
+        let nonce = "abcdefgh".to_string();
+        let signature = hex::encode(signing_key.sign(format!("{}:{}", timestamp, nonce).as_bytes()).to_bytes());
+        WebSocketAuthMessage::new(public_key, timestamp, nonce, signature)
+    }
+
+    async fn service_with_registered_key(signing_key: &SigningKey) -> (SignatureService<InMemoryUserStorage>, i64) {
+        let storage = Arc::new(InMemoryUserStorage::new());
+        let user = storage
+            .create_user(crate::models::user::CreateUserDto {
+                email: "signer@example.com".to_string(),
+                username: "signer".to_string(),
+                password: "password123".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap();
+        let service = SignatureService::new(storage);
+        service
+            .register_public_key(user.id, &hex::encode(signing_key.verifying_key().to_bytes()))
+            .await
+            .unwrap();
+        (service, user.id)
+    }
+
+    #[tokio::test]
+    async fn test_unknown_key_is_ok_none() {
+        let (service, _) = service_with_registered_key(&test_signing_key()).await;
+        let stranger = SigningKey::from_bytes(&[9u8; 32]);
+
+        let result = service.verify_websocket_auth(&signed_auth_message(&stranger)).await;
+        assert!(matches!(result, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_bad_signature_is_authentication_error() {
+        let signing_key = test_signing_key();
+        let (service, _) = service_with_registered_key(&signing_key).await;
+
+        let mut auth_msg = signed_auth_message(&signing_key);
+        auth_msg.nonce = "tampered".to_string();
+
+        let result = service.verify_websocket_auth(&auth_msg).await;
+        assert!(matches!(result, Err(DashboardError::Authentication(_))));
+    }
+
+    #[tokio::test]
+    async fn test_batch_preserves_outcome_per_message() {
+        let signing_key = test_signing_key();
+        let (service, user_id) = service_with_registered_key(&signing_key).await;
+
+        let valid = signed_auth_message(&signing_key);
+        let unknown = signed_auth_message(&SigningKey::from_bytes(&[9u8; 32]));
+        let mut bad = signed_auth_message(&signing_key);
+        bad.nonce = "tampered".to_string();
+
+        let results = service.verify_websocket_auth_batch(&[valid, unknown, bad]).await;
+        assert!(matches!(results[0], Ok(Some(id)) if id == user_id));
+        assert!(matches!(results[1], Ok(None)));
+        assert!(matches!(results[2], Err(DashboardError::Authentication(_))));
+    }
+
+    #[tokio::test]
+    async fn test_register_and_revoke_follow_contract() {
+        let signing_key = test_signing_key();
+        let (service, user_id) = service_with_registered_key(&signing_key).await;
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+
+        // Idempotent for the owner, validation error for anyone else
+        assert!(service.register_public_key(user_id, &public_key).await.is_ok());
+        assert!(matches!(
+            service.register_public_key(user_id + 1, &public_key).await,
+            Err(DashboardError::Validation(_))
+        ));
+        assert!(matches!(
+            service.register_public_key(user_id, "not-hex").await,
+            Err(DashboardError::Validation(_))
+        ));
+
+        assert!(service.revoke_public_key(user_id, &public_key).await.unwrap());
+        assert!(!service.revoke_public_key(user_id, &public_key).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_key_is_ignored_until_reactivated() {
+        let signing_key = test_signing_key();
+        let (service, user_id) = service_with_registered_key(&signing_key).await;
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+
+        assert!(service.revoke_public_key(user_id, &public_key).await.unwrap());
+        let mut auth_msg = signed_auth_message(&signing_key);
+        assert!(matches!(service.verify_websocket_auth(&auth_msg).await, Ok(None)));
+
+        // The key keeps its registration, so it can be reactivated without re-adding it
+        assert!(service.user_storage.reactivate_public_key(user_id, &public_key).await.unwrap());
+        auth_msg.nonce = "ijklmnop".to_string();
+        auth_msg.signature = hex::encode(signing_key.sign(auth_msg.get_signed_message().as_bytes()).to_bytes());
+        assert!(matches!(
+            service.verify_websocket_auth(&auth_msg).await,
+            Ok(Some(id)) if id == user_id
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_denied_key_fails_even_when_registered() {
+        let signing_key = test_signing_key();
+        let (service, user_id) = service_with_registered_key(&signing_key).await;
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+        let deny_list = KeyDenyList::default();
+        let service = service.with_key_deny_list(deny_list.clone());
+
+        // Denying takes effect on the shared list without rebuilding the service
+        deny_list.replace(vec![public_key.to_uppercase()]);
+        assert!(matches!(
+            service.verify_websocket_auth(&signed_auth_message(&signing_key)).await,
+            Err(DashboardError::Authentication(ref msg)) if msg == "key revoked globally"
+        ));
+        assert!(matches!(
+            service.register_public_key(user_id, &public_key).await,
+            Err(DashboardError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_replayed_nonce_is_rejected() {
+        let signing_key = test_signing_key();
+        let (service, _) = service_with_registered_key(&signing_key).await;
+        let auth_msg = signed_auth_message(&signing_key);
+
+        assert!(service.verify_websocket_auth(&auth_msg).await.is_ok());
+        assert!(matches!(
+            service.verify_websocket_auth(&auth_msg).await,
+            Err(DashboardError::Authentication(ref msg)) if msg.contains("Nonce")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_required_challenge_rejects_client_invented_nonces() {
+        let signing_key = test_signing_key();
+        let resign = |nonce: &str| {
+            let mut auth_msg = signed_auth_message(&signing_key);
+            auth_msg.nonce = nonce.to_string();
+            auth_msg.signature = hex::encode(signing_key.sign(auth_msg.get_signed_message().as_bytes()).to_bytes());
+            auth_msg
+        };
+
+        // Off by default: any fresh nonce is accepted
+        let (service, user_id) = service_with_registered_key(&signing_key).await;
+        assert_eq!(service.verify_websocket_auth(&resign("invented1")).await.unwrap(), Some(user_id));
+
+        let challenges = AuthChallenges::new(Duration::from_secs(60), 16);
+        let service = service.with_required_challenges(challenges.clone());
+        assert!(matches!(
+            service.verify_websocket_auth(&resign("invented2")).await,
+            Err(DashboardError::Authentication(ref msg)) if msg.contains("challenge")
+        ));
+
+        let nonce = challenges.issue(Instant::now()).unwrap();
+        assert_eq!(service.verify_websocket_auth(&resign(&nonce)).await.unwrap(), Some(user_id));
+        // Single-use
+        assert!(matches!(
+            service.verify_websocket_auth(&resign(&nonce)).await,
+            Err(DashboardError::Authentication(ref msg)) if msg.contains("challenge")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_forged_signature_does_not_consume_challenge() {
+        let signing_key = test_signing_key();
+        let challenges = AuthChallenges::new(Duration::from_secs(60), 16);
+        let (service, user_id) = service_with_registered_key(&signing_key).await;
+        let service = service.with_required_challenges(challenges.clone());
+
+        let nonce = challenges.issue(Instant::now()).unwrap();
+        let mut auth_msg = signed_auth_message(&signing_key);
+        auth_msg.nonce = nonce;
+        // Still signed over the old nonce
+        assert!(service.verify_websocket_auth(&auth_msg).await.is_err());
+
+        auth_msg.signature = hex::encode(signing_key.sign(auth_msg.get_signed_message().as_bytes()).to_bytes());
+        assert_eq!(service.verify_websocket_auth(&auth_msg).await.unwrap(), Some(user_id));
+    }
+
+    #[tokio::test]
+    async fn test_nonce_cache_is_bounded() {
+        let signing_key = test_signing_key();
+        let service = test_service().with_nonce_cache_capacity(4);
+
+        for i in 0..20 {
+            let mut auth_msg = signed_auth_message(&signing_key);
+            auth_msg.nonce = format!("nonce-{:04}", i);
+            service.record_nonce(&auth_msg).unwrap();
+        }
+        assert_eq!(service.nonce_cache_len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_key_cache_only_keeps_keys_that_verified() {
+        let signing_key = test_signing_key();
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+        let signature = hex::encode(signing_key.sign(b"1700000000:abcdefgh").to_bytes());
+        let service = test_service();
+
+        let tampered = service
+            .verify_signature_offloaded(public_key.clone(), "1700000000:tampered".to_string(), signature.clone())
+            .await
+            .unwrap();
+        assert!(!tampered);
+        assert_eq!(service.key_cache_len(), 0);
+
+        for _ in 0..2 {
+            assert!(service
+                .verify_signature_offloaded(public_key.clone(), "1700000000:abcdefgh".to_string(), signature.clone())
+                .await
+                .unwrap());
+        }
+        assert_eq!(service.key_cache_len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_warm_key_cache_loads_registered_keys_up_to_capacity() {
+        let signing_key = test_signing_key();
+        let (service, user_id) = service_with_registered_key(&signing_key).await;
+        for seed in 10..13u8 {
+            let extra = SigningKey::from_bytes(&[seed; 32]);
+            service
+                .register_public_key(user_id, &hex::encode(extra.verifying_key().to_bytes()))
+                .await
+                .unwrap();
+        }
+
+        let service = service.with_key_cache_capacity(2);
+        assert_eq!(service.warm_key_cache().await.unwrap(), 2);
+        assert_eq!(service.key_cache_len(), 2);
+
+        // A warmed key still has to produce a valid signature
+        assert!(service.verify_websocket_auth(&signed_auth_message(&signing_key)).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_key_accepted_only_within_grace_period() {
+        let signing_key = test_signing_key();
+        let (service, user_id) = service_with_registered_key(&signing_key).await;
+        let service = service.with_revoked_key_grace(Duration::from_secs(60));
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+        let resigned = |nonce: &str| {
+            let mut auth_msg = signed_auth_message(&signing_key);
+            auth_msg.nonce = nonce.to_string();
+            auth_msg.signature = hex::encode(signing_key.sign(auth_msg.get_signed_message().as_bytes()).to_bytes());
+            auth_msg
+        };
+
+        let authentication = service.authenticate_websocket(&resigned("active00")).await.unwrap().unwrap();
+        assert!(!authentication.revoked_in_grace);
+
+        assert!(service.revoke_public_key(user_id, &public_key).await.unwrap());
+        assert_eq!(
+            service.authenticate_websocket(&resigned("grace000")).await.unwrap(),
+            Some(KeyAuthentication { user_id, revoked_in_grace: true })
+        );
+
+        // Once the grace period has passed the key is refused like any revoked key
+        service
+            .user_storage
+            .get_public_key_info_map()
+            .lock()
+            .unwrap()
+            .get_mut(&public_key)
+            .unwrap()
+            .revoked_at = Some(Utc::now() - chrono::Duration::seconds(61));
+        assert!(matches!(service.verify_websocket_auth(&resigned("expired0")).await, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_key_use_is_recorded_only_when_asked() {
+        let signing_key = test_signing_key();
+        let (service, user_id) = service_with_registered_key(&signing_key).await;
+        let public_key = hex::encode(signing_key.verifying_key().to_bytes());
+        let last_used = |service: &SignatureService<InMemoryUserStorage>| {
+            service.user_storage.get_public_key_info_map().lock().unwrap()[&public_key].last_used
+        };
+
+        // A verified signature alone doesn't count as use; the session may still be refused
+        assert!(service.verify_websocket_auth(&signed_auth_message(&signing_key)).await.unwrap().is_some());
+        assert!(last_used(&service).is_none());
+
+        service.record_key_used(user_id, &public_key).await.unwrap();
+        assert!(last_used(&service).is_some());
+    }
+}
@@ -1,16 +1,31 @@
-use crate::errors::{DashboardError, DashboardResult};
-use crate::models::user::{CreateUserDto, UpdateUserDto, User, UserLoginResponse, UserSession};
-use crate::storage::UserStorage;
+use crate::cache::BoundedCache;
+use crate::errors::{DashboardError, DashboardResult, StorageError};
+use crate::models::user::{
+    AccountDeletion, CreateUserDto, EarningsSummary, NewUserAccount, NotificationPreferences, PublicKeyInfo,
+    PublicKeyRegistrationResult, PublicKeyRegistrationStatus, ImpersonationToken, UpdateUserDto, User, UserDataExport, UserFieldLimits, UserLoginResponse, UserSession,
+    UserStatus,
+};
+use crate::services::broadcast::KNOWN_CHANNELS;
+use crate::services::email_verification::{
+    EmailVerificationTokens, LogVerificationNotifier, VerificationNotifier,
+};
+use crate::services::key_deny_list::KeyDenyList;
+use crate::services::login_risk::{AllowAllPolicy, LoginAttempt, LoginRiskDecision, LoginRiskPolicy};
+use crate::services::maintenance::ReadOnlyMode;
+use crate::services::notification_preferences::NotificationPreferenceCache;
+use crate::storage::{NetworkStorage, UserStorage};
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
     Argon2,
 };
 use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::errors::ErrorKind;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tracing::{error, info};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+use tracing::{error, info, warn};
 
 /// Claims for JWT token
 #[derive(Debug, Serialize, Deserialize)]
@@ -19,10 +34,155 @@ struct Claims {
     sub: String,
     /// Issuer
     iss: String,
+    /// Audience
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aud: Option<String>,
     /// Expiration time
     exp: usize,
     /// Issued at
     iat: usize,
+    /// Roles granted at login, for coarse authorization checks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    roles: Vec<String>,
+    /// Scopes derived from the roles
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    scopes: Vec<String>,
+    /// Admin acting as the subject, on impersonation tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    impersonated_by: Option<i64>,
+    /// Login session the token belongs to, required to refresh it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sid: Option<String>,
+}
+
+/// Identity and grants carried by a verified token
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenClaims {
+    /// Authenticated user ID
+    pub user_id: i64,
+    /// Roles at the time the token was issued
+    pub roles: Vec<String>,
+    /// Scopes at the time the token was issued
+    pub scopes: Vec<String>,
+    /// Admin acting as the user, if this is an impersonation token
+    pub impersonated_by: Option<i64>,
+    /// Login session the token belongs to; impersonation tokens have none
+    pub session_id: Option<String>,
+}
+
+/// Role held by every user
+pub const ROLE_USER: &str = "user";
+
+/// Role held by users listed in `ADMIN_USER_IDS`
+pub const ROLE_ADMIN: &str = "admin";
+
+/// Scopes granted by a role
+fn scopes_for_role(role: &str) -> &'static [&'static str] {
+    match role {
+        ROLE_USER => &["profile:read", "profile:write", "keys:write", "networks:read"],
+        ROLE_ADMIN => &["admin:read", "admin:write"],
+        _ => &[],
+    }
+}
+
+/// Default maximum number of public keys a user may register
+const DEFAULT_MAX_PUBLIC_KEYS: usize = 10;
+
+/// Default issuer for tokens minted by this service
+const DEFAULT_JWT_ISSUER: &str = "dashboard_system";
+
+/// Default maximum number of emails tracked for failed logins
+pub const DEFAULT_FAILED_LOGIN_CACHE_CAPACITY: usize = 10_000;
+
+/// Default failed attempts allowed per email within the window before logins are refused
+pub const DEFAULT_MAX_FAILED_LOGINS: u32 = 5;
+
+/// Default extra attempts at writing a login session after a transient storage failure
+pub const DEFAULT_SESSION_WRITE_RETRIES: u32 = 2;
+
+/// Default minimum time between data exports for one user, in seconds
+pub const DEFAULT_DATA_EXPORT_INTERVAL_SECS: i64 = 60;
+
+/// Maximum number of users tracked for data-export rate limiting and pending account deletions
+const PER_USER_CACHE_CAPACITY: usize = 10_000;
+
+/// How long an account deletion confirmation token stays valid, in minutes
+const ACCOUNT_DELETION_TOKEN_TTL_MINUTES: i64 = 10;
+
+/// Window over which failed logins are counted, in minutes
+const FAILED_LOGIN_WINDOW_MINUTES: i64 = 15;
+
+/// Default lifetime of an impersonation token, in seconds
+pub const DEFAULT_IMPERSONATION_TTL_SECS: i64 = 900;
+
+/// Default impersonation tokens one admin may mint per hour
+pub const DEFAULT_MAX_IMPERSONATIONS_PER_HOUR: u32 = 10;
+
+/// Default number of replaced passwords a user may not reuse
+pub const DEFAULT_PASSWORD_HISTORY: usize = 5;
+
+/// Default time after login beyond which a session can't be refreshed (30 days), in seconds
+pub const DEFAULT_SESSION_MAX_LIFETIME_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Maximum number of admins tracked for impersonation rate limiting
+const IMPERSONATION_CACHE_CAPACITY: usize = 1_000;
+
+/// Default time a token holder's account status is trusted before re-reading it, in seconds
+pub const DEFAULT_ACCOUNT_STATUS_CACHE_TTL_SECS: i64 = 30;
+
+/// Argon2 hash of a random password, verified against when a login has no stored hash
+fn dummy_password_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(nanoid!().as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .expect("hashing the dummy password with default Argon2 parameters")
+    })
+}
+
+/// Check `password` against the stored hash.
+///
+/// Without a stored hash (unknown email, missing credentials) the dummy hash is verified
+/// instead and the result discarded, so every login attempt costs the same Argon2 work and
+/// response time does not reveal whether the account exists.
+fn password_matches(password: &str, stored_hash: Option<&str>) -> DashboardResult<bool> {
+    let parsed_hash = PasswordHash::new(stored_hash.unwrap_or_else(|| dummy_password_hash()))
+        .map_err(|e| DashboardError::internal_server(format!("Password parsing error: {}", e)))?;
+    let verified = Argon2::default()
+        .verify_password(password.as_bytes(), &parsed_hash)
+        .is_ok();
+    Ok(verified && stored_hash.is_some())
+}
+
+/// Time left until a rate-limit window closes at `ends_at`
+fn retry_after(ends_at: DateTime<Utc>) -> std::time::Duration {
+    (ends_at - Utc::now()).to_std().unwrap_or_default()
+}
+
+/// Hash a password with a fresh salt, returning the PHC hash string and the salt
+fn hash_password(password: &str) -> DashboardResult<(String, String)> {
+    let salt = SaltString::generate(&mut OsRng);
+    let password_hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| DashboardError::internal_server(format!("Password hashing error: {}", e)))?
+        .to_string();
+    Ok((password_hash, salt.to_string()))
+}
+
+/// Failed login attempts recorded for one email
+#[derive(Debug, Clone, Copy)]
+struct FailedLogins {
+    count: u32,
+    first_failed_at: DateTime<Utc>,
+}
+
+/// Impersonation tokens minted by one admin in the current hour
+#[derive(Debug, Clone, Copy)]
+struct ImpersonationWindow {
+    count: u32,
+    started_at: DateTime<Utc>,
 }
 
 /// User service for handling user-related operations
@@ -30,20 +190,279 @@ pub struct UserService<T: UserStorage> {
     storage: Arc<T>,
     jwt_secret: String,
     jwt_expiration: i64,
+    jwt_issuer: String,
+    jwt_audience: Option<String>,
+    max_public_keys: usize,
+    admin_user_ids: Vec<i64>,
+    failed_logins: Mutex<BoundedCache<String, FailedLogins>>,
+    max_failed_logins: u32,
+    login_risk_policy: Arc<dyn LoginRiskPolicy>,
+    deny_list: KeyDenyList,
+    verification_tokens: EmailVerificationTokens,
+    verification_notifier: Arc<dyn VerificationNotifier>,
+    require_email_verification: bool,
+    session_write_retries: u32,
+    network_storage: Option<Arc<dyn NetworkStorage>>,
+    /// user_id -> time of the last data export
+    data_exports: Mutex<BoundedCache<i64, DateTime<Utc>>>,
+    data_export_interval: Duration,
+    /// user_id -> (deletion confirmation token, expires_at)
+    deletion_tokens: Mutex<BoundedCache<i64, (String, DateTime<Utc>)>>,
+    field_limits: UserFieldLimits,
+    read_only: ReadOnlyMode,
+    impersonation_ttl: Duration,
+    max_impersonations_per_hour: u32,
+    /// admin user_id -> impersonations in the current window
+    impersonations: Mutex<BoundedCache<i64, ImpersonationWindow>>,
+    /// Replaced passwords kept per user and refused on change
+    password_history: usize,
+    /// Absolute session lifetime counted from login, regardless of refreshes
+    session_max_lifetime: Duration,
+    /// Preferences applied by the notifier, updated when a user saves new ones
+    notification_preferences: Option<NotificationPreferenceCache<T>>,
+    /// user_id -> (account status, or `None` once deleted; when it was read)
+    account_statuses: Mutex<BoundedCache<i64, (Option<UserStatus>, DateTime<Utc>)>>,
+    account_status_ttl: Duration,
 }
 
 impl<T: UserStorage> UserService<T> {
     /// Create a new UserService with the given storage
     pub fn new(storage: Arc<T>, jwt_secret: String, jwt_expiration: i64) -> Self {
+        // Hash the dummy password now rather than during the first unknown-email login
+        dummy_password_hash();
+
         Self {
             storage,
             jwt_secret,
             jwt_expiration,
+            jwt_issuer: DEFAULT_JWT_ISSUER.to_string(),
+            jwt_audience: None,
+            max_public_keys: DEFAULT_MAX_PUBLIC_KEYS,
+            admin_user_ids: Vec::new(),
+            failed_logins: Mutex::new(BoundedCache::new(DEFAULT_FAILED_LOGIN_CACHE_CAPACITY)),
+            max_failed_logins: DEFAULT_MAX_FAILED_LOGINS,
+            login_risk_policy: Arc::new(AllowAllPolicy),
+            deny_list: KeyDenyList::default(),
+            verification_tokens: EmailVerificationTokens::default(),
+            verification_notifier: Arc::new(LogVerificationNotifier),
+            require_email_verification: false,
+            session_write_retries: DEFAULT_SESSION_WRITE_RETRIES,
+            network_storage: None,
+            data_exports: Mutex::new(BoundedCache::new(PER_USER_CACHE_CAPACITY)),
+            data_export_interval: Duration::seconds(DEFAULT_DATA_EXPORT_INTERVAL_SECS),
+            deletion_tokens: Mutex::new(BoundedCache::new(PER_USER_CACHE_CAPACITY)),
+            field_limits: UserFieldLimits::default(),
+            read_only: ReadOnlyMode::default(),
+            impersonation_ttl: Duration::seconds(DEFAULT_IMPERSONATION_TTL_SECS),
+            max_impersonations_per_hour: DEFAULT_MAX_IMPERSONATIONS_PER_HOUR,
+            impersonations: Mutex::new(BoundedCache::new(IMPERSONATION_CACHE_CAPACITY)),
+            password_history: DEFAULT_PASSWORD_HISTORY,
+            session_max_lifetime: Duration::seconds(DEFAULT_SESSION_MAX_LIFETIME_SECS),
+            notification_preferences: None,
+            account_statuses: Mutex::new(BoundedCache::new(PER_USER_CACHE_CAPACITY)),
+            account_status_ttl: Duration::seconds(DEFAULT_ACCOUNT_STATUS_CACHE_TTL_SECS),
+        }
+    }
+
+    /// Re-read a token holder's account status from storage at most every `seconds` (0 reads
+    /// it on every request). Status changes made through this service apply at once.
+    pub fn with_account_status_cache_ttl(mut self, seconds: i64) -> Self {
+        self.account_status_ttl = Duration::seconds(seconds);
+        self
+    }
+
+    /// Stop refreshing a session `seconds` after its login, forcing the user to log in again
+    pub fn with_session_max_lifetime(mut self, seconds: i64) -> Self {
+        self.session_max_lifetime = Duration::seconds(seconds);
+        self
+    }
+
+    /// Refuse a new password matching the current one or any of the last `count` replaced
+    /// ones (0 disables the check)
+    pub fn with_password_history(mut self, count: usize) -> Self {
+        self.password_history = count;
+        self
+    }
+
+    /// Set how long impersonation tokens last and how many one admin may mint per hour
+    /// (0 refuses impersonation)
+    pub fn with_impersonation(mut self, ttl_seconds: i64, max_per_hour: u32) -> Self {
+        self.impersonation_ttl = Duration::seconds(ttl_seconds);
+        self.max_impersonations_per_hour = max_per_hour;
+        self
+    }
+
+    /// Include network connections and earnings from `network_storage` in data exports and
+    /// account deletion
+    pub fn with_network_storage(mut self, network_storage: Arc<dyn NetworkStorage>) -> Self {
+        self.network_storage = Some(network_storage);
+        self
+    }
+
+    /// Refuse every write while `read_only` is enabled
+    pub fn with_read_only(mut self, read_only: ReadOnlyMode) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Reject registrations and profile updates with emails or usernames over these limits
+    pub fn with_field_limits(mut self, limits: UserFieldLimits) -> Self {
+        self.field_limits = limits;
+        self
+    }
+
+    /// Allow one data export per user every `seconds` (zero disables the limit)
+    pub fn with_data_export_interval(mut self, seconds: i64) -> Self {
+        self.data_export_interval = Duration::seconds(seconds);
+        self
+    }
+
+    /// Retry writing a login session up to `retries` more times after a transient failure
+    pub fn with_session_write_retries(mut self, retries: u32) -> Self {
+        self.session_write_retries = retries;
+        self
+    }
+
+    /// Set how many failed logins per email are tolerated before lockout (zero disables lockout)
+    pub fn with_max_failed_logins(mut self, max_failed_logins: u32) -> Self {
+        self.max_failed_logins = max_failed_logins;
+        self
+    }
+
+    /// Set the policy consulted before each password check
+    pub fn with_login_risk_policy(mut self, policy: Arc<dyn LoginRiskPolicy>) -> Self {
+        self.login_risk_policy = policy;
+        self
+    }
+
+    /// Bound the failed-login cache to `capacity` emails, evicting the least recently failed
+    pub fn with_failed_login_cache_capacity(mut self, capacity: usize) -> Self {
+        self.failed_logins = Mutex::new(BoundedCache::new(capacity));
+        self
+    }
+
+    /// Number of emails currently tracked for failed logins
+    pub fn failed_login_cache_len(&self) -> usize {
+        self.failed_logins.lock().map(|cache| cache.len()).unwrap_or(0)
+    }
+
+    /// Refuse logins for an email with too many recent failures, returning the failure count
+    fn check_login_allowed(&self, email: &str) -> DashboardResult<u32> {
+        let mut cache = self
+            .failed_logins
+            .lock()
+            .map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let key = email.to_lowercase();
+
+        let window = Duration::minutes(FAILED_LOGIN_WINDOW_MINUTES);
+        let (recent_failures, window_ends_at) = match cache.get_mut(&key) {
+            Some(failed) if Utc::now() - failed.first_failed_at > window => {
+                cache.remove(&key);
+                (0, None)
+            }
+            Some(failed) => (failed.count, Some(failed.first_failed_at + window)),
+            None => (0, None),
+        };
+
+        if self.max_failed_logins > 0 && recent_failures >= self.max_failed_logins {
+            return Err(DashboardError::rate_limit(
+                "Too many failed login attempts, try again later",
+                retry_after(window_ends_at.unwrap_or_else(Utc::now)),
+            ));
+        }
+        Ok(recent_failures)
+    }
+
+    /// Count a failed login for an email
+    fn record_failed_login(&self, email: &str) {
+        let mut cache = match self.failed_logins.lock() {
+            Ok(cache) => cache,
+            Err(e) => {
+                error!("Failed to lock failed-login cache: {}", e);
+                return;
+            }
+        };
+        let key = email.to_lowercase();
+
+        match cache.get_mut(&key) {
+            Some(failed) => failed.count += 1,
+            None => cache.insert(key, FailedLogins { count: 1, first_failed_at: Utc::now() }),
+        }
+    }
+
+    /// Forget failed logins for an email after a successful login
+    fn clear_failed_logins(&self, email: &str) {
+        if let Ok(mut cache) = self.failed_logins.lock() {
+            cache.remove(&email.to_lowercase());
+        }
+    }
+
+    /// Set the users granted the admin role in issued tokens
+    pub fn with_admin_user_ids(mut self, admin_user_ids: Vec<i64>) -> Self {
+        self.admin_user_ids = admin_user_ids;
+        self
+    }
+
+    /// Roles and scopes granted to a user
+    fn grants_for(&self, user_id: i64) -> (Vec<String>, Vec<String>) {
+        let mut roles = vec![ROLE_USER.to_string()];
+        if self.admin_user_ids.contains(&user_id) {
+            roles.push(ROLE_ADMIN.to_string());
         }
+        let scopes = roles
+            .iter()
+            .flat_map(|role| scopes_for_role(role).iter().map(|s| s.to_string()))
+            .collect();
+        (roles, scopes)
+    }
+
+    /// Set the issuer stamped on and required from tokens, and optionally an audience
+    pub fn with_jwt_issuer(mut self, issuer: String, audience: Option<String>) -> Self {
+        self.jwt_issuer = issuer;
+        self.jwt_audience = audience;
+        self
+    }
+
+    /// Refuse to register keys in the shared `deny_list`
+    pub fn with_key_deny_list(mut self, deny_list: KeyDenyList) -> Self {
+        self.deny_list = deny_list;
+        self
+    }
+
+    /// Apply saved notification preferences to `cache` right away
+    pub fn with_notification_preferences(mut self, cache: NotificationPreferenceCache<T>) -> Self {
+        self.notification_preferences = Some(cache);
+        self
+    }
+
+    /// Use `tokens` for email verification and deliver them with `notifier`
+    pub fn with_email_verification(
+        mut self,
+        tokens: EmailVerificationTokens,
+        notifier: Arc<dyn VerificationNotifier>,
+    ) -> Self {
+        self.verification_tokens = tokens;
+        self.verification_notifier = notifier;
+        self
+    }
+
+    /// Refuse logins from users who have not verified their email address
+    pub fn with_require_email_verification(mut self, required: bool) -> Self {
+        self.require_email_verification = required;
+        self
+    }
+
+    /// Set the maximum number of public keys a user may register
+    pub fn with_max_public_keys(mut self, max_public_keys: usize) -> Self {
+        self.max_public_keys = max_public_keys;
+        self
     }
 
     /// Register a new user
     pub async fn register_user(&self, user_data: CreateUserDto) -> DashboardResult<User> {
+        self.read_only.ensure_writable()?;
+        user_data.validate(&self.field_limits)?;
+
         // Check if email already exists
         if let Some(_) = self.storage.find_user_by_email(&user_data.email).await? {
             return Err(DashboardError::validation(format!(
@@ -52,23 +471,108 @@ impl<T: UserStorage> UserService<T> {
             )));
         }
 
-        // Hash password
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        let password_hash = argon2
-            .hash_password(user_data.password.as_bytes(), &salt)
-            .map_err(|e| DashboardError::internal_server(format!("Password hashing error: {}", e)))?
-            .to_string();
+        let (password_hash, salt) = hash_password(&user_data.password)?;
+
+        // Create the user with their credentials atomically; the account stays pending until
+        // the email address is confirmed
+        let user = self
+            .storage
+            .create_user_with_credentials(NewUserAccount {
+                user: user_data,
+                password_hash,
+                salt,
+                status: UserStatus::PendingVerification,
+            })
+            .await?;
+        self.send_verification(&user).await?;
+
+        Ok(user)
+    }
+
+    /// Issue a verification token and hand it to the notifier; delivery failures are logged
+    async fn send_verification(&self, user: &User) -> DashboardResult<()> {
+        let token = self.verification_tokens.issue(user.id, Instant::now())?;
+        if let Err(e) = self.verification_notifier.send_verification(user, &token).await {
+            error!("Failed to send verification email to user {}: {}", user.id, e);
+        }
+        Ok(())
+    }
+
+    /// Activate the account a verification token was issued for
+    pub async fn verify_email(&self, token: &str) -> DashboardResult<User> {
+        self.read_only.ensure_writable()?;
+        let user_id = self
+            .verification_tokens
+            .redeem(token, Instant::now())?
+            .ok_or_else(|| DashboardError::validation("Invalid or expired verification token"))?;
+
+        let user = self.get_user(user_id).await?;
+        if user.status != UserStatus::PendingVerification {
+            // Already verified, or suspended in the meantime; never lift a suspension here
+            return Ok(user);
+        }
+
+        let user = self.storage.set_user_status(user_id, UserStatus::Active).await?;
+        info!("User {} verified their email address", user_id);
+        Ok(user)
+    }
+
+    /// Send a fresh verification token to a pending account.
+    ///
+    /// Unknown or already verified addresses succeed silently so the endpoint doesn't reveal
+    /// which emails are registered.
+    pub async fn resend_verification(&self, email: &str) -> DashboardResult<()> {
+        match self.storage.find_user_by_email(email).await? {
+            Some(user) if user.status == UserStatus::PendingVerification => {
+                self.send_verification(&user).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Replace a user's password after checking the current one.
+    ///
+    /// A new password matching the current one or any of the last `password_history` replaced
+    /// ones is refused with `Validation("password recently used")`; the replaced hash is
+    /// recorded and older history pruned.
+    pub async fn change_password(
+        &self,
+        user_id: i64,
+        current_password: &str,
+        new_password: &str,
+    ) -> DashboardResult<()> {
+        self.read_only.ensure_writable()?;
+        if new_password.is_empty() {
+            return Err(DashboardError::validation("New password must not be empty"));
+        }
+
+        let credentials = self
+            .storage
+            .get_credentials(user_id)
+            .await?
+            .ok_or_else(|| DashboardError::not_found(format!("Credentials for user {} not found", user_id)))?;
+        if !password_matches(current_password, Some(&credentials.password_hash))? {
+            return Err(DashboardError::authentication("Current password is incorrect"));
+        }
 
-        // Create user
-        let user = self.storage.create_user(user_data).await?;
+        if self.password_history > 0 {
+            let history = self.storage.get_password_history(user_id).await?;
+            let recent = std::iter::once(&credentials.password_hash).chain(history.iter().take(self.password_history));
+            for password_hash in recent {
+                if password_matches(new_password, Some(password_hash))? {
+                    return Err(DashboardError::validation("password recently used"));
+                }
+            }
+        }
 
-        // Store credentials
+        let (password_hash, salt) = hash_password(new_password)?;
+        self.storage.store_credentials(user_id, &password_hash, &salt).await?;
         self.storage
-            .store_credentials(user.id, &password_hash, &salt.to_string())
+            .record_password_history(user_id, &credentials.password_hash, self.password_history)
             .await?;
 
-        Ok(user)
+        info!("User {} changed their password", user_id);
+        Ok(())
     }
 
     /// Authenticate user and return JWT token
@@ -79,52 +583,191 @@ impl<T: UserStorage> UserService<T> {
         ip_address: &str,
         user_agent: &str,
     ) -> DashboardResult<UserLoginResponse> {
-        // Find user by email
-        let user = self
-            .storage
-            .find_user_by_email(email)
-            .await?
-            .ok_or_else(|| DashboardError::authentication("Invalid email or password"))?;
+        self.login_with_challenge(email, password, ip_address, user_agent, None)
+            .await
+    }
 
-        // Get credentials
-        let credentials = self
-            .storage
-            .get_credentials(user.id)
-            .await?
-            .ok_or_else(|| DashboardError::authentication("Credentials not found"))?;
+    /// Authenticate user and return JWT token, passing any challenge token to the risk policy
+    pub async fn login_with_challenge(
+        &self,
+        email: &str,
+        password: &str,
+        ip_address: &str,
+        user_agent: &str,
+        challenge_token: Option<&str>,
+    ) -> DashboardResult<UserLoginResponse> {
+        let recent_failures = self.check_login_allowed(email)?;
 
-        // Verify password
-        let parsed_hash = PasswordHash::new(&credentials.password_hash)
-            .map_err(|e| DashboardError::internal_server(format!("Password parsing error: {}", e)))?;
+        let attempt = LoginAttempt {
+            email,
+            ip_address,
+            user_agent,
+            recent_failures,
+            challenge_token,
+        };
+        match self.login_risk_policy.assess(&attempt).await {
+            LoginRiskDecision::Allow => {}
+            LoginRiskDecision::RequireChallenge => {
+                info!("Login challenge required for {}", email);
+                return Err(DashboardError::challenge_required(
+                    "Solve the challenge and retry with the X-Challenge-Token header",
+                ));
+            }
+            LoginRiskDecision::Block => {
+                info!("Login blocked by risk policy for {}", email);
+                return Err(DashboardError::authorization("Login blocked"));
+            }
+        }
 
-        Argon2::default()
-            .verify_password(password.as_bytes(), &parsed_hash)
-            .map_err(|_| DashboardError::authentication("Invalid email or password"))?;
+        // Unknown emails and missing credentials go through the same password check as a
+        // wrong password, so neither the error nor the timing reveals which one it was
+        let user = self.storage.find_user_by_email(email).await?;
+        let credentials = match &user {
+            Some(user) => self.storage.get_credentials(user.id).await?,
+            None => None,
+        };
+        let verified = password_matches(password, credentials.as_ref().map(|c| c.password_hash.as_str()))?;
 
-        // Create session
-        self.storage
-            .create_session(user.id, ip_address, user_agent, self.jwt_expiration)
-            .await?;
+        let user = match user {
+            Some(user) if verified => user,
+            _ => {
+                self.record_failed_login(email);
+                return Err(DashboardError::authentication("Invalid email or password"));
+            }
+        };
+        self.clear_failed_logins(email);
 
-        // Update last active
-        self.storage.update_last_active(user.id).await?;
+        // Only reveal the account status to someone who knows the password
+        if user.status.is_suspended() {
+            info!("Login refused for suspended user {}", user.id);
+            return Err(DashboardError::authorization("Account suspended"));
+        }
+        if self.require_email_verification && user.status == UserStatus::PendingVerification {
+            info!("Login refused for unverified user {}", user.id);
+            return Err(DashboardError::authorization("Email address not verified"));
+        }
 
-        // Generate JWT token
+        // The token names its session, so the session is written first; a token that then
+        // fails to sign takes the session with it
+        let session = self.create_login_session(user.id, ip_address, user_agent).await?;
         let now = Utc::now();
         let exp_time = now + Duration::seconds(self.jwt_expiration);
-        let claims = Claims {
-            sub: user.id.to_string(),
-            iss: "dashboard_system".to_string(),
-            exp: exp_time.timestamp() as usize,
-            iat: now.timestamp() as usize,
+        let token = match self.session_token(user.id, &session.id, now, exp_time) {
+            Ok(token) => token,
+            Err(e) => {
+                if let Err(cleanup) = self.storage.delete_session(&session.id).await {
+                    warn!("Failed to remove session {} after token error: {}", session.id, cleanup);
+                }
+                return Err(e);
+            }
         };
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| DashboardError::internal_server(format!("Token generation error: {}", e)))?;
+        // Advisory only; the login is already complete
+        if let Err(e) = self.storage.update_last_active(user.id).await {
+            warn!("Failed to update last active for user {} after login: {}", user.id, e);
+        }
+
+        Ok(UserLoginResponse {
+            token,
+            user,
+            expires_at: exp_time,
+        })
+    }
+
+    /// Write the login session, retrying transient storage failures.
+    ///
+    /// A session store that stays unreachable yields `ServiceUnavailable`; other failures are
+    /// returned as-is.
+    async fn create_login_session(
+        &self,
+        user_id: i64,
+        ip_address: &str,
+        user_agent: &str,
+    ) -> DashboardResult<UserSession> {
+        let mut attempt = 0;
+        loop {
+            match self
+                .storage
+                .create_session(user_id, ip_address, user_agent, self.jwt_expiration)
+                .await
+            {
+                Ok(session) => return Ok(session),
+                Err(e) => match StorageError::classify(&e) {
+                    StorageError::Transient if attempt < self.session_write_retries => {
+                        attempt += 1;
+                        warn!(
+                            "Session write for user {} failed, retrying ({}/{}): {}",
+                            user_id, attempt, self.session_write_retries, e
+                        );
+                    }
+                    StorageError::Transient => {
+                        error!("Session write for user {} failed after {} attempts: {}", user_id, attempt + 1, e);
+                        return Err(DashboardError::service_unavailable("Login could not be completed, try again"));
+                    }
+                    StorageError::Permanent => return Err(e),
+                },
+            }
+        }
+    }
+
+    /// Sign a token for `user_id` under login session `session_id`, with grants as of now
+    fn session_token(
+        &self,
+        user_id: i64,
+        session_id: &str,
+        issued_at: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> DashboardResult<String> {
+        let (roles, scopes) = self.grants_for(user_id);
+        self.encode_token(&Claims {
+            sub: user_id.to_string(),
+            iss: self.jwt_issuer.clone(),
+            aud: self.jwt_audience.clone(),
+            exp: expires_at.timestamp() as usize,
+            iat: issued_at.timestamp() as usize,
+            roles,
+            scopes,
+            impersonated_by: None,
+            sid: Some(session_id.to_string()),
+        })
+    }
+
+    /// Exchange a valid token for a fresh one on the same login session.
+    ///
+    /// The session's expiry moves forward with each refresh, but never past
+    /// `created_at + session_max_lifetime`, less the clock-skew leeway verifiers allow after
+    /// `exp`. Once that point is reached, or the session is gone, the user must log in again.
+    /// Impersonation tokens can't be refreshed.
+    pub async fn refresh_token(&self, token: &str) -> DashboardResult<UserLoginResponse> {
+        let claims = self.verify_token_claims(token).await?;
+        if claims.impersonated_by.is_some() {
+            return Err(DashboardError::authorization("Impersonation tokens can't be refreshed"));
+        }
+        let session_id = claims
+            .session_id
+            .ok_or_else(|| DashboardError::authentication("Token has no session, log in again"))?;
+
+        let now = Utc::now();
+        let session = match self.storage.find_session_by_id(&session_id).await? {
+            Some(session) if session.user_id == claims.user_id && session.expires_at > now => session,
+            _ => return Err(DashboardError::authentication("Session ended, log in again")),
+        };
+
+        // A token stays usable for `leeway` seconds past `exp`, so stop that much short of the
+        // limit to keep the session from outliving it on a lenient clock
+        let leeway = Duration::seconds(self.token_validation().leeway as i64);
+        let deadline = session.created_at + self.session_max_lifetime - leeway;
+        if now >= deadline {
+            info!("Session {} of user {} reached its maximum lifetime", session.id, session.user_id);
+            return Err(DashboardError::authentication("Session reached its maximum lifetime, log in again"));
+        }
+
+        let user = self.get_user(claims.user_id).await?;
+        let exp_time = (now + Duration::seconds(self.jwt_expiration)).min(deadline);
+        let token = self.session_token(user.id, &session.id, now, exp_time)?;
+        if self.storage.extend_session(&session.id, exp_time).await?.is_none() {
+            return Err(DashboardError::authentication("Session ended, log in again"));
+        }
 
         Ok(UserLoginResponse {
             token,
@@ -133,14 +776,121 @@ impl<T: UserStorage> UserService<T> {
         })
     }
 
+    /// Sign `claims` into a JWT
+    fn encode_token(&self, claims: &Claims) -> DashboardResult<String> {
+        encode(&Header::default(), claims, &EncodingKey::from_secret(self.jwt_secret.as_bytes()))
+            .map_err(|e| DashboardError::internal_server(format!("Token generation error: {}", e)))
+    }
+
+    /// Mint a short-lived token letting admin `admin_id` act as user `user_id` for support.
+    ///
+    /// The token carries only the user role, names the admin in its `impersonated_by` claim and
+    /// is refused for other admins, suspended accounts and the admin themselves. Each admin may
+    /// mint a limited number per hour; auditing is up to the caller.
+    pub async fn impersonate(&self, admin_id: i64, user_id: i64) -> DashboardResult<ImpersonationToken> {
+        if !self.admin_user_ids.contains(&admin_id) {
+            return Err(DashboardError::authorization("Admin privileges required"));
+        }
+        if admin_id == user_id {
+            return Err(DashboardError::validation("Admins can't impersonate themselves"));
+        }
+        if self.admin_user_ids.contains(&user_id) {
+            return Err(DashboardError::authorization("Admins can't be impersonated"));
+        }
+        let user = self.get_user(user_id).await?;
+        if user.status.is_suspended() {
+            return Err(DashboardError::authorization("Account suspended"));
+        }
+        self.check_impersonation_allowed(admin_id)?;
+
+        let now = Utc::now();
+        let expires_at = now + self.impersonation_ttl;
+        let claims = Claims {
+            sub: user.id.to_string(),
+            iss: self.jwt_issuer.clone(),
+            aud: self.jwt_audience.clone(),
+            exp: expires_at.timestamp() as usize,
+            iat: now.timestamp() as usize,
+            roles: vec![ROLE_USER.to_string()],
+            scopes: scopes_for_role(ROLE_USER).iter().map(|s| s.to_string()).collect(),
+            impersonated_by: Some(admin_id),
+            sid: None,
+        };
+        let token = self.encode_token(&claims)?;
+
+        info!("Admin {} started impersonating user {} until {}", admin_id, user_id, expires_at);
+        Ok(ImpersonationToken {
+            token,
+            user,
+            expires_at,
+            impersonated_by: admin_id,
+        })
+    }
+
+    /// Count an impersonation against the admin's hourly limit, failing once it is used up
+    fn check_impersonation_allowed(&self, admin_id: i64) -> DashboardResult<()> {
+        let mut impersonations = self
+            .impersonations
+            .lock()
+            .map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let now = Utc::now();
+
+        let (count, window_ends_at) = match impersonations.get_mut(&admin_id) {
+            Some(window) if now - window.started_at <= Duration::hours(1) => {
+                (window.count, window.started_at + Duration::hours(1))
+            }
+            _ => (0, now),
+        };
+        if count >= self.max_impersonations_per_hour {
+            warn!("Admin {} hit the impersonation limit", admin_id);
+            return Err(DashboardError::rate_limit(
+                "Too many impersonations, try again later",
+                retry_after(window_ends_at),
+            ));
+        }
+        match impersonations.get_mut(&admin_id) {
+            Some(window) if count > 0 => window.count += 1,
+            _ => impersonations.insert(admin_id, ImpersonationWindow { count: 1, started_at: now }),
+        }
+        Ok(())
+    }
+
+    /// Token validation rules: signature, expiry, trusted issuer and optional audience
+    fn token_validation(&self) -> Validation {
+        let mut validation = Validation::default();
+        validation.set_issuer(&[self.jwt_issuer.as_str()]);
+        match &self.jwt_audience {
+            Some(audience) => {
+                validation.set_audience(&[audience.as_str()]);
+                validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+            }
+            None => validation.set_required_spec_claims(&["exp", "iss"]),
+        }
+        validation
+    }
+
     /// Verify JWT token and return user ID
     pub async fn verify_token(&self, token: &str) -> DashboardResult<i64> {
+        Ok(self.verify_token_claims(token).await?.user_id)
+    }
+
+    /// Verify JWT token and return the user ID with the roles and scopes it carries.
+    ///
+    /// Grants reflect the moment of issue; sensitive actions should re-check storage or config.
+    pub async fn verify_token_claims(&self, token: &str) -> DashboardResult<TokenClaims> {
         let token_data = decode::<Claims>(
             token,
             &DecodingKey::from_secret(self.jwt_secret.as_bytes()),
-            &Validation::default(),
+            &self.token_validation(),
         )
-        .map_err(|e| DashboardError::authentication(format!("Invalid token: {}", e)))?;
+        .map_err(|e| match e.kind() {
+            ErrorKind::InvalidIssuer => DashboardError::authentication("Invalid token: untrusted issuer"),
+            ErrorKind::InvalidAudience => DashboardError::authentication("Invalid token: wrong audience"),
+            ErrorKind::MissingRequiredClaim(claim) => {
+                DashboardError::authentication(format!("Invalid token: missing {} claim", claim))
+            }
+            _ => DashboardError::authentication(format!("Invalid token: {}", e)),
+        })?;
 
         let user_id = token_data
             .claims
@@ -148,7 +898,29 @@ impl<T: UserStorage> UserService<T> {
             .parse::<i64>()
             .map_err(|_| DashboardError::authentication("Invalid user ID in token"))?;
 
-        Ok(user_id)
+        // Tokens of suspended or deleted users stop working within the status cache TTL
+        match self.account_status(user_id).await? {
+            Some(status) if status.is_suspended() => {
+                return Err(DashboardError::authorization("Account suspended"));
+            }
+            Some(_) => {}
+            None => return Err(DashboardError::authentication("Invalid token: user no longer exists")),
+        }
+
+        // Impersonation ends as soon as the impersonator stops being an admin
+        if let Some(admin_id) = token_data.claims.impersonated_by {
+            if !self.admin_user_ids.contains(&admin_id) {
+                return Err(DashboardError::authentication("Invalid token: impersonator is no longer an admin"));
+            }
+        }
+
+        Ok(TokenClaims {
+            user_id,
+            roles: token_data.claims.roles,
+            scopes: token_data.claims.scopes,
+            impersonated_by: token_data.claims.impersonated_by,
+            session_id: token_data.claims.sid,
+        })
     }
 
     /// Get user by ID
@@ -159,40 +931,245 @@ impl<T: UserStorage> UserService<T> {
             .ok_or_else(|| DashboardError::not_found(format!("User with ID {} not found", id)))
     }
 
-    /// Update user
-    pub async fn update_user(&self, id: i64, update: UpdateUserDto) -> DashboardResult<User> {
-        // Check if user exists
-        self.get_user(id).await?;
-        
-        // If email is being updated, check if it's available
-        if let Some(ref email) = update.email {
-            if let Some(existing) = self.storage.find_user_by_email(email).await? {
-                if existing.id != id {
-                    return Err(DashboardError::validation(format!(
-                        "Email {} is already in use",
-                        email
-                    )));
+    /// Collect everything stored about a user into one export.
+    ///
+    /// Refused with `RateLimit` within the configured interval of the user's previous export.
+    pub async fn export_user_data(&self, user_id: i64) -> DashboardResult<UserDataExport> {
+        self.check_data_export_allowed(user_id)?;
+
+        let user = self.get_user(user_id).await?;
+        let mut public_keys = Vec::new();
+        for public_key in self.storage.get_public_keys_for_user(user_id).await? {
+            if let Some(info) = self.storage.get_public_key_info(user_id, &public_key).await? {
+                public_keys.push(info);
+            }
+        }
+        let sessions = self.storage.find_sessions_by_user_id(user_id).await?;
+        let network_connections = match &self.network_storage {
+            Some(network_storage) => network_storage.find_connections_by_user_id(user_id).await?,
+            None => Vec::new(),
+        };
+
+        info!("Exported data for user {}", user_id);
+        Ok(UserDataExport {
+            exported_at: Utc::now(),
+            user,
+            public_keys,
+            sessions,
+            earnings: EarningsSummary::from_connections(&network_connections),
+            network_connections,
+        })
+    }
+
+    /// Account status of a token's subject, `None` for a deleted user; read from storage at
+    /// most once per `account_status_ttl`
+    async fn account_status(&self, user_id: i64) -> DashboardResult<Option<UserStatus>> {
+        let now = Utc::now();
+        {
+            let mut statuses = self
+                .account_statuses
+                .lock()
+                .map_err(|e| DashboardError::internal_server(e.to_string()))?;
+            if let Some((status, read_at)) = statuses.get_mut(&user_id) {
+                if now - *read_at < self.account_status_ttl {
+                    return Ok(*status);
                 }
             }
         }
 
-        self.storage.update_user(id, update).await
+        let status = self.storage.find_user_by_id(user_id).await?.map(|user| user.status);
+        self.cache_account_status(user_id, status);
+        Ok(status)
     }
 
-    /// Delete user
-    pub async fn delete_user(&self, id: i64) -> DashboardResult<bool> {
+    /// Remember a status this instance just wrote, so its tokens see the change at once
+    fn cache_account_status(&self, user_id: i64, status: Option<UserStatus>) {
+        match self.account_statuses.lock() {
+            Ok(mut statuses) => statuses.insert(user_id, (status, Utc::now())),
+            Err(e) => error!("Failed to lock account status cache: {}", e),
+        }
+    }
+
+    /// Record a data export, failing if the user's previous one was too recent
+    fn check_data_export_allowed(&self, user_id: i64) -> DashboardResult<()> {
+        let mut exports = self
+            .data_exports
+            .lock()
+            .map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let now = Utc::now();
+
+        if let Some(last) = exports.get_mut(&user_id) {
+            if now - *last < self.data_export_interval {
+                return Err(DashboardError::rate_limit(
+                    "Data exported recently, try again later",
+                    retry_after(*last + self.data_export_interval),
+                ));
+            }
+        }
+        exports.insert(user_id, now);
+        Ok(())
+    }
+
+    /// Change a user's account status.
+    ///
+    /// Suspending also deletes the user's stored sessions; closing live WebSocket sessions is
+    /// up to the caller.
+    pub async fn set_user_status(&self, id: i64, status: UserStatus) -> DashboardResult<User> {
+        self.read_only.ensure_writable()?;
+        let user = self.storage.set_user_status(id, status).await?;
+        self.cache_account_status(id, Some(user.status));
+        if status.is_suspended() {
+            let deleted = self.storage.delete_user_sessions(id).await?;
+            info!("Suspended user {}, deleted {} sessions", id, deleted);
+        } else {
+            info!("Set status of user {} to {:?}", id, status);
+        }
+        Ok(user)
+    }
+
+    /// A user's notification preferences; users who never saved any get the defaults, which
+    /// mute nothing
+    pub async fn get_notification_preferences(&self, user_id: i64) -> DashboardResult<NotificationPreferences> {
+        self.get_user(user_id).await?;
+        Ok(self.storage.get_notification_preferences(user_id).await?.unwrap_or_default())
+    }
+
+    /// Replace a user's notification preferences
+    pub async fn update_notification_preferences(
+        &self,
+        user_id: i64,
+        preferences: NotificationPreferences,
+    ) -> DashboardResult<NotificationPreferences> {
+        self.read_only.ensure_writable()?;
+        preferences.validate(KNOWN_CHANNELS)?;
+        self.get_user(user_id).await?;
+
+        self.storage.set_notification_preferences(user_id, &preferences).await?;
+        if let Some(cache) = &self.notification_preferences {
+            cache.put(user_id, preferences.clone());
+        }
+        info!("Notification preferences updated for user {}", user_id);
+        Ok(preferences)
+    }
+
+    /// Get a user's stored login sessions, oldest first
+    pub async fn get_user_sessions(&self, user_id: i64) -> DashboardResult<Vec<UserSession>> {
+        self.get_user(user_id).await?;
+
+        let mut sessions = self.storage.find_sessions_by_user_id(user_id).await?;
+        sessions.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+        Ok(sessions)
+    }
+
+    /// List a page of users ordered by ID (admin listing)
+    pub async fn list_users(
+        &self,
+        after_id: Option<i64>,
+        limit: usize,
+        email_contains: Option<&str>,
+    ) -> DashboardResult<Vec<User>> {
+        self.storage.list_users(after_id, limit, email_contains).await
+    }
+    
+    /// Update user
+    pub async fn update_user(&self, id: i64, update: UpdateUserDto) -> DashboardResult<User> {
+        self.read_only.ensure_writable()?;
+        update.validate(&self.field_limits)?;
+
         // Check if user exists
         self.get_user(id).await?;
         
-        // Delete user sessions
-        self.storage.delete_user_sessions(id).await?;
-        
-        // Delete user
-        self.storage.delete_user(id).await
+        // If email is being updated, check if it's available
+        if let Some(ref email) = update.email {
+            if let Some(existing) = self.storage.find_user_by_email(email).await? {
+                if existing.id != id {
+                    return Err(DashboardError::validation(format!(
+                        "Email {} is already in use",
+                        email
+                    )));
+                }
+            }
+        }
+
+        self.storage.update_user(id, update).await
+    }
+
+    /// Delete user
+    pub async fn delete_user(&self, id: i64) -> DashboardResult<bool> {
+        Ok(self.delete_account(id).await?.user_deleted)
+    }
+
+    /// Delete a user and everything stored about them in every subsystem.
+    ///
+    /// The user with their sessions, keys and credentials is removed in one storage call, a
+    /// transaction for Postgres. Network connections live in a separate store, so they go
+    /// first: a failure after them leaves the user in place and the deletion can simply be
+    /// retried, and nothing is left pointing at a deleted user. The counts are those the
+    /// deletes actually removed.
+    pub async fn delete_account(&self, id: i64) -> DashboardResult<AccountDeletion> {
+        self.read_only.ensure_writable()?;
+        self.get_user(id).await?;
+
+        let network_connections = match &self.network_storage {
+            Some(network_storage) => network_storage.delete_user_connections(id).await?,
+            None => 0,
+        };
+        let deletion = self.storage.delete_user_records(id).await?;
+        self.cache_account_status(id, None);
+
+        info!(
+            "Deleted account {}: {} sessions, {} public keys, {} network connections",
+            id, deletion.sessions, deletion.public_keys, network_connections
+        );
+        Ok(AccountDeletion {
+            network_connections,
+            ..deletion
+        })
+    }
+
+    /// Start self-service account deletion, returning a single-use confirmation token and
+    /// its expiry. Requesting again replaces the previous token.
+    pub async fn request_account_deletion(&self, user_id: i64) -> DashboardResult<(String, DateTime<Utc>)> {
+        self.read_only.ensure_writable()?;
+        self.get_user(user_id).await?;
+
+        let token = nanoid!(32);
+        let expires_at = Utc::now() + Duration::minutes(ACCOUNT_DELETION_TOKEN_TTL_MINUTES);
+        self.deletion_tokens
+            .lock()
+            .map_err(|e| DashboardError::internal_server(e.to_string()))?
+            .insert(user_id, (token.clone(), expires_at));
+        Ok((token, expires_at))
+    }
+
+    /// Delete the user's account if `token` matches their outstanding, unexpired confirmation
+    pub async fn confirm_account_deletion(&self, user_id: i64, token: &str) -> DashboardResult<AccountDeletion> {
+        self.read_only.ensure_writable()?;
+        let confirmed = {
+            let mut tokens = self
+                .deletion_tokens
+                .lock()
+                .map_err(|e| DashboardError::internal_server(e.to_string()))?;
+            match tokens.remove(&user_id) {
+                Some((expected, expires_at)) if expected == token && expires_at > Utc::now() => true,
+                // A wrong guess doesn't burn a still-valid token
+                Some(entry) if entry.1 > Utc::now() => {
+                    tokens.insert(user_id, entry);
+                    false
+                }
+                _ => false,
+            }
+        };
+        if !confirmed {
+            return Err(DashboardError::validation("Invalid or expired confirmation token"));
+        }
+
+        self.delete_account(user_id).await
     }
 
     /// Add a public key to a user
     pub async fn add_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
+        self.read_only.ensure_writable()?;
         // Validate that user exists
         self.get_user(user_id).await?;
         
@@ -200,9 +1177,57 @@ impl<T: UserStorage> UserService<T> {
         if !Self::is_valid_ed25519_public_key(public_key) {
             return Err(DashboardError::validation("Invalid public key format. Expected a 64-character hex string."));
         }
+        if self.deny_list.contains(public_key) {
+            return Err(DashboardError::validation("Public key has been revoked globally"));
+        }
+        
+        // Stored as a batch of one so the per-user key limit is checked atomically with the insert
+        let results = self
+            .storage
+            .store_public_keys(user_id, &[public_key.to_string()], self.max_public_keys)
+            .await?;
+        match results.into_iter().next() {
+            Some(result) if result.status == PublicKeyRegistrationStatus::Error => {
+                let message = result.message.unwrap_or_else(|| "Failed to register public key".to_string());
+                Err(DashboardError::validation(message))
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Add several public keys to a user at once
+    ///
+    /// All keys are format-checked before any are stored, and the batch is rejected if it
+    /// would take the user over the per-user key limit.
+    pub async fn add_public_keys(
+        &self,
+        user_id: i64,
+        public_keys: Vec<String>,
+    ) -> DashboardResult<Vec<PublicKeyRegistrationResult>> {
+        self.read_only.ensure_writable()?;
+        // Validate that user exists
+        self.get_user(user_id).await?;
+        
+        if public_keys.is_empty() {
+            return Err(DashboardError::validation("No public keys provided"));
+        }
+        
+        if let Some(invalid) = public_keys.iter().find(|k| !Self::is_valid_ed25519_public_key(k)) {
+            return Err(DashboardError::validation(format!(
+                "Invalid public key format: {}. Expected a 64-character hex string.",
+                invalid
+            )));
+        }
+        if let Some(denied) = public_keys.iter().find(|k| self.deny_list.contains(k)) {
+            return Err(DashboardError::validation(format!(
+                "Public key has been revoked globally: {}",
+                denied
+            )));
+        }
         
-        // Store the public key
-        self.storage.store_public_key(user_id, public_key).await
+        self.storage
+            .store_public_keys(user_id, &public_keys, self.max_public_keys)
+            .await
     }
     
     /// Get public keys for a user
@@ -214,8 +1239,20 @@ impl<T: UserStorage> UserService<T> {
         self.storage.get_public_keys_for_user(user_id).await
     }
     
+    /// Get metadata for a public key registered to a user
+    pub async fn get_public_key_info(&self, user_id: i64, public_key: &str) -> DashboardResult<PublicKeyInfo> {
+        // Validate that user exists
+        self.get_user(user_id).await?;
+        
+        self.storage
+            .get_public_key_info(user_id, public_key)
+            .await?
+            .ok_or_else(|| DashboardError::not_found("Public key not associated with this user"))
+    }
+    
     /// Revoke a public key for a user
     pub async fn revoke_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.read_only.ensure_writable()?;
         // Validate that user exists
         self.get_user(user_id).await?;
         
@@ -223,6 +1260,28 @@ impl<T: UserStorage> UserService<T> {
         self.storage.revoke_public_key(user_id, public_key).await
     }
     
+    /// Reactivate a revoked public key for a user
+    pub async fn reactivate_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.read_only.ensure_writable()?;
+        // Validate that user exists
+        self.get_user(user_id).await?;
+        
+        if self.deny_list.contains(public_key) {
+            return Err(DashboardError::validation("Public key has been revoked globally"));
+        }
+        
+        self.storage.reactivate_public_key(user_id, public_key).await
+    }
+    
+    /// Permanently delete a public key and its history (admin action)
+    pub async fn delete_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.read_only.ensure_writable()?;
+        // Validate that user exists
+        self.get_user(user_id).await?;
+        
+        self.storage.delete_public_key(user_id, public_key).await
+    }
+    
     /// Find a user by public key
     pub async fn find_user_by_public_key(&self, public_key: &str) -> DashboardResult<Option<User>> {
         self.storage.find_user_by_public_key(public_key).await
@@ -232,4 +1291,980 @@ impl<T: UserStorage> UserService<T> {
     fn is_valid_ed25519_public_key(public_key: &str) -> bool {
         public_key.len() == 64 && public_key.chars().all(|c| c.is_ascii_hexdigit())
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::InMemoryUserStorage;
+
+    const SECRET: &str = "test_secret";
+
+    fn test_service() -> UserService<InMemoryUserStorage> {
+        UserService::new(Arc::new(InMemoryUserStorage::new()), SECRET.to_string(), 3600)
+    }
+
+    fn sign(claims: serde_json::Value) -> String {
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(SECRET.as_bytes())).unwrap()
+    }
+
+    fn exp() -> i64 {
+        (Utc::now() + Duration::hours(1)).timestamp()
+    }
+
+    async fn register(service: &UserService<InMemoryUserStorage>, name: &str) -> User {
+        service
+            .register_user(CreateUserDto {
+                email: format!("{}@example.com", name),
+                username: name.to_string(),
+                password: "password123".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_notification_preferences_default_and_validate() {
+        let service = test_service();
+        let user = register(&service, "listener").await;
+        assert_eq!(
+            service.get_notification_preferences(user.id).await.unwrap(),
+            NotificationPreferences::default()
+        );
+
+        let mut preferences = NotificationPreferences::default();
+        preferences.muted_webhook_channels.insert("earnings".to_string());
+        service.update_notification_preferences(user.id, preferences.clone()).await.unwrap();
+        assert_eq!(service.get_notification_preferences(user.id).await.unwrap(), preferences);
+
+        let mut unknown = NotificationPreferences::default();
+        unknown.muted_websocket_channels.insert("earnigns".to_string());
+        assert!(matches!(
+            service.update_notification_preferences(user.id, unknown).await,
+            Err(DashboardError::Validation(_))
+        ));
+        assert_eq!(service.get_notification_preferences(user.id).await.unwrap(), preferences);
+        assert!(matches!(
+            service.get_notification_preferences(user.id + 1).await,
+            Err(DashboardError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_change_password_rejects_recent_passwords() {
+        let service = test_service().with_password_history(2);
+        let user = register(&service, "rotator").await;
+
+        assert!(matches!(
+            service.change_password(user.id, "wrong", "second-pass").await,
+            Err(DashboardError::Authentication(_))
+        ));
+        assert!(matches!(
+            service.change_password(user.id, "password123", "password123").await,
+            Err(DashboardError::Validation(ref msg)) if msg == "password recently used"
+        ));
+
+        service.change_password(user.id, "password123", "second-pass").await.unwrap();
+        service.change_password(user.id, "second-pass", "third-pass").await.unwrap();
+        for reused in ["password123", "second-pass", "third-pass"] {
+            assert!(matches!(
+                service.change_password(user.id, "third-pass", reused).await,
+                Err(DashboardError::Validation(ref msg)) if msg == "password recently used"
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_stops_at_session_max_lifetime() {
+        let storage = Arc::new(InMemoryUserStorage::new());
+        let service = UserService::new(storage.clone(), SECRET.to_string(), 3600).with_session_max_lifetime(86_400);
+        let user = register(&service, "refresher").await;
+        let login = service.login("refresher@example.com", "password123", "127.0.0.1", "test").await.unwrap();
+
+        let refreshed = service.refresh_token(&login.token).await.unwrap();
+        let session_id = service.verify_token_claims(&refreshed.token).await.unwrap().session_id.unwrap();
+        assert_eq!(refreshed.user.id, user.id);
+
+        // Near the limit the new token is cut short of it by the validation leeway
+        let backdate = |age: Duration| {
+            let mut sessions = storage.get_sessions_map().lock().unwrap();
+            let session = sessions.get_mut(&session_id).unwrap();
+            session.created_at = Utc::now() - age;
+            session.created_at
+        };
+        let created_at = backdate(Duration::seconds(86_400 - 600));
+        let capped = service.refresh_token(&refreshed.token).await.unwrap();
+        assert_eq!(capped.expires_at, created_at + Duration::seconds(86_400 - 60));
+        let stored = storage.find_session_by_id(&session_id).await.unwrap().unwrap();
+        assert_eq!(stored.expires_at, capped.expires_at);
+
+        backdate(Duration::seconds(86_400 + 1));
+        assert!(matches!(
+            service.refresh_token(&capped.token).await,
+            Err(DashboardError::Authentication(ref msg)) if msg.contains("maximum lifetime")
+        ));
+        // The token itself stays valid until its own expiry
+        assert!(service.verify_token(&capped.token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_and_sessionless_tokens_cannot_be_refreshed() {
+        let service = test_service();
+        let admin = register(&service, "support").await;
+        let user = register(&service, "customer").await;
+        let service = service.with_admin_user_ids(vec![admin.id]);
+
+        let impersonation = service.impersonate(admin.id, user.id).await.unwrap();
+        assert!(matches!(
+            service.refresh_token(&impersonation.token).await,
+            Err(DashboardError::Authorization(_))
+        ));
+
+        let sessionless = sign(serde_json::json!({
+            "sub": user.id.to_string(), "iss": DEFAULT_JWT_ISSUER, "exp": exp(), "iat": Utc::now().timestamp()
+        }));
+        assert!(matches!(
+            service.refresh_token(&sessionless).await,
+            Err(DashboardError::Authentication(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_password_history_is_pruned_to_configured_count() {
+        let service = test_service().with_password_history(1);
+        let user = register(&service, "pruned").await;
+
+        service.change_password(user.id, "password123", "second-pass").await.unwrap();
+        service.change_password(user.id, "second-pass", "third-pass").await.unwrap();
+        assert_eq!(service.storage.get_password_history(user.id).await.unwrap().len(), 1);
+
+        // The original password fell out of the history, so it may be used again
+        service.change_password(user.id, "third-pass", "password123").await.unwrap();
+        assert!(service.login("pruned@example.com", "password123", "127.0.0.1", "test").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_token_names_the_admin_and_drops_admin_grants() {
+        let service = test_service();
+        let admin = register(&service, "support").await;
+        let user = register(&service, "customer").await;
+        let service = service.with_admin_user_ids(vec![admin.id]);
+
+        let impersonation = service.impersonate(admin.id, user.id).await.unwrap();
+        assert!(impersonation.expires_at <= Utc::now() + Duration::seconds(DEFAULT_IMPERSONATION_TTL_SECS));
+        let claims = service.verify_token_claims(&impersonation.token).await.unwrap();
+        assert_eq!(claims.user_id, user.id);
+        assert_eq!(claims.impersonated_by, Some(admin.id));
+        assert_eq!(claims.roles, vec![ROLE_USER.to_string()]);
+
+        // Admins can't be impersonated, and non-admins can't impersonate
+        assert!(matches!(
+            service.impersonate(admin.id, admin.id).await,
+            Err(DashboardError::Validation(_))
+        ));
+        assert!(matches!(
+            service.impersonate(user.id, admin.id).await,
+            Err(DashboardError::Authorization(_))
+        ));
+
+        // Revoking the admin invalidates outstanding impersonation tokens
+        let service = service.with_admin_user_ids(vec![]);
+        assert!(matches!(
+            service.verify_token_claims(&impersonation.token).await,
+            Err(DashboardError::Authentication(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_impersonation_is_rate_limited_per_admin() {
+        let service = test_service();
+        let admin = register(&service, "support").await;
+        let user = register(&service, "customer").await;
+        let service = service.with_admin_user_ids(vec![admin.id]).with_impersonation(60, 2);
+
+        assert!(service.impersonate(admin.id, user.id).await.is_ok());
+        assert!(service.impersonate(admin.id, user.id).await.is_ok());
+        assert!(matches!(
+            service.impersonate(admin.id, user.id).await,
+            Err(DashboardError::RateLimit(..))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_token_with_trusted_issuer_is_accepted() {
+        let service = test_service();
+        let user = register(&service, "issuer").await;
+        let token = sign(serde_json::json!({
+            "sub": user.id.to_string(), "iss": DEFAULT_JWT_ISSUER, "exp": exp(), "iat": Utc::now().timestamp()
+        }));
+
+        assert_eq!(service.verify_token(&token).await.unwrap(), user.id);
+    }
+
+    #[tokio::test]
+    async fn test_token_with_mismatched_issuer_is_rejected() {
+        let token = sign(serde_json::json!({
+            "sub": "42", "iss": "other_service", "exp": exp(), "iat": Utc::now().timestamp()
+        }));
+
+        let err = test_service().verify_token(&token).await.unwrap_err();
+        assert!(matches!(err, DashboardError::Authentication(ref msg) if msg.contains("issuer")));
+    }
+
+    #[tokio::test]
+    async fn test_token_without_issuer_is_rejected() {
+        let token = sign(serde_json::json!({
+            "sub": "42", "exp": exp(), "iat": Utc::now().timestamp()
+        }));
+
+        let err = test_service().verify_token(&token).await.unwrap_err();
+        assert!(matches!(err, DashboardError::Authentication(_)));
+    }
+
+    #[tokio::test]
+    async fn test_token_with_wrong_audience_is_rejected() {
+        let service = test_service().with_jwt_issuer(DEFAULT_JWT_ISSUER.to_string(), Some("dashboard".to_string()));
+        let token = sign(serde_json::json!({
+            "sub": "42", "iss": DEFAULT_JWT_ISSUER, "aud": "other", "exp": exp(), "iat": Utc::now().timestamp()
+        }));
+
+        let err = service.verify_token(&token).await.unwrap_err();
+        assert!(matches!(err, DashboardError::Authentication(ref msg) if msg.contains("audience")));
+    }
+
+    #[tokio::test]
+    async fn test_login_token_carries_roles_and_scopes() {
+        let service = test_service();
+        let user = service
+            .register_user(CreateUserDto {
+                email: "admin@example.com".to_string(),
+                username: "admin".to_string(),
+                password: "password123".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap();
+        let service = service.with_admin_user_ids(vec![user.id]);
+
+        let login = service
+            .login("admin@example.com", "password123", "127.0.0.1", "test")
+            .await
+            .unwrap();
+        let claims = service.verify_token_claims(&login.token).await.unwrap();
+
+        assert_eq!(claims.user_id, user.id);
+        assert_eq!(claims.roles, vec![ROLE_USER.to_string(), ROLE_ADMIN.to_string()]);
+        assert!(claims.scopes.contains(&"admin:write".to_string()));
+        assert!(claims.scopes.contains(&"profile:read".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_token_without_grants_has_empty_roles() {
+        let service = test_service();
+        let user = register(&service, "grants").await;
+        let token = sign(serde_json::json!({
+            "sub": user.id.to_string(), "iss": DEFAULT_JWT_ISSUER, "exp": exp(), "iat": Utc::now().timestamp()
+        }));
+
+        let claims = service.verify_token_claims(&token).await.unwrap();
+        assert!(claims.roles.is_empty());
+        assert!(claims.scopes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_logins_lock_out_email() {
+        let service = test_service();
+        for _ in 0..DEFAULT_MAX_FAILED_LOGINS {
+            let err = service.login("nobody@example.com", "wrong", "127.0.0.1", "test").await.unwrap_err();
+            assert!(matches!(err, DashboardError::Authentication(_)));
+        }
+
+        let err = service.login("Nobody@Example.com", "wrong", "127.0.0.1", "test").await.unwrap_err();
+        assert!(matches!(err, DashboardError::RateLimit(..)));
+    }
+
+    #[tokio::test]
+    async fn test_failed_login_cache_is_bounded() {
+        let service = test_service().with_failed_login_cache_capacity(8);
+        for i in 0..100 {
+            let _ = service.login(&format!("user{}@example.com", i), "wrong", "127.0.0.1", "test").await;
+        }
+        assert_eq!(service.failed_login_cache_len(), 8);
+    }
+
+    /// Demands a challenge after any failure and accepts the token "solved"
+    struct ChallengeAfterFailure;
+
+    #[async_trait::async_trait]
+    impl LoginRiskPolicy for ChallengeAfterFailure {
+        async fn assess(&self, attempt: &LoginAttempt<'_>) -> LoginRiskDecision {
+            match (attempt.recent_failures, attempt.challenge_token) {
+                (0, _) | (_, Some("solved")) => LoginRiskDecision::Allow,
+                _ => LoginRiskDecision::RequireChallenge,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_risk_policy_requires_challenge() {
+        let service = test_service().with_login_risk_policy(Arc::new(ChallengeAfterFailure));
+
+        let err = service.login("nobody@example.com", "wrong", "127.0.0.1", "test").await.unwrap_err();
+        assert!(matches!(err, DashboardError::Authentication(_)));
+
+        let err = service.login("nobody@example.com", "wrong", "127.0.0.1", "test").await.unwrap_err();
+        assert!(matches!(err, DashboardError::ChallengeRequired(_)));
+
+        // With a solved challenge the normal password check runs again
+        let err = service
+            .login_with_challenge("nobody@example.com", "wrong", "127.0.0.1", "test", Some("solved"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DashboardError::Authentication(_)));
+    }
+
+    /// Notifier that keeps the last token it was asked to deliver
+    #[derive(Default)]
+    struct CapturingNotifier {
+        last_token: Mutex<Option<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl VerificationNotifier for CapturingNotifier {
+        async fn send_verification(&self, _: &User, token: &str) -> DashboardResult<()> {
+            *self.last_token.lock().unwrap() = Some(token.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_email_verification_gates_login() {
+        let notifier = Arc::new(CapturingNotifier::default());
+        let service = test_service()
+            .with_email_verification(EmailVerificationTokens::default(), notifier.clone())
+            .with_require_email_verification(true);
+        let user = service
+            .register_user(CreateUserDto {
+                email: "new@example.com".to_string(),
+                username: "new".to_string(),
+                password: "password123".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(user.status, UserStatus::PendingVerification);
+
+        let err = service.login("new@example.com", "password123", "127.0.0.1", "test").await.unwrap_err();
+        assert!(matches!(err, DashboardError::Authorization(_)));
+
+        // A resend right after registration is throttled
+        let err = service.resend_verification("new@example.com").await.unwrap_err();
+        assert!(matches!(err, DashboardError::RateLimit(..)));
+        service.resend_verification("unknown@example.com").await.unwrap();
+
+        let token = notifier.last_token.lock().unwrap().clone().unwrap();
+        assert_eq!(service.verify_email(&token).await.unwrap().status, UserStatus::Active);
+        assert!(service.verify_email(&token).await.is_err());
+        service.login("new@example.com", "password123", "127.0.0.1", "test").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_suspended_user_cannot_log_in_or_use_tokens() {
+        let service = test_service();
+        let user = service
+            .register_user(CreateUserDto {
+                email: "user@example.com".to_string(),
+                username: "user".to_string(),
+                password: "password123".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap();
+        let login = service.login("user@example.com", "password123", "127.0.0.1", "test").await.unwrap();
+
+        service.set_user_status(user.id, UserStatus::Suspended).await.unwrap();
+
+        let err = service.login("user@example.com", "password123", "127.0.0.1", "test").await.unwrap_err();
+        assert!(matches!(err, DashboardError::Authorization(_)));
+        let err = service.verify_token_claims(&login.token).await.unwrap_err();
+        assert!(matches!(err, DashboardError::Authorization(_)));
+    }
+
+    #[tokio::test]
+    async fn test_token_account_status_is_cached_and_deleted_users_are_rejected() {
+        let storage = Arc::new(InMemoryUserStorage::new());
+        let service = UserService::new(storage.clone(), SECRET.to_string(), 3600);
+        let user = register(&service, "cached").await;
+        let login = service.login(&user.email, "password123", "127.0.0.1", "test").await.unwrap();
+        service.verify_token_claims(&login.token).await.unwrap();
+
+        // A suspension written behind the service's back is seen once the cached status expires
+        storage.set_user_status(user.id, UserStatus::Suspended).await.unwrap();
+        assert!(service.verify_token_claims(&login.token).await.is_ok());
+        let service = service.with_account_status_cache_ttl(0);
+        assert!(matches!(
+            service.verify_token_claims(&login.token).await,
+            Err(DashboardError::Authorization(_))
+        ));
+
+        // Deleting through the service rejects the token at once
+        let service = service.with_account_status_cache_ttl(3600);
+        service.delete_account(user.id).await.unwrap();
+        assert!(matches!(
+            service.verify_token_claims(&login.token).await,
+            Err(DashboardError::Authentication(_))
+        ));
+
+        let token = sign(serde_json::json!({
+            "sub": "999", "iss": DEFAULT_JWT_ISSUER, "exp": exp(), "iat": Utc::now().timestamp()
+        }));
+        assert!(matches!(service.verify_token_claims(&token).await, Err(DashboardError::Authentication(_))));
+    }
+
+    /// User storage whose user reads, session writes and last-active writes can be made to fail
+    /// independently, standing in for separate user and session backends
+    #[derive(Default)]
+    struct FlakyStorage {
+        inner: InMemoryUserStorage,
+        fail_user_reads: std::sync::atomic::AtomicBool,
+        /// Number of upcoming session writes that fail
+        failing_session_writes: std::sync::atomic::AtomicU32,
+        /// Session writes fail with a permanent error instead of a transient one
+        session_writes_rejected: std::sync::atomic::AtomicBool,
+        fail_last_active: std::sync::atomic::AtomicBool,
+        fail_credential_writes: std::sync::atomic::AtomicBool,
+    }
+
+    impl FlakyStorage {
+        fn backend_down(backend: &str) -> DashboardError {
+            DashboardError::database(format!("{} backend unavailable", backend))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl UserStorage for FlakyStorage {
+        async fn find_user_by_id(&self, id: i64) -> DashboardResult<Option<User>> {
+            self.inner.find_user_by_id(id).await
+        }
+
+        async fn find_user_by_email(&self, email: &str) -> DashboardResult<Option<User>> {
+            if self.fail_user_reads.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(Self::backend_down("user"));
+            }
+            self.inner.find_user_by_email(email).await
+        }
+
+        async fn create_user(&self, user: CreateUserDto) -> DashboardResult<User> {
+            self.inner.create_user(user).await
+        }
+
+        async fn update_user(&self, id: i64, update: UpdateUserDto) -> DashboardResult<User> {
+            self.inner.update_user(id, update).await
+        }
+
+        async fn set_user_status(&self, id: i64, status: UserStatus) -> DashboardResult<User> {
+            self.inner.set_user_status(id, status).await
+        }
+
+        async fn delete_user(&self, id: i64) -> DashboardResult<bool> {
+            self.inner.delete_user(id).await
+        }
+
+        async fn delete_user_records(&self, id: i64) -> DashboardResult<AccountDeletion> {
+            self.inner.delete_user_records(id).await
+        }
+
+        async fn list_users(
+            &self,
+            after_id: Option<i64>,
+            limit: usize,
+            email_contains: Option<&str>,
+        ) -> DashboardResult<Vec<User>> {
+            self.inner.list_users(after_id, limit, email_contains).await
+        }
+
+        async fn store_credentials(&self, user_id: i64, password_hash: &str, salt: &str) -> DashboardResult<()> {
+            if self.fail_credential_writes.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(Self::backend_down("credential"));
+            }
+            self.inner.store_credentials(user_id, password_hash, salt).await
+        }
+
+        async fn get_credentials(&self, user_id: i64) -> DashboardResult<Option<crate::models::user::UserCredentials>> {
+            self.inner.get_credentials(user_id).await
+        }
+
+        async fn get_password_history(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+            self.inner.get_password_history(user_id).await
+        }
+
+        async fn record_password_history(&self, user_id: i64, password_hash: &str, keep: usize) -> DashboardResult<()> {
+            self.inner.record_password_history(user_id, password_hash, keep).await
+        }
+
+        async fn find_user_by_external_identity(&self, issuer: &str, subject: &str) -> DashboardResult<Option<User>> {
+            self.inner.find_user_by_external_identity(issuer, subject).await
+        }
+
+        async fn link_external_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DashboardResult<()> {
+            self.inner.link_external_identity(user_id, issuer, subject).await
+        }
+
+        async fn get_notification_preferences(&self, user_id: i64) -> DashboardResult<Option<NotificationPreferences>> {
+            self.inner.get_notification_preferences(user_id).await
+        }
+
+        async fn set_notification_preferences(&self, user_id: i64, preferences: &NotificationPreferences) -> DashboardResult<()> {
+            self.inner.set_notification_preferences(user_id, preferences).await
+        }
+
+        async fn create_session(
+            &self,
+            user_id: i64,
+            ip_address: &str,
+            user_agent: &str,
+            expires_in_seconds: i64,
+        ) -> DashboardResult<UserSession> {
+            use std::sync::atomic::Ordering;
+            if self.session_writes_rejected.load(Ordering::SeqCst) {
+                return Err(DashboardError::internal_server("session store rejected the write"));
+            }
+            let failing = self.failing_session_writes.load(Ordering::SeqCst);
+            if failing > 0 {
+                self.failing_session_writes.store(failing - 1, Ordering::SeqCst);
+                return Err(Self::backend_down("session"));
+            }
+            self.inner.create_session(user_id, ip_address, user_agent, expires_in_seconds).await
+        }
+
+        async fn find_session_by_id(&self, session_id: &str) -> DashboardResult<Option<UserSession>> {
+            self.inner.find_session_by_id(session_id).await
+        }
+
+        async fn extend_session(&self, session_id: &str, expires_at: DateTime<Utc>) -> DashboardResult<Option<UserSession>> {
+            self.inner.extend_session(session_id, expires_at).await
+        }
+
+        async fn find_sessions_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<UserSession>> {
+            self.inner.find_sessions_by_user_id(user_id).await
+        }
+
+        async fn delete_session(&self, session_id: &str) -> DashboardResult<bool> {
+            self.inner.delete_session(session_id).await
+        }
+
+        async fn delete_user_sessions(&self, user_id: i64) -> DashboardResult<i64> {
+            self.inner.delete_user_sessions(user_id).await
+        }
+
+        async fn update_last_active(&self, user_id: i64) -> DashboardResult<()> {
+            if self.fail_last_active.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(Self::backend_down("user"));
+            }
+            self.inner.update_last_active(user_id).await
+        }
+
+        async fn find_user_by_public_key(&self, public_key: &str) -> DashboardResult<Option<User>> {
+            self.inner.find_user_by_public_key(public_key).await
+        }
+
+        async fn store_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
+            self.inner.store_public_key(user_id, public_key).await
+        }
+
+        async fn revoke_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+            self.inner.revoke_public_key(user_id, public_key).await
+        }
+
+        async fn reactivate_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+            self.inner.reactivate_public_key(user_id, public_key).await
+        }
+
+        async fn delete_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+            self.inner.delete_public_key(user_id, public_key).await
+        }
+
+        async fn get_public_keys_for_user(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+            self.inner.get_public_keys_for_user(user_id).await
+        }
+
+        async fn find_public_key_info(&self, public_key: &str) -> DashboardResult<Option<PublicKeyInfo>> {
+            self.inner.find_public_key_info(public_key).await
+        }
+
+        async fn get_public_key_info(&self, user_id: i64, public_key: &str) -> DashboardResult<Option<PublicKeyInfo>> {
+            self.inner.get_public_key_info(user_id, public_key).await
+        }
+
+        async fn update_public_key_last_used(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
+            self.inner.update_public_key_last_used(user_id, public_key).await
+        }
+    }
+
+    async fn flaky_service() -> (UserService<FlakyStorage>, Arc<FlakyStorage>, i64) {
+        let storage = Arc::new(FlakyStorage::default());
+        let service = UserService::new(storage.clone(), SECRET.to_string(), 3600);
+        let user = service
+            .register_user(CreateUserDto {
+                email: "flaky@example.com".to_string(),
+                username: "flaky".to_string(),
+                password: "password123".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap();
+        (service, storage, user.id)
+    }
+
+    #[tokio::test]
+    async fn test_login_fails_cleanly_when_user_backend_is_down() {
+        let (service, storage, user_id) = flaky_service().await;
+        storage.fail_user_reads.store(true, std::sync::atomic::Ordering::SeqCst);
+
+        let err = service.login("flaky@example.com", "password123", "127.0.0.1", "test").await.unwrap_err();
+        assert!(matches!(err, DashboardError::Database(_)));
+        assert_eq!(StorageError::classify(&err), StorageError::Transient);
+        assert_eq!(storage.delete_user_sessions(user_id).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_login_retries_transient_session_write_failures() {
+        let (service, storage, user_id) = flaky_service().await;
+        storage.failing_session_writes.store(DEFAULT_SESSION_WRITE_RETRIES, std::sync::atomic::Ordering::SeqCst);
+
+        service.login("flaky@example.com", "password123", "127.0.0.1", "test").await.unwrap();
+        assert_eq!(storage.delete_user_sessions(user_id).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_login_without_session_store_is_unavailable_and_leaves_no_session() {
+        let (service, storage, user_id) = flaky_service().await;
+        storage
+            .failing_session_writes
+            .store(DEFAULT_SESSION_WRITE_RETRIES + 1, std::sync::atomic::Ordering::SeqCst);
+
+        let err = service.login("flaky@example.com", "password123", "127.0.0.1", "test").await.unwrap_err();
+        assert!(matches!(err, DashboardError::ServiceUnavailable(_)));
+        assert_eq!(storage.delete_user_sessions(user_id).await.unwrap(), 0);
+
+        // A rejected write is not retried and is reported as-is
+        storage.session_writes_rejected.store(true, std::sync::atomic::Ordering::SeqCst);
+        let err = service.login("flaky@example.com", "password123", "127.0.0.1", "test").await.unwrap_err();
+        assert!(matches!(err, DashboardError::InternalServer(_)));
+        assert_eq!(StorageError::classify(&err), StorageError::Permanent);
+    }
+
+    #[tokio::test]
+    async fn test_login_survives_last_active_write_failure() {
+        let (service, storage, user_id) = flaky_service().await;
+        storage.fail_last_active.store(true, std::sync::atomic::Ordering::SeqCst);
+
+        service.login("flaky@example.com", "password123", "127.0.0.1", "test").await.unwrap();
+        assert_eq!(storage.delete_user_sessions(user_id).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_export_user_data_aggregates_subsystems_and_is_rate_limited() {
+        use crate::models::network::CreateNetworkConnectionDto;
+        use crate::storage::memory::InMemoryNetworkStorage;
+
+        let network_storage = Arc::new(InMemoryNetworkStorage::new());
+        let service = test_service().with_network_storage(network_storage.clone());
+        let user = service
+            .register_user(CreateUserDto {
+                email: "export@example.com".to_string(),
+                username: "export".to_string(),
+                password: "password123".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap();
+        service.add_public_key(user.id, &"ab".repeat(32)).await.unwrap();
+        service.login("export@example.com", "password123", "127.0.0.1", "test").await.unwrap();
+        for network_name in ["home", "office"] {
+            let connection = network_storage
+                .create_connection(CreateNetworkConnectionDto {
+                    user_id: user.id,
+                    network_name: network_name.to_string(),
+                    ip_address: "10.0.0.1".to_string(),
+                    initial_score: None,
+                    allow_duplicate: false,
+                })
+                .await
+                .unwrap();
+            network_storage.record_earned_points(connection.id, 2.5).await.unwrap();
+        }
+
+        let export = service.export_user_data(user.id).await.unwrap();
+        assert_eq!(export.user.id, user.id);
+        assert_eq!(export.public_keys.len(), 1);
+        assert_eq!(export.public_keys[0].public_key, "ab".repeat(32));
+        assert_eq!(export.sessions.len(), 1);
+        assert_eq!(export.network_connections.len(), 2);
+        assert_eq!(export.earnings.total_points_earned, 5.0);
+        assert!(serde_json::to_value(&export).is_ok());
+
+        let err = service.export_user_data(user.id).await.unwrap_err();
+        assert!(matches!(err, DashboardError::RateLimit(..)));
+    }
+
+    #[tokio::test]
+    async fn test_account_deletion_cascades_and_leaves_nothing_orphaned() {
+        use crate::models::network::CreateNetworkConnectionDto;
+        use crate::storage::memory::InMemoryNetworkStorage;
+
+        let storage = Arc::new(InMemoryUserStorage::new());
+        let network_storage = Arc::new(InMemoryNetworkStorage::new());
+        let service = UserService::new(storage.clone(), SECRET.to_string(), 3600)
+            .with_network_storage(network_storage.clone());
+        let mut user_ids = Vec::new();
+        for name in ["leaving", "staying"] {
+            let user = service
+                .register_user(CreateUserDto {
+                    email: format!("{}@example.com", name),
+                    username: name.to_string(),
+                    password: "password123".to_string(),
+                    wallet_address: None,
+                })
+                .await
+                .unwrap();
+            service.login(&user.email, "password123", "127.0.0.1", "test").await.unwrap();
+            let connection = network_storage
+                .create_connection(CreateNetworkConnectionDto {
+                    user_id: user.id,
+                    network_name: "home".to_string(),
+                    ip_address: "10.0.0.1".to_string(),
+                    initial_score: None,
+                    allow_duplicate: false,
+                })
+                .await
+                .unwrap();
+            network_storage.update_network_status(connection.id, true, "ok", None).await.unwrap();
+            user_ids.push((user.id, connection.id));
+        }
+        let ((leaving, leaving_connection), (staying, staying_connection)) = (user_ids[0], user_ids[1]);
+        let key = "cd".repeat(32);
+        service.add_public_key(leaving, &key).await.unwrap();
+
+        let err = service.confirm_account_deletion(leaving, "guess").await.unwrap_err();
+        assert!(matches!(err, DashboardError::Validation(_)));
+        let (token, _) = service.request_account_deletion(leaving).await.unwrap();
+        let err = service.confirm_account_deletion(leaving, "guess").await.unwrap_err();
+        assert!(matches!(err, DashboardError::Validation(_)));
+
+        let deleted = service.confirm_account_deletion(leaving, &token).await.unwrap();
+        assert_eq!(
+            deleted,
+            AccountDeletion {
+                user_deleted: true,
+                sessions: 1,
+                public_keys: 1,
+                network_connections: 1,
+            }
+        );
+
+        assert!(storage.find_user_by_id(leaving).await.unwrap().is_none());
+        assert!(storage.find_sessions_by_user_id(leaving).await.unwrap().is_empty());
+        assert!(storage.find_user_by_public_key(&key).await.unwrap().is_none());
+        assert!(network_storage.find_connections_by_user_id(leaving).await.unwrap().is_empty());
+        assert!(network_storage.get_network_status(leaving_connection).await.unwrap().is_none());
+
+        // Other users are untouched, and the token was single-use
+        assert_eq!(storage.find_sessions_by_user_id(staying).await.unwrap().len(), 1);
+        assert!(network_storage.get_network_status(staying_connection).await.unwrap().is_some());
+        assert!(service.confirm_account_deletion(leaving, &token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_email_does_the_same_hashing_work_as_a_wrong_password() {
+        let storage = Arc::new(InMemoryUserStorage::new());
+        let service = UserService::new(storage.clone(), SECRET.to_string(), 3600);
+        let user = service
+            .register_user(CreateUserDto {
+                email: "known@example.com".to_string(),
+                username: "known".to_string(),
+                password: "password123".to_string(),
+                wallet_address: None,
+            })
+            .await
+            .unwrap();
+
+        // The dummy hash uses the same algorithm and cost as stored hashes
+        let stored = storage.get_credentials(user.id).await.unwrap().unwrap().password_hash;
+        let stored = PasswordHash::new(&stored).unwrap();
+        let dummy = PasswordHash::new(dummy_password_hash()).unwrap();
+        assert_eq!(dummy.algorithm, stored.algorithm);
+        assert_eq!(dummy.version, stored.version);
+        assert_eq!(dummy.params, stored.params);
+
+        // A missing hash never verifies, whatever the password
+        assert!(!password_matches("password123", None).unwrap());
+        assert!(!password_matches("wrong", Some(&stored.to_string())).unwrap());
+        assert!(password_matches("password123", Some(&stored.to_string())).unwrap());
+
+        let unknown = service.login("unknown@example.com", "password123", "127.0.0.1", "test").await.unwrap_err();
+        let wrong = service.login("known@example.com", "wrong", "127.0.0.1", "test").await.unwrap_err();
+        assert_eq!(unknown.to_string(), wrong.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode_refuses_writes_but_serves_reads() {
+        let read_only = ReadOnlyMode::new(false);
+        let service = test_service().with_read_only(read_only.clone());
+        let dto = |email: &str| CreateUserDto {
+            email: email.to_string(),
+            username: "reader".to_string(),
+            password: "password123".to_string(),
+            wallet_address: None,
+        };
+        let user = service.register_user(dto("reader@example.com")).await.unwrap();
+
+        read_only.set_enabled(true);
+        let err = service.register_user(dto("writer@example.com")).await.unwrap_err();
+        assert!(matches!(err, DashboardError::ReadOnly(_)));
+        assert!(matches!(
+            service.delete_account(user.id).await.unwrap_err(),
+            DashboardError::ReadOnly(_)
+        ));
+        assert_eq!(service.get_user(user.id).await.unwrap().email, "reader@example.com");
+        service.login("reader@example.com", "password123", "127.0.0.1", "test").await.unwrap();
+
+        read_only.set_enabled(false);
+        service.register_user(dto("writer@example.com")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_registration_leaves_no_orphan_user() {
+        let storage = Arc::new(FlakyStorage::default());
+        let service = UserService::new(storage.clone(), SECRET.to_string(), 3600);
+        let dto = || CreateUserDto {
+            email: "orphan@example.com".to_string(),
+            username: "orphan".to_string(),
+            password: "password123".to_string(),
+            wallet_address: None,
+        };
+
+        // The user row is written, then the credential write fails
+        storage.fail_credential_writes.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert!(service.register_user(dto()).await.is_err());
+        assert!(storage.find_user_by_email("orphan@example.com").await.unwrap().is_none());
+        assert!(storage.list_users(None, 10, None).await.unwrap().is_empty());
+
+        // The email is free to register again once the backend recovers
+        storage.fail_credential_writes.store(false, std::sync::atomic::Ordering::SeqCst);
+        let user = service.register_user(dto()).await.unwrap();
+        assert_eq!(user.status, UserStatus::PendingVerification);
+        assert!(storage.get_credentials(user.id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_key_registration_reports_each_key_and_respects_the_limit() {
+        let service = test_service().with_max_public_keys(3);
+        let owner = register(&service, "keyholder").await;
+        let other = register(&service, "neighbour").await;
+        let key = |n: u8| format!("{:064x}", n);
+        service.add_public_key(other.id, &key(9)).await.unwrap();
+
+        let results = service
+            .add_public_keys(owner.id, vec![key(1), key(1), key(2), key(9)])
+            .await
+            .unwrap();
+        let statuses: Vec<_> = results.iter().map(|result| result.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                PublicKeyRegistrationStatus::Added,
+                PublicKeyRegistrationStatus::AlreadyExists,
+                PublicKeyRegistrationStatus::Added,
+                PublicKeyRegistrationStatus::Error,
+            ]
+        );
+        assert!(results[3].message.is_some());
+
+        // Two more would make four; nothing from the rejected batch is stored
+        assert!(matches!(
+            service.add_public_keys(owner.id, vec![key(3), key(4)]).await,
+            Err(DashboardError::Validation(_))
+        ));
+        assert_eq!(service.get_public_keys(owner.id).await.unwrap().len(), 2);
+        assert!(matches!(
+            service.add_public_key(owner.id, &key(9)).await,
+            Err(DashboardError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_key_registrations_cannot_exceed_the_limit() {
+        let service = Arc::new(test_service().with_max_public_keys(3));
+        let user_id = register(&service, "racer").await.id;
+
+        let registrations: Vec<_> = (0..10u8)
+            .map(|n| {
+                let service = service.clone();
+                tokio::spawn(async move { service.add_public_key(user_id, &format!("{:064x}", n)).await })
+            })
+            .collect();
+        let mut added = 0;
+        for registration in registrations {
+            if registration.await.unwrap().is_ok() {
+                added += 1;
+            }
+        }
+
+        assert_eq!(added, 3);
+        assert_eq!(service.get_public_keys(user_id).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_account_deletion_counts_what_was_actually_removed() {
+        let storage = Arc::new(InMemoryUserStorage::new());
+        let service = UserService::new(storage.clone(), SECRET.to_string(), 3600);
+        let user = register(&service, "counted").await;
+        for _ in 0..2 {
+            service.login(&user.email, "password123", "127.0.0.1", "test").await.unwrap();
+        }
+        let (active, revoked) = ("ab".repeat(32), "ef".repeat(32));
+        service.add_public_key(user.id, &active).await.unwrap();
+        service.add_public_key(user.id, &revoked).await.unwrap();
+        service.revoke_public_key(user.id, &revoked).await.unwrap();
+
+        let deleted = service.delete_account(user.id).await.unwrap();
+        assert_eq!((deleted.sessions, deleted.public_keys), (2, 2));
+        assert!(storage.find_public_key_info(&revoked).await.unwrap().is_none());
+
+        // A second deletion finds nothing to remove
+        assert!(matches!(service.delete_account(user.id).await, Err(DashboardError::NotFound(_))));
+        assert_eq!(storage.delete_user_records(user.id).await.unwrap(), AccountDeletion::default());
+    }
+
+    #[tokio::test]
+    async fn test_public_key_info_is_scoped_to_the_owner_and_masks_on_request() {
+        let service = test_service();
+        let owner = register(&service, "owner").await;
+        let other = register(&service, "other").await;
+        let key = format!("abcd{}ef01", "0".repeat(56));
+        service.add_public_key(owner.id, &key).await.unwrap();
+
+        let info = service.get_public_key_info(owner.id, &key).await.unwrap();
+        assert_eq!(info.user_id, owner.id);
+        assert_eq!(info.public_key, key);
+        assert!(!info.revoked);
+
+        let masked = info.masked();
+        assert_eq!(masked.public_key, "abcd…ef01");
+        assert_eq!(masked.user_id, info.user_id);
+        assert_eq!(masked.created_at, info.created_at);
+        assert_eq!(masked.revoked, info.revoked);
+
+        // Another user's key, or a missing user, is not found rather than leaked
+        assert!(matches!(
+            service.get_public_key_info(other.id, &key).await,
+            Err(DashboardError::NotFound(_))
+        ));
+        assert!(matches!(
+            service.get_public_key_info(999, &key).await,
+            Err(DashboardError::NotFound(_))
+        ));
+    }
+}
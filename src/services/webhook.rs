@@ -0,0 +1,386 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use nanoid::nanoid;
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tracing::{debug, error, info, warn};
+
+use crate::config::WebhookConfig;
+use crate::errors::{DashboardError, DashboardResult};
+use crate::metrics::Metrics;
+use crate::services::broadcast::{EARNINGS_CHANNEL, NETWORK_CHANNEL};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Header carrying `sha256=<hex HMAC>` of `"{timestamp}.{body}"`
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Header carrying the unix timestamp that was signed
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+
+/// Header carrying the event ID, the same on every retry so receivers can deduplicate
+pub const EVENT_ID_HEADER: &str = "X-Webhook-Id";
+
+/// Broadcast channels whose events are delivered to webhooks
+pub const WEBHOOK_CHANNELS: &[&str] = &[NETWORK_CHANNEL, EARNINGS_CHANNEL];
+
+/// Deliveries kept for the admin API, oldest dropped first
+const RECENT_DELIVERIES: usize = 200;
+
+/// Longest wait between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Body POSTed to every webhook URL
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    pub id: String,
+    pub user_id: i64,
+    pub channel: String,
+    /// The frame the user's WebSocket sessions received
+    pub event: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Where a delivery stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Not yet acknowledged; retries may be outstanding
+    Pending,
+    /// Acknowledged with a `2xx`
+    Delivered,
+    /// Every attempt failed
+    Failed,
+}
+
+/// One event's delivery to one URL, as listed by the admin API
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookDelivery {
+    pub event_id: String,
+    pub url: String,
+    pub user_id: i64,
+    pub channel: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    /// HTTP status of the latest attempt, if the receiver answered
+    pub last_status_code: Option<u16>,
+    /// Why the latest attempt failed
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// `sha256=<hex>` HMAC-SHA256 of `"{timestamp}.{body}"` under `secret`.
+///
+/// Receivers recompute it over the raw body and the `X-Webhook-Timestamp` header; including
+/// the timestamp lets them refuse replays of old deliveries.
+pub fn sign(secret: &[u8], timestamp: i64, body: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Wait before retrying after failed attempt number `attempt` (1-based): doubling from
+/// `initial`, capped at a minute
+pub fn backoff(initial: Duration, attempt: u32) -> Duration {
+    initial
+        .checked_mul(1 << attempt.saturating_sub(1).min(16))
+        .map_or(MAX_BACKOFF, |wait| wait.min(MAX_BACKOFF))
+}
+
+/// Queue of network and earnings events delivered to the configured webhook URLs.
+///
+/// Each event is POSTed to every URL independently, retried with exponential backoff until
+/// acknowledged or `WEBHOOK_MAX_ATTEMPTS` is reached. Delivery happens in the background, so
+/// publishing never waits on a receiver.
+#[derive(Clone, Default)]
+pub struct Webhooks {
+    queue: Option<UnboundedSender<WebhookEvent>>,
+    deliveries: Arc<Mutex<VecDeque<WebhookDelivery>>>,
+}
+
+impl Webhooks {
+    /// Webhooks that deliver nothing, for when no URLs are configured
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Start delivering to `config.urls`; with no URLs the result is disabled.
+    ///
+    /// Fails if URLs are configured without a `WEBHOOK_SECRET`, since receivers could not
+    /// verify the deliveries.
+    pub fn spawn(config: WebhookConfig, metrics: Arc<Metrics>) -> DashboardResult<Self> {
+        if config.urls.is_empty() {
+            return Ok(Self::disabled());
+        }
+        let secret = config
+            .secret
+            .clone()
+            .filter(|secret| !secret.is_empty())
+            .ok_or_else(|| DashboardError::internal_server("WEBHOOK_SECRET is required with WEBHOOK_URLS"))?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout))
+            .build()
+            .map_err(|e| DashboardError::internal_server(format!("Failed to build webhook client: {}", e)))?;
+
+        let (queue, events) = tokio::sync::mpsc::unbounded_channel();
+        let webhooks = Self {
+            queue: Some(queue),
+            deliveries: Arc::default(),
+        };
+        let dispatcher = Arc::new(Dispatcher {
+            client,
+            secret,
+            config,
+            deliveries: webhooks.deliveries.clone(),
+            metrics,
+        });
+        info!("Delivering network and earnings events to {} webhooks", dispatcher.config.urls.len());
+        actix_rt::spawn(dispatcher.run(events));
+        Ok(webhooks)
+    }
+
+    /// Whether any webhook URL is configured
+    pub fn is_enabled(&self) -> bool {
+        self.queue.is_some()
+    }
+
+    /// Queue a user's event for delivery; events on other channels than `WEBHOOK_CHANNELS`
+    /// are ignored
+    pub fn enqueue(&self, user_id: i64, channel: &str, event: &serde_json::Value) {
+        let queue = match &self.queue {
+            Some(queue) if WEBHOOK_CHANNELS.contains(&channel) => queue,
+            _ => return,
+        };
+        let event = WebhookEvent {
+            id: nanoid!(),
+            user_id,
+            channel: channel.to_string(),
+            event: event.clone(),
+            created_at: Utc::now(),
+        };
+        if queue.send(event).is_err() {
+            warn!("Webhook dispatcher has stopped; event not delivered");
+        }
+    }
+
+    /// The most recent deliveries, newest first
+    pub fn recent_deliveries(&self) -> Vec<WebhookDelivery> {
+        match self.deliveries.lock() {
+            Ok(deliveries) => deliveries.iter().rev().cloned().collect(),
+            Err(e) => {
+                error!("Failed to lock webhook deliveries: {}", e);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// Background side of `Webhooks`
+struct Dispatcher {
+    client: reqwest::Client,
+    secret: String,
+    config: WebhookConfig,
+    deliveries: Arc<Mutex<VecDeque<WebhookDelivery>>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Dispatcher {
+    async fn run(self: Arc<Self>, mut events: UnboundedReceiver<WebhookEvent>) {
+        while let Some(event) = events.recv().await {
+            let event = Arc::new(event);
+            for url in &self.config.urls {
+                actix_rt::spawn(self.clone().deliver(event.clone(), url.clone()));
+            }
+        }
+    }
+
+    /// POST `event` to `url` until it is acknowledged or the attempts run out
+    async fn deliver(self: Arc<Self>, event: Arc<WebhookEvent>, url: String) {
+        let body = match serde_json::to_string(&*event) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Failed to serialize webhook event {}: {}", event.id, e);
+                return;
+            }
+        };
+        self.record(WebhookDelivery {
+            event_id: event.id.clone(),
+            url: url.clone(),
+            user_id: event.user_id,
+            channel: event.channel.clone(),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            last_status_code: None,
+            last_error: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+
+        let max_attempts = self.config.max_attempts.max(1);
+        for attempt in 1..=max_attempts {
+            let (status_code, error) = self.attempt(&event.id, &url, &body).await;
+            let delivered = error.is_none();
+            let status = match (delivered, attempt == max_attempts) {
+                (true, _) => DeliveryStatus::Delivered,
+                (false, true) => DeliveryStatus::Failed,
+                (false, false) => DeliveryStatus::Pending,
+            };
+            self.update(&event.id, &url, |delivery| {
+                delivery.status = status;
+                delivery.attempts = attempt;
+                delivery.last_status_code = status_code;
+                delivery.last_error = error.clone();
+                delivery.updated_at = Utc::now();
+            });
+
+            match status {
+                DeliveryStatus::Delivered => {
+                    debug!("Delivered webhook event {} to {} on attempt {}", event.id, url, attempt);
+                    self.metrics.record_webhook_delivered();
+                    return;
+                }
+                DeliveryStatus::Failed => {
+                    warn!(
+                        "Giving up on webhook event {} to {} after {} attempts: {}",
+                        event.id,
+                        url,
+                        attempt,
+                        error.unwrap_or_default()
+                    );
+                    self.metrics.record_webhook_failed();
+                    return;
+                }
+                DeliveryStatus::Pending => {
+                    self.metrics.record_webhook_retry();
+                    tokio::time::sleep(backoff(Duration::from_millis(self.config.initial_backoff_ms), attempt)).await;
+                }
+            }
+        }
+    }
+
+    /// One signed POST, returning the response status and, unless it was a `2xx`, why it failed
+    async fn attempt(&self, event_id: &str, url: &str, body: &str) -> (Option<u16>, Option<String>) {
+        let timestamp = Utc::now().timestamp();
+        let response = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_ID_HEADER, event_id)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(self.secret.as_bytes(), timestamp, body))
+            .body(body.to_string())
+            .send()
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+            Ok(response) => (
+                Some(response.status().as_u16()),
+                Some(format!("Receiver answered {}", response.status())),
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        }
+    }
+
+    fn record(&self, delivery: WebhookDelivery) {
+        match self.deliveries.lock() {
+            Ok(mut deliveries) => {
+                if deliveries.len() >= RECENT_DELIVERIES {
+                    deliveries.pop_front();
+                }
+                deliveries.push_back(delivery);
+            }
+            Err(e) => error!("Failed to lock webhook deliveries: {}", e),
+        }
+    }
+
+    fn update<F>(&self, event_id: &str, url: &str, update: F)
+    where
+        F: FnOnce(&mut WebhookDelivery),
+    {
+        match self.deliveries.lock() {
+            Ok(mut deliveries) => {
+                if let Some(delivery) = deliveries
+                    .iter_mut()
+                    .rev()
+                    .find(|delivery| delivery.event_id == event_id && delivery.url == url)
+                {
+                    update(delivery);
+                }
+            }
+            Err(e) => error!("Failed to lock webhook deliveries: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(urls: Vec<String>) -> WebhookConfig {
+        WebhookConfig {
+            urls,
+            secret: Some("whsec_test".to_string()),
+            max_attempts: 2,
+            initial_backoff_ms: 10,
+            timeout: 1,
+        }
+    }
+
+    #[test]
+    fn test_signature_matches_reference_hmac() {
+        assert_eq!(
+            sign(b"whsec_test", 1_700_000_000, r#"{"hello":"world"}"#),
+            "sha256=f592bbf3951cfc94e560eecfb5d9dd4da6b0fff2e626235f8ab4b54860925d0b"
+        );
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let initial = Duration::from_millis(500);
+        assert_eq!(backoff(initial, 1), Duration::from_millis(500));
+        assert_eq!(backoff(initial, 2), Duration::from_secs(1));
+        assert_eq!(backoff(initial, 4), Duration::from_secs(4));
+        assert_eq!(backoff(initial, 50), MAX_BACKOFF);
+    }
+
+    #[actix_rt::test]
+    async fn test_urls_without_secret_are_refused() {
+        let mut config = config(vec!["http://127.0.0.1:9/hook".to_string()]);
+        config.secret = None;
+        assert!(Webhooks::spawn(config, Arc::new(Metrics::new())).is_err());
+        assert!(!Webhooks::spawn(self::config(vec![]), Arc::new(Metrics::new())).unwrap().is_enabled());
+    }
+
+    #[actix_rt::test]
+    async fn test_unreachable_receiver_is_retried_then_failed() {
+        // Nothing listens on the discard port, so every attempt is refused
+        let webhooks = Webhooks::spawn(config(vec!["http://127.0.0.1:9/hook".to_string()]), Arc::new(Metrics::new())).unwrap();
+
+        webhooks.enqueue(7, "referrals", &serde_json::json!({ "type": "ignored" }));
+        webhooks.enqueue(7, EARNINGS_CHANNEL, &serde_json::json!({ "type": "EarningsUpdate" }));
+
+        let mut delivery = None;
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            delivery = webhooks
+                .recent_deliveries()
+                .into_iter()
+                .find(|delivery| delivery.status == DeliveryStatus::Failed);
+            if delivery.is_some() {
+                break;
+            }
+        }
+        let delivery = delivery.expect("delivery should fail");
+        assert_eq!(delivery.attempts, 2);
+        assert_eq!(delivery.channel, EARNINGS_CHANNEL);
+        assert!(delivery.last_error.is_some());
+        assert_eq!(webhooks.recent_deliveries().len(), 1);
+    }
+}
@@ -0,0 +1,109 @@
+use nanoid::nanoid;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+/// Short-lived, single-use tickets that authenticate a WebSocket handshake via `?ticket=`.
+///
+/// Browsers cannot set headers on the upgrade request, and a JWT in the URL ends up in
+/// access logs; a ticket is useless once consumed or expired.
+#[derive(Clone)]
+pub struct WsTickets {
+    ttl: Duration,
+    /// ticket -> (user_id, expires_at)
+    tickets: Arc<Mutex<HashMap<String, (i64, Instant)>>>,
+}
+
+impl WsTickets {
+    /// Create a ticket store whose tickets expire `ttl` after issue
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            tickets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// How long a ticket stays valid
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Mint a ticket for an authenticated user
+    pub fn issue(&self, user_id: i64, now: Instant) -> Option<String> {
+        let mut tickets = match self.tickets.lock() {
+            Ok(tickets) => tickets,
+            Err(e) => {
+                error!("Failed to lock WebSocket tickets: {}", e);
+                return None;
+            }
+        };
+        tickets.retain(|_, (_, expires_at)| *expires_at > now);
+
+        let ticket = nanoid!(32);
+        tickets.insert(ticket.clone(), (user_id, now + self.ttl));
+        Some(ticket)
+    }
+
+    /// Consume a ticket, returning its user if it had not expired
+    pub fn redeem(&self, ticket: &str, now: Instant) -> Option<i64> {
+        let (user_id, expires_at) = match self.tickets.lock() {
+            Ok(mut tickets) => tickets.remove(ticket)?,
+            Err(e) => {
+                error!("Failed to lock WebSocket tickets: {}", e);
+                return None;
+            }
+        };
+
+        if expires_at <= now {
+            return None;
+        }
+        Some(user_id)
+    }
+
+    /// Drop every outstanding ticket of a user, returning how many were removed
+    pub fn revoke_user(&self, user_id: i64) -> usize {
+        match self.tickets.lock() {
+            Ok(mut tickets) => {
+                let before = tickets.len();
+                tickets.retain(|_, (owner, _)| *owner != user_id);
+                before - tickets.len()
+            }
+            Err(e) => {
+                error!("Failed to lock WebSocket tickets: {}", e);
+                0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticket_is_single_use_and_expires() {
+        let tickets = WsTickets::new(Duration::from_secs(30));
+        let now = Instant::now();
+
+        let ticket = tickets.issue(7, now).unwrap();
+        assert_eq!(tickets.redeem(&ticket, now), Some(7));
+        assert_eq!(tickets.redeem(&ticket, now), None);
+
+        let ticket = tickets.issue(7, now).unwrap();
+        assert_eq!(tickets.redeem(&ticket, now + Duration::from_secs(30)), None);
+        assert_eq!(tickets.redeem("unknown", now), None);
+    }
+
+    #[test]
+    fn test_revoke_user_drops_only_their_tickets() {
+        let tickets = WsTickets::new(Duration::from_secs(30));
+        let now = Instant::now();
+
+        let mine = tickets.issue(7, now).unwrap();
+        let theirs = tickets.issue(8, now).unwrap();
+        assert_eq!(tickets.revoke_user(7), 1);
+        assert_eq!(tickets.redeem(&mine, now), None);
+        assert_eq!(tickets.redeem(&theirs, now), Some(8));
+    }
+}
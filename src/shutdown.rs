@@ -0,0 +1,128 @@
+use futures::future::LocalBoxFuture;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// One step of the shutdown sequence
+struct ShutdownPhase {
+    name: &'static str,
+    timeout: Duration,
+    run: Box<dyn FnOnce() -> LocalBoxFuture<'static, ()>>,
+}
+
+/// Result of running one shutdown phase
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseOutcome {
+    /// Phase name
+    pub name: &'static str,
+    /// `false` if the phase hit its timeout and was abandoned
+    pub completed: bool,
+}
+
+/// Runs shutdown phases in order, each bounded by its own timeout.
+///
+/// A phase that times out is logged and abandoned; later phases still run, so one stuck
+/// step can't keep the process alive indefinitely.
+#[derive(Default)]
+pub struct ShutdownCoordinator {
+    phases: Vec<ShutdownPhase>,
+}
+
+impl ShutdownCoordinator {
+    /// Create a coordinator with no phases
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a phase that runs `step` for at most `timeout`
+    pub fn phase<F, Fut>(mut self, name: &'static str, timeout: Duration, step: F) -> Self
+    where
+        F: FnOnce() -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        self.phases.push(ShutdownPhase {
+            name,
+            timeout,
+            run: Box::new(move || Box::pin(step())),
+        });
+        self
+    }
+
+    /// Run every phase in order
+    pub async fn run(self) -> Vec<PhaseOutcome> {
+        let mut outcomes = Vec::with_capacity(self.phases.len());
+        for phase in self.phases {
+            info!("Shutdown phase '{}' starting (timeout {:?})", phase.name, phase.timeout);
+            let started = Instant::now();
+            let completed = tokio::time::timeout(phase.timeout, (phase.run)()).await.is_ok();
+            if completed {
+                info!("Shutdown phase '{}' finished in {:?}", phase.name, started.elapsed());
+            } else {
+                warn!("Shutdown phase '{}' timed out after {:?}", phase.name, phase.timeout);
+            }
+            outcomes.push(PhaseOutcome {
+                name: phase.name,
+                completed,
+            });
+        }
+        outcomes
+    }
+}
+
+/// Wait for SIGTERM or SIGINT, returning the signal's name
+pub async fn wait_for_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+            },
+            Err(e) => {
+                warn!("Failed to install SIGTERM handler, only SIGINT triggers shutdown: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl-C"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[actix_rt::test]
+    async fn test_phases_run_in_order_and_timeouts_do_not_block_later_phases() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let (first, second, third) = (log.clone(), log.clone(), log.clone());
+
+        let outcomes = ShutdownCoordinator::new()
+            .phase("first", Duration::from_secs(1), move || async move {
+                first.borrow_mut().push("first");
+            })
+            .phase("stuck", Duration::from_millis(20), move || async move {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                second.borrow_mut().push("stuck");
+            })
+            .phase("last", Duration::from_secs(1), move || async move {
+                third.borrow_mut().push("last");
+            })
+            .run()
+            .await;
+
+        assert_eq!(*log.borrow(), vec!["first", "last"]);
+        assert_eq!(
+            outcomes.iter().map(|o| (o.name, o.completed)).collect::<Vec<_>>(),
+            vec![("first", true), ("stuck", false), ("last", true)]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::errors::DashboardResult;
+use crate::models::user::{
+    AccountDeletion, CreateUserDto, NewUserAccount, NotificationPreferences, PublicKeyInfo, PublicKeyRegistrationResult,
+    UpdateUserDto, User, UserCredentials, UserSession, UserStatus,
+};
+use crate::storage::memory::InMemoryUserStorage;
+use crate::storage::postgres::PostgresUserStorage;
+use crate::storage::UserStorage;
+
+/// User storage backend chosen at startup: PostgreSQL when `DATABASE_URL` is set, otherwise
+/// the in-memory store
+#[derive(Clone)]
+pub enum UserBackend {
+    Memory(InMemoryUserStorage),
+    Postgres(PostgresUserStorage),
+}
+
+#[async_trait]
+impl UserStorage for UserBackend {
+    async fn find_user_by_id(&self, id: i64) -> DashboardResult<Option<User>> {
+        match self {
+            UserBackend::Memory(storage) => storage.find_user_by_id(id).await,
+            UserBackend::Postgres(storage) => storage.find_user_by_id(id).await,
+        }
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> DashboardResult<Option<User>> {
+        match self {
+            UserBackend::Memory(storage) => storage.find_user_by_email(email).await,
+            UserBackend::Postgres(storage) => storage.find_user_by_email(email).await,
+        }
+    }
+
+    async fn create_user(&self, user: CreateUserDto) -> DashboardResult<User> {
+        match self {
+            UserBackend::Memory(storage) => storage.create_user(user).await,
+            UserBackend::Postgres(storage) => storage.create_user(user).await,
+        }
+    }
+
+    async fn create_user_with_credentials(&self, account: NewUserAccount) -> DashboardResult<User> {
+        match self {
+            UserBackend::Memory(storage) => storage.create_user_with_credentials(account).await,
+            UserBackend::Postgres(storage) => storage.create_user_with_credentials(account).await,
+        }
+    }
+
+    async fn update_user(&self, id: i64, update: UpdateUserDto) -> DashboardResult<User> {
+        match self {
+            UserBackend::Memory(storage) => storage.update_user(id, update).await,
+            UserBackend::Postgres(storage) => storage.update_user(id, update).await,
+        }
+    }
+
+    async fn set_user_status(&self, id: i64, status: UserStatus) -> DashboardResult<User> {
+        match self {
+            UserBackend::Memory(storage) => storage.set_user_status(id, status).await,
+            UserBackend::Postgres(storage) => storage.set_user_status(id, status).await,
+        }
+    }
+
+    async fn delete_user(&self, id: i64) -> DashboardResult<bool> {
+        match self {
+            UserBackend::Memory(storage) => storage.delete_user(id).await,
+            UserBackend::Postgres(storage) => storage.delete_user(id).await,
+        }
+    }
+
+    async fn delete_user_records(&self, id: i64) -> DashboardResult<AccountDeletion> {
+        match self {
+            UserBackend::Memory(storage) => storage.delete_user_records(id).await,
+            UserBackend::Postgres(storage) => storage.delete_user_records(id).await,
+        }
+    }
+
+    async fn list_users(
+        &self,
+        after_id: Option<i64>,
+        limit: usize,
+        email_contains: Option<&str>,
+    ) -> DashboardResult<Vec<User>> {
+        match self {
+            UserBackend::Memory(storage) => storage.list_users(after_id, limit, email_contains).await,
+            UserBackend::Postgres(storage) => storage.list_users(after_id, limit, email_contains).await,
+        }
+    }
+
+    async fn store_credentials(
+        &self,
+        user_id: i64,
+        password_hash: &str,
+        salt: &str,
+    ) -> DashboardResult<()> {
+        match self {
+            UserBackend::Memory(storage) => storage.store_credentials(user_id, password_hash, salt).await,
+            UserBackend::Postgres(storage) => storage.store_credentials(user_id, password_hash, salt).await,
+        }
+    }
+
+    async fn get_credentials(&self, user_id: i64) -> DashboardResult<Option<UserCredentials>> {
+        match self {
+            UserBackend::Memory(storage) => storage.get_credentials(user_id).await,
+            UserBackend::Postgres(storage) => storage.get_credentials(user_id).await,
+        }
+    }
+
+    async fn get_password_history(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+        match self {
+            UserBackend::Memory(storage) => storage.get_password_history(user_id).await,
+            UserBackend::Postgres(storage) => storage.get_password_history(user_id).await,
+        }
+    }
+
+    async fn record_password_history(
+        &self,
+        user_id: i64,
+        password_hash: &str,
+        keep: usize,
+    ) -> DashboardResult<()> {
+        match self {
+            UserBackend::Memory(storage) => storage.record_password_history(user_id, password_hash, keep).await,
+            UserBackend::Postgres(storage) => storage.record_password_history(user_id, password_hash, keep).await,
+        }
+    }
+
+    async fn find_user_by_external_identity(&self, issuer: &str, subject: &str) -> DashboardResult<Option<User>> {
+        match self {
+            UserBackend::Memory(storage) => storage.find_user_by_external_identity(issuer, subject).await,
+            UserBackend::Postgres(storage) => storage.find_user_by_external_identity(issuer, subject).await,
+        }
+    }
+
+    async fn link_external_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DashboardResult<()> {
+        match self {
+            UserBackend::Memory(storage) => storage.link_external_identity(user_id, issuer, subject).await,
+            UserBackend::Postgres(storage) => storage.link_external_identity(user_id, issuer, subject).await,
+        }
+    }
+
+    async fn get_notification_preferences(&self, user_id: i64) -> DashboardResult<Option<NotificationPreferences>> {
+        match self {
+            UserBackend::Memory(storage) => storage.get_notification_preferences(user_id).await,
+            UserBackend::Postgres(storage) => storage.get_notification_preferences(user_id).await,
+        }
+    }
+
+    async fn set_notification_preferences(&self, user_id: i64, preferences: &NotificationPreferences) -> DashboardResult<()> {
+        match self {
+            UserBackend::Memory(storage) => storage.set_notification_preferences(user_id, preferences).await,
+            UserBackend::Postgres(storage) => storage.set_notification_preferences(user_id, preferences).await,
+        }
+    }
+
+    async fn create_session(
+        &self,
+        user_id: i64,
+        ip_address: &str,
+        user_agent: &str,
+        expires_in_seconds: i64,
+    ) -> DashboardResult<UserSession> {
+        match self {
+            UserBackend::Memory(storage) => storage.create_session(user_id, ip_address, user_agent, expires_in_seconds).await,
+            UserBackend::Postgres(storage) => storage.create_session(user_id, ip_address, user_agent, expires_in_seconds).await,
+        }
+    }
+
+    async fn find_session_by_id(&self, session_id: &str) -> DashboardResult<Option<UserSession>> {
+        match self {
+            UserBackend::Memory(storage) => storage.find_session_by_id(session_id).await,
+            UserBackend::Postgres(storage) => storage.find_session_by_id(session_id).await,
+        }
+    }
+
+    async fn extend_session(&self, session_id: &str, expires_at: DateTime<Utc>) -> DashboardResult<Option<UserSession>> {
+        match self {
+            UserBackend::Memory(storage) => storage.extend_session(session_id, expires_at).await,
+            UserBackend::Postgres(storage) => storage.extend_session(session_id, expires_at).await,
+        }
+    }
+
+    async fn find_sessions_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<UserSession>> {
+        match self {
+            UserBackend::Memory(storage) => storage.find_sessions_by_user_id(user_id).await,
+            UserBackend::Postgres(storage) => storage.find_sessions_by_user_id(user_id).await,
+        }
+    }
+
+    async fn delete_session(&self, session_id: &str) -> DashboardResult<bool> {
+        match self {
+            UserBackend::Memory(storage) => storage.delete_session(session_id).await,
+            UserBackend::Postgres(storage) => storage.delete_session(session_id).await,
+        }
+    }
+
+    async fn delete_user_sessions(&self, user_id: i64) -> DashboardResult<i64> {
+        match self {
+            UserBackend::Memory(storage) => storage.delete_user_sessions(user_id).await,
+            UserBackend::Postgres(storage) => storage.delete_user_sessions(user_id).await,
+        }
+    }
+
+    async fn update_last_active(&self, user_id: i64) -> DashboardResult<()> {
+        match self {
+            UserBackend::Memory(storage) => storage.update_last_active(user_id).await,
+            UserBackend::Postgres(storage) => storage.update_last_active(user_id).await,
+        }
+    }
+
+    async fn find_user_by_public_key(&self, public_key: &str) -> DashboardResult<Option<User>> {
+        match self {
+            UserBackend::Memory(storage) => storage.find_user_by_public_key(public_key).await,
+            UserBackend::Postgres(storage) => storage.find_user_by_public_key(public_key).await,
+        }
+    }
+
+    async fn store_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
+        match self {
+            UserBackend::Memory(storage) => storage.store_public_key(user_id, public_key).await,
+            UserBackend::Postgres(storage) => storage.store_public_key(user_id, public_key).await,
+        }
+    }
+
+    async fn store_public_keys(
+        &self,
+        user_id: i64,
+        public_keys: &[String],
+        max_keys: usize,
+    ) -> DashboardResult<Vec<PublicKeyRegistrationResult>> {
+        match self {
+            UserBackend::Memory(storage) => storage.store_public_keys(user_id, public_keys, max_keys).await,
+            UserBackend::Postgres(storage) => storage.store_public_keys(user_id, public_keys, max_keys).await,
+        }
+    }
+
+    async fn revoke_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        match self {
+            UserBackend::Memory(storage) => storage.revoke_public_key(user_id, public_key).await,
+            UserBackend::Postgres(storage) => storage.revoke_public_key(user_id, public_key).await,
+        }
+    }
+
+    async fn reactivate_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        match self {
+            UserBackend::Memory(storage) => storage.reactivate_public_key(user_id, public_key).await,
+            UserBackend::Postgres(storage) => storage.reactivate_public_key(user_id, public_key).await,
+        }
+    }
+
+    async fn delete_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        match self {
+            UserBackend::Memory(storage) => storage.delete_public_key(user_id, public_key).await,
+            UserBackend::Postgres(storage) => storage.delete_public_key(user_id, public_key).await,
+        }
+    }
+
+    async fn get_public_keys_for_user(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+        match self {
+            UserBackend::Memory(storage) => storage.get_public_keys_for_user(user_id).await,
+            UserBackend::Postgres(storage) => storage.get_public_keys_for_user(user_id).await,
+        }
+    }
+
+    async fn find_public_key_info(
+        &self,
+        public_key: &str,
+    ) -> DashboardResult<Option<PublicKeyInfo>> {
+        match self {
+            UserBackend::Memory(storage) => storage.find_public_key_info(public_key).await,
+            UserBackend::Postgres(storage) => storage.find_public_key_info(public_key).await,
+        }
+    }
+
+    async fn get_public_key_info(
+        &self,
+        user_id: i64,
+        public_key: &str,
+    ) -> DashboardResult<Option<PublicKeyInfo>> {
+        match self {
+            UserBackend::Memory(storage) => storage.get_public_key_info(user_id, public_key).await,
+            UserBackend::Postgres(storage) => storage.get_public_key_info(user_id, public_key).await,
+        }
+    }
+
+    async fn update_public_key_last_used(
+        &self,
+        user_id: i64,
+        public_key: &str,
+    ) -> DashboardResult<()> {
+        match self {
+            UserBackend::Memory(storage) => storage.update_public_key_last_used(user_id, public_key).await,
+            UserBackend::Postgres(storage) => storage.update_public_key_last_used(user_id, public_key).await,
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::errors::DashboardResult;
+use crate::models::network::{
+    ConnectionScoreFilter, CreateNetworkConnectionDto, NetworkConnection, NetworkEvent,
+    NetworkStatistics, NetworkStatus, UpdateNetworkConnectionDto,
+};
+use crate::models::user::{
+    AccountDeletion, CreateUserDto, NewUserAccount, NotificationPreferences, PublicKeyInfo, PublicKeyRegistrationResult,
+    UpdateUserDto, User, UserCredentials, UserSession, UserStatus,
+};
+use crate::services::circuit_breaker::CircuitBreaker;
+use crate::storage::{NetworkStorage, UserStorage};
+
+/// Storage decorator running every call of the wrapped store through a `CircuitBreaker`.
+///
+/// Give the user and network stores the same breaker when they share a database, so an outage
+/// seen by one short-circuits both.
+#[derive(Clone)]
+pub struct CircuitBreakerStorage<S> {
+    inner: S,
+    breaker: CircuitBreaker,
+}
+
+impl<S> CircuitBreakerStorage<S> {
+    /// Wrap `inner` with `breaker`
+    pub fn new(inner: S, breaker: CircuitBreaker) -> Self {
+        Self { inner, breaker }
+    }
+}
+
+#[async_trait]
+impl<S: UserStorage> UserStorage for CircuitBreakerStorage<S> {
+    async fn find_user_by_id(&self, id: i64) -> DashboardResult<Option<User>> {
+        self.breaker.call(self.inner.find_user_by_id(id)).await
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> DashboardResult<Option<User>> {
+        self.breaker.call(self.inner.find_user_by_email(email)).await
+    }
+
+    async fn create_user(&self, user: CreateUserDto) -> DashboardResult<User> {
+        self.breaker.call(self.inner.create_user(user)).await
+    }
+
+    async fn create_user_with_credentials(&self, account: NewUserAccount) -> DashboardResult<User> {
+        self.breaker.call(self.inner.create_user_with_credentials(account)).await
+    }
+
+    async fn update_user(&self, id: i64, update: UpdateUserDto) -> DashboardResult<User> {
+        self.breaker.call(self.inner.update_user(id, update)).await
+    }
+
+    async fn set_user_status(&self, id: i64, status: UserStatus) -> DashboardResult<User> {
+        self.breaker.call(self.inner.set_user_status(id, status)).await
+    }
+
+    async fn delete_user(&self, id: i64) -> DashboardResult<bool> {
+        self.breaker.call(self.inner.delete_user(id)).await
+    }
+
+    async fn delete_user_records(&self, id: i64) -> DashboardResult<AccountDeletion> {
+        self.breaker.call(self.inner.delete_user_records(id)).await
+    }
+
+    async fn list_users(
+        &self,
+        after_id: Option<i64>,
+        limit: usize,
+        email_contains: Option<&str>,
+    ) -> DashboardResult<Vec<User>> {
+        self.breaker.call(self.inner.list_users(after_id, limit, email_contains)).await
+    }
+
+    async fn store_credentials(
+        &self,
+        user_id: i64,
+        password_hash: &str,
+        salt: &str,
+    ) -> DashboardResult<()> {
+        self.breaker.call(self.inner.store_credentials(user_id, password_hash, salt)).await
+    }
+
+    async fn get_credentials(&self, user_id: i64) -> DashboardResult<Option<UserCredentials>> {
+        self.breaker.call(self.inner.get_credentials(user_id)).await
+    }
+
+    async fn get_password_history(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+        self.breaker.call(self.inner.get_password_history(user_id)).await
+    }
+
+    async fn record_password_history(
+        &self,
+        user_id: i64,
+        password_hash: &str,
+        keep: usize,
+    ) -> DashboardResult<()> {
+        self.breaker.call(self.inner.record_password_history(user_id, password_hash, keep)).await
+    }
+
+    async fn find_user_by_external_identity(&self, issuer: &str, subject: &str) -> DashboardResult<Option<User>> {
+        self.breaker.call(self.inner.find_user_by_external_identity(issuer, subject)).await
+    }
+
+    async fn link_external_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DashboardResult<()> {
+        self.breaker.call(self.inner.link_external_identity(user_id, issuer, subject)).await
+    }
+
+    async fn get_notification_preferences(&self, user_id: i64) -> DashboardResult<Option<NotificationPreferences>> {
+        self.breaker.call(self.inner.get_notification_preferences(user_id)).await
+    }
+
+    async fn set_notification_preferences(&self, user_id: i64, preferences: &NotificationPreferences) -> DashboardResult<()> {
+        self.breaker.call(self.inner.set_notification_preferences(user_id, preferences)).await
+    }
+
+    async fn create_session(
+        &self,
+        user_id: i64,
+        ip_address: &str,
+        user_agent: &str,
+        expires_in_seconds: i64,
+    ) -> DashboardResult<UserSession> {
+        self.breaker
+            .call(self.inner.create_session(user_id, ip_address, user_agent, expires_in_seconds))
+            .await
+    }
+
+    async fn find_session_by_id(&self, session_id: &str) -> DashboardResult<Option<UserSession>> {
+        self.breaker.call(self.inner.find_session_by_id(session_id)).await
+    }
+
+    async fn extend_session(&self, session_id: &str, expires_at: DateTime<Utc>) -> DashboardResult<Option<UserSession>> {
+        self.breaker.call(self.inner.extend_session(session_id, expires_at)).await
+    }
+
+    async fn find_sessions_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<UserSession>> {
+        self.breaker.call(self.inner.find_sessions_by_user_id(user_id)).await
+    }
+
+    async fn delete_session(&self, session_id: &str) -> DashboardResult<bool> {
+        self.breaker.call(self.inner.delete_session(session_id)).await
+    }
+
+    async fn delete_user_sessions(&self, user_id: i64) -> DashboardResult<i64> {
+        self.breaker.call(self.inner.delete_user_sessions(user_id)).await
+    }
+
+    async fn update_last_active(&self, user_id: i64) -> DashboardResult<()> {
+        self.breaker.call(self.inner.update_last_active(user_id)).await
+    }
+
+    async fn find_user_by_public_key(&self, public_key: &str) -> DashboardResult<Option<User>> {
+        self.breaker.call(self.inner.find_user_by_public_key(public_key)).await
+    }
+
+    async fn store_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
+        self.breaker.call(self.inner.store_public_key(user_id, public_key)).await
+    }
+
+    async fn store_public_keys(
+        &self,
+        user_id: i64,
+        public_keys: &[String],
+        max_keys: usize,
+    ) -> DashboardResult<Vec<PublicKeyRegistrationResult>> {
+        self.breaker.call(self.inner.store_public_keys(user_id, public_keys, max_keys)).await
+    }
+
+    async fn revoke_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.breaker.call(self.inner.revoke_public_key(user_id, public_key)).await
+    }
+
+    async fn reactivate_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.breaker.call(self.inner.reactivate_public_key(user_id, public_key)).await
+    }
+
+    async fn delete_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.breaker.call(self.inner.delete_public_key(user_id, public_key)).await
+    }
+
+    async fn get_public_keys_for_user(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+        self.breaker.call(self.inner.get_public_keys_for_user(user_id)).await
+    }
+
+    async fn find_public_key_info(
+        &self,
+        public_key: &str,
+    ) -> DashboardResult<Option<PublicKeyInfo>> {
+        self.breaker.call(self.inner.find_public_key_info(public_key)).await
+    }
+
+    async fn get_public_key_info(
+        &self,
+        user_id: i64,
+        public_key: &str,
+    ) -> DashboardResult<Option<PublicKeyInfo>> {
+        self.breaker.call(self.inner.get_public_key_info(user_id, public_key)).await
+    }
+
+    async fn update_public_key_last_used(
+        &self,
+        user_id: i64,
+        public_key: &str,
+    ) -> DashboardResult<()> {
+        self.breaker.call(self.inner.update_public_key_last_used(user_id, public_key)).await
+    }
+}
+
+#[async_trait]
+impl<S: NetworkStorage> NetworkStorage for CircuitBreakerStorage<S> {
+    async fn find_connection_by_id(&self, id: i64) -> DashboardResult<Option<NetworkConnection>> {
+        self.breaker.call(self.inner.find_connection_by_id(id)).await
+    }
+
+    async fn find_connections_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        self.breaker.call(self.inner.find_connections_by_user_id(user_id)).await
+    }
+
+    async fn find_connections_page(
+        &self,
+        user_id: i64,
+        after_id: Option<i64>,
+        limit: usize,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        self.breaker.call(self.inner.find_connections_page(user_id, after_id, limit)).await
+    }
+
+    async fn find_connections_by_score(
+        &self,
+        user_id: i64,
+        filter: &ConnectionScoreFilter,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        self.breaker.call(self.inner.find_connections_by_score(user_id, filter)).await
+    }
+
+    async fn find_active_connections_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        self.breaker.call(self.inner.find_active_connections_by_user_id(user_id)).await
+    }
+
+    async fn create_connection(
+        &self,
+        connection: CreateNetworkConnectionDto,
+    ) -> DashboardResult<NetworkConnection> {
+        self.breaker.call(self.inner.create_connection(connection)).await
+    }
+
+    async fn update_connection(
+        &self,
+        id: i64,
+        update: UpdateNetworkConnectionDto,
+    ) -> DashboardResult<NetworkConnection> {
+        self.breaker.call(self.inner.update_connection(id, update)).await
+    }
+
+    async fn delete_connection(&self, id: i64) -> DashboardResult<bool> {
+        self.breaker.call(self.inner.delete_connection(id)).await
+    }
+
+    async fn delete_user_connections(&self, user_id: i64) -> DashboardResult<i64> {
+        self.breaker.call(self.inner.delete_user_connections(user_id)).await
+    }
+
+    async fn get_network_status(
+        &self,
+        connection_id: i64,
+    ) -> DashboardResult<Option<NetworkStatus>> {
+        self.breaker.call(self.inner.get_network_status(connection_id)).await
+    }
+
+    async fn update_network_status(
+        &self,
+        connection_id: i64,
+        connected: bool,
+        status_message: &str,
+        network_score: Option<f64>,
+    ) -> DashboardResult<NetworkStatus> {
+        self.breaker
+            .call(self.inner.update_network_status(
+                connection_id,
+                connected,
+                status_message,
+                network_score,
+            ))
+            .await
+    }
+
+    async fn list_events(
+        &self,
+        connection_id: i64,
+        offset: usize,
+        limit: usize,
+    ) -> DashboardResult<Vec<NetworkEvent>> {
+        self.breaker.call(self.inner.list_events(connection_id, offset, limit)).await
+    }
+
+    async fn count_events(&self, connection_id: i64) -> DashboardResult<usize> {
+        self.breaker.call(self.inner.count_events(connection_id)).await
+    }
+
+    async fn get_network_statistics(&self, user_id: i64) -> DashboardResult<NetworkStatistics> {
+        self.breaker.call(self.inner.get_network_statistics(user_id)).await
+    }
+
+    async fn record_connection_time(
+        &self,
+        connection_id: i64,
+        seconds: i64,
+    ) -> DashboardResult<i64> {
+        self.breaker.call(self.inner.record_connection_time(connection_id, seconds)).await
+    }
+
+    async fn record_earned_points(&self, connection_id: i64, points: f64) -> DashboardResult<f64> {
+        self.breaker.call(self.inner.record_earned_points(connection_id, points)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::DashboardError;
+    use crate::storage::memory::{InMemoryNetworkStorage, InMemoryUserStorage};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_open_breaker_short_circuits_every_store_sharing_it() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        let users = CircuitBreakerStorage::new(InMemoryUserStorage::new(), breaker.clone());
+        let networks = CircuitBreakerStorage::new(InMemoryNetworkStorage::new(), breaker.clone());
+        assert!(users.find_user_by_id(1).await.unwrap().is_none());
+
+        let _ = breaker.call(async { Err::<(), _>(DashboardError::database("timeout")) }).await;
+
+        assert!(matches!(
+            users.find_user_by_id(1).await,
+            Err(DashboardError::DatabaseUnavailable(_))
+        ));
+        assert!(matches!(
+            networks.find_connections_by_user_id(1).await,
+            Err(DashboardError::DatabaseUnavailable(_))
+        ));
+    }
+}
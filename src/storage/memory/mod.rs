@@ -1,7 +1,49 @@
 // Export in-memory storage implementations
 pub mod user;
-// pub mod network;
+pub mod network;
 
 // Re-export storage implementations for easier importing
 pub use user::InMemoryUserStorage;
-// pub use network::InMemoryNetworkStorage; 
\ No newline at end of file
+pub use network::InMemoryNetworkStorage;
+
+use serde::Deserialize;
+use std::str::FromStr;
+
+use crate::errors::{DashboardError, DashboardResult};
+
+/// What an in-memory session store at capacity does with a new session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEviction {
+    /// Refuse the new session (login fails with `503`)
+    #[default]
+    Reject,
+    /// Drop the session created longest ago to make room
+    EvictOldest,
+}
+
+impl FromStr for SessionEviction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "reject" => Ok(SessionEviction::Reject),
+            "evict_oldest" => Ok(SessionEviction::EvictOldest),
+            other => Err(format!(
+                "Unknown session eviction policy '{}'; expected reject or evict_oldest",
+                other
+            )),
+        }
+    }
+}
+
+/// Fail with `503` when a store holding `len` entries has reached `max` (zero is unbounded)
+pub(crate) fn ensure_capacity(len: usize, max: usize, store: &str) -> DashboardResult<()> {
+    if max > 0 && len >= max {
+        return Err(DashboardError::service_unavailable(format!(
+            "In-memory {} storage is full ({} entries)",
+            store, max
+        )));
+    }
+    Ok(())
+}
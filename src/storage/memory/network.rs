@@ -0,0 +1,340 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::errors::{DashboardError, DashboardResult};
+use crate::models::network::{
+    ConnectionScoreFilter, CreateNetworkConnectionDto, NetworkConnection, NetworkEvent, NetworkStatistics,
+    NetworkStatus, UpdateNetworkConnectionDto,
+};
+use crate::storage::memory::ensure_capacity;
+use crate::storage::NetworkStorage;
+
+/// Status events kept per connection; the oldest are dropped first
+pub const MAX_EVENTS_PER_CONNECTION: usize = 256;
+
+/// In-memory implementation of the NetworkStorage trait for development and testing
+#[derive(Clone)]
+pub struct InMemoryNetworkStorage {
+    connections: Arc<Mutex<HashMap<i64, NetworkConnection>>>,
+    statuses: Arc<Mutex<HashMap<i64, NetworkStatus>>>,
+    /// connection ID -> status history, oldest first
+    events: Arc<Mutex<HashMap<i64, VecDeque<NetworkEvent>>>>,
+    next_id: Arc<Mutex<i64>>,
+    /// Most connections held; 0 is unbounded
+    max_connections: usize,
+}
+
+impl Default for InMemoryNetworkStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryNetworkStorage {
+    /// Create a new empty in-memory network storage
+    pub fn new() -> Self {
+        Self {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            statuses: Arc::new(Mutex::new(HashMap::new())),
+            events: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(Mutex::new(1)),
+            max_connections: 0,
+        }
+    }
+
+    /// Refuse new connections with `503` once `max_connections` exist (0 is unbounded); updating
+    /// an existing connection in place still works
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Number of stored connections
+    pub fn connection_count(&self) -> usize {
+        self.connections.lock().map(|connections| connections.len()).unwrap_or(0)
+    }
+
+    /// Get direct access to the connections map for genesis data seeding
+    pub fn get_connections_map(&self) -> &Arc<Mutex<HashMap<i64, NetworkConnection>>> {
+        &self.connections
+    }
+
+    /// Get direct access to the statuses map for genesis data seeding
+    pub fn get_statuses_map(&self) -> &Arc<Mutex<HashMap<i64, NetworkStatus>>> {
+        &self.statuses
+    }
+
+    /// Get direct access to the next_id for genesis data seeding
+    pub fn get_next_id(&self) -> &Arc<Mutex<i64>> {
+        &self.next_id
+    }
+
+    /// Collect a user's connections matching a predicate, ordered by ID
+    fn collect_user_connections<F>(&self, user_id: i64, predicate: F) -> DashboardResult<Vec<NetworkConnection>>
+    where
+        F: Fn(&NetworkConnection) -> bool,
+    {
+        let connections = self.connections.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        let mut result: Vec<NetworkConnection> = connections
+            .values()
+            .filter(|c| c.user_id == user_id && predicate(c))
+            .cloned()
+            .collect();
+        result.sort_by_key(|c| c.id);
+
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl NetworkStorage for InMemoryNetworkStorage {
+    async fn find_connection_by_id(&self, id: i64) -> DashboardResult<Option<NetworkConnection>> {
+        let connections = self.connections.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        Ok(connections.get(&id).cloned())
+    }
+
+    async fn find_connections_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<NetworkConnection>> {
+        self.collect_user_connections(user_id, |_| true)
+    }
+
+    async fn find_connections_page(
+        &self,
+        user_id: i64,
+        after_id: Option<i64>,
+        limit: usize,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        // Snapshot under the lock, then page over the snapshot
+        let after_id = after_id.unwrap_or(i64::MIN);
+        let mut page = self.collect_user_connections(user_id, |c| c.id > after_id)?;
+        page.truncate(limit);
+
+        Ok(page)
+    }
+
+    async fn find_connections_by_score(
+        &self,
+        user_id: i64,
+        filter: &ConnectionScoreFilter,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        // Already ordered by ID, so the stable sort keeps ties in ID order
+        let mut result = self.collect_user_connections(user_id, |c| filter.matches(c.network_score))?;
+        result.sort_by(|a, b| a.network_score.total_cmp(&b.network_score));
+
+        Ok(result)
+    }
+
+    async fn find_active_connections_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<NetworkConnection>> {
+        self.collect_user_connections(user_id, |c| c.connected)
+    }
+
+    async fn create_connection(&self, connection: CreateNetworkConnectionDto) -> DashboardResult<NetworkConnection> {
+        let mut connections = self.connections.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        if !connection.allow_duplicate {
+            // Lowest ID wins if duplicates were created earlier with `allow_duplicate`
+            let existing = connections
+                .values_mut()
+                .filter(|c| c.user_id == connection.user_id && c.network_name == connection.network_name && c.connected)
+                .min_by_key(|c| c.id);
+            if let Some(existing) = existing {
+                existing.ip_address = connection.ip_address;
+                if let Some(score) = connection.initial_score {
+                    existing.network_score = score;
+                }
+                existing.updated_at = Utc::now();
+                return Ok(existing.clone());
+            }
+        }
+
+        ensure_capacity(connections.len(), self.max_connections, "network connection")?;
+
+        let mut next_id = self.next_id.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let id = *next_id;
+        *next_id += 1;
+
+        let mut new_connection = NetworkConnection::new(
+            connection.user_id,
+            connection.network_name,
+            connection.ip_address,
+            connection.initial_score,
+        );
+        new_connection.id = id;
+
+        connections.insert(id, new_connection.clone());
+
+        Ok(new_connection)
+    }
+
+    async fn update_connection(
+        &self,
+        id: i64,
+        update: UpdateNetworkConnectionDto,
+    ) -> DashboardResult<NetworkConnection> {
+        let mut connections = self.connections.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        let connection = connections
+            .get_mut(&id)
+            .ok_or_else(|| DashboardError::not_found(format!("Network connection with ID {} not found", id)))?;
+
+        if let Some(connected) = update.connected {
+            connection.update_status(connected);
+        }
+
+        if let Some(score) = update.network_score {
+            connection.update_score(score);
+        }
+
+        if let Some(seconds) = update.additional_time {
+            connection.add_connection_time(seconds);
+        }
+
+        if let Some(points) = update.additional_points {
+            connection.add_points(points);
+        }
+
+        Ok(connection.clone())
+    }
+
+    async fn delete_connection(&self, id: i64) -> DashboardResult<bool> {
+        let mut connections = self.connections.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut statuses = self.statuses.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut events = self.events.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        statuses.remove(&id);
+        events.remove(&id);
+
+        Ok(connections.remove(&id).is_some())
+    }
+
+    async fn delete_user_connections(&self, user_id: i64) -> DashboardResult<i64> {
+        // The locks are held together so no status or event outlives its connection
+        let mut connections = self.connections.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut statuses = self.statuses.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut events = self.events.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        let ids: Vec<i64> = connections
+            .values()
+            .filter(|c| c.user_id == user_id)
+            .map(|c| c.id)
+            .collect();
+        for id in &ids {
+            connections.remove(id);
+            statuses.remove(id);
+            events.remove(id);
+        }
+
+        Ok(ids.len() as i64)
+    }
+
+    async fn get_network_status(&self, connection_id: i64) -> DashboardResult<Option<NetworkStatus>> {
+        let statuses = self.statuses.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        Ok(statuses.get(&connection_id).cloned())
+    }
+
+    async fn update_network_status(
+        &self,
+        connection_id: i64,
+        connected: bool,
+        status_message: &str,
+        network_score: Option<f64>,
+    ) -> DashboardResult<NetworkStatus> {
+        let connections = self.connections.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut statuses = self.statuses.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut events = self.events.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        let connection = connections.get(&connection_id).ok_or_else(|| {
+            DashboardError::not_found(format!("Network connection with ID {} not found", connection_id))
+        })?;
+
+        let status = NetworkStatus {
+            connection_id,
+            user_id: connection.user_id,
+            network_name: connection.network_name.clone(),
+            connected,
+            status_message: status_message.to_string(),
+            network_score: network_score.unwrap_or(connection.network_score),
+            updated_at: Utc::now(),
+        };
+
+        let previous = statuses.insert(connection_id, status.clone());
+        let history = events.entry(connection_id).or_default();
+        if history.len() >= MAX_EVENTS_PER_CONNECTION {
+            history.pop_front();
+        }
+        history.push_back(NetworkEvent {
+            connection_id,
+            previous_connected: previous.as_ref().map(|p| p.connected),
+            connected: status.connected,
+            previous_score: previous.as_ref().map(|p| p.network_score),
+            network_score: status.network_score,
+            status_message: status.status_message.clone(),
+            recorded_at: status.updated_at,
+        });
+
+        Ok(status)
+    }
+
+    async fn list_events(&self, connection_id: i64, offset: usize, limit: usize) -> DashboardResult<Vec<NetworkEvent>> {
+        let events = self.events.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        Ok(events
+            .get(&connection_id)
+            .map(|history| history.iter().rev().skip(offset).take(limit).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn count_events(&self, connection_id: i64) -> DashboardResult<usize> {
+        let events = self.events.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        Ok(events.get(&connection_id).map_or(0, |history| history.len()))
+    }
+
+    async fn get_network_statistics(&self, user_id: i64) -> DashboardResult<NetworkStatistics> {
+        let connections = self.collect_user_connections(user_id, |_| true)?;
+
+        let total_networks = connections.len() as i64;
+        let active_connections = connections.iter().filter(|c| c.connected).count() as i64;
+        let total_connection_time = connections.iter().map(|c| c.connection_time.unwrap_or(0)).sum();
+        let total_points_earned = connections.iter().map(|c| c.points_earned).sum();
+        let average_network_score = NetworkStatistics::average_score(&connections);
+        let weighted_network_score = NetworkStatistics::time_weighted_score(&connections);
+
+        Ok(NetworkStatistics {
+            user_id,
+            total_networks,
+            active_connections,
+            total_connection_time,
+            average_network_score,
+            weighted_network_score,
+            total_points_earned,
+            last_updated: Utc::now(),
+        })
+    }
+
+    async fn record_connection_time(&self, connection_id: i64, seconds: i64) -> DashboardResult<i64> {
+        let mut connections = self.connections.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        let connection = connections.get_mut(&connection_id).ok_or_else(|| {
+            DashboardError::not_found(format!("Network connection with ID {} not found", connection_id))
+        })?;
+        connection.add_connection_time(seconds);
+
+        Ok(connection.connection_time.unwrap_or(0))
+    }
+
+    async fn record_earned_points(&self, connection_id: i64, points: f64) -> DashboardResult<f64> {
+        let mut connections = self.connections.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        let connection = connections.get_mut(&connection_id).ok_or_else(|| {
+            DashboardError::not_found(format!("Network connection with ID {} not found", connection_id))
+        })?;
+        connection.add_points(points);
+
+        Ok(connection.points_earned)
+    }
+}
@@ -1,23 +1,50 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
-use nanoid::nanoid;
+use tracing::debug;
 
 use crate::errors::{DashboardError, DashboardResult};
-use crate::models::user::{CreateUserDto, UpdateUserDto, User, UserCredentials, UserSession};
+use crate::ids::{nanoid_generator, unique_id, IdGenerator};
+use crate::models::user::{
+    AccountDeletion, CreateUserDto, NewUserAccount, NotificationPreferences, PublicKeyInfo, PublicKeyRegistrationResult,
+    PublicKeyRegistrationStatus, RateLimitTier, UpdateUserDto, User, UserCredentials, UserSession,
+    UserStatus,
+};
+use crate::storage::memory::{ensure_capacity, SessionEviction};
 use crate::storage::UserStorage;
 
 /// In-memory implementation of the UserStorage trait for development and testing
+///
+/// # Lock order
+///
+/// Methods that hold more than one map at a time lock them in the order the fields are
+/// declared: `users`, `emails`, `next_id`, `credentials`, `password_history`,
+/// `external_identities`, `notification_preferences`, `sessions`, `public_keys`,
+/// `user_public_keys`, `public_key_info`. Any subset may be held, but a map is never locked
+/// while a later one in that list is held, so no two methods can deadlock. Methods that only
+/// need one map at a time release it before locking the next.
 #[derive(Clone)]
 pub struct InMemoryUserStorage {
     users: Arc<Mutex<HashMap<i64, User>>>,
     emails: Arc<Mutex<HashMap<String, i64>>>,
+    next_id: Arc<Mutex<i64>>,
     credentials: Arc<Mutex<HashMap<i64, UserCredentials>>>,
+    /// user_id -> replaced password hashes, newest first
+    password_history: Arc<Mutex<HashMap<i64, VecDeque<String>>>>,
+    /// (issuer, subject) -> user_id
+    external_identities: Arc<Mutex<HashMap<(String, String), i64>>>,
+    notification_preferences: Arc<Mutex<HashMap<i64, NotificationPreferences>>>,
     sessions: Arc<Mutex<HashMap<String, UserSession>>>,
     public_keys: Arc<Mutex<HashMap<String, i64>>>,
     user_public_keys: Arc<Mutex<HashMap<i64, Vec<String>>>>,
-    next_id: Arc<Mutex<i64>>,
+    public_key_info: Arc<Mutex<HashMap<String, PublicKeyInfo>>>,
+    session_ids: IdGenerator,
+    /// Most users held; 0 is unbounded
+    max_users: usize,
+    /// Most sessions held; 0 is unbounded
+    max_sessions: usize,
+    session_eviction: SessionEviction,
 }
 
 impl Default for InMemoryUserStorage {
@@ -32,13 +59,50 @@ impl InMemoryUserStorage {
         Self {
             users: Arc::new(Mutex::new(HashMap::new())),
             emails: Arc::new(Mutex::new(HashMap::new())),
+            next_id: Arc::new(Mutex::new(1)),
             credentials: Arc::new(Mutex::new(HashMap::new())),
+            password_history: Arc::new(Mutex::new(HashMap::new())),
+            external_identities: Arc::new(Mutex::new(HashMap::new())),
+            notification_preferences: Arc::new(Mutex::new(HashMap::new())),
             sessions: Arc::new(Mutex::new(HashMap::new())),
             public_keys: Arc::new(Mutex::new(HashMap::new())),
             user_public_keys: Arc::new(Mutex::new(HashMap::new())),
-            next_id: Arc::new(Mutex::new(1)),
+            public_key_info: Arc::new(Mutex::new(HashMap::new())),
+            session_ids: nanoid_generator(),
+            max_users: 0,
+            max_sessions: 0,
+            session_eviction: SessionEviction::default(),
         }
     }
+
+    /// Refuse new users with `503` once `max_users` exist (0 is unbounded)
+    pub fn with_max_users(mut self, max_users: usize) -> Self {
+        self.max_users = max_users;
+        self
+    }
+
+    /// Hold at most `max_sessions` sessions (0 is unbounded), applying `eviction` when full
+    pub fn with_max_sessions(mut self, max_sessions: usize, eviction: SessionEviction) -> Self {
+        self.max_sessions = max_sessions;
+        self.session_eviction = eviction;
+        self
+    }
+
+    /// Number of stored users
+    pub fn user_count(&self) -> usize {
+        self.users.lock().map(|users| users.len()).unwrap_or(0)
+    }
+
+    /// Number of stored sessions, including expired ones not yet removed
+    pub fn session_count(&self) -> usize {
+        self.sessions.lock().map(|sessions| sessions.len()).unwrap_or(0)
+    }
+
+    /// Draw session ids from `generator` instead of random nanoids
+    pub fn with_session_id_generator(mut self, generator: IdGenerator) -> Self {
+        self.session_ids = generator;
+        self
+    }
     
     /// Get direct access to the users map for genesis data seeding
     pub fn get_users_map(&self) -> &Arc<Mutex<HashMap<i64, User>>> {
@@ -60,6 +124,27 @@ impl InMemoryUserStorage {
         &self.next_id
     }
     
+    /// Flip a key's revoked flag, returning whether it changed
+    fn set_public_key_revoked(&self, user_id: i64, public_key: &str, revoked: bool) -> DashboardResult<bool> {
+        let public_keys = self.public_keys.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        match public_keys.get(public_key) {
+            Some(existing_user_id) if *existing_user_id == user_id => {
+                let mut public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+                match public_key_info.get_mut(public_key) {
+                    Some(info) if info.revoked != revoked => {
+                        info.revoked = revoked;
+                        info.revoked_at = if revoked { Some(Utc::now()) } else { None };
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                }
+            },
+            Some(_) => Err(DashboardError::validation("Public key belongs to another user")),
+            None => Ok(false),
+        }
+    }
+    
     /// Get direct access to the public_keys map for genesis data seeding
     pub fn get_public_keys_map(&self) -> &Arc<Mutex<HashMap<String, i64>>> {
         &self.public_keys
@@ -69,6 +154,16 @@ impl InMemoryUserStorage {
     pub fn get_user_public_keys_map(&self) -> &Arc<Mutex<HashMap<i64, Vec<String>>>> {
         &self.user_public_keys
     }
+    
+    /// Get direct access to the sessions map
+    pub fn get_sessions_map(&self) -> &Arc<Mutex<HashMap<String, UserSession>>> {
+        &self.sessions
+    }
+    
+    /// Get direct access to the public key metadata map for genesis data seeding
+    pub fn get_public_key_info_map(&self) -> &Arc<Mutex<HashMap<String, PublicKeyInfo>>> {
+        &self.public_key_info
+    }
 }
 
 #[async_trait]
@@ -100,6 +195,41 @@ impl UserStorage for InMemoryUserStorage {
         if emails.contains_key(&user_dto.email) {
             return Err(DashboardError::validation(format!("Email {} is already in use", user_dto.email)));
         }
+        ensure_capacity(users.len(), self.max_users, "user")?;
+        
+        let id = *next_id;
+        *next_id += 1;
+        
+        let now = Utc::now();
+        let user = User {
+            id,
+            email: user_dto.email.clone(),
+            username: user_dto.username,
+            wallet_address: user_dto.wallet_address,
+            created_at: now,
+            last_active: now,
+            status: UserStatus::default(),
+            rate_limit_tier: RateLimitTier::default(),
+        };
+        
+        emails.insert(user_dto.email, id);
+        users.insert(id, user.clone());
+        
+        Ok(user)
+    }
+    
+    async fn create_user_with_credentials(&self, account: NewUserAccount) -> DashboardResult<User> {
+        // Hold every map the account touches so no one observes a user without credentials
+        let mut users = self.users.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut emails = self.emails.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut next_id = self.next_id.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut credentials = self.credentials.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        let user_dto = account.user;
+        if emails.contains_key(&user_dto.email) {
+            return Err(DashboardError::validation(format!("Email {} is already in use", user_dto.email)));
+        }
+        ensure_capacity(users.len(), self.max_users, "user")?;
         
         let id = *next_id;
         *next_id += 1;
@@ -112,8 +242,16 @@ impl UserStorage for InMemoryUserStorage {
             wallet_address: user_dto.wallet_address,
             created_at: now,
             last_active: now,
+            status: account.status,
+            rate_limit_tier: RateLimitTier::default(),
         };
         
+        credentials.insert(id, UserCredentials {
+            user_id: id,
+            password_hash: account.password_hash,
+            salt: account.salt,
+            updated_at: now,
+        });
         emails.insert(user_dto.email, id);
         users.insert(id, user.clone());
         
@@ -153,67 +291,81 @@ impl UserStorage for InMemoryUserStorage {
         Ok(user.clone())
     }
     
+    async fn set_user_status(&self, id: i64, status: UserStatus) -> DashboardResult<User> {
+        let mut users = self.users.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let user = users.get_mut(&id).ok_or_else(|| DashboardError::not_found(format!("User with ID {} not found", id)))?;
+        user.status = status;
+        Ok(user.clone())
+    }
+    
+    async fn list_users(
+        &self,
+        after_id: Option<i64>,
+        limit: usize,
+        email_contains: Option<&str>,
+    ) -> DashboardResult<Vec<User>> {
+        let users = self.users.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let email_contains = email_contains.map(str::to_lowercase);
+        
+        let mut page: Vec<User> = users
+            .values()
+            .filter(|user| after_id.map_or(true, |after| user.id > after))
+            .filter(|user| {
+                email_contains
+                    .as_deref()
+                    .map_or(true, |needle| user.email.to_lowercase().contains(needle))
+            })
+            .cloned()
+            .collect();
+        page.sort_by_key(|user| user.id);
+        page.truncate(limit);
+        
+        Ok(page)
+    }
+    
     async fn delete_user(&self, id: i64) -> DashboardResult<bool> {
-        // First check if user exists to avoid complex error handling later
-        if let Ok(None) = self.find_user_by_id(id).await {
-            return Ok(false);
-        }
-        
-        // Obtain user email for later removal
-        let user_email = {
-            let users = self.users.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
-            users.get(&id).map(|u| u.email.clone())
-        };
-        
-        // If user has no email (shouldn't happen), return false
-        let user_email = match user_email {
-            Some(email) => email,
-            None => return Ok(false),
-        };
-        
-        // Delete user's sessions
-        let _ = self.delete_user_sessions(id).await?;
+        Ok(self.delete_user_records(id).await?.user_deleted)
+    }
+    
+    async fn delete_user_records(&self, id: i64) -> DashboardResult<AccountDeletion> {
+        // Hold every map the user appears in, in lock order, so the removal is atomic: no one
+        // can register a key or open a session for the user halfway through
+        let mut users = self.users.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut emails = self.emails.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut credentials = self.credentials.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut password_history = self.password_history.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut external_identities = self.external_identities.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut notification_preferences = self.notification_preferences.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut sessions = self.sessions.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut public_keys = self.public_keys.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut user_public_keys = self.user_public_keys.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
         
-        // Get user public keys for removal
-        let keys_to_remove = {
-            let user_public_keys = self.user_public_keys.lock()
-                .map_err(|e| DashboardError::internal_server(e.to_string()))?;
-            user_public_keys.get(&id).cloned().unwrap_or_default()
+        let user = match users.remove(&id) {
+            Some(user) => user,
+            None => return Ok(AccountDeletion::default()),
         };
         
-        // Remove user from various storage
-        {
-            let mut users = self.users.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
-            users.remove(&id);
-        }
-        
-        {
-            let mut emails = self.emails.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
-            emails.remove(&user_email);
+        emails.remove(&user.email);
+        credentials.remove(&id);
+        password_history.remove(&id);
+        external_identities.retain(|_, user_id| *user_id != id);
+        notification_preferences.remove(&id);
+        let session_count = sessions.len();
+        sessions.retain(|_, session| session.user_id != id);
+        
+        let keys = user_public_keys.remove(&id).unwrap_or_default();
+        for key in &keys {
+            public_keys.remove(key);
+            public_key_info.remove(key);
         }
         
-        {
-            let mut credentials = self.credentials.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
-            credentials.remove(&id);
-        }
-        
-        {
-            let mut user_public_keys = self.user_public_keys.lock()
-                .map_err(|e| DashboardError::internal_server(e.to_string()))?;
-            user_public_keys.remove(&id);
-        }
-        
-        // Remove all user's public keys
-        {
-            let mut public_keys = self.public_keys.lock()
-                .map_err(|e| DashboardError::internal_server(e.to_string()))?;
-            
-            for key in keys_to_remove {
-                public_keys.remove(&key);
-            }
-        }
-        
-        Ok(true)
+        Ok(AccountDeletion {
+            user_deleted: true,
+            sessions: (session_count - sessions.len()) as i64,
+            public_keys: keys.len(),
+            network_connections: 0,
+        })
     }
     
     async fn store_credentials(&self, user_id: i64, password_hash: &str, salt: &str) -> DashboardResult<()> {
@@ -235,6 +387,72 @@ impl UserStorage for InMemoryUserStorage {
         Ok(credentials.get(&user_id).cloned())
     }
     
+    async fn get_password_history(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+        let password_history = self.password_history.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        Ok(password_history
+            .get(&user_id)
+            .map(|hashes| hashes.iter().cloned().collect())
+            .unwrap_or_default())
+    }
+    
+    async fn record_password_history(&self, user_id: i64, password_hash: &str, keep: usize) -> DashboardResult<()> {
+        let mut password_history = self.password_history.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        let hashes = password_history.entry(user_id).or_default();
+        hashes.push_front(password_hash.to_string());
+        hashes.truncate(keep);
+        if hashes.is_empty() {
+            password_history.remove(&user_id);
+        }
+        
+        Ok(())
+    }
+    
+    async fn find_user_by_external_identity(&self, issuer: &str, subject: &str) -> DashboardResult<Option<User>> {
+        let user_id = {
+            let external_identities = self.external_identities.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+            external_identities.get(&(issuer.to_string(), subject.to_string())).copied()
+        };
+        
+        match user_id {
+            Some(user_id) => self.find_user_by_id(user_id).await,
+            None => Ok(None),
+        }
+    }
+    
+    async fn link_external_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DashboardResult<()> {
+        if self.find_user_by_id(user_id).await?.is_none() {
+            return Err(DashboardError::not_found(format!("User with ID {} not found", user_id)));
+        }
+        let mut external_identities = self.external_identities.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        match external_identities.get(&(issuer.to_string(), subject.to_string())) {
+            Some(linked) if *linked == user_id => Ok(()),
+            Some(_) => Err(DashboardError::validation("External identity is linked to another user")),
+            None => {
+                external_identities.insert((issuer.to_string(), subject.to_string()), user_id);
+                Ok(())
+            }
+        }
+    }
+    
+    async fn get_notification_preferences(&self, user_id: i64) -> DashboardResult<Option<NotificationPreferences>> {
+        let notification_preferences = self.notification_preferences.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        Ok(notification_preferences.get(&user_id).cloned())
+    }
+    
+    async fn set_notification_preferences(&self, user_id: i64, preferences: &NotificationPreferences) -> DashboardResult<()> {
+        if self.find_user_by_id(user_id).await?.is_none() {
+            return Err(DashboardError::not_found(format!("User with ID {} not found", user_id)));
+        }
+        let mut notification_preferences = self.notification_preferences.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        notification_preferences.insert(user_id, preferences.clone());
+        Ok(())
+    }
+    
     async fn create_session(
         &self,
         user_id: i64,
@@ -242,18 +460,42 @@ impl UserStorage for InMemoryUserStorage {
         user_agent: &str,
         expires_in_seconds: i64,
     ) -> DashboardResult<UserSession> {
+        // The user stays locked until the session is in, so it can't be deleted in between and
+        // leave the session behind
+        let users = self.users.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        if !users.contains_key(&user_id) {
+            return Err(DashboardError::not_found(format!("User with ID {} not found", user_id)));
+        }
         let mut sessions = self.sessions.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
         
         let now = Utc::now();
         let expires_at = now + Duration::seconds(expires_in_seconds);
         
+        if self.max_sessions > 0 && sessions.len() >= self.max_sessions {
+            match self.session_eviction {
+                SessionEviction::Reject => ensure_capacity(sessions.len(), self.max_sessions, "session")?,
+                SessionEviction::EvictOldest => {
+                    let oldest = sessions
+                        .values()
+                        .min_by_key(|session| session.created_at)
+                        .map(|session| session.id.clone());
+                    if let Some(oldest) = oldest {
+                        debug!("Session storage full, evicting session {}", oldest);
+                        sessions.remove(&oldest);
+                    }
+                }
+            }
+        }
+        
+        // Never overwrite an existing session on an id collision
+        let id = unique_id(&self.session_ids, "session", |id| sessions.contains_key(id))?;
         let session = UserSession {
-            id: nanoid!(),
+            id,
             user_id,
             created_at: now,
             expires_at,
-            ip_address: ip_address.to_string(),
-            user_agent: user_agent.to_string(),
+            ip_address: UserSession::sanitize_ip_address(ip_address),
+            user_agent: UserSession::sanitize_user_agent(user_agent),
         };
         
         sessions.insert(session.id.clone(), session.clone());
@@ -267,12 +509,34 @@ impl UserStorage for InMemoryUserStorage {
         Ok(sessions.get(session_id).cloned())
     }
     
+    async fn extend_session(&self, session_id: &str, expires_at: DateTime<Utc>) -> DashboardResult<Option<UserSession>> {
+        let mut sessions = self.sessions.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        Ok(sessions.get_mut(session_id).map(|session| {
+            session.expires_at = expires_at;
+            session.clone()
+        }))
+    }
+    
     async fn delete_session(&self, session_id: &str) -> DashboardResult<bool> {
         let mut sessions = self.sessions.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
         
         Ok(sessions.remove(session_id).is_some())
     }
     
+    async fn find_sessions_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<UserSession>> {
+        let sessions = self.sessions.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+
+        let mut result: Vec<UserSession> = sessions
+            .values()
+            .filter(|session| session.user_id == user_id)
+            .cloned()
+            .collect();
+        result.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.cmp(&b.id)));
+
+        Ok(result)
+    }
+
     async fn delete_user_sessions(&self, user_id: i64) -> DashboardResult<i64> {
         let mut sessions = self.sessions.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
         
@@ -303,7 +567,10 @@ impl UserStorage for InMemoryUserStorage {
     async fn find_user_by_public_key(&self, public_key: &str) -> DashboardResult<Option<User>> {
         let user_id = {
             let public_keys = self.public_keys.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
-            public_keys.get(public_key).copied()
+            let public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+            // Revoked keys stay registered to their owner but no longer identify them
+            let revoked = public_key_info.get(public_key).map_or(false, |info| info.revoked);
+            public_keys.get(public_key).copied().filter(|_| !revoked)
         };
         
         match user_id {
@@ -332,10 +599,90 @@ impl UserStorage for InMemoryUserStorage {
             .or_insert_with(Vec::new)
             .push(public_key.to_string());
         
+        // Record key metadata
+        let mut public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        public_key_info.insert(public_key.to_string(), PublicKeyInfo {
+            user_id,
+            public_key: public_key.to_string(),
+            created_at: Utc::now(),
+            last_used: None,
+            revoked: false,
+            revoked_at: None,
+        });
+        
         Ok(())
     }
     
+    async fn store_public_keys(
+        &self,
+        user_id: i64,
+        public_keys: &[String],
+        max_keys: usize,
+    ) -> DashboardResult<Vec<PublicKeyRegistrationResult>> {
+        // Hold all key locks for the whole batch so the limit check and inserts are atomic
+        let mut public_keys_map = self.public_keys.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut user_public_keys = self.user_public_keys.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let mut public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        let existing_count = user_public_keys.get(&user_id).map(Vec::len).unwrap_or(0);
+        let new_keys: HashSet<&String> = public_keys
+            .iter()
+            .filter(|k| !public_keys_map.contains_key(k.as_str()))
+            .collect();
+        if existing_count + new_keys.len() > max_keys {
+            return Err(DashboardError::validation(format!(
+                "Registering {} new keys would exceed the limit of {} keys per user",
+                new_keys.len(),
+                max_keys
+            )));
+        }
+        
+        let now = Utc::now();
+        let mut results = Vec::with_capacity(public_keys.len());
+        for public_key in public_keys {
+            let (status, message) = match public_keys_map.get(public_key) {
+                Some(existing_user_id) if *existing_user_id == user_id => {
+                    (PublicKeyRegistrationStatus::AlreadyExists, None)
+                }
+                Some(_) => (
+                    PublicKeyRegistrationStatus::Error,
+                    Some("Public key already associated with another user".to_string()),
+                ),
+                None => {
+                    public_keys_map.insert(public_key.clone(), user_id);
+                    user_public_keys.entry(user_id)
+                        .or_insert_with(Vec::new)
+                        .push(public_key.clone());
+                    public_key_info.insert(public_key.clone(), PublicKeyInfo {
+                        user_id,
+                        public_key: public_key.clone(),
+                        created_at: now,
+                        last_used: None,
+                        revoked: false,
+                        revoked_at: None,
+                    });
+                    (PublicKeyRegistrationStatus::Added, None)
+                }
+            };
+            results.push(PublicKeyRegistrationResult {
+                public_key: public_key.clone(),
+                status,
+                message,
+            });
+        }
+        
+        Ok(results)
+    }
+    
     async fn revoke_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.set_public_key_revoked(user_id, public_key, true)
+    }
+    
+    async fn reactivate_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.set_public_key_revoked(user_id, public_key, false)
+    }
+    
+    async fn delete_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
         let mut public_keys = self.public_keys.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
         let mut user_public_keys = self.user_public_keys.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
         
@@ -348,6 +695,9 @@ impl UserStorage for InMemoryUserStorage {
                     keys.retain(|k| k != public_key);
                 }
                 
+                let mut public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+                public_key_info.remove(public_key);
+                
                 Ok(true)
             },
             Some(_) => Err(DashboardError::validation(format!("Public key belongs to another user"))),
@@ -357,13 +707,135 @@ impl UserStorage for InMemoryUserStorage {
     
     async fn get_public_keys_for_user(&self, user_id: i64) -> DashboardResult<Vec<String>> {
         let user_public_keys = self.user_public_keys.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        let public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        let mut keys: Vec<(&String, Option<&PublicKeyInfo>)> = user_public_keys
+            .get(&user_id)
+            .map(|keys| keys.iter().map(|key| (key, public_key_info.get(key))).collect())
+            .unwrap_or_default();
+        // Keys without metadata sort last, by key
+        keys.sort_by(|(a_key, a_info), (b_key, b_info)| match (a_info, b_info) {
+            (Some(a), Some(b)) => a.cmp_listing_order(b),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a_key.cmp(b_key),
+        });
         
-        Ok(user_public_keys.get(&user_id).cloned().unwrap_or_default())
+        Ok(keys.into_iter().map(|(key, _)| key.clone()).collect())
+    }
+    
+    async fn find_public_key_info(&self, public_key: &str) -> DashboardResult<Option<PublicKeyInfo>> {
+        let public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        Ok(public_key_info.get(public_key).cloned())
+    }
+    
+    async fn get_public_key_info(&self, user_id: i64, public_key: &str) -> DashboardResult<Option<PublicKeyInfo>> {
+        let public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        Ok(public_key_info
+            .get(public_key)
+            .filter(|info| info.user_id == user_id)
+            .cloned())
     }
     
     async fn update_public_key_last_used(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
-        // For in-memory storage, we don't track last used timestamp
-        // This would be implemented in a real database storage
+        let mut public_key_info = self.public_key_info.lock().map_err(|e| DashboardError::internal_server(e.to_string()))?;
+        
+        if let Some(info) = public_key_info.get_mut(public_key) {
+            if info.user_id == user_id {
+                info.last_used = Some(Utc::now());
+            }
+        }
+        
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_dto(email: String, username: String) -> CreateUserDto {
+        CreateUserDto {
+            email,
+            username,
+            password: "password123".to_string(),
+            wallet_address: None,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_interleaved_writes_and_deletes_neither_deadlock_nor_tear() {
+        let storage = InMemoryUserStorage::new();
+
+        let mut tasks = Vec::new();
+        for writer in 0..4 {
+            let storage = storage.clone();
+            tasks.push(tokio::spawn(async move {
+                for i in 0..100 {
+                    let dto = create_dto(format!("w{}-{}@example.com", writer, i), format!("w{}-{}", writer, i));
+                    let user = storage.create_user(dto).await.unwrap();
+                    let key = format!("{:02x}{:03x}", writer, i).repeat(8);
+                    let _ = storage.store_public_key(user.id, &key).await;
+                    let _ = storage.store_public_keys(user.id, &[key.to_uppercase()], 10).await;
+                    let _ = storage.create_session(user.id, "10.0.0.1", "agent", 3600).await;
+                    let _ = storage.update_user(user.id, UpdateUserDto {
+                        email: Some(format!("moved-w{}-{}@example.com", writer, i)),
+                        username: None,
+                        wallet_address: None,
+                    }).await;
+                    let _ = storage.delete_public_key(user.id, &key).await;
+                }
+            }));
+        }
+        for _ in 0..2 {
+            let storage = storage.clone();
+            tasks.push(tokio::spawn(async move {
+                for _ in 0..5 {
+                    for id in 1..=400 {
+                        let _ = storage.delete_user(id).await;
+                    }
+                    tokio::task::yield_now().await;
+                }
+            }));
+        }
+
+        let all = futures::future::join_all(tasks);
+        let results = tokio::time::timeout(std::time::Duration::from_secs(30), all)
+            .await
+            .expect("storage operations deadlocked");
+        for result in results {
+            result.unwrap();
+        }
+
+        // Every remaining email points at a live user, and the three key maps agree
+        let users = storage.users.lock().unwrap();
+        let emails = storage.emails.lock().unwrap();
+        for (email, id) in emails.iter() {
+            assert_eq!(&users[id].email, email);
+        }
+        assert_eq!(emails.len(), users.len());
+        let sessions = storage.sessions.lock().unwrap();
+        let public_keys = storage.public_keys.lock().unwrap();
+        let user_public_keys = storage.user_public_keys.lock().unwrap();
+        let public_key_info = storage.public_key_info.lock().unwrap();
+        for (key, owner) in public_keys.iter() {
+            assert!(user_public_keys[owner].contains(key));
+            assert_eq!(public_key_info[key].user_id, *owner);
+        }
+        let listed: usize = user_public_keys.values().map(Vec::len).sum();
+        assert_eq!(listed, public_keys.len());
+        assert_eq!(public_key_info.len(), public_keys.len());
+
+        // No session outlives its user
+        for session in sessions.values() {
+            assert!(
+                users.contains_key(&session.user_id),
+                "session {} left behind by deleted user {}",
+                session.id,
+                session.user_id
+            );
+        }
+    }
+}
@@ -1,9 +1,20 @@
 // Export storage traits
 pub mod traits;
-// pub mod postgres;
+pub mod postgres;
 // pub mod redis;
 pub mod memory;
+pub mod breaker;
+pub mod traced;
+pub mod backend;
 
 // Re-export traits for easier importing
 pub use traits::user::UserStorage;
-pub use traits::network::NetworkStorage; 
\ No newline at end of file
+pub use traits::network::NetworkStorage;
+pub use breaker::CircuitBreakerStorage;
+pub use traced::TracedStorage;
+pub use backend::UserBackend;
+
+/// User storage the server runs on, traced per call and behind the database circuit breaker
+pub type AppUserStorage = CircuitBreakerStorage<TracedStorage<UserBackend>>;
+/// Network storage the server runs on, traced per call and behind the database circuit breaker
+pub type AppNetworkStorage = CircuitBreakerStorage<TracedStorage<memory::InMemoryNetworkStorage>>; 
\ No newline at end of file
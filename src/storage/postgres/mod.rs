@@ -0,0 +1,5 @@
+// Export PostgreSQL storage implementations
+pub mod user;
+
+// Re-export storage implementations for easier importing
+pub use user::PostgresUserStorage;
@@ -0,0 +1,848 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::{HashMap, HashSet};
+use tracing::warn;
+
+use crate::errors::{DashboardError, DashboardResult};
+use crate::ids::{nanoid_generator, IdGenerator, MAX_ID_ATTEMPTS};
+use crate::models::user::{
+    AccountDeletion, CreateUserDto, NewUserAccount, NotificationPreferences, PublicKeyInfo, PublicKeyRegistrationResult,
+    PublicKeyRegistrationStatus, UpdateUserDto, User, UserCredentials, UserSession, UserStatus,
+};
+use crate::storage::traits::user::PUBLIC_KEY_LIST_ORDER_BY;
+use crate::storage::UserStorage;
+
+/// Columns of `users` in `User` field order
+const USER_COLUMNS: &str = "id, email, username, wallet_address, created_at, last_active, status, rate_limit_tier";
+
+/// Columns of `user_public_keys` in `PublicKeyInfo` field order
+const PUBLIC_KEY_COLUMNS: &str = "user_id, public_key, created_at, last_used, revoked, revoked_at";
+
+/// Columns of `user_sessions` in `UserSession` field order
+const SESSION_COLUMNS: &str = "id, user_id, created_at, expires_at, ip_address, user_agent";
+
+/// PostgreSQL implementation of the UserStorage trait, behaving like `InMemoryUserStorage`.
+///
+/// Expects the schema from `migrations/`. Multi-step writes run in one transaction, and
+/// uniqueness is enforced by the database, so a duplicate email or a key registered to
+/// another user fails with `Validation` as it does in memory. Unlike the in-memory store
+/// there is no capacity limit on users or sessions.
+#[derive(Clone)]
+pub struct PostgresUserStorage {
+    pool: PgPool,
+    session_ids: IdGenerator,
+}
+
+impl PostgresUserStorage {
+    /// Store users in the database behind `pool`
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            session_ids: nanoid_generator(),
+        }
+    }
+
+    /// Draw session ids from `generator` instead of random nanoids
+    pub fn with_session_id_generator(mut self, generator: IdGenerator) -> Self {
+        self.session_ids = generator;
+        self
+    }
+
+    /// Owner of a registered key, revoked or not
+    async fn public_key_owner(&self, public_key: &str) -> DashboardResult<Option<i64>> {
+        let owner = sqlx::query_scalar("SELECT user_id FROM user_public_keys WHERE public_key = $1")
+            .bind(public_key)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(owner)
+    }
+
+    /// Flip a key's revoked flag, returning whether it changed
+    async fn set_public_key_revoked(&self, user_id: i64, public_key: &str, revoked: bool) -> DashboardResult<bool> {
+        match self.public_key_owner(public_key).await? {
+            Some(owner) if owner == user_id => {}
+            Some(_) => return Err(DashboardError::validation("Public key belongs to another user")),
+            None => return Ok(false),
+        }
+
+        let result = sqlx::query(
+            "UPDATE user_public_keys
+             SET revoked = $3, revoked_at = CASE WHEN $3 THEN NOW() ELSE NULL END
+             WHERE user_id = $1 AND public_key = $2 AND revoked <> $3",
+        )
+        .bind(user_id)
+        .bind(public_key)
+        .bind(revoked)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn user_exists(&self, user_id: i64) -> DashboardResult<bool> {
+        let exists = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = $1)")
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(exists)
+    }
+}
+
+/// Map a unique violation to `Validation` with `message`, leaving other errors to `From<sqlx::Error>`
+fn unique_violation(err: sqlx::Error, message: impl Into<String>) -> DashboardError {
+    match &err {
+        sqlx::Error::Database(db) if db.is_unique_violation() => DashboardError::validation(message),
+        _ => err.into(),
+    }
+}
+
+fn user_not_found(id: i64) -> DashboardError {
+    DashboardError::not_found(format!("User with ID {} not found", id))
+}
+
+/// Insert a user row inside `tx`
+async fn insert_user(
+    tx: &mut Transaction<'_, Postgres>,
+    user: &CreateUserDto,
+    status: UserStatus,
+) -> DashboardResult<User> {
+    sqlx::query_as::<_, User>(&format!(
+        "INSERT INTO users (email, username, wallet_address, status)
+         VALUES ($1, $2, $3, $4)
+         RETURNING {}",
+        USER_COLUMNS
+    ))
+    .bind(&user.email)
+    .bind(&user.username)
+    .bind(&user.wallet_address)
+    .bind(status)
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(|e| unique_violation(e, format!("Email {} is already in use", user.email)))
+}
+
+#[async_trait]
+impl UserStorage for PostgresUserStorage {
+    async fn find_user_by_id(&self, id: i64) -> DashboardResult<Option<User>> {
+        let user = sqlx::query_as::<_, User>(&format!("SELECT {} FROM users WHERE id = $1", USER_COLUMNS))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(user)
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> DashboardResult<Option<User>> {
+        let user = sqlx::query_as::<_, User>(&format!("SELECT {} FROM users WHERE email = $1", USER_COLUMNS))
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(user)
+    }
+
+    async fn create_user(&self, user_dto: CreateUserDto) -> DashboardResult<User> {
+        let mut tx = self.pool.begin().await?;
+        let user = insert_user(&mut tx, &user_dto, UserStatus::default()).await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn create_user_with_credentials(&self, account: NewUserAccount) -> DashboardResult<User> {
+        let mut tx = self.pool.begin().await?;
+        let user = insert_user(&mut tx, &account.user, account.status).await?;
+        sqlx::query("INSERT INTO user_credentials (user_id, password_hash, salt) VALUES ($1, $2, $3)")
+            .bind(user.id)
+            .bind(&account.password_hash)
+            .bind(&account.salt)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn update_user(&self, id: i64, update: UpdateUserDto) -> DashboardResult<User> {
+        let email = update.email.clone();
+        sqlx::query_as::<_, User>(&format!(
+            "UPDATE users
+             SET email = COALESCE($2, email),
+                 username = COALESCE($3, username),
+                 wallet_address = COALESCE($4, wallet_address)
+             WHERE id = $1
+             RETURNING {}",
+            USER_COLUMNS
+        ))
+        .bind(id)
+        .bind(update.email)
+        .bind(update.username)
+        .bind(update.wallet_address)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| unique_violation(e, format!("Email {} is already in use", email.unwrap_or_default())))?
+        .ok_or_else(|| user_not_found(id))
+    }
+
+    async fn set_user_status(&self, id: i64, status: UserStatus) -> DashboardResult<User> {
+        sqlx::query_as::<_, User>(&format!("UPDATE users SET status = $2 WHERE id = $1 RETURNING {}", USER_COLUMNS))
+            .bind(id)
+            .bind(status)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| user_not_found(id))
+    }
+
+    async fn delete_user(&self, id: i64) -> DashboardResult<bool> {
+        // Credentials, history, identities, preferences, sessions and keys go with the row
+        // via `ON DELETE CASCADE`, in the same statement
+        let result = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_user_records(&self, id: i64) -> DashboardResult<AccountDeletion> {
+        let mut tx = self.pool.begin().await?;
+
+        // Locking the user row keeps sessions and keys from being added until the delete commits
+        let locked = sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if locked.is_none() {
+            return Ok(AccountDeletion::default());
+        }
+        let sessions = sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let public_keys = sqlx::query("DELETE FROM user_public_keys WHERE user_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        // The rest goes with the row via `ON DELETE CASCADE`
+        sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(AccountDeletion {
+            user_deleted: true,
+            sessions: sessions as i64,
+            public_keys: public_keys as usize,
+            network_connections: 0,
+        })
+    }
+
+    async fn list_users(
+        &self,
+        after_id: Option<i64>,
+        limit: usize,
+        email_contains: Option<&str>,
+    ) -> DashboardResult<Vec<User>> {
+        // POSITION rather than LIKE, so `%` and `_` in the filter match literally
+        let users = sqlx::query_as::<_, User>(&format!(
+            "SELECT {} FROM users
+             WHERE ($1::BIGINT IS NULL OR id > $1)
+               AND ($2::TEXT IS NULL OR POSITION(LOWER($2) IN LOWER(email)) > 0)
+             ORDER BY id
+             LIMIT $3",
+            USER_COLUMNS
+        ))
+        .bind(after_id)
+        .bind(email_contains)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(users)
+    }
+
+    async fn store_credentials(&self, user_id: i64, password_hash: &str, salt: &str) -> DashboardResult<()> {
+        sqlx::query(
+            "INSERT INTO user_credentials (user_id, password_hash, salt, updated_at)
+             VALUES ($1, $2, $3, NOW())
+             ON CONFLICT (user_id) DO UPDATE
+             SET password_hash = EXCLUDED.password_hash, salt = EXCLUDED.salt, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(user_id)
+        .bind(password_hash)
+        .bind(salt)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_credentials(&self, user_id: i64) -> DashboardResult<Option<UserCredentials>> {
+        let credentials = sqlx::query_as::<_, UserCredentials>(
+            "SELECT user_id, password_hash, salt, updated_at FROM user_credentials WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(credentials)
+    }
+
+    async fn get_password_history(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+        let hashes = sqlx::query_scalar(
+            "SELECT password_hash FROM password_history WHERE user_id = $1 ORDER BY created_at DESC, id DESC",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(hashes)
+    }
+
+    async fn record_password_history(&self, user_id: i64, password_hash: &str, keep: usize) -> DashboardResult<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO password_history (user_id, password_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(password_hash)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "DELETE FROM password_history
+             WHERE user_id = $1 AND id NOT IN (
+                 SELECT id FROM password_history WHERE user_id = $1
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT $2
+             )",
+        )
+        .bind(user_id)
+        .bind(i64::try_from(keep).unwrap_or(i64::MAX))
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn find_user_by_external_identity(&self, issuer: &str, subject: &str) -> DashboardResult<Option<User>> {
+        let user = sqlx::query_as::<_, User>(&format!(
+            "SELECT {} FROM users
+             WHERE id = (SELECT user_id FROM external_identities WHERE issuer = $1 AND subject = $2)",
+            USER_COLUMNS
+        ))
+        .bind(issuer)
+        .bind(subject)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(user)
+    }
+
+    async fn link_external_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DashboardResult<()> {
+        if !self.user_exists(user_id).await? {
+            return Err(user_not_found(user_id));
+        }
+        sqlx::query(
+            "INSERT INTO external_identities (issuer, subject, user_id) VALUES ($1, $2, $3)
+             ON CONFLICT (issuer, subject) DO NOTHING",
+        )
+        .bind(issuer)
+        .bind(subject)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        // Whoever holds the link now, this insert or an earlier one
+        let linked: i64 = sqlx::query_scalar("SELECT user_id FROM external_identities WHERE issuer = $1 AND subject = $2")
+            .bind(issuer)
+            .bind(subject)
+            .fetch_one(&self.pool)
+            .await?;
+        if linked != user_id {
+            return Err(DashboardError::validation("External identity is linked to another user"));
+        }
+        Ok(())
+    }
+
+    async fn get_notification_preferences(&self, user_id: i64) -> DashboardResult<Option<NotificationPreferences>> {
+        let row: Option<(Vec<String>, Vec<String>)> = sqlx::query_as(
+            "SELECT muted_websocket_channels, muted_webhook_channels FROM notification_preferences WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|(websocket, webhook)| NotificationPreferences {
+            muted_websocket_channels: websocket.into_iter().collect(),
+            muted_webhook_channels: webhook.into_iter().collect(),
+        }))
+    }
+
+    async fn set_notification_preferences(&self, user_id: i64, preferences: &NotificationPreferences) -> DashboardResult<()> {
+        if !self.user_exists(user_id).await? {
+            return Err(user_not_found(user_id));
+        }
+        let websocket: Vec<&str> = preferences.muted_websocket_channels.iter().map(String::as_str).collect();
+        let webhook: Vec<&str> = preferences.muted_webhook_channels.iter().map(String::as_str).collect();
+        sqlx::query(
+            "INSERT INTO notification_preferences (user_id, muted_websocket_channels, muted_webhook_channels, updated_at)
+             VALUES ($1, $2, $3, NOW())
+             ON CONFLICT (user_id) DO UPDATE
+             SET muted_websocket_channels = EXCLUDED.muted_websocket_channels,
+                 muted_webhook_channels = EXCLUDED.muted_webhook_channels,
+                 updated_at = EXCLUDED.updated_at",
+        )
+        .bind(user_id)
+        .bind(websocket)
+        .bind(webhook)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_session(
+        &self,
+        user_id: i64,
+        ip_address: &str,
+        user_agent: &str,
+        expires_in_seconds: i64,
+    ) -> DashboardResult<UserSession> {
+        let now = Utc::now();
+        let expires_at = now + Duration::seconds(expires_in_seconds);
+        let ip_address = UserSession::sanitize_ip_address(ip_address);
+        let user_agent = UserSession::sanitize_user_agent(user_agent);
+
+        // Never overwrite an existing session on an id collision
+        for _ in 0..MAX_ID_ATTEMPTS {
+            let session = sqlx::query_as::<_, UserSession>(&format!(
+                "INSERT INTO user_sessions (id, user_id, created_at, expires_at, ip_address, user_agent)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (id) DO NOTHING
+                 RETURNING {}",
+                SESSION_COLUMNS
+            ))
+            .bind((self.session_ids)())
+            .bind(user_id)
+            .bind(now)
+            .bind(expires_at)
+            .bind(&ip_address)
+            .bind(&user_agent)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_foreign_key_violation() => user_not_found(user_id),
+                _ => e.into(),
+            })?;
+            match session {
+                Some(session) => return Ok(session),
+                None => warn!("Generated session id collided with an existing one, regenerating"),
+            }
+        }
+        Err(DashboardError::internal_server(format!(
+            "Could not generate a unique session id after {} attempts",
+            MAX_ID_ATTEMPTS
+        )))
+    }
+
+    async fn find_session_by_id(&self, session_id: &str) -> DashboardResult<Option<UserSession>> {
+        let session = sqlx::query_as::<_, UserSession>(&format!(
+            "SELECT {} FROM user_sessions WHERE id = $1",
+            SESSION_COLUMNS
+        ))
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(session)
+    }
+
+    async fn extend_session(&self, session_id: &str, expires_at: DateTime<Utc>) -> DashboardResult<Option<UserSession>> {
+        let session = sqlx::query_as::<_, UserSession>(&format!(
+            "UPDATE user_sessions SET expires_at = $2 WHERE id = $1 RETURNING {}",
+            SESSION_COLUMNS
+        ))
+        .bind(session_id)
+        .bind(expires_at)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(session)
+    }
+
+    async fn find_sessions_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<UserSession>> {
+        let sessions = sqlx::query_as::<_, UserSession>(&format!(
+            "SELECT {} FROM user_sessions WHERE user_id = $1 ORDER BY created_at, id",
+            SESSION_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(sessions)
+    }
+
+    async fn delete_session(&self, session_id: &str) -> DashboardResult<bool> {
+        let result = sqlx::query("DELETE FROM user_sessions WHERE id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete_user_sessions(&self, user_id: i64) -> DashboardResult<i64> {
+        let result = sqlx::query("DELETE FROM user_sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() as i64)
+    }
+
+    async fn update_last_active(&self, user_id: i64) -> DashboardResult<()> {
+        let result = sqlx::query("UPDATE users SET last_active = NOW() WHERE id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(user_not_found(user_id));
+        }
+        Ok(())
+    }
+
+    async fn find_user_by_public_key(&self, public_key: &str) -> DashboardResult<Option<User>> {
+        // Revoked keys stay registered to their owner but no longer identify them
+        let user = sqlx::query_as::<_, User>(&format!(
+            "SELECT {} FROM users
+             WHERE id = (SELECT user_id FROM user_public_keys WHERE public_key = $1 AND NOT revoked)",
+            USER_COLUMNS
+        ))
+        .bind(public_key)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(user)
+    }
+
+    async fn store_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
+        match self.public_key_owner(public_key).await? {
+            Some(owner) if owner == user_id => return Ok(()),
+            Some(_) => return Err(DashboardError::validation("Public key already associated with another user")),
+            None => {}
+        }
+
+        // A concurrent registration by another user trips the unique index on `public_key`
+        sqlx::query(
+            "INSERT INTO user_public_keys (user_id, public_key) VALUES ($1, $2)
+             ON CONFLICT (user_id, public_key) DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(public_key)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| unique_violation(e, "Public key already associated with another user"))?;
+        Ok(())
+    }
+
+    async fn store_public_keys(
+        &self,
+        user_id: i64,
+        public_keys: &[String],
+        max_keys: usize,
+    ) -> DashboardResult<Vec<PublicKeyRegistrationResult>> {
+        let mut tx = self.pool.begin().await?;
+
+        // Locking the user row serializes batches for the same user, so the limit check and
+        // inserts are atomic
+        sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| user_not_found(user_id))?;
+        let existing_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM user_public_keys WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        let owners: HashMap<String, i64> = sqlx::query_as::<_, (String, i64)>(
+            "SELECT public_key, user_id FROM user_public_keys WHERE public_key = ANY($1)",
+        )
+        .bind(public_keys)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+
+        let new_keys: HashSet<&String> = public_keys.iter().filter(|k| !owners.contains_key(k.as_str())).collect();
+        if existing_count as usize + new_keys.len() > max_keys {
+            return Err(DashboardError::validation(format!(
+                "Registering {} new keys would exceed the limit of {} keys per user",
+                new_keys.len(),
+                max_keys
+            )));
+        }
+
+        let now = Utc::now();
+        let mut added = HashSet::new();
+        let mut results = Vec::with_capacity(public_keys.len());
+        for public_key in public_keys {
+            let (status, message) = match owners.get(public_key) {
+                Some(owner) if *owner == user_id => (PublicKeyRegistrationStatus::AlreadyExists, None),
+                Some(_) => (
+                    PublicKeyRegistrationStatus::Error,
+                    Some("Public key already associated with another user".to_string()),
+                ),
+                None if added.contains(public_key) => (PublicKeyRegistrationStatus::AlreadyExists, None),
+                None => {
+                    sqlx::query("INSERT INTO user_public_keys (user_id, public_key, created_at) VALUES ($1, $2, $3)")
+                        .bind(user_id)
+                        .bind(public_key)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await
+                        .map_err(|e| unique_violation(e, "Public key already associated with another user"))?;
+                    added.insert(public_key);
+                    (PublicKeyRegistrationStatus::Added, None)
+                }
+            };
+            results.push(PublicKeyRegistrationResult {
+                public_key: public_key.clone(),
+                status,
+                message,
+            });
+        }
+
+        tx.commit().await?;
+        Ok(results)
+    }
+
+    async fn revoke_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.set_public_key_revoked(user_id, public_key, true).await
+    }
+
+    async fn reactivate_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        self.set_public_key_revoked(user_id, public_key, false).await
+    }
+
+    async fn delete_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        match self.public_key_owner(public_key).await? {
+            Some(owner) if owner == user_id => {}
+            Some(_) => return Err(DashboardError::validation("Public key belongs to another user")),
+            None => return Ok(false),
+        }
+
+        let result = sqlx::query("DELETE FROM user_public_keys WHERE user_id = $1 AND public_key = $2")
+            .bind(user_id)
+            .bind(public_key)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_public_keys_for_user(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+        let keys = sqlx::query_scalar(&format!(
+            "SELECT public_key FROM user_public_keys WHERE user_id = $1 {}",
+            PUBLIC_KEY_LIST_ORDER_BY
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(keys)
+    }
+
+    async fn find_public_key_info(&self, public_key: &str) -> DashboardResult<Option<PublicKeyInfo>> {
+        let info = sqlx::query_as::<_, PublicKeyInfo>(&format!(
+            "SELECT {} FROM user_public_keys WHERE public_key = $1",
+            PUBLIC_KEY_COLUMNS
+        ))
+        .bind(public_key)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(info)
+    }
+
+    async fn get_public_key_info(&self, user_id: i64, public_key: &str) -> DashboardResult<Option<PublicKeyInfo>> {
+        let info = sqlx::query_as::<_, PublicKeyInfo>(&format!(
+            "SELECT {} FROM user_public_keys WHERE user_id = $1 AND public_key = $2",
+            PUBLIC_KEY_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(public_key)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(info)
+    }
+
+    async fn update_public_key_last_used(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
+        sqlx::query("UPDATE user_public_keys SET last_used = NOW() WHERE user_id = $1 AND public_key = $2")
+            .bind(user_id)
+            .bind(public_key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+// These run against a real database migrated from `migrations/`:
+// DATABASE_URL=postgres://... cargo test -- --ignored postgres
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nanoid::nanoid;
+
+    async fn storage() -> PostgresUserStorage {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for PostgreSQL tests");
+        PostgresUserStorage::new(PgPool::connect(&url).await.unwrap())
+    }
+
+    /// Users and keys get random names so runs don't collide on the unique indexes
+    async fn create_user(storage: &PostgresUserStorage) -> User {
+        let name = nanoid!(12);
+        storage
+            .create_user_with_credentials(NewUserAccount {
+                user: CreateUserDto {
+                    email: format!("{}@example.com", name),
+                    username: name,
+                    password: String::new(),
+                    wallet_address: None,
+                },
+                password_hash: "hash".to_string(),
+                salt: "salt".to_string(),
+                status: UserStatus::Active,
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_postgres_creates_user_with_credentials() {
+        let storage = storage().await;
+        let user = create_user(&storage).await;
+
+        assert_eq!(storage.find_user_by_email(&user.email).await.unwrap().unwrap().id, user.id);
+        assert_eq!(user.status, UserStatus::Active);
+        let credentials = storage.get_credentials(user.id).await.unwrap().unwrap();
+        assert_eq!((credentials.password_hash.as_str(), credentials.salt.as_str()), ("hash", "salt"));
+
+        // A duplicate email fails without leaving a user behind
+        let duplicate = NewUserAccount {
+            user: CreateUserDto {
+                email: user.email.clone(),
+                username: nanoid!(12),
+                password: String::new(),
+                wallet_address: None,
+            },
+            password_hash: "hash".to_string(),
+            salt: "salt".to_string(),
+            status: UserStatus::Active,
+        };
+        assert!(matches!(
+            storage.create_user_with_credentials(duplicate).await,
+            Err(DashboardError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_postgres_public_keys_store_look_up_and_revoke() {
+        let storage = storage().await;
+        let user = create_user(&storage).await;
+        let other = create_user(&storage).await;
+        let (first, second) = (nanoid!(32), nanoid!(32));
+
+        let results = storage
+            .store_public_keys(user.id, &[first.clone(), first.clone(), second.clone()], 5)
+            .await
+            .unwrap();
+        let statuses: Vec<_> = results.iter().map(|result| result.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                PublicKeyRegistrationStatus::Added,
+                PublicKeyRegistrationStatus::AlreadyExists,
+                PublicKeyRegistrationStatus::Added,
+            ]
+        );
+        assert_eq!(storage.find_user_by_public_key(&first).await.unwrap().unwrap().id, user.id);
+
+        // Another user can't claim the key, nor go over the limit
+        assert!(matches!(
+            storage.store_public_key(other.id, &first).await,
+            Err(DashboardError::Validation(_))
+        ));
+        assert!(matches!(
+            storage.store_public_keys(user.id, &[nanoid!(32)], 2).await,
+            Err(DashboardError::Validation(_))
+        ));
+
+        assert!(storage.revoke_public_key(user.id, &first).await.unwrap());
+        assert!(storage.find_user_by_public_key(&first).await.unwrap().is_none());
+        let info = storage.find_public_key_info(&first).await.unwrap().unwrap();
+        assert!(info.revoked && info.revoked_at.is_some());
+        assert!(!storage.revoke_public_key(user.id, &first).await.unwrap());
+        assert!(matches!(
+            storage.revoke_public_key(other.id, &second).await,
+            Err(DashboardError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_postgres_writes_for_missing_user_are_not_found() {
+        let storage = storage().await;
+        let user = create_user(&storage).await;
+        assert!(storage.delete_user(user.id).await.unwrap());
+
+        assert!(matches!(
+            storage.store_public_keys(user.id, &[nanoid!(32)], 5).await,
+            Err(DashboardError::NotFound(_))
+        ));
+        assert!(matches!(
+            storage.create_session(user.id, "127.0.0.1", "test-agent", 3600).await,
+            Err(DashboardError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_postgres_sessions() {
+        let storage = storage().await;
+        let user = create_user(&storage).await;
+
+        let first = storage.create_session(user.id, "127.0.0.1", "test-agent", 3600).await.unwrap();
+        let second = storage.create_session(user.id, "127.0.0.1", "test-agent", 3600).await.unwrap();
+        assert_eq!(storage.find_session_by_id(&first.id).await.unwrap().unwrap().user_id, user.id);
+        let ids: Vec<_> = storage
+            .find_sessions_by_user_id(user.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|session| session.id)
+            .collect();
+        assert_eq!(ids, [first.id.clone(), second.id.clone()]);
+
+        let expires_at = first.expires_at + Duration::hours(1);
+        let extended = storage.extend_session(&first.id, expires_at).await.unwrap().unwrap();
+        assert_eq!(extended.created_at, first.created_at);
+        assert!(extended.expires_at > first.expires_at);
+
+        assert!(storage.delete_session(&first.id).await.unwrap());
+        assert!(storage.find_session_by_id(&first.id).await.unwrap().is_none());
+        assert_eq!(storage.delete_user_sessions(user.id).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_postgres_delete_user_removes_everything_of_theirs() {
+        let storage = storage().await;
+        let user = create_user(&storage).await;
+        let public_key = nanoid!(32);
+        storage.store_public_key(user.id, &public_key).await.unwrap();
+        let session = storage.create_session(user.id, "127.0.0.1", "test-agent", 3600).await.unwrap();
+
+        assert!(storage.delete_user(user.id).await.unwrap());
+        assert!(storage.find_user_by_id(user.id).await.unwrap().is_none());
+        assert!(storage.get_credentials(user.id).await.unwrap().is_none());
+        assert!(storage.find_session_by_id(&session.id).await.unwrap().is_none());
+        assert!(storage.find_public_key_info(&public_key).await.unwrap().is_none());
+        assert!(!storage.delete_user(user.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_postgres_delete_user_records_counts_removed_rows() {
+        let storage = storage().await;
+        let user = create_user(&storage).await;
+        storage.store_public_keys(user.id, &[nanoid!(32), nanoid!(32)], 5).await.unwrap();
+        storage.create_session(user.id, "127.0.0.1", "test-agent", 3600).await.unwrap();
+
+        let deleted = storage.delete_user_records(user.id).await.unwrap();
+        assert!(deleted.user_deleted);
+        assert_eq!((deleted.sessions, deleted.public_keys), (1, 2));
+        assert_eq!(storage.delete_user_records(user.id).await.unwrap(), AccountDeletion::default());
+    }
+}
@@ -0,0 +1,409 @@
+use std::future::Future;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tracing::{debug, field, info_span, Instrument, Span};
+
+use crate::errors::DashboardResult;
+use crate::models::network::{
+    ConnectionScoreFilter, CreateNetworkConnectionDto, NetworkConnection, NetworkEvent,
+    NetworkStatistics, NetworkStatus, UpdateNetworkConnectionDto,
+};
+use crate::models::user::{
+    AccountDeletion, CreateUserDto, NewUserAccount, NotificationPreferences, PublicKeyInfo, PublicKeyRegistrationResult,
+    UpdateUserDto, User, UserCredentials, UserSession, UserStatus,
+};
+use crate::storage::{NetworkStorage, UserStorage};
+
+/// Storage decorator wrapping every call of the wrapped store in a `storage` span named after
+/// the operation, recording how long it took and whether it failed.
+///
+/// Durations cover the whole backend call, so for the in-memory stores they include waiting
+/// on their locks.
+#[derive(Clone)]
+pub struct TracedStorage<S> {
+    inner: S,
+}
+
+impl<S> TracedStorage<S> {
+    /// Wrap `inner`
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+/// Run a storage call inside its span, recording the outcome once it completes
+async fn instrumented<T>(
+    operation: &'static str,
+    call: impl Future<Output = DashboardResult<T>>,
+) -> DashboardResult<T> {
+    let span = info_span!("storage", operation, duration_ms = field::Empty, error = field::Empty);
+    async move {
+        let started = Instant::now();
+        let result = call.await;
+        let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+        let span = Span::current();
+        span.record("duration_ms", duration_ms);
+        if let Err(err) = &result {
+            span.record("error", field::display(err));
+        }
+        debug!(operation, duration_ms, "Storage call finished");
+        result
+    }
+    .instrument(span)
+    .await
+}
+
+#[async_trait]
+impl<S: UserStorage> UserStorage for TracedStorage<S> {
+    async fn find_user_by_id(&self, id: i64) -> DashboardResult<Option<User>> {
+        instrumented("find_user_by_id", self.inner.find_user_by_id(id)).await
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> DashboardResult<Option<User>> {
+        instrumented("find_user_by_email", self.inner.find_user_by_email(email)).await
+    }
+
+    async fn create_user(&self, user: CreateUserDto) -> DashboardResult<User> {
+        instrumented("create_user", self.inner.create_user(user)).await
+    }
+
+    async fn create_user_with_credentials(&self, account: NewUserAccount) -> DashboardResult<User> {
+        instrumented("create_user_with_credentials", self.inner.create_user_with_credentials(account)).await
+    }
+
+    async fn update_user(&self, id: i64, update: UpdateUserDto) -> DashboardResult<User> {
+        instrumented("update_user", self.inner.update_user(id, update)).await
+    }
+
+    async fn set_user_status(&self, id: i64, status: UserStatus) -> DashboardResult<User> {
+        instrumented("set_user_status", self.inner.set_user_status(id, status)).await
+    }
+
+    async fn delete_user(&self, id: i64) -> DashboardResult<bool> {
+        instrumented("delete_user", self.inner.delete_user(id)).await
+    }
+
+    async fn delete_user_records(&self, id: i64) -> DashboardResult<AccountDeletion> {
+        instrumented("delete_user_records", self.inner.delete_user_records(id)).await
+    }
+
+    async fn list_users(
+        &self,
+        after_id: Option<i64>,
+        limit: usize,
+        email_contains: Option<&str>,
+    ) -> DashboardResult<Vec<User>> {
+        instrumented("list_users", self.inner.list_users(after_id, limit, email_contains)).await
+    }
+
+    async fn store_credentials(
+        &self,
+        user_id: i64,
+        password_hash: &str,
+        salt: &str,
+    ) -> DashboardResult<()> {
+        instrumented("store_credentials", self.inner.store_credentials(user_id, password_hash, salt)).await
+    }
+
+    async fn get_credentials(&self, user_id: i64) -> DashboardResult<Option<UserCredentials>> {
+        instrumented("get_credentials", self.inner.get_credentials(user_id)).await
+    }
+
+    async fn get_password_history(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+        instrumented("get_password_history", self.inner.get_password_history(user_id)).await
+    }
+
+    async fn record_password_history(
+        &self,
+        user_id: i64,
+        password_hash: &str,
+        keep: usize,
+    ) -> DashboardResult<()> {
+        instrumented("record_password_history", self.inner.record_password_history(user_id, password_hash, keep)).await
+    }
+
+    async fn find_user_by_external_identity(&self, issuer: &str, subject: &str) -> DashboardResult<Option<User>> {
+        instrumented(
+            "find_user_by_external_identity",
+            self.inner.find_user_by_external_identity(issuer, subject),
+        )
+        .await
+    }
+
+    async fn link_external_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DashboardResult<()> {
+        instrumented("link_external_identity", self.inner.link_external_identity(user_id, issuer, subject)).await
+    }
+
+    async fn get_notification_preferences(&self, user_id: i64) -> DashboardResult<Option<NotificationPreferences>> {
+        instrumented("get_notification_preferences", self.inner.get_notification_preferences(user_id)).await
+    }
+
+    async fn set_notification_preferences(&self, user_id: i64, preferences: &NotificationPreferences) -> DashboardResult<()> {
+        instrumented(
+            "set_notification_preferences",
+            self.inner.set_notification_preferences(user_id, preferences),
+        )
+        .await
+    }
+
+    async fn create_session(
+        &self,
+        user_id: i64,
+        ip_address: &str,
+        user_agent: &str,
+        expires_in_seconds: i64,
+    ) -> DashboardResult<UserSession> {
+        instrumented(
+            "create_session",
+            self.inner.create_session(user_id, ip_address, user_agent, expires_in_seconds),
+        )
+        .await
+    }
+
+    async fn find_session_by_id(&self, session_id: &str) -> DashboardResult<Option<UserSession>> {
+        instrumented("find_session_by_id", self.inner.find_session_by_id(session_id)).await
+    }
+
+    async fn extend_session(&self, session_id: &str, expires_at: DateTime<Utc>) -> DashboardResult<Option<UserSession>> {
+        instrumented("extend_session", self.inner.extend_session(session_id, expires_at)).await
+    }
+
+    async fn find_sessions_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<UserSession>> {
+        instrumented("find_sessions_by_user_id", self.inner.find_sessions_by_user_id(user_id)).await
+    }
+
+    async fn delete_session(&self, session_id: &str) -> DashboardResult<bool> {
+        instrumented("delete_session", self.inner.delete_session(session_id)).await
+    }
+
+    async fn delete_user_sessions(&self, user_id: i64) -> DashboardResult<i64> {
+        instrumented("delete_user_sessions", self.inner.delete_user_sessions(user_id)).await
+    }
+
+    async fn update_last_active(&self, user_id: i64) -> DashboardResult<()> {
+        instrumented("update_last_active", self.inner.update_last_active(user_id)).await
+    }
+
+    async fn find_user_by_public_key(&self, public_key: &str) -> DashboardResult<Option<User>> {
+        instrumented("find_user_by_public_key", self.inner.find_user_by_public_key(public_key)).await
+    }
+
+    async fn store_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<()> {
+        instrumented("store_public_key", self.inner.store_public_key(user_id, public_key)).await
+    }
+
+    async fn store_public_keys(
+        &self,
+        user_id: i64,
+        public_keys: &[String],
+        max_keys: usize,
+    ) -> DashboardResult<Vec<PublicKeyRegistrationResult>> {
+        instrumented("store_public_keys", self.inner.store_public_keys(user_id, public_keys, max_keys)).await
+    }
+
+    async fn revoke_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        instrumented("revoke_public_key", self.inner.revoke_public_key(user_id, public_key)).await
+    }
+
+    async fn reactivate_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        instrumented("reactivate_public_key", self.inner.reactivate_public_key(user_id, public_key)).await
+    }
+
+    async fn delete_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool> {
+        instrumented("delete_public_key", self.inner.delete_public_key(user_id, public_key)).await
+    }
+
+    async fn get_public_keys_for_user(&self, user_id: i64) -> DashboardResult<Vec<String>> {
+        instrumented("get_public_keys_for_user", self.inner.get_public_keys_for_user(user_id)).await
+    }
+
+    async fn find_public_key_info(
+        &self,
+        public_key: &str,
+    ) -> DashboardResult<Option<PublicKeyInfo>> {
+        instrumented("find_public_key_info", self.inner.find_public_key_info(public_key)).await
+    }
+
+    async fn get_public_key_info(
+        &self,
+        user_id: i64,
+        public_key: &str,
+    ) -> DashboardResult<Option<PublicKeyInfo>> {
+        instrumented("get_public_key_info", self.inner.get_public_key_info(user_id, public_key)).await
+    }
+
+    async fn update_public_key_last_used(
+        &self,
+        user_id: i64,
+        public_key: &str,
+    ) -> DashboardResult<()> {
+        instrumented("update_public_key_last_used", self.inner.update_public_key_last_used(user_id, public_key)).await
+    }
+}
+
+#[async_trait]
+impl<S: NetworkStorage> NetworkStorage for TracedStorage<S> {
+    async fn find_connection_by_id(&self, id: i64) -> DashboardResult<Option<NetworkConnection>> {
+        instrumented("find_connection_by_id", self.inner.find_connection_by_id(id)).await
+    }
+
+    async fn find_connections_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        instrumented("find_connections_by_user_id", self.inner.find_connections_by_user_id(user_id)).await
+    }
+
+    async fn find_connections_page(
+        &self,
+        user_id: i64,
+        after_id: Option<i64>,
+        limit: usize,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        instrumented("find_connections_page", self.inner.find_connections_page(user_id, after_id, limit)).await
+    }
+
+    async fn find_connections_by_score(
+        &self,
+        user_id: i64,
+        filter: &ConnectionScoreFilter,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        instrumented("find_connections_by_score", self.inner.find_connections_by_score(user_id, filter)).await
+    }
+
+    async fn find_active_connections_by_user_id(
+        &self,
+        user_id: i64,
+    ) -> DashboardResult<Vec<NetworkConnection>> {
+        instrumented("find_active_connections_by_user_id", self.inner.find_active_connections_by_user_id(user_id)).await
+    }
+
+    async fn create_connection(
+        &self,
+        connection: CreateNetworkConnectionDto,
+    ) -> DashboardResult<NetworkConnection> {
+        instrumented("create_connection", self.inner.create_connection(connection)).await
+    }
+
+    async fn update_connection(
+        &self,
+        id: i64,
+        update: UpdateNetworkConnectionDto,
+    ) -> DashboardResult<NetworkConnection> {
+        instrumented("update_connection", self.inner.update_connection(id, update)).await
+    }
+
+    async fn delete_connection(&self, id: i64) -> DashboardResult<bool> {
+        instrumented("delete_connection", self.inner.delete_connection(id)).await
+    }
+
+    async fn delete_user_connections(&self, user_id: i64) -> DashboardResult<i64> {
+        instrumented("delete_user_connections", self.inner.delete_user_connections(user_id)).await
+    }
+
+    async fn get_network_status(
+        &self,
+        connection_id: i64,
+    ) -> DashboardResult<Option<NetworkStatus>> {
+        instrumented("get_network_status", self.inner.get_network_status(connection_id)).await
+    }
+
+    async fn update_network_status(
+        &self,
+        connection_id: i64,
+        connected: bool,
+        status_message: &str,
+        network_score: Option<f64>,
+    ) -> DashboardResult<NetworkStatus> {
+        instrumented(
+            "update_network_status",
+            self.inner.update_network_status(connection_id, connected, status_message, network_score),
+        )
+        .await
+    }
+
+    async fn list_events(
+        &self,
+        connection_id: i64,
+        offset: usize,
+        limit: usize,
+    ) -> DashboardResult<Vec<NetworkEvent>> {
+        instrumented("list_events", self.inner.list_events(connection_id, offset, limit)).await
+    }
+
+    async fn count_events(&self, connection_id: i64) -> DashboardResult<usize> {
+        instrumented("count_events", self.inner.count_events(connection_id)).await
+    }
+
+    async fn get_network_statistics(&self, user_id: i64) -> DashboardResult<NetworkStatistics> {
+        instrumented("get_network_statistics", self.inner.get_network_statistics(user_id)).await
+    }
+
+    async fn record_connection_time(
+        &self,
+        connection_id: i64,
+        seconds: i64,
+    ) -> DashboardResult<i64> {
+        instrumented("record_connection_time", self.inner.record_connection_time(connection_id, seconds)).await
+    }
+
+    async fn record_earned_points(&self, connection_id: i64, points: f64) -> DashboardResult<f64> {
+        instrumented("record_earned_points", self.inner.record_earned_points(connection_id, points)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::memory::{InMemoryNetworkStorage, InMemoryUserStorage};
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+    /// Collects the `operation` of every `storage` span opened
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<String>>>);
+
+    struct OperationVisitor<'a>(&'a mut Option<String>);
+
+    impl field::Visit for OperationVisitor<'_> {
+        fn record_str(&mut self, field: &field::Field, value: &str) {
+            if field.name() == "operation" {
+                *self.0 = Some(value.to_string());
+            }
+        }
+
+        fn record_debug(&mut self, _field: &field::Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    impl<S: Subscriber> Layer<S> for SpanRecorder {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            if attrs.metadata().name() != "storage" {
+                return;
+            }
+            let mut operation = None;
+            attrs.record(&mut OperationVisitor(&mut operation));
+            self.0.lock().unwrap().extend(operation);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_storage_calls_open_a_span_per_operation() {
+        let recorder = SpanRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let users = TracedStorage::new(InMemoryUserStorage::new());
+        let networks = TracedStorage::new(InMemoryNetworkStorage::new());
+        assert!(users.find_user_by_id(1).await.unwrap().is_none());
+        assert!(networks.find_connections_by_user_id(1).await.unwrap().is_empty());
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec!["find_user_by_id".to_string(), "find_connections_by_user_id".to_string()]
+        );
+    }
+}
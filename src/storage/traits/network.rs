@@ -1,7 +1,7 @@
 use crate::errors::DashboardResult;
 use crate::models::network::{
-    CreateNetworkConnectionDto, NetworkConnection, NetworkStatistics, NetworkStatus,
-    UpdateNetworkConnectionDto,
+    ConnectionScoreFilter, CreateNetworkConnectionDto, NetworkConnection, NetworkEvent, NetworkStatistics,
+    NetworkStatus, UpdateNetworkConnectionDto,
 };
 use async_trait::async_trait;
 
@@ -14,10 +14,33 @@ pub trait NetworkStorage: Send + Sync + 'static {
     /// Find all network connections for a user
     async fn find_connections_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<NetworkConnection>>;
     
+    /// Find a page of a user's network connections ordered by ID, starting after `after_id`.
+    ///
+    /// Keyset pagination lets a database implementation serve this from an index cursor.
+    async fn find_connections_page(
+        &self,
+        user_id: i64,
+        after_id: Option<i64>,
+        limit: usize,
+    ) -> DashboardResult<Vec<NetworkConnection>>;
+    
+    /// Find a user's network connections whose score is within `filter`, ordered by score
+    /// ascending, then by ID
+    async fn find_connections_by_score(
+        &self,
+        user_id: i64,
+        filter: &ConnectionScoreFilter,
+    ) -> DashboardResult<Vec<NetworkConnection>>;
+    
     /// Find active network connections for a user
     async fn find_active_connections_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<NetworkConnection>>;
     
-    /// Create a new network connection
+    /// Create a new network connection.
+    ///
+    /// Unless `allow_duplicate` is set, an active connection for the same user and network name
+    /// is updated in place (IP address, and score if given) and returned instead, so reconnects
+    /// don't inflate statistics. The check and write must be atomic; a database implementation
+    /// can do both in one statement (an `UPDATE ... RETURNING` that falls back to `INSERT`).
     async fn create_connection(&self, connection: CreateNetworkConnectionDto) -> DashboardResult<NetworkConnection>;
     
     /// Update a network connection
@@ -30,10 +53,18 @@ pub trait NetworkStorage: Send + Sync + 'static {
     /// Delete a network connection
     async fn delete_connection(&self, id: i64) -> DashboardResult<bool>;
     
+    /// Delete all of a user's network connections and their statuses, returning how many
+    /// connections were removed.
+    ///
+    /// Used when an account is deleted; a database implementation should remove both in one
+    /// transaction.
+    async fn delete_user_connections(&self, user_id: i64) -> DashboardResult<i64>;
+    
     /// Get current network status
     async fn get_network_status(&self, connection_id: i64) -> DashboardResult<Option<NetworkStatus>>;
     
-    /// Update network status
+    /// Update network status, appending a `NetworkEvent` with the old and new values to the
+    /// connection's history
     async fn update_network_status(
         &self,
         connection_id: i64,
@@ -42,6 +73,15 @@ pub trait NetworkStorage: Send + Sync + 'static {
         network_score: Option<f64>,
     ) -> DashboardResult<NetworkStatus>;
     
+    /// List a connection's status history, newest first, skipping `offset` events and returning
+    /// at most `limit`.
+    ///
+    /// Implementations may keep only the most recent events of each connection.
+    async fn list_events(&self, connection_id: i64, offset: usize, limit: usize) -> DashboardResult<Vec<NetworkEvent>>;
+    
+    /// Count the events `list_events` can return for a connection
+    async fn count_events(&self, connection_id: i64) -> DashboardResult<usize>;
+    
     /// Get network statistics for a user
     async fn get_network_statistics(&self, user_id: i64) -> DashboardResult<NetworkStatistics>;
     
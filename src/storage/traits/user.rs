@@ -1,6 +1,16 @@
-use crate::errors::DashboardResult;
-use crate::models::user::{CreateUserDto, UpdateUserDto, User, UserCredentials, UserSession};
+use crate::errors::{DashboardError, DashboardResult};
+use crate::models::user::{
+    AccountDeletion, CreateUserDto, NewUserAccount, NotificationPreferences, PublicKeyInfo, PublicKeyRegistrationResult,
+    PublicKeyRegistrationStatus, UpdateUserDto, User, UserCredentials, UserSession, UserStatus,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use tracing::error;
+
+/// `ORDER BY` clause for listing a user's keys from `user_public_keys`, matching
+/// `PublicKeyInfo::cmp_listing_order`
+pub const PUBLIC_KEY_LIST_ORDER_BY: &str = "ORDER BY revoked ASC, created_at DESC, public_key ASC";
 
 /// Trait defining storage operations for User-related data
 #[async_trait]
@@ -14,19 +24,79 @@ pub trait UserStorage: Send + Sync + 'static {
     /// Create a new user
     async fn create_user(&self, user: CreateUserDto) -> DashboardResult<User>;
     
+    /// Create a user with their credentials and initial status as one unit of work: either
+    /// all of it is written or none of it is.
+    ///
+    /// Backends with transactions should run the writes in a single one (`BEGIN` ... `COMMIT`
+    /// for Postgres). This default issues separate calls and deletes the half-created user if
+    /// a later step fails.
+    async fn create_user_with_credentials(&self, account: NewUserAccount) -> DashboardResult<User> {
+        let user = self.create_user(account.user).await?;
+        let completed = match self.store_credentials(user.id, &account.password_hash, &account.salt).await {
+            Ok(()) => self.set_user_status(user.id, account.status).await,
+            Err(e) => Err(e),
+        };
+        
+        if completed.is_err() {
+            if let Err(e) = self.delete_user(user.id).await {
+                error!("Failed to roll back partially created user {}: {}", user.id, e);
+            }
+        }
+        completed
+    }
+    
     /// Update an existing user
     async fn update_user(&self, id: i64, update: UpdateUserDto) -> DashboardResult<User>;
     
+    /// Change a user's account status
+    async fn set_user_status(&self, id: i64, status: UserStatus) -> DashboardResult<User>;
+    
     /// Delete a user
     async fn delete_user(&self, id: i64) -> DashboardResult<bool>;
     
+    /// Delete a user and everything stored about them as one unit of work, reporting the
+    /// sessions and public keys removed with them. `network_connections` is left at 0, as
+    /// those live in `NetworkStorage`; a missing user yields `user_deleted: false`.
+    async fn delete_user_records(&self, id: i64) -> DashboardResult<AccountDeletion>;
+    
+    /// List up to `limit` users with IDs greater than `after_id`, ordered by ID, optionally
+    /// keeping only emails containing `email_contains` (case-insensitive)
+    async fn list_users(
+        &self,
+        after_id: Option<i64>,
+        limit: usize,
+        email_contains: Option<&str>,
+    ) -> DashboardResult<Vec<User>>;
+    
     /// Store user credentials
     async fn store_credentials(&self, user_id: i64, password_hash: &str, salt: &str) -> DashboardResult<()>;
     
     /// Get user credentials
     async fn get_credentials(&self, user_id: i64) -> DashboardResult<Option<UserCredentials>>;
     
-    /// Create a user session
+    /// Password hashes a user has replaced, newest first
+    async fn get_password_history(&self, user_id: i64) -> DashboardResult<Vec<String>>;
+    
+    /// Record a replaced password hash, pruning all but the newest `keep` entries
+    async fn record_password_history(&self, user_id: i64, password_hash: &str, keep: usize) -> DashboardResult<()>;
+    
+    /// Find the user linked to `subject` at the external identity provider `issuer`
+    async fn find_user_by_external_identity(&self, issuer: &str, subject: &str) -> DashboardResult<Option<User>>;
+    
+    /// Link `subject` at `issuer` to a user; fails with `Validation` if it is already linked
+    async fn link_external_identity(&self, user_id: i64, issuer: &str, subject: &str) -> DashboardResult<()>;
+    
+    /// A user's notification preferences, `None` if they never saved any
+    async fn get_notification_preferences(&self, user_id: i64) -> DashboardResult<Option<NotificationPreferences>>;
+    
+    /// Replace a user's notification preferences
+    async fn set_notification_preferences(&self, user_id: i64, preferences: &NotificationPreferences) -> DashboardResult<()>;
+    
+    /// Create a user session.
+    ///
+    /// `ip_address` and `user_agent` come straight from request headers; implementations
+    /// must store them via `UserSession::sanitize_ip_address` / `sanitize_user_agent`.
+    /// Fails with `NotFound` for a user that doesn't exist, including one deleted concurrently.
     async fn create_session(
         &self,
         user_id: i64,
@@ -38,6 +108,13 @@ pub trait UserStorage: Send + Sync + 'static {
     /// Find a session by ID
     async fn find_session_by_id(&self, session_id: &str) -> DashboardResult<Option<UserSession>>;
     
+    /// Move a session's expiry to `expires_at`, returning the updated session or `None` if it
+    /// no longer exists. `created_at` is left untouched.
+    async fn extend_session(&self, session_id: &str, expires_at: DateTime<Utc>) -> DashboardResult<Option<UserSession>>;
+    
+    /// Find all of a user's sessions, oldest first
+    async fn find_sessions_by_user_id(&self, user_id: i64) -> DashboardResult<Vec<UserSession>>;
+    
     /// Delete a session
     async fn delete_session(&self, session_id: &str) -> DashboardResult<bool>;
     
@@ -47,18 +124,74 @@ pub trait UserStorage: Send + Sync + 'static {
     /// Update user's last active timestamp
     async fn update_last_active(&self, user_id: i64) -> DashboardResult<()>;
     
-    /// Find a user by their public key
+    /// Find a user by one of their active public keys; revoked keys match nobody
     async fn find_user_by_public_key(&self, public_key: &str) -> DashboardResult<Option<User>>;
     
     /// Store a public key for a user
     async fn store_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<()>;
     
-    /// Revoke a public key for a user
+    /// Store several public keys for a user, rejecting the whole batch if the user would
+    /// end up with more than `max_keys` keys
+    async fn store_public_keys(
+        &self,
+        user_id: i64,
+        public_keys: &[String],
+        max_keys: usize,
+    ) -> DashboardResult<Vec<PublicKeyRegistrationResult>> {
+        let existing: HashSet<String> = self.get_public_keys_for_user(user_id).await?.into_iter().collect();
+        let new_keys: HashSet<&String> = public_keys.iter().filter(|k| !existing.contains(*k)).collect();
+        if existing.len() + new_keys.len() > max_keys {
+            return Err(DashboardError::validation(format!(
+                "Registering {} new keys would exceed the limit of {} keys per user",
+                new_keys.len(),
+                max_keys
+            )));
+        }
+        
+        let mut seen = HashSet::new();
+        let mut results = Vec::with_capacity(public_keys.len());
+        for public_key in public_keys {
+            let (status, message) = if existing.contains(public_key) || !seen.insert(public_key) {
+                (PublicKeyRegistrationStatus::AlreadyExists, None)
+            } else {
+                match self.store_public_key(user_id, public_key).await {
+                    Ok(()) => (PublicKeyRegistrationStatus::Added, None),
+                    Err(e) => (PublicKeyRegistrationStatus::Error, Some(e.to_string())),
+                }
+            };
+            results.push(PublicKeyRegistrationResult {
+                public_key: public_key.clone(),
+                status,
+                message,
+            });
+        }
+        
+        Ok(results)
+    }
+    
+    /// Revoke a public key for a user, keeping it registered with `revoked_at` set.
+    ///
+    /// Revoked keys are ignored by `find_user_by_public_key`. Returns `false` when the user
+    /// has no such key or it is already revoked.
     async fn revoke_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool>;
     
-    /// Get all public keys for a user
+    /// Reactivate a revoked public key. Returns `false` when the user has no such key or it
+    /// is already active.
+    async fn reactivate_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool>;
+    
+    /// Permanently remove a public key and its metadata
+    async fn delete_public_key(&self, user_id: i64, public_key: &str) -> DashboardResult<bool>;
+    
+    /// Get all public keys for a user: active before revoked, then newest first, then by key
+    /// (see `PublicKeyInfo::cmp_listing_order`)
     async fn get_public_keys_for_user(&self, user_id: i64) -> DashboardResult<Vec<String>>;
     
+    /// Get metadata for a registered public key whoever owns it, including revoked keys
+    async fn find_public_key_info(&self, public_key: &str) -> DashboardResult<Option<PublicKeyInfo>>;
+    
+    /// Get metadata for a public key registered to a user
+    async fn get_public_key_info(&self, user_id: i64, public_key: &str) -> DashboardResult<Option<PublicKeyInfo>>;
+    
     /// Update the last_used timestamp for a public key
     async fn update_public_key_last_used(&self, user_id: i64, public_key: &str) -> DashboardResult<()>;
 }
\ No newline at end of file
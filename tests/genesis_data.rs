@@ -0,0 +1,86 @@
+use temp_rust_websocket::genesis::memory_seed::{seed_network_storage_with, seed_storage_with};
+use temp_rust_websocket::genesis::{GenesisData, UserPublicKey};
+use temp_rust_websocket::models::network::NetworkConnection;
+use temp_rust_websocket::models::user::{CreateUserDto, User};
+use temp_rust_websocket::storage::memory::{InMemoryNetworkStorage, InMemoryUserStorage};
+use temp_rust_websocket::storage::{NetworkStorage, UserStorage};
+use chrono::Utc;
+
+/// Fresh in-memory storage seeded with `data`
+async fn seeded(data: &GenesisData) -> (InMemoryUserStorage, InMemoryNetworkStorage) {
+    let users = InMemoryUserStorage::new();
+    let networks = InMemoryNetworkStorage::new();
+    seed_storage_with(&users, data).await.unwrap();
+    seed_network_storage_with(&networks, data).await.unwrap();
+    (users, networks)
+}
+
+#[tokio::test]
+async fn test_embedded_genesis_data_loads_and_seeds() {
+    let data = GenesisData::embedded();
+    assert_eq!(data.users.len(), 5);
+    assert_eq!(data.user_credentials.len(), 5);
+    assert_eq!(data.network_connections.len(), 8);
+    assert_eq!(data.network_statuses.len(), 5);
+    assert_eq!(data.user_public_keys.len(), 5);
+
+    let (users, networks) = seeded(&data).await;
+    for user in &data.users {
+        let seeded = users.find_user_by_id(user.id).await.unwrap().unwrap();
+        assert_eq!(seeded.email, user.email);
+        assert!(users.get_credentials(user.id).await.unwrap().is_some());
+    }
+    for key in &data.user_public_keys {
+        let info = users.get_public_key_info(key.user_id, &key.public_key).await.unwrap().unwrap();
+        assert_eq!(info.created_at, key.created_at);
+    }
+    for connection in &data.network_connections {
+        let seeded = networks.find_connection_by_id(connection.id).await.unwrap().unwrap();
+        assert_eq!(seeded.user_id, connection.user_id);
+    }
+
+    // New rows get ids after the seeded ones
+    let max_id = data.users.iter().map(|user| user.id).max().unwrap();
+    let created = users
+        .create_user(CreateUserDto {
+            email: "new@example.com".to_string(),
+            username: "newuser".to_string(),
+            password: "password123".to_string(),
+            wallet_address: None,
+        })
+        .await
+        .unwrap();
+    assert!(created.id > max_id);
+}
+
+#[test]
+fn test_embedded_genesis_data_is_deterministic() {
+    let first = serde_json::to_value(GenesisData::embedded()).unwrap();
+    let second = serde_json::to_value(GenesisData::embedded()).unwrap();
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn test_builder_seeds_custom_data() {
+    let mut user = User::new("custom@example.com".to_string(), "custom".to_string(), None);
+    user.id = 42;
+    let mut connection = NetworkConnection::new(42, "lab".to_string(), "10.0.0.42".to_string(), Some(70.0));
+    connection.id = 7;
+    let data = GenesisData::builder()
+        .with_user(user)
+        .with_network_connection(connection)
+        .with_public_key(UserPublicKey {
+            user_id: 42,
+            public_key: "ab".repeat(32),
+            created_at: Utc::now(),
+            last_used: None,
+            revoked: true,
+        })
+        .build();
+
+    let (users, networks) = seeded(&data).await;
+    assert_eq!(users.find_user_by_email("custom@example.com").await.unwrap().unwrap().id, 42);
+    assert!(users.find_user_by_id(1).await.unwrap().is_none());
+    assert!(users.get_public_key_info(42, &"ab".repeat(32)).await.unwrap().unwrap().revoked);
+    assert_eq!(networks.find_connections_by_user_id(42).await.unwrap().len(), 1);
+}
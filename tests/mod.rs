@@ -1,5 +1,9 @@
 // Model tests
 mod user_models;
 mod network_models;
+mod websocket_models;
+
+// Seeding from the embedded genesis dataset
+mod genesis_data;
 
 // Add more test modules as they are implemented 
\ No newline at end of file
@@ -1,4 +1,9 @@
-use temp_rust_websocket::models::network::{CreateNetworkConnectionDto, NetworkConnection, UpdateNetworkConnectionDto};
+use temp_rust_websocket::errors::DashboardError;
+use temp_rust_websocket::models::network::{
+    CreateNetworkConnectionDto, NetworkConnection, NetworkStatistics, UpdateNetworkConnectionDto,
+};
+use temp_rust_websocket::storage::memory::InMemoryNetworkStorage;
+use temp_rust_websocket::storage::NetworkStorage;
 use chrono::Utc;
 
 #[test]
@@ -100,4 +105,51 @@ fn test_add_points() {
     // Add another 5 points
     connection.add_points(5.0);
     assert_eq!(connection.points_earned, 15.0);
-} 
\ No newline at end of file
+} 
+
+#[tokio::test]
+async fn test_full_connection_store_rejects_new_rows_but_reuses_existing() {
+    let storage = InMemoryNetworkStorage::new().with_max_connections(1);
+    let dto = |network_name: &str| CreateNetworkConnectionDto {
+        user_id: 1,
+        network_name: network_name.to_string(),
+        ip_address: "10.0.0.1".to_string(),
+        initial_score: None,
+        allow_duplicate: false,
+    };
+
+    let first = storage.create_connection(dto("alpha")).await.unwrap();
+    // Reconnecting to the same network updates the existing row
+    assert_eq!(storage.create_connection(dto("alpha")).await.unwrap().id, first.id);
+
+    let err = storage.create_connection(dto("beta")).await.unwrap_err();
+    assert!(matches!(err, DashboardError::ServiceUnavailable(_)));
+    assert_eq!(storage.connection_count(), 1);
+}
+
+fn scored(network_score: f64, connection_time: Option<i64>) -> NetworkConnection {
+    let mut connection = NetworkConnection::new(1, "net".to_string(), "10.0.0.1".to_string(), Some(network_score));
+    connection.connection_time = connection_time;
+    connection
+}
+
+#[test]
+fn test_weighted_score_favours_long_running_connections() {
+    // A long-lived good connection and a brief flaky one
+    let connections = [scored(90.0, Some(9_000)), scored(10.0, Some(1_000))];
+
+    assert_eq!(NetworkStatistics::average_score(&connections), 50.0);
+    assert_eq!(NetworkStatistics::time_weighted_score(&connections), 82.0);
+}
+
+#[test]
+fn test_weighted_score_falls_back_to_plain_average_without_connected_time() {
+    let connections = [scored(90.0, Some(0)), scored(10.0, None)];
+    assert_eq!(NetworkStatistics::time_weighted_score(&connections), 50.0);
+
+    // A connection with no time contributes nothing once another has some
+    let connections = [scored(90.0, Some(60)), scored(10.0, None)];
+    assert_eq!(NetworkStatistics::time_weighted_score(&connections), 90.0);
+
+    assert_eq!(NetworkStatistics::time_weighted_score(&[]), 0.0);
+}
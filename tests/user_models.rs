@@ -1,5 +1,12 @@
-use temp_rust_websocket::models::user::{CreateUserDto, UpdateUserDto, User};
-use chrono::Utc;
+use temp_rust_websocket::models::user::{
+    mask_public_key, CreateUserDto, UpdateUserDto, User, UserFieldLimits, UserSession, DEFAULT_MAX_EMAIL_LEN,
+    DEFAULT_MAX_USERNAME_LEN, MAX_IP_ADDRESS_LEN, MAX_USER_AGENT_LEN,
+};
+use temp_rust_websocket::storage::memory::{InMemoryUserStorage, SessionEviction};
+use temp_rust_websocket::storage::UserStorage;
+use temp_rust_websocket::errors::DashboardError;
+use chrono::{Duration, Utc};
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn test_user_creation() {
@@ -41,4 +48,179 @@ fn test_update_user_dto() {
     assert_eq!(dto.username, Some("newusername".to_string()));
     assert_eq!(dto.email, None);
     assert_eq!(dto.wallet_address, Some("0xnewaddress".to_string()));
-} 
\ No newline at end of file
+} 
+
+#[test]
+fn test_sanitize_user_agent_strips_control_chars() {
+    let sanitized = UserSession::sanitize_user_agent("Mozilla/5.0\r\nX-Injected: 1\u{1b}[31m");
+    assert_eq!(sanitized, "Mozilla/5.0X-Injected: 1[31m");
+}
+
+#[test]
+fn test_sanitize_truncates_oversized_values() {
+    let user_agent = "a".repeat(MAX_USER_AGENT_LEN * 4);
+    assert_eq!(UserSession::sanitize_user_agent(&user_agent).chars().count(), MAX_USER_AGENT_LEN);
+
+    let ip = "1".repeat(1000);
+    assert_eq!(UserSession::sanitize_ip_address(&ip).len(), MAX_IP_ADDRESS_LEN);
+
+    // Truncation counts characters, so multi-byte input is never split mid-character
+    let wide = "é".repeat(MAX_USER_AGENT_LEN + 1);
+    assert_eq!(UserSession::sanitize_user_agent(&wide).chars().count(), MAX_USER_AGENT_LEN);
+}
+
+#[tokio::test]
+async fn test_create_session_stores_sanitized_values() {
+    let storage = InMemoryUserStorage::new();
+    create_users(&storage, 1).await;
+    let user_agent = format!("agent\n{}", "x".repeat(2000));
+
+    let session = storage
+        .create_session(1, "10.0.0.1\u{0}\r\n", &user_agent, 3600)
+        .await
+        .unwrap();
+
+    assert_eq!(session.ip_address, "10.0.0.1");
+    assert!(session.user_agent.starts_with("agentx"));
+    assert_eq!(session.user_agent.chars().count(), MAX_USER_AGENT_LEN);
+
+    let stored = storage.find_session_by_id(&session.id).await.unwrap().unwrap();
+    assert_eq!(stored.user_agent, session.user_agent);
+}
+
+#[tokio::test]
+async fn test_create_session_regenerates_colliding_ids() {
+    let ids = Arc::new(Mutex::new(vec!["s2", "s1", "s1"]));
+    let storage = InMemoryUserStorage::new().with_session_id_generator(Arc::new(move || {
+        ids.lock().unwrap().pop().unwrap_or("s2").to_string()
+    }));
+    create_users(&storage, 3).await;
+
+    let first = storage.create_session(1, "10.0.0.1", "agent", 3600).await.unwrap();
+    let second = storage.create_session(2, "10.0.0.2", "agent", 3600).await.unwrap();
+    assert_eq!((first.id.as_str(), second.id.as_str()), ("s1", "s2"));
+
+    // Every id the generator can produce is taken
+    let err = storage.create_session(3, "10.0.0.3", "agent", 3600).await.unwrap_err();
+    assert!(matches!(err, DashboardError::InternalServer(_)));
+    assert_eq!(storage.find_session_by_id("s1").await.unwrap().unwrap().user_id, 1);
+}
+
+#[tokio::test]
+async fn test_full_stores_reject_or_evict_per_policy() {
+    let storage = InMemoryUserStorage::new().with_max_users(1).with_max_sessions(2, SessionEviction::Reject);
+    storage.create_user(create_dto("a@example.com".to_string(), "a".to_string())).await.unwrap();
+    let err = storage.create_user(create_dto("b@example.com".to_string(), "b".to_string())).await.unwrap_err();
+    assert!(matches!(err, DashboardError::ServiceUnavailable(_)));
+    assert_eq!(storage.user_count(), 1);
+
+    storage.create_session(1, "10.0.0.1", "agent", 3600).await.unwrap();
+    storage.create_session(1, "10.0.0.1", "agent", 3600).await.unwrap();
+    let err = storage.create_session(1, "10.0.0.1", "agent", 3600).await.unwrap_err();
+    assert!(matches!(err, DashboardError::ServiceUnavailable(_)));
+
+    let storage = InMemoryUserStorage::new().with_max_sessions(2, SessionEviction::EvictOldest);
+    create_users(&storage, 3).await;
+    let oldest = storage.create_session(1, "10.0.0.1", "agent", 3600).await.unwrap();
+    // Distinct creation times, so which session is oldest is unambiguous
+    tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+    let kept = storage.create_session(2, "10.0.0.2", "agent", 3600).await.unwrap();
+    let newest = storage.create_session(3, "10.0.0.3", "agent", 3600).await.unwrap();
+    assert_eq!(storage.session_count(), 2);
+    assert!(storage.find_session_by_id(&oldest.id).await.unwrap().is_none());
+    assert!(storage.find_session_by_id(&kept.id).await.unwrap().is_some());
+    assert!(storage.find_session_by_id(&newest.id).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_public_keys_listed_active_first_then_newest() {
+    let storage = InMemoryUserStorage::new();
+    let keys: Vec<String> = ["aa", "bb", "cc", "dd"].iter().map(|k| k.repeat(32)).collect();
+    storage.store_public_keys(1, &keys, 10).await.unwrap();
+
+    // Give the keys distinct ages and revoke the newest one
+    {
+        let mut info = storage.get_public_key_info_map().lock().unwrap();
+        let now = Utc::now();
+        info.get_mut(&keys[0]).unwrap().created_at = now - Duration::hours(3);
+        info.get_mut(&keys[1]).unwrap().created_at = now - Duration::hours(1);
+        info.get_mut(&keys[2]).unwrap().created_at = now - Duration::hours(2);
+        let revoked = info.get_mut(&keys[3]).unwrap();
+        revoked.created_at = now;
+        revoked.revoked = true;
+    }
+
+    let listed = storage.get_public_keys_for_user(1).await.unwrap();
+    assert_eq!(listed, vec![keys[1].clone(), keys[2].clone(), keys[0].clone(), keys[3].clone()]);
+}
+
+#[test]
+fn test_mask_public_key() {
+    let key = format!("abcd{}wxyz", "0".repeat(56));
+    assert_eq!(mask_public_key(&key), "abcd…wxyz");
+
+    // Too short to hide anything
+    assert_eq!(mask_public_key("abcdwxyz"), "abcdwxyz");
+    assert_eq!(mask_public_key(""), "");
+}
+
+fn email_of_len(len: usize) -> String {
+    format!("{}@example.com", "a".repeat(len - "@example.com".len()))
+}
+
+fn create_dto(email: String, username: String) -> CreateUserDto {
+    CreateUserDto {
+        email,
+        username,
+        password: "password123".to_string(),
+        wallet_address: None,
+    }
+}
+
+/// Create users with ids 1 to `count`, since sessions can only be opened for existing users
+async fn create_users(storage: &InMemoryUserStorage, count: usize) {
+    for i in 1..=count {
+        storage
+            .create_user(create_dto(format!("user{}@example.com", i), format!("user{}", i)))
+            .await
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_create_user_dto_length_limits() {
+    let limits = UserFieldLimits::default();
+    let at_limit = create_dto(email_of_len(DEFAULT_MAX_EMAIL_LEN), "u".repeat(DEFAULT_MAX_USERNAME_LEN));
+    assert!(at_limit.validate(&limits).is_ok());
+
+    let long_email = create_dto(email_of_len(DEFAULT_MAX_EMAIL_LEN + 1), "user".to_string());
+    let err = long_email.validate(&limits).unwrap_err().to_string();
+    assert!(err.contains("email: must be at most 254 characters"), "{}", err);
+    assert!(!err.contains("username"), "{}", err);
+
+    // Every offending field is reported, and limits count characters rather than bytes
+    let both = create_dto(email_of_len(DEFAULT_MAX_EMAIL_LEN + 1), "é".repeat(DEFAULT_MAX_USERNAME_LEN + 1));
+    let err = both.validate(&limits).unwrap_err().to_string();
+    assert!(err.contains("email:") && err.contains("username: must be at most 64 characters"), "{}", err);
+    assert!(create_dto(email_of_len(20), "é".repeat(DEFAULT_MAX_USERNAME_LEN)).validate(&limits).is_ok());
+}
+
+#[test]
+fn test_update_user_dto_length_limits_are_configurable() {
+    let limits = UserFieldLimits {
+        max_email_len: 20,
+        max_username_len: 8,
+    };
+    let update = |email: Option<String>, username: Option<&str>| UpdateUserDto {
+        username: username.map(str::to_string),
+        email,
+        wallet_address: None,
+    };
+
+    assert!(update(None, None).validate(&limits).is_ok());
+    assert!(update(Some(email_of_len(20)), Some("12345678")).validate(&limits).is_ok());
+    let err = update(Some(email_of_len(21)), None).validate(&limits).unwrap_err().to_string();
+    assert!(err.contains("email: must be at most 20 characters"), "{}", err);
+    let err = update(None, Some("123456789")).validate(&limits).unwrap_err().to_string();
+    assert!(err.contains("username: must be at most 8 characters"), "{}", err);
+}
@@ -0,0 +1,101 @@
+use temp_rust_websocket::freshness::MAX_TIMESTAMP_OFFSET_SECS;
+use temp_rust_websocket::models::earnings::EarningSource;
+use temp_rust_websocket::models::websocket::{
+    TimestampWindow, WebSocketAuthMessage, WebSocketAuthRequest, WebSocketMessage,
+};
+use chrono::Utc;
+
+fn auth_message(timestamp: i64) -> WebSocketAuthMessage {
+    WebSocketAuthMessage::new("a".repeat(64), timestamp, "abcdefgh".to_string(), "b".repeat(128))
+}
+
+#[test]
+fn test_current_timestamp_is_accepted() {
+    assert!(auth_message(Utc::now().timestamp()).validate().is_ok());
+}
+
+#[test]
+fn test_extreme_timestamps_are_rejected() {
+    for timestamp in [i64::MAX, i64::MIN, i64::MIN + 1, 0, -1] {
+        let result = auth_message(timestamp).validate();
+        assert_eq!(result, Err("Timestamp is out of range".to_string()), "timestamp {}", timestamp);
+    }
+}
+
+#[test]
+fn test_plausible_but_stale_timestamp_uses_window_check() {
+    let now = Utc::now().timestamp();
+    assert!(auth_message(now - 3600).validate().unwrap_err().starts_with("Authentication message has expired"));
+    assert!(auth_message(now + 3600).validate().unwrap_err().starts_with("Client clock ahead"));
+    assert_eq!(
+        auth_message(now + MAX_TIMESTAMP_OFFSET_SECS + 60).validate(),
+        Err("Timestamp is out of range".to_string())
+    );
+}
+
+#[test]
+fn test_max_age_boundary() {
+    let now = 1_700_000_000;
+    let window = TimestampWindow {
+        max_age_secs: 300,
+        max_future_secs: 60,
+    };
+    assert!(auth_message(now - 300).validate_at(&window, now).is_ok());
+    let err = auth_message(now - 301).validate_at(&window, now).unwrap_err();
+    assert!(err.starts_with("Authentication message has expired"), "{}", err);
+}
+
+#[test]
+fn test_max_future_boundary_is_independent_of_max_age() {
+    let now = 1_700_000_000;
+    let window = TimestampWindow {
+        max_age_secs: 300,
+        max_future_secs: 120,
+    };
+    assert!(auth_message(now + 120).validate_at(&window, now).is_ok());
+    let err = auth_message(now + 121).validate_at(&window, now).unwrap_err();
+    assert!(err.starts_with("Client clock ahead"), "{}", err);
+
+    // Widening the future tolerance leaves the expiry bound where it was
+    assert!(auth_message(now - 301).validate_at(&window, now).is_err());
+    assert_eq!(TimestampWindow::default().max_future_secs, 60);
+    assert!(auth_message(now + 61).validate_at(&TimestampWindow::default(), now).is_err());
+}
+
+#[test]
+fn test_earnings_update_source_is_typed() {
+    let message: WebSocketMessage =
+        serde_json::from_str(r#"{"type":"EarningsUpdate","data":{"amount":1.5,"source":"referral"}}"#).unwrap();
+    assert!(matches!(
+        message,
+        WebSocketMessage::EarningsUpdate { source: EarningSource::Referral, total, .. } if total == 0.0
+    ));
+
+    for source in EarningSource::ALL {
+        let json = serde_json::to_string(&source).unwrap();
+        assert_eq!(json, format!("\"{}\"", source));
+        assert_eq!(serde_json::from_str::<EarningSource>(&json).unwrap(), source);
+    }
+
+    let err = serde_json::from_str::<WebSocketMessage>(
+        r#"{"type":"EarningsUpdate","data":{"amount":1.5,"source":"free money"}}"#,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("Unknown earning source 'free money'"), "{}", err);
+    assert!(serde_json::from_str::<EarningSource>(r#""Uptime""#).is_err());
+}
+
+#[test]
+fn test_auth_payload_shape_selects_the_auth_method() {
+    let signed = serde_json::to_value(auth_message(1_700_000_000)).unwrap();
+    let message: WebSocketMessage = serde_json::from_value(serde_json::json!({"type": "Auth", "data": signed})).unwrap();
+    assert!(matches!(message, WebSocketMessage::Auth(WebSocketAuthRequest::Signature(_))));
+
+    let message: WebSocketMessage = serde_json::from_str(r#"{"type":"Auth","data":{"token":"eyJ.a.b"}}"#).unwrap();
+    assert!(matches!(
+        message,
+        WebSocketMessage::Auth(WebSocketAuthRequest::ExternalToken { token }) if token == "eyJ.a.b"
+    ));
+
+    assert!(serde_json::from_str::<WebSocketMessage>(r#"{"type":"Auth","data":{"nonce":"abcdefgh"}}"#).is_err());
+}